@@ -1,5 +1,12 @@
+use crate::configuration_service::config_format::ConfigFormat;
+use crate::configuration_service::env_interpolation::from_str_with_env;
 use crate::configuration_service::logger;
+use crate::configuration_service::readiness::mark_config_loaded;
+use crate::configuration_service::readiness::mark_listener_failed;
+use crate::configuration_service::readiness::mark_listener_starting;
+use crate::configuration_service::readiness::remove_listener_state;
 use crate::constants;
+use crate::constants::common_constants::DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT;
 use crate::constants::common_constants::ENV_ACCESS_LOG;
 use crate::constants::common_constants::ENV_ADMIN_PORT;
 use crate::constants::common_constants::ENV_CONFIG_FILE_PATH;
@@ -7,25 +14,34 @@ use crate::constants::common_constants::ENV_DATABASE_URL;
 use crate::constants::common_constants::TIMER_WAIT_SECONDS;
 use crate::health_check::health_check_task::HealthCheck;
 use crate::proxy::http1::http_proxy::HttpProxy;
+use crate::proxy::http1::redirect_proxy::RedirectProxy;
 use crate::proxy::http2::grpc_proxy::GrpcProxy;
 use crate::proxy::tcp::tcp_proxy::TcpProxy;
+use crate::proxy::udp::udp_proxy::UdpProxy;
 use crate::vojo::api_service_manager::ApiServiceManager;
 use crate::vojo::app_config::ServiceConfig;
 use crate::vojo::app_config::{ApiService, AppConfig, ServiceType};
 use crate::vojo::app_config_vistor::ApiServiceVistor;
-use crate::vojo::app_error::AppError;
+use crate::vojo::app_error::{AppError, AppErrorKind};
 use dashmap::DashMap;
+use futures::future::join_all;
 use futures::FutureExt;
 use lazy_static::lazy_static;
 use log::Level;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::env;
 use tokio::sync::mpsc;
+use tokio::sync::Mutex;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
+use tokio::time::timeout;
 lazy_static! {
     pub static ref GLOBAL_APP_CONFIG: RwLock<AppConfig> = RwLock::new(Default::default());
     pub static ref GLOBAL_CONFIG_MAPPING: DashMap<String, ApiServiceManager> = Default::default();
+    static ref MAPPING_UPDATE_LOCK: Mutex<()> = Mutex::new(());
+    static ref LISTENER_TASKS: DashMap<String, JoinHandle<()>> = Default::default();
 }
 
 pub async fn init() {
@@ -47,21 +63,49 @@ async fn sync_mapping_from_global_app_config() {
         let async_result = std::panic::AssertUnwindSafe(update_mapping_from_global_appconfig())
             .catch_unwind()
             .await;
-        if async_result.is_err() {
-            error!("sync_mapping_from_global_app_config catch panic successfully!");
+        match async_result {
+            Ok(Ok(_)) => mark_config_loaded(),
+            Ok(Err(_)) => {}
+            Err(_) => error!("sync_mapping_from_global_app_config catch panic successfully!"),
         }
         sleep(std::time::Duration::from_secs(TIMER_WAIT_SECONDS)).await;
     }
 }
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MappingDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub updated: Vec<String>,
+}
+pub fn diff_mapping_keys(current_keys: Vec<String>, new_keys: Vec<String>) -> MappingDiff {
+    let mut mapping_diff = MappingDiff::default();
+    for key in current_keys.iter() {
+        if new_keys.contains(key) {
+            mapping_diff.updated.push(key.clone());
+        } else {
+            mapping_diff.removed.push(key.clone());
+        }
+    }
+    for key in new_keys {
+        if !current_keys.contains(&key) {
+            mapping_diff.added.push(key);
+        }
+    }
+    mapping_diff.added.sort();
+    mapping_diff.removed.sort();
+    mapping_diff.updated.sort();
+    mapping_diff
+}
 /**
 *Key in Old Map:[1,2]
  Key in Current Map:[2,4,5]
 */
-async fn update_mapping_from_global_appconfig() -> Result<(), AppError> {
+pub async fn update_mapping_from_global_appconfig() -> Result<MappingDiff, AppError> {
     let rw_global_app_config = GLOBAL_APP_CONFIG
         .try_read()
-        .map_err(|err| AppError(err.to_string()))?;
+        .map_err(|err| AppError::from(err.to_string()))?;
     let api_services = rw_global_app_config.api_service_config.clone();
+    drop(rw_global_app_config);
 
     let new_item_hash = api_services
         .iter()
@@ -72,61 +116,227 @@ async fn update_mapping_from_global_appconfig() -> Result<(), AppError> {
             )
         })
         .collect::<HashMap<String, ServiceConfig>>();
-
-    let difference_ports = GLOBAL_CONFIG_MAPPING
-        .iter()
-        .map(|s| s.key().clone())
-        .filter(|item| !new_item_hash.contains_key(item))
-        .collect::<Vec<String>>();
+    Ok(apply_mapping(&GLOBAL_CONFIG_MAPPING, new_item_hash).await)
+}
+async fn apply_mapping(
+    mapping: &DashMap<String, ApiServiceManager>,
+    new_item_hash: HashMap<String, ServiceConfig>,
+) -> MappingDiff {
+    let mapping_update_lock = MAPPING_UPDATE_LOCK.lock().await;
+    let mut pending_listeners = PendingListeners::default();
+    let mapping_diff = diff_mapping_keys(
+        mapping.iter().map(|s| s.key().clone()).collect(),
+        new_item_hash.keys().cloned().collect(),
+    );
     if log_enabled!(Level::Info) {
-        debug!("The len of different ports is {}", difference_ports.len());
+        debug!(
+            "The len of different ports is {}",
+            mapping_diff.removed.len()
+        );
     }
     //delete the old mapping
-    for item in difference_ports {
-        let key = item.clone();
-        let value = GLOBAL_CONFIG_MAPPING.get(&key).unwrap().sender.clone();
-        match value.send(()).await {
-            Ok(_) => info!("close the socket on the port {}", key),
-            Err(err) => {
-                error!(
-                    "Cause error when closing the socket,the key is {},the error is {}.",
-                    key,
-                    err.to_string()
-                )
-            }
-        };
-        GLOBAL_CONFIG_MAPPING.remove(&key);
+    for item in mapping_diff.removed.iter() {
+        pending_listeners
+            .stopping
+            .extend(stop_listener(mapping, item).await);
     }
     //add the new mapping and update the old
     for (key, value) in new_item_hash {
-        //update
-        if GLOBAL_CONFIG_MAPPING.contains_key(&key) {
-            let mut ref_value = GLOBAL_CONFIG_MAPPING.get(&key).unwrap().clone();
-            ref_value.service_config = value.clone();
-            GLOBAL_CONFIG_MAPPING.insert(key.clone(), ref_value);
-            //add
+        if mapping.contains_key(&key) {
+            update_listener(mapping, key, value, &mut pending_listeners).await;
+        } else if pending_listeners.is_releasing_port(&key) {
+            pending_listeners.starting.push((key, value));
         } else {
-            let (sender, receiver) = tokio::sync::mpsc::channel(10);
-            GLOBAL_CONFIG_MAPPING.insert(
-                key.clone(),
-                ApiServiceManager {
-                    service_config: value.clone(),
-                    sender,
-                },
-            );
-            let item_list: Vec<&str> = key.split('-').collect();
-            let port_str = item_list.first().unwrap();
-            let port: i32 = port_str.parse().unwrap();
-
-            tokio::task::spawn(async move {
-                if let Err(err) = start_proxy(port, receiver, value.server_type, key.clone()).await
-                {
-                    error!("{}", err.to_string());
-                }
-            });
+            start_listener(mapping, key, value);
+        }
+    }
+    drop(mapping_update_lock);
+    pending_listeners.finish(mapping).await;
+    mapping_diff
+}
+/**
+ *The stopped listeners are drained after the mapping update lock is released,
+ *the listeners on the same ports are started after the old ones have been stopped.
+ */
+#[derive(Default)]
+struct PendingListeners {
+    stopping: Vec<StoppingListener>,
+    starting: Vec<(String, ServiceConfig)>,
+}
+impl PendingListeners {
+    fn is_releasing_port(&self, key: &str) -> bool {
+        self.stopping
+            .iter()
+            .any(|item| listener_port(&item.key) == listener_port(key))
+    }
+    async fn finish(self, mapping: &DashMap<String, ApiServiceManager>) {
+        join_all(self.stopping.into_iter().map(wait_listener_stopped)).await;
+        if self.starting.is_empty() {
+            return;
+        }
+        let _mapping_update_lock = MAPPING_UPDATE_LOCK.lock().await;
+        for (key, service_config) in self.starting {
+            if !mapping.contains_key(&key) {
+                start_listener(mapping, key, service_config);
+            }
+        }
+    }
+}
+struct StoppingListener {
+    key: String,
+    join_handle: JoinHandle<()>,
+    graceful_shutdown_timeout: u64,
+}
+fn listener_port(key: &str) -> &str {
+    key.split('-').next().unwrap_or_default()
+}
+async fn update_listener(
+    mapping: &DashMap<String, ApiServiceManager>,
+    key: String,
+    service_config: ServiceConfig,
+    pending_listeners: &mut PendingListeners,
+) {
+    let listener_changed = mapping
+        .get(&key)
+        .is_some_and(|item| is_listener_changed(&item.service_config, &service_config));
+    if listener_changed {
+        info!("The listener of {} is changed,restart it.", key);
+        pending_listeners
+            .stopping
+            .extend(stop_listener(mapping, &key).await);
+        pending_listeners.starting.push((key, service_config));
+    } else if let Some(mut ref_value) = mapping.get_mut(&key) {
+        ref_value.service_config = service_config;
+    }
+}
+/**
+ *The fields are read when the listener starts,so the listener is restarted when one of them is changed.
+ */
+fn is_listener_changed(old: &ServiceConfig, new: &ServiceConfig) -> bool {
+    old.socket_options != new.socket_options
+        || old.bind_addresses != new.bind_addresses
+        || old.header_limit != new.header_limit
+        || old.tls_policy != new.tls_policy
+        || old.upstream_pool != new.upstream_pool
+        || old.upstream_tls != new.upstream_tls
+        || old.tcp.as_ref().and_then(|item| item.limit.as_ref())
+            != new.tcp.as_ref().and_then(|item| item.limit.as_ref())
+        || old.udp != new.udp
+}
+fn start_listener(
+    mapping: &DashMap<String, ApiServiceManager>,
+    key: String,
+    service_config: ServiceConfig,
+) {
+    let (sender, receiver) = tokio::sync::mpsc::channel(10);
+    mapping.insert(
+        key.clone(),
+        ApiServiceManager {
+            service_config: service_config.clone(),
+            sender,
+        },
+    );
+    let item_list: Vec<&str> = key.split('-').collect();
+    let port_str = item_list.first().unwrap();
+    let port: i32 = port_str.parse().unwrap();
+    mark_listener_starting(&key);
+    let task_key = key.clone();
+    let join_handle = tokio::task::spawn(async move {
+        if let Err(err) =
+            start_proxy(port, receiver, service_config.server_type, task_key.clone()).await
+        {
+            mark_listener_failed(&task_key);
+            error!("{}", err.to_string());
         }
+    });
+    LISTENER_TASKS.insert(key, join_handle);
+}
+/**
+ *Notify the listener to stop,the returned listener should be waited after the mapping update lock is released.
+ */
+async fn stop_listener(
+    mapping: &DashMap<String, ApiServiceManager>,
+    key: &str,
+) -> Option<StoppingListener> {
+    let (_, api_service_manager) = mapping.remove(key)?;
+    match api_service_manager.sender.send(()).await {
+        Ok(_) => info!("close the socket on the port {}", key),
+        Err(err) => {
+            error!(
+                "Cause error when closing the socket,the key is {},the error is {}.",
+                key,
+                err.to_string()
+            )
+        }
+    };
+    remove_listener_state(key);
+    let (_, join_handle) = LISTENER_TASKS.remove(key)?;
+    Some(StoppingListener {
+        key: key.to_string(),
+        join_handle,
+        graceful_shutdown_timeout: api_service_manager
+            .service_config
+            .graceful_shutdown_timeout
+            .unwrap_or(DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT),
+    })
+}
+/**
+ *Wait for the listener to stop,so the port could be bound again by the new listener.
+ */
+async fn wait_listener_stopped(mut stopping_listener: StoppingListener) {
+    let wait_timeout =
+        std::time::Duration::from_secs(stopping_listener.graceful_shutdown_timeout + 1);
+    if timeout(wait_timeout, &mut stopping_listener.join_handle)
+        .await
+        .is_err()
+    {
+        error!(
+            "The listener of {} is not stopped in time,abort it.",
+            stopping_listener.key
+        );
+        stopping_listener.join_handle.abort();
+        let _ = stopping_listener.join_handle.await;
     }
+}
+pub async fn reload_service_config(
+    listen_port: i32,
+    service_config: ServiceConfig,
+) -> Result<(), AppError> {
+    let mapping_update_lock = MAPPING_UPDATE_LOCK.lock().await;
+    let mut rw_global_app_config = GLOBAL_APP_CONFIG.write().await;
+    let api_service = rw_global_app_config
+        .api_service_config
+        .iter_mut()
+        .find(|item| item.listen_port == listen_port)
+        .ok_or(AppError::new(
+            AppErrorKind::NotFound,
+            format!("Can not find the api service on the port {}!", listen_port),
+        ))?;
+    if api_service.service_config.server_type != service_config.server_type {
+        return Err(AppError::new(
+            AppErrorKind::Config,
+            format!(
+                "The server type of the port {} can not be changed from {} to {}!",
+                listen_port, api_service.service_config.server_type, service_config.server_type
+            ),
+        ));
+    }
+    api_service.service_config = service_config.clone();
+    drop(rw_global_app_config);
 
+    let mapping_key = format!("{}-{}", listen_port, service_config.server_type);
+    let mut pending_listeners = PendingListeners::default();
+    if GLOBAL_CONFIG_MAPPING.contains_key(&mapping_key) {
+        update_listener(
+            &GLOBAL_CONFIG_MAPPING,
+            mapping_key,
+            service_config,
+            &mut pending_listeners,
+        )
+        .await;
+    }
+    drop(mapping_update_lock);
+    pending_listeners.finish(&GLOBAL_CONFIG_MAPPING).await;
     Ok(())
 }
 pub async fn start_proxy(
@@ -136,10 +346,40 @@ pub async fn start_proxy(
     mapping_key: String,
 ) -> Result<(), AppError> {
     if server_type == ServiceType::Http {
+        let graceful_shutdown_timeout = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key)
+            .and_then(|item| item.service_config.graceful_shutdown_timeout)
+            .unwrap_or(DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT);
+        let socket_options = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key)
+            .and_then(|item| item.service_config.socket_options.clone())
+            .unwrap_or_default();
+        let upstream_pool = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key)
+            .and_then(|item| item.service_config.upstream_pool.clone())
+            .unwrap_or_default();
+        let upstream_tls = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key)
+            .and_then(|item| item.service_config.upstream_tls.clone());
+        let header_limit = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key)
+            .and_then(|item| item.service_config.header_limit.clone())
+            .unwrap_or_default();
+        let bind_addresses = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key)
+            .and_then(|item| item.service_config.bind_addresses.clone());
         let mut http_proxy = HttpProxy {
             port,
             channel,
             mapping_key: mapping_key.clone(),
+            graceful_shutdown_timeout,
+            socket_options,
+            upstream_pool,
+            upstream_tls,
+            tls_policy: None,
+            sni_certs: None,
+            header_limit,
+            bind_addresses,
         };
         http_proxy.start_http_server().await
     } else if server_type == ServiceType::Https {
@@ -155,6 +395,16 @@ pub async fn start_proxy(
             port,
             channel,
             mapping_key: mapping_key.clone(),
+            graceful_shutdown_timeout: service_config
+                .graceful_shutdown_timeout
+                .unwrap_or(DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT),
+            socket_options: service_config.socket_options.clone().unwrap_or_default(),
+            upstream_pool: service_config.upstream_pool.clone().unwrap_or_default(),
+            upstream_tls: service_config.upstream_tls.clone(),
+            tls_policy: service_config.tls_policy.clone(),
+            sni_certs: service_config.sni_certs.clone(),
+            header_limit: service_config.header_limit.clone().unwrap_or_default(),
+            bind_addresses: service_config.bind_addresses.clone(),
         };
         http_proxy.start_https_server(pem_str, key_str).await
     } else if server_type == ServiceType::Tcp {
@@ -164,6 +414,13 @@ pub async fn start_proxy(
             channel,
         };
         tcp_proxy.start_proxy().await
+    } else if server_type == ServiceType::Udp {
+        let mut udp_proxy = UdpProxy {
+            port,
+            mapping_key,
+            channel,
+        };
+        udp_proxy.start_proxy().await
     } else if server_type == ServiceType::Http2 {
         let mut grpc_proxy = GrpcProxy {
             port,
@@ -171,6 +428,13 @@ pub async fn start_proxy(
             channel,
         };
         grpc_proxy.start_proxy().await
+    } else if server_type == ServiceType::Redirect {
+        let mut redirect_proxy = RedirectProxy {
+            port,
+            mapping_key,
+            channel,
+        };
+        redirect_proxy.start_proxy().await
     } else {
         let key_clone = mapping_key.clone();
         let service_config = GLOBAL_CONFIG_MAPPING
@@ -223,31 +487,49 @@ async fn init_app_service_config() -> Result<(), AppError> {
     drop(rw_app_config_read);
     let file_path = config_file_path.unwrap().clone();
     info!("the config file is in{}", file_path.clone());
-    let file = std::fs::File::open(file_path).map_err(|e| AppError(e.to_string()))?;
-    let scrape_config: Vec<ApiServiceVistor> = match serde_yaml::from_reader(file) {
-        Ok(apiservices) => apiservices,
-        Err(err) => return Err(AppError(err.to_string())),
-    };
+    let format = ConfigFormat::from_path(&file_path);
+    let content = std::fs::read_to_string(file_path)
+        .map_err(|e| AppError::new(AppErrorKind::Config, e.to_string()))?;
+    let res = parse_api_services(&content, format).await?;
     let mut rw_app_config_write = GLOBAL_APP_CONFIG.write().await;
-
-    let mut res = vec![];
-    for item in scrape_config {
-        res.push(ApiService::from(item).await?);
-    }
     rw_app_config_write.api_service_config = res;
     Ok(())
 }
+pub async fn parse_api_services(
+    content: &str,
+    format: ConfigFormat,
+) -> Result<Vec<ApiService>, AppError> {
+    let scrape_config: Vec<ApiServiceVistor> = from_str_with_env(content, format)?;
+    let mut res: Vec<ApiService> = vec![];
+    for item in scrape_config {
+        let api_service = ApiService::from(item).await?;
+        if res
+            .iter()
+            .any(|item| item.listen_port == api_service.listen_port)
+        {
+            return Err(AppError::new(
+                AppErrorKind::Config,
+                format!("The listen port {} is duplicated!", api_service.listen_port),
+            ));
+        }
+        res.push(api_service);
+    }
+    Ok(res)
+}
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::configuration_service::readiness::{get_readiness_report, ListenerState};
     use crate::vojo::app_config::LivenessStatus;
     use crate::vojo::app_config::Route;
+    use crate::vojo::app_config::UdpConfig;
     use crate::vojo::route::AnomalyDetectionStatus;
     use crate::vojo::route::{BaseRoute, LoadbalancerStrategy, RandomBaseRoute, RandomRoute};
     use serial_test::serial;
     use std::sync::Arc;
+    use tokio::net::{TcpListener, TcpStream};
     use tokio::runtime::{Builder, Runtime};
     use tokio::sync::RwLock;
     lazy_static! {
@@ -337,6 +619,210 @@ mod tests {
             assert_eq!(api_service_routes.matcher.unwrap().prefix_rewrite, "/");
         });
     }
+    #[tokio::test]
+    async fn test_parse_api_services_error() {
+        let malformed = "- listen_port: 4486\n  service_config: [";
+        let err = parse_api_services(malformed, ConfigFormat::Yaml)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("line"));
+
+        let duplicated = r#"
+- listen_port: 4486
+  service_config:
+    server_type: Http
+    routes: []
+- listen_port: 4486
+  service_config:
+    server_type: Http
+    routes: []
+"#;
+        let err = parse_api_services(duplicated, ConfigFormat::Yaml)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("duplicated"));
+    }
+    #[test]
+    fn test_diff_mapping_keys() {
+        let mapping_diff = diff_mapping_keys(
+            vec![String::from("1-HTTP"), String::from("2-HTTP")],
+            vec![String::from("3-HTTP"), String::from("2-HTTP")],
+        );
+        assert_eq!(
+            mapping_diff,
+            MappingDiff {
+                added: vec![String::from("3-HTTP")],
+                removed: vec![String::from("1-HTTP")],
+                updated: vec![String::from("2-HTTP")],
+            }
+        );
+    }
+    #[test]
+    fn test_apply_mapping_add_remove_and_update_ports() {
+        TOKIO_RUNTIME.block_on(async {
+            let mapping = DashMap::new();
+            let (removed_sender, mut removed_receiver) = mpsc::channel(10);
+            mapping.insert(
+                String::from("10071-HTTP"),
+                ApiServiceManager {
+                    sender: removed_sender,
+                    service_config: ServiceConfig::default(),
+                },
+            );
+            let (updated_sender, mut updated_receiver) = mpsc::channel(10);
+            mapping.insert(
+                String::from("10072-HTTP"),
+                ApiServiceManager {
+                    sender: updated_sender,
+                    service_config: ServiceConfig::default(),
+                },
+            );
+            let mut new_item_hash = HashMap::new();
+            new_item_hash.insert(
+                String::from("10072-HTTP"),
+                ServiceConfig {
+                    graceful_shutdown_timeout: Some(5),
+                    ..Default::default()
+                },
+            );
+            new_item_hash.insert(String::from("10073-HTTP"), ServiceConfig::default());
+
+            let mapping_diff = apply_mapping(&mapping, new_item_hash).await;
+            assert_eq!(mapping_diff.added, vec![String::from("10073-HTTP")]);
+            assert_eq!(mapping_diff.removed, vec![String::from("10071-HTTP")]);
+            assert_eq!(mapping_diff.updated, vec![String::from("10072-HTTP")]);
+
+            assert_eq!(removed_receiver.recv().await, Some(()));
+            assert!(!mapping.contains_key("10071-HTTP"));
+            assert!(updated_receiver.try_recv().is_err());
+            let graceful_shutdown_timeout = mapping
+                .get("10072-HTTP")
+                .unwrap()
+                .service_config
+                .graceful_shutdown_timeout;
+            assert_eq!(graceful_shutdown_timeout, Some(5));
+
+            let mut is_listening = false;
+            for _ in 0..20 {
+                if TcpStream::connect("127.0.0.1:10073").await.is_ok() {
+                    is_listening = true;
+                    break;
+                }
+                sleep(std::time::Duration::from_millis(100)).await;
+            }
+            assert!(is_listening);
+            let added_sender = mapping.get("10073-HTTP").unwrap().sender.clone();
+            added_sender.send(()).await.unwrap();
+        });
+    }
+    async fn wait_for_listener_state(mapping_key: &str, state: ListenerState) -> bool {
+        for _ in 0..20 {
+            if get_readiness_report().listeners.get(mapping_key) == Some(&state) {
+                return true;
+            }
+            sleep(std::time::Duration::from_millis(100)).await;
+        }
+        false
+    }
+    #[test]
+    #[serial("test")]
+    fn test_apply_mapping_restart_listener_when_bind_addresses_changed() {
+        TOKIO_RUNTIME.block_on(async {
+            let mapping = DashMap::new();
+            let mapping_key = String::from("10151-Http");
+            let service_config = ServiceConfig {
+                graceful_shutdown_timeout: Some(1),
+                ..Default::default()
+            };
+            let (sender, _) = mpsc::channel(10);
+            GLOBAL_CONFIG_MAPPING.insert(
+                mapping_key.clone(),
+                ApiServiceManager {
+                    sender: sender.clone(),
+                    service_config: service_config.clone(),
+                },
+            );
+            apply_mapping(
+                &mapping,
+                HashMap::from([(mapping_key.clone(), service_config.clone())]),
+            )
+            .await;
+            assert!(wait_for_listener_state(&mapping_key, ListenerState::Bound).await);
+            assert!(TcpListener::bind("127.0.0.2:10151").await.is_err());
+
+            let bound_config = ServiceConfig {
+                bind_addresses: Some(vec!["127.0.0.1".parse().unwrap()]),
+                ..service_config
+            };
+            GLOBAL_CONFIG_MAPPING
+                .get_mut(&mapping_key)
+                .unwrap()
+                .service_config = bound_config.clone();
+            apply_mapping(
+                &mapping,
+                HashMap::from([(mapping_key.clone(), bound_config)]),
+            )
+            .await;
+            assert!(wait_for_listener_state(&mapping_key, ListenerState::Bound).await);
+            assert!(TcpStream::connect("127.0.0.1:10151").await.is_ok());
+            assert!(TcpListener::bind("127.0.0.2:10151").await.is_ok());
+
+            apply_mapping(&mapping, HashMap::new()).await;
+            GLOBAL_CONFIG_MAPPING.remove(&mapping_key);
+        });
+    }
+    #[test]
+    fn test_is_listener_changed_with_udp_config() {
+        let old = ServiceConfig {
+            server_type: ServiceType::Udp,
+            udp: Some(UdpConfig {
+                idle_timeout: Some(30),
+                max_sessions: None,
+            }),
+            ..Default::default()
+        };
+        assert!(!is_listener_changed(&old, &old.clone()));
+        let new = ServiceConfig {
+            udp: Some(UdpConfig {
+                idle_timeout: Some(30),
+                max_sessions: Some(10),
+            }),
+            ..old.clone()
+        };
+        assert!(is_listener_changed(&old, &new));
+    }
+    #[test]
+    fn test_apply_mapping_rebind_when_server_type_changed() {
+        TOKIO_RUNTIME.block_on(async {
+            let mapping = DashMap::new();
+            apply_mapping(
+                &mapping,
+                HashMap::from([(
+                    String::from("10152-Http"),
+                    ServiceConfig {
+                        graceful_shutdown_timeout: Some(1),
+                        ..Default::default()
+                    },
+                )]),
+            )
+            .await;
+            assert!(wait_for_listener_state("10152-Http", ListenerState::Bound).await);
+            apply_mapping(
+                &mapping,
+                HashMap::from([(
+                    String::from("10152-Tcp"),
+                    ServiceConfig {
+                        server_type: ServiceType::Tcp,
+                        ..Default::default()
+                    },
+                )]),
+            )
+            .await;
+            assert!(wait_for_listener_state("10152-Tcp", ListenerState::Bound).await);
+            assert!(!mapping.contains_key("10152-Http"));
+            apply_mapping(&mapping, HashMap::new()).await;
+        });
+    }
     #[test]
     #[serial("test")]
     fn test_update_mapping_from_global_appconfig_with_default() {
@@ -398,10 +884,18 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("httpbin.org:80"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
             }],
         });
@@ -419,9 +913,26 @@ mod tests {
                     matcher: Default::default(),
                     route_cluster: route,
                     allow_deny_list: None,
+                    allow_deny_default: None,
+                    client_ip: None,
                     authentication: None,
                     ratelimit: None,
+                    combined_limit: None,
+                    bulkhead: None,
                     rewrite_headers: None,
+                    query_rewrite: None,
+                    response_headers: None,
+                    forward_headers: None,
+                    mirror: None,
+                    timeout: None,
+                    circuit_breaker: None,
+                    hedge: None,
+                    retry: None,
+                    body_logging: None,
+                    maintenance: None,
+                    cache: None,
+                    single_flight: None,
+                    websocket: None,
 
                     health_check: None,
                     anomaly_detection: None,
@@ -430,6 +941,7 @@ mod tests {
                         current_liveness_count: 0,
                     })),
                 }],
+                ..Default::default()
             },
         };
         GLOBAL_CONFIG_MAPPING.insert(String::from("test"), api_service_manager);
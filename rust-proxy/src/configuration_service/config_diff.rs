@@ -0,0 +1,296 @@
+use crate::configuration_service::config_format::ConfigFormat;
+use crate::configuration_service::env_interpolation::from_str_with_env;
+use crate::vojo::app_config_vistor::ApiServiceVistor;
+use crate::vojo::app_error::AppError;
+use serde_yaml::Mapping;
+
+pub async fn handle_diff_command(
+    old_file_path: Option<String>,
+    new_file_path: Option<String>,
+) -> i32 {
+    let (Some(old_file_path), Some(new_file_path)) = (old_file_path, new_file_path) else {
+        eprintln!("Usage: silverwind diff <old config file> <new config file>");
+        return 2;
+    };
+    let result = async {
+        let old_content = tokio::fs::read_to_string(&old_file_path)
+            .await
+            .map_err(|e| {
+                AppError::from(format!(
+                    "Can not read {},the error is {}!",
+                    old_file_path, e
+                ))
+            })?;
+        let new_content = tokio::fs::read_to_string(&new_file_path)
+            .await
+            .map_err(|e| {
+                AppError::from(format!(
+                    "Can not read {},the error is {}!",
+                    new_file_path, e
+                ))
+            })?;
+        diff_config(
+            &old_content,
+            ConfigFormat::from_path(&old_file_path),
+            &new_content,
+            ConfigFormat::from_path(&new_file_path),
+        )
+    }
+    .await;
+    match result {
+        Ok(differences) if differences.is_empty() => {
+            println!("The configs are identical.");
+            0
+        }
+        Ok(differences) => {
+            for difference in differences.iter() {
+                println!("{}", difference);
+            }
+            1
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            2
+        }
+    }
+}
+fn parse_listeners(content: &str, format: ConfigFormat) -> Result<Vec<(i64, Mapping)>, AppError> {
+    from_str_with_env::<Vec<ApiServiceVistor>>(content, format)?;
+    let api_services: Vec<Mapping> = serde_yaml::from_value(format.parse_value(content)?)
+        .map_err(|e| AppError::from(format!("Can not parse the config, the error is {}!", e)))?;
+    Ok(api_services
+        .into_iter()
+        .map(|item| {
+            let listen_port = item
+                .get("listen_port")
+                .and_then(|item| item.as_i64())
+                .unwrap_or_default();
+            let service_config = item
+                .get("service_config")
+                .and_then(|item| item.as_mapping())
+                .cloned()
+                .unwrap_or_default();
+            (listen_port, service_config)
+        })
+        .collect())
+}
+fn get_routes(service_config: &Mapping) -> Vec<(String, Mapping)> {
+    service_config
+        .get("routes")
+        .and_then(|item| item.as_sequence())
+        .map(|routes| {
+            routes
+                .iter()
+                .enumerate()
+                .filter_map(|(index, route)| {
+                    let route = route.as_mapping()?.clone();
+                    let route_id = route
+                        .get("route_id")
+                        .and_then(|item| item.as_str())
+                        .map(|item| item.to_string())
+                        .unwrap_or(format!("#{}", index));
+                    Some((route_id, route))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+fn changed_keys(old: &Mapping, new: &Mapping, ignored_key: &str) -> Vec<String> {
+    let mut keys = old
+        .keys()
+        .chain(new.keys())
+        .filter_map(|item| item.as_str())
+        .filter(|item| *item != ignored_key)
+        .filter(|item| old.get(*item) != new.get(*item))
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+pub fn diff_config(
+    old_content: &str,
+    old_format: ConfigFormat,
+    new_content: &str,
+    new_format: ConfigFormat,
+) -> Result<Vec<String>, AppError> {
+    let old_listeners = parse_listeners(old_content, old_format)?;
+    let new_listeners = parse_listeners(new_content, new_format)?;
+    let mut differences = vec![];
+    for (listen_port, _) in old_listeners.iter() {
+        if !new_listeners.iter().any(|(port, _)| port == listen_port) {
+            differences.push(format!("- listener {}", listen_port));
+        }
+    }
+    for (listen_port, new_service_config) in new_listeners.iter() {
+        let Some((_, old_service_config)) =
+            old_listeners.iter().find(|(port, _)| port == listen_port)
+        else {
+            differences.push(format!("+ listener {}", listen_port));
+            continue;
+        };
+        let keys = changed_keys(old_service_config, new_service_config, "routes");
+        if !keys.is_empty() {
+            differences.push(format!("~ listener {}: {}", listen_port, keys.join(", ")));
+        }
+        let old_routes = get_routes(old_service_config);
+        let new_routes = get_routes(new_service_config);
+        for (route_id, _) in old_routes.iter() {
+            if !new_routes.iter().any(|(id, _)| id == route_id) {
+                differences.push(format!("- route {} on listener {}", route_id, listen_port));
+            }
+        }
+        for (route_id, new_route) in new_routes.iter() {
+            match old_routes.iter().find(|(id, _)| id == route_id) {
+                Some((_, old_route)) => {
+                    let keys = changed_keys(old_route, new_route, "route_id");
+                    if !keys.is_empty() {
+                        differences.push(format!(
+                            "~ route {} on listener {}: {}",
+                            route_id,
+                            listen_port,
+                            keys.join(", ")
+                        ));
+                    }
+                }
+                None => {
+                    differences.push(format!("+ route {} on listener {}", route_id, listen_port))
+                }
+            }
+        }
+    }
+    Ok(differences)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::app_config_vistor::new_uuid;
+    use std::env;
+    const BASE_CONFIG: &str = r#"
+- listen_port: 10080
+  service_config:
+    server_type: Http
+    routes:
+      - route_id: orders
+        matcher:
+          prefix: /orders
+          prefix_rewrite: /
+        route_cluster:
+          type: RandomRoute
+          routes:
+            - base_route:
+                endpoint: http://127.0.0.1:9001
+"#;
+    #[test]
+    fn test_diff_config_identical() {
+        assert!(diff_config(
+            BASE_CONFIG,
+            ConfigFormat::Yaml,
+            BASE_CONFIG,
+            ConfigFormat::Yaml
+        )
+        .unwrap()
+        .is_empty());
+    }
+    #[test]
+    fn test_diff_config_changed_routes_and_listeners() {
+        let new_config = r#"
+- listen_port: 10080
+  service_config:
+    server_type: Http
+    graceful_shutdown_timeout: 5
+    routes:
+      - route_id: orders
+        matcher:
+          prefix: /orders
+          prefix_rewrite: /
+        ratelimit:
+          type: TokenBucketRateLimit
+          rate_per_unit: 3
+          unit:
+            type: Second
+          capacity: 10
+          limit_location:
+            type: IP
+            value: 127.0.0.1
+        route_cluster:
+          type: RandomRoute
+          routes:
+            - base_route:
+                endpoint: http://127.0.0.1:9001
+      - route_id: users
+        matcher:
+          prefix: /users
+          prefix_rewrite: /
+        route_cluster:
+          type: RandomRoute
+          routes:
+            - base_route:
+                endpoint: http://127.0.0.1:9002
+- listen_port: 10081
+  service_config:
+    server_type: Tcp
+    routes: []
+"#;
+        assert_eq!(
+            diff_config(
+                BASE_CONFIG,
+                ConfigFormat::Yaml,
+                new_config,
+                ConfigFormat::Yaml
+            )
+            .unwrap(),
+            vec![
+                "~ listener 10080: graceful_shutdown_timeout",
+                "~ route orders on listener 10080: ratelimit",
+                "+ route users on listener 10080",
+                "+ listener 10081",
+            ]
+        );
+        assert_eq!(
+            diff_config(
+                new_config,
+                ConfigFormat::Yaml,
+                BASE_CONFIG,
+                ConfigFormat::Yaml
+            )
+            .unwrap(),
+            vec![
+                "- listener 10081",
+                "~ listener 10080: graceful_shutdown_timeout",
+                "- route users on listener 10080",
+                "~ route orders on listener 10080: ratelimit",
+            ]
+        );
+        assert!(diff_config(
+            BASE_CONFIG,
+            ConfigFormat::Yaml,
+            "- listen_port: [",
+            ConfigFormat::Yaml
+        )
+        .is_err());
+    }
+    #[tokio::test]
+    async fn test_handle_diff_command_exit_code() {
+        let old_path = env::temp_dir().join(format!("silverwind-{}.yaml", new_uuid()));
+        tokio::fs::write(&old_path, BASE_CONFIG).await.unwrap();
+        let new_path = env::temp_dir().join(format!("silverwind-{}.yaml", new_uuid()));
+        tokio::fs::write(&new_path, BASE_CONFIG.replace("/orders", "/v2/orders"))
+            .await
+            .unwrap();
+        let old_path_str = old_path.to_string_lossy().to_string();
+        let new_path_str = new_path.to_string_lossy().to_string();
+
+        assert_eq!(
+            handle_diff_command(Some(old_path_str.clone()), Some(old_path_str.clone())).await,
+            0
+        );
+        assert_eq!(
+            handle_diff_command(Some(old_path_str.clone()), Some(new_path_str)).await,
+            1
+        );
+        assert_eq!(handle_diff_command(Some(old_path_str), None).await, 2);
+        tokio::fs::remove_file(old_path).await.unwrap();
+        tokio::fs::remove_file(new_path).await.unwrap();
+    }
+}
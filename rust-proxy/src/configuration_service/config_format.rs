@@ -0,0 +1,187 @@
+use crate::vojo::app_error::{AppError, AppErrorKind};
+use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+use serde_yaml::Value;
+use std::path::Path;
+
+static API_SERVICES_KEY: &str = "api_services";
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Yaml,
+    Toml,
+    Json,
+}
+impl ConfigFormat {
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|item| item.to_str())
+            .map(|item| item.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("toml") => ConfigFormat::Toml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+    pub fn from_content_type(headers: &HeaderMap) -> Self {
+        let content_type = headers
+            .get(CONTENT_TYPE)
+            .and_then(|item| item.to_str().ok())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if content_type.contains("json") {
+            ConfigFormat::Json
+        } else if content_type.contains("toml") {
+            ConfigFormat::Toml
+        } else {
+            ConfigFormat::Yaml
+        }
+    }
+    /**
+     *TOML has no top-level arrays, so the api services can also be listed under `api_services`.
+     */
+    pub fn parse_value(&self, content: &str) -> Result<Value, AppError> {
+        let value: Value = match self {
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| {
+            AppError::new(
+                AppErrorKind::Config,
+                format!("Can not parse the config, the error is {}!", e),
+            )
+        })?;
+        match value {
+            Value::Mapping(mut mapping)
+                if mapping.len() == 1 && mapping.contains_key(API_SERVICES_KEY) =>
+            {
+                Ok(mapping.remove(API_SERVICES_KEY).unwrap_or_default())
+            }
+            value => Ok(value),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration_service::env_interpolation::from_str_with_env;
+    use crate::vojo::app_config_vistor::ApiServiceVistor;
+    const YAML_CONFIG: &str = r#"
+- listen_port: 10080
+  api_service_id: orders-service
+  service_config:
+    server_type: Http
+    graceful_shutdown_timeout: 5
+    routes:
+      - route_id: orders
+        matcher:
+          prefix: /orders
+          prefix_rewrite: /
+        ratelimit:
+          type: TokenBucketRateLimit
+          rate_per_unit: 3
+          unit:
+            type: Second
+          capacity: 10
+          limit_location:
+            type: IP
+            value: 127.0.0.1
+        route_cluster:
+          type: RandomRoute
+          routes:
+            - base_route:
+                endpoint: http://127.0.0.1:9001
+"#;
+    const TOML_CONFIG: &str = r#"
+[[api_services]]
+listen_port = 10080
+api_service_id = "orders-service"
+
+[api_services.service_config]
+server_type = "Http"
+graceful_shutdown_timeout = 5
+
+[[api_services.service_config.routes]]
+route_id = "orders"
+matcher = { prefix = "/orders", prefix_rewrite = "/" }
+ratelimit = { type = "TokenBucketRateLimit", rate_per_unit = 3, unit = { type = "Second" }, capacity = 10, limit_location = { type = "IP", value = "127.0.0.1" } }
+route_cluster = { type = "RandomRoute", routes = [{ base_route = { endpoint = "http://127.0.0.1:9001" } }] }
+"#;
+    const JSON_CONFIG: &str = r#"[
+  {
+    "listen_port": 10080,
+    "api_service_id": "orders-service",
+    "service_config": {
+      "server_type": "Http",
+      "graceful_shutdown_timeout": 5,
+      "routes": [
+        {
+          "route_id": "orders",
+          "matcher": { "prefix": "/orders", "prefix_rewrite": "/" },
+          "ratelimit": {
+            "type": "TokenBucketRateLimit",
+            "rate_per_unit": 3,
+            "unit": { "type": "Second" },
+            "capacity": 10,
+            "limit_location": { "type": "IP", "value": "127.0.0.1" }
+          },
+          "route_cluster": {
+            "type": "RandomRoute",
+            "routes": [{ "base_route": { "endpoint": "http://127.0.0.1:9001" } }]
+          }
+        }
+      ]
+    }
+  }
+]"#;
+    #[test]
+    fn test_config_format_detection() {
+        assert_eq!(ConfigFormat::from_path("config.yaml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("config.yml"), ConfigFormat::Yaml);
+        assert_eq!(
+            ConfigFormat::from_path("/etc/config.TOML"),
+            ConfigFormat::Toml
+        );
+        assert_eq!(ConfigFormat::from_path("config.json"), ConfigFormat::Json);
+        assert_eq!(ConfigFormat::from_path("config"), ConfigFormat::Yaml);
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            ConfigFormat::from_content_type(&headers),
+            ConfigFormat::Yaml
+        );
+        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+        assert_eq!(
+            ConfigFormat::from_content_type(&headers),
+            ConfigFormat::Json
+        );
+        headers.insert(CONTENT_TYPE, "application/toml".parse().unwrap());
+        assert_eq!(
+            ConfigFormat::from_content_type(&headers),
+            ConfigFormat::Toml
+        );
+    }
+    #[test]
+    fn test_parse_equivalent_config_in_all_formats() {
+        let parse = |content: &str, format: ConfigFormat| {
+            let api_services: Vec<ApiServiceVistor> = from_str_with_env(content, format).unwrap();
+            serde_json::to_value(api_services).unwrap()
+        };
+        let yaml_config = parse(YAML_CONFIG, ConfigFormat::Yaml);
+        assert_eq!(yaml_config, parse(TOML_CONFIG, ConfigFormat::Toml));
+        assert_eq!(yaml_config, parse(JSON_CONFIG, ConfigFormat::Json));
+        assert_eq!(
+            yaml_config[0]["service_config"]["routes"][0]["route_id"],
+            "orders"
+        );
+    }
+    #[test]
+    fn test_parse_value_error() {
+        let err = ConfigFormat::Toml
+            .parse_value("listen_port = [")
+            .unwrap_err();
+        assert!(err.to_string().contains("Can not parse the config"));
+        assert!(ConfigFormat::Json.parse_value("[{").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::vojo::app_config_vistor::ApiServiceVistor;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{RootSchema, Schema, SchemaObject, SubschemaValidation};
+use schemars::{schema_for, JsonSchema};
+use serde_json::json;
+
+pub fn handle_schema_command() -> i32 {
+    match serde_json::to_string_pretty(&config_schema()) {
+        Ok(schema) => {
+            println!("{}", schema);
+            0
+        }
+        Err(err) => {
+            eprintln!("Can not generate the config schema,the error is {}!", err);
+            1
+        }
+    }
+}
+/**
+ *The config file is a list of the api services.
+ */
+pub fn config_schema() -> RootSchema {
+    let mut schema = schema_for!(Vec<ApiServiceVistor>);
+    schema.schema.metadata().title = Some(String::from("AppConfig"));
+    schema
+}
+/**
+ *The schema of a typetag implementation, which is tagged by the `type` field with the type name.
+ */
+pub fn tagged_schema<T: JsonSchema>(gen: &mut SchemaGenerator, type_name: &str) -> Schema {
+    let mut schema = T::json_schema(gen).into_object();
+    let object = schema.object();
+    object.properties.insert(
+        String::from("type"),
+        Schema::Object(SchemaObject {
+            const_value: Some(json!(type_name)),
+            ..Default::default()
+        }),
+    );
+    object.required.insert(String::from("type"));
+    Schema::Object(schema)
+}
+pub fn one_of_schema(schemas: Vec<Schema>) -> Schema {
+    Schema::Object(SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            one_of: Some(schemas),
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonschema::JSONSchema;
+    use serde_json::Value;
+    use std::env;
+
+    fn compile_schema() -> JSONSchema {
+        let schema = serde_json::to_value(config_schema()).unwrap();
+        JSONSchema::compile(&schema).unwrap()
+    }
+    fn yaml_to_json(content: &str) -> Value {
+        serde_yaml::from_str(content).unwrap()
+    }
+    #[test]
+    fn test_schema_validates_bundled_config() {
+        let content = std::fs::read_to_string(
+            env::current_dir()
+                .unwrap()
+                .join("config")
+                .join("app_config.yaml"),
+        )
+        .unwrap();
+        let schema = compile_schema();
+        assert!(schema.is_valid(&yaml_to_json(&content)));
+    }
+    #[test]
+    fn test_schema_rejects_invalid_config() {
+        let schema = compile_schema();
+        let unknown_server_type = r#"
+- listen_port: 4486
+  service_config:
+    server_type: Ftp
+    routes: []
+"#;
+        assert!(!schema.is_valid(&yaml_to_json(unknown_server_type)));
+        let invalid_port = r#"
+- listen_port: abc
+  service_config:
+    server_type: Http
+    routes: []
+"#;
+        assert!(!schema.is_valid(&yaml_to_json(invalid_port)));
+        let unknown_ratelimit = r#"
+- listen_port: 4486
+  service_config:
+    server_type: Http
+    routes:
+    - route_cluster:
+        type: RandomRoute
+        routes: []
+      ratelimit:
+        type: UnknownRateLimit
+"#;
+        assert!(!schema.is_valid(&yaml_to_json(unknown_ratelimit)));
+        let valid_ratelimit = r#"
+- listen_port: 4486
+  service_config:
+    server_type: Http
+    routes:
+    - route_cluster:
+        type: RandomRoute
+        routes: []
+      ratelimit:
+        type: TokenBucketRateLimit
+        rate_per_unit: 3
+        unit:
+          type: Second
+        capacity: 10
+        limit_location:
+          type: IP
+          value: 192.168.0.1
+"#;
+        assert!(schema.is_valid(&yaml_to_json(valid_ratelimit)));
+    }
+}
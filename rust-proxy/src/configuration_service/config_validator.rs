@@ -0,0 +1,426 @@
+use crate::configuration_service::config_format::ConfigFormat;
+use crate::configuration_service::env_interpolation::from_str_with_env;
+use crate::constants::common_constants::ENV_CONFIG_FILE_PATH;
+use crate::constants::common_constants::UPSTREAM_CHECK_TIMEOUT_MILLIS;
+use crate::vojo::app_config::Matcher;
+use crate::vojo::app_config::PathMatchType;
+use crate::vojo::app_config::ServiceType;
+use crate::vojo::app_config_vistor::from_loadbalancer_strategy_vistor;
+use crate::vojo::app_config_vistor::ApiServiceVistor;
+use crate::vojo::app_config_vistor::RouteVistor;
+use crate::vojo::app_config_vistor::ServiceConfigVistor;
+use crate::vojo::rate_limit::FixedWindowRateLimit;
+use crate::vojo::rate_limit::RatelimitStrategy;
+use crate::vojo::rate_limit::TokenBucketRateLimit;
+use futures::future::join_all;
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::env;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use url::Url;
+
+pub async fn handle_validate_command(
+    config_file_path: Option<String>,
+    check_upstreams: bool,
+) -> i32 {
+    let Some(config_file_path) = config_file_path.or(env::var(ENV_CONFIG_FILE_PATH).ok()) else {
+        eprintln!("The config file path is not set!");
+        return 1;
+    };
+    let content = match tokio::fs::read_to_string(&config_file_path).await {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!(
+                "Can not read the config file {},the error is {}!",
+                config_file_path, err
+            );
+            return 1;
+        }
+    };
+    let format = ConfigFormat::from_path(&config_file_path);
+    let errors = validate_config(&content, format).await;
+    if check_upstreams && errors.is_empty() {
+        for warning in check_upstream_reachable(&content, format).await {
+            eprintln!("warning: {}", warning);
+        }
+    }
+    if errors.is_empty() {
+        println!("The config file {} is valid.", config_file_path);
+        return 0;
+    }
+    for error in errors.iter() {
+        eprintln!("{}", error);
+    }
+    1
+}
+pub async fn validate_config(content: &str, format: ConfigFormat) -> Vec<String> {
+    let api_services: Vec<ApiServiceVistor> = match from_str_with_env(content, format) {
+        Ok(api_services) => api_services,
+        Err(err) => return vec![err.to_string()],
+    };
+    let mut errors = vec![];
+    let mut listen_ports = HashSet::new();
+    for api_service in api_services.iter() {
+        let port = api_service.listen_port;
+        if !listen_ports.insert(port) {
+            errors.push(format!("The listen port {} is duplicated!", port));
+        }
+        errors.extend(validate_service(port, &api_service.service_config).await);
+    }
+    errors
+}
+/**
+ *Check the config of one port,which is shared by the validate command and the reload endpoints.
+ */
+pub async fn validate_service(port: i32, service_config: &ServiceConfigVistor) -> Vec<String> {
+    let mut errors = vec![];
+    if (service_config.server_type == ServiceType::Https
+        || service_config.server_type == ServiceType::Http2Tls)
+        && (service_config.cert_str.is_none() || service_config.key_str.is_none())
+    {
+        errors.push(format!(
+            "The port {} uses {} but the cert_str or key_str is missing!",
+            port, service_config.server_type
+        ));
+    }
+    for route in service_config.routes.iter() {
+        validate_route(port, route, &mut errors).await;
+    }
+    errors
+}
+async fn validate_route(port: i32, route: &RouteVistor, errors: &mut Vec<String>) {
+    let route_id = &route.route_id;
+    let mut route_cluster = from_loadbalancer_strategy_vistor(route.route_cluster.clone());
+    match route_cluster.get_all_route().await {
+        Ok(base_routes) if base_routes.is_empty() => errors.push(format!(
+            "The route {} on the port {} has no upstream!",
+            route_id, port
+        )),
+        Ok(base_routes) => {
+            if base_routes
+                .iter()
+                .any(|item| item.endpoint.trim().is_empty())
+            {
+                errors.push(format!(
+                    "The route {} on the port {} has an empty endpoint!",
+                    route_id, port
+                ));
+            }
+        }
+        Err(err) => errors.push(format!(
+            "The route {} on the port {} is invalid,the error is {}!",
+            route_id, port, err
+        )),
+    }
+    if let Some(matcher) = &route.matcher {
+        for err in validate_matcher(matcher) {
+            errors.push(format!(
+                "The route {} on the port {} has an invalid regex,the error is {}!",
+                route_id, port, err
+            ));
+        }
+    }
+    for allow_deny_object in route.allow_deny_list.iter().flatten() {
+        if let Err(err) = allow_deny_object.validate() {
+            errors.push(format!(
+                "The route {} on the port {} has an invalid allow_deny_list,the error is {}!",
+                route_id, port, err
+            ));
+        }
+    }
+    if let Some(Err(err)) = route.client_ip.as_ref().map(|item| item.validate()) {
+        errors.push(format!(
+            "The route {} on the port {} has invalid trusted_proxies,the error is {}!",
+            route_id, port, err
+        ));
+    }
+    let ratelimits = route.ratelimit.iter().chain(
+        route
+            .combined_limit
+            .iter()
+            .flat_map(|item| item.ratelimit.iter()),
+    );
+    let limit_status_code = route
+        .combined_limit
+        .as_ref()
+        .and_then(|item| item.response.as_ref())
+        .and_then(|item| item.status_code);
+    if let Some(status_code) = limit_status_code.filter(|item| !(400..=599).contains(item)) {
+        errors.push(format!(
+            "The route {} on the port {} has an invalid ratelimit response status {}!",
+            route_id, port, status_code
+        ));
+    }
+    for ratelimit in ratelimits {
+        if get_rate_per_unit(ratelimit.as_ref()) == Some(0) {
+            errors.push(format!(
+                "The route {} on the port {} has a ratelimit with zero rate_per_unit!",
+                route_id, port
+            ));
+        }
+    }
+}
+/**
+ *Try to connect every distinct endpoint, the unreachable ones are returned as warnings.
+ */
+pub async fn check_upstream_reachable(content: &str, format: ConfigFormat) -> Vec<String> {
+    let api_services: Vec<ApiServiceVistor> = match from_str_with_env(content, format) {
+        Ok(api_services) => api_services,
+        Err(err) => return vec![err.to_string()],
+    };
+    let mut endpoints = BTreeSet::new();
+    for route in api_services
+        .iter()
+        .flat_map(|item| item.service_config.routes.iter())
+    {
+        let mut route_cluster = from_loadbalancer_strategy_vistor(route.route_cluster.clone());
+        if let Ok(base_routes) = route_cluster.get_all_route().await {
+            endpoints.extend(base_routes.into_iter().map(|item| item.endpoint));
+        }
+    }
+    let checks = endpoints.into_iter().filter_map(|endpoint| {
+        let address = endpoint_address(&endpoint)?;
+        Some(async move {
+            let result = timeout(
+                Duration::from_millis(UPSTREAM_CHECK_TIMEOUT_MILLIS),
+                TcpStream::connect(&address),
+            )
+            .await;
+            match result {
+                Ok(Ok(_)) => None,
+                Ok(Err(err)) => Some(format!(
+                    "The upstream {} is unreachable,the error is {}!",
+                    endpoint, err
+                )),
+                Err(_) => Some(format!(
+                    "The upstream {} is unreachable,the connection timed out!",
+                    endpoint
+                )),
+            }
+        })
+    });
+    join_all(checks).await.into_iter().flatten().collect()
+}
+/**
+ *The endpoint is an url or the `host:port` of the tcp proxy, the static file directory has no address.
+ */
+fn endpoint_address(endpoint: &str) -> Option<String> {
+    if !endpoint.contains("://") {
+        let (host, port) = endpoint.rsplit_once(':')?;
+        port.parse::<u16>().ok()?;
+        return Some(format!("{}:{}", host, port));
+    }
+    let url = Url::parse(endpoint).ok()?;
+    let port = url.port_or_known_default().or(match url.scheme() {
+        "grpc" => Some(80),
+        "grpcs" => Some(443),
+        _ => None,
+    })?;
+    Some(format!("{}:{}", url.host_str()?, port))
+}
+fn validate_matcher(matcher: &Matcher) -> Vec<String> {
+    if matcher.match_type != PathMatchType::Regex {
+        return vec![];
+    }
+    std::iter::once(&matcher.prefix)
+        .chain(matcher.paths.iter().flatten())
+        .filter_map(|pattern| Regex::new(pattern).err())
+        .map(|err| err.to_string())
+        .collect()
+}
+fn get_rate_per_unit(ratelimit: &dyn RatelimitStrategy) -> Option<u128> {
+    if let Some(token_bucket) = ratelimit.as_any().downcast_ref::<TokenBucketRateLimit>() {
+        return Some(token_bucket.rate_per_unit);
+    }
+    ratelimit
+        .as_any()
+        .downcast_ref::<FixedWindowRateLimit>()
+        .map(|item| item.rate_per_unit)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::app_config_vistor::new_uuid;
+    async fn write_config_file(content: &str) -> String {
+        let path = env::temp_dir().join(format!("silverwind-{}.yaml", new_uuid()));
+        tokio::fs::write(&path, content).await.unwrap();
+        path.to_string_lossy().to_string()
+    }
+    const ROUTE_CLUSTER: &str = r#"
+        route_cluster:
+          type: PollRoute
+          routes:
+            - base_route:
+                endpoint: http://127.0.0.1:9001"#;
+    #[tokio::test]
+    async fn test_validate_config_ok() {
+        let content = format!(
+            r#"
+- listen_port: 10080
+  service_config:
+    server_type: Http
+    routes:
+      - route_id: route1
+        matcher:
+          prefix: /
+          prefix_rewrite: /{}
+"#,
+            ROUTE_CLUSTER
+        );
+        assert!(validate_config(&content, ConfigFormat::Yaml)
+            .await
+            .is_empty());
+    }
+    #[tokio::test]
+    async fn test_validate_config_collects_all_errors() {
+        let content = format!(
+            r#"
+- listen_port: 10080
+  service_config:
+    server_type: Http
+    routes:
+      - route_id: empty_route
+        matcher:
+          prefix: /
+          prefix_rewrite: /
+        route_cluster:
+          type: PollRoute
+          routes: []
+      - route_id: regex_route
+        matcher:
+          prefix: "/api/(["
+          prefix_rewrite: /
+          match_type: Regex{}
+      - route_id: ratelimit_route
+        matcher:
+          prefix: /
+          prefix_rewrite: /
+        ratelimit:
+          type: TokenBucketRateLimit
+          rate_per_unit: 0
+          unit:
+            type: Second
+          capacity: 10
+          limit_location:
+            type: IP
+            value: 127.0.0.1{}
+      - route_id: limit_response_route
+        matcher:
+          prefix: /
+          prefix_rewrite: /
+        combined_limit:
+          response:
+            status_code: 200{}
+      - route_id: allow_deny_route
+        matcher:
+          prefix: /
+          prefix_rewrite: /
+        allow_deny_list:
+          - limit_type: Allow
+            value: 10.0.0.0/33
+          - limit_type: Deny
+            value: 10.0.0.50-10.0.0.1
+        client_ip:
+          use_forwarded_proto: true
+          trusted_proxies:
+            - 10.0.0.1-10.0.0.a{}
+- listen_port: 10080
+  service_config:
+    server_type: Https
+    routes: []
+"#,
+            ROUTE_CLUSTER, ROUTE_CLUSTER, ROUTE_CLUSTER, ROUTE_CLUSTER
+        );
+        let errors = validate_config(&content, ConfigFormat::Yaml).await;
+        assert_eq!(errors.len(), 9);
+        assert!(errors[0].contains("empty_route") && errors[0].contains("no upstream"));
+        assert!(errors[1].contains("regex_route") && errors[1].contains("invalid regex"));
+        assert!(errors[2].contains("ratelimit_route") && errors[2].contains("rate_per_unit"));
+        assert!(errors[3].contains("limit_response_route") && errors[3].contains("200"));
+        assert!(errors[4].contains("allow_deny_route") && errors[4].contains("10.0.0.0/33"));
+        assert!(errors[5].contains("allow_deny_route") && errors[5].contains("10.0.0.50-10.0.0.1"));
+        assert!(errors[6].contains("allow_deny_route") && errors[6].contains("trusted_proxies"));
+        assert!(errors[7].contains("10080") && errors[7].contains("duplicated"));
+        assert!(errors[8].contains("10080") && errors[8].contains("cert_str"));
+    }
+    #[tokio::test]
+    async fn test_validate_config_malformed_yaml() {
+        let errors = validate_config("- listen_port: [", ConfigFormat::Yaml).await;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Can not parse the config"));
+    }
+    #[tokio::test]
+    async fn test_handle_validate_command_exit_code() {
+        let path = write_config_file(
+            "- listen_port: 10080\n  service_config:\n    server_type: Https\n    routes: []\n",
+        )
+        .await;
+        assert_eq!(handle_validate_command(Some(path.clone()), false).await, 1);
+        tokio::fs::remove_file(path).await.unwrap();
+
+        let path = write_config_file(
+            "- listen_port: 10080\n  service_config:\n    server_type: Http\n    routes: []\n",
+        )
+        .await;
+        assert_eq!(handle_validate_command(Some(path.clone()), false).await, 0);
+        tokio::fs::remove_file(path).await.unwrap();
+        assert_eq!(
+            handle_validate_command(Some(String::from("/not/exist/config.yaml")), false).await,
+            1
+        );
+    }
+    #[test]
+    fn test_endpoint_address() {
+        for (endpoint, address) in [
+            ("http://127.0.0.1:9001", Some("127.0.0.1:9001")),
+            ("http://localhost/api", Some("localhost:80")),
+            ("https://example.com", Some("example.com:443")),
+            ("grpc://backend", Some("backend:80")),
+            ("grpcs://backend:50051", Some("backend:50051")),
+            ("http://[::1]:8080", Some("[::1]:8080")),
+            ("httpbin.org:80", Some("httpbin.org:80")),
+            ("config", None),
+        ] {
+            assert_eq!(
+                endpoint_address(endpoint).as_deref(),
+                address,
+                "{}",
+                endpoint
+            );
+        }
+    }
+    #[tokio::test]
+    async fn test_check_upstream_reachable_warns_unreachable_endpoint() {
+        let _listener = tokio::net::TcpListener::bind("127.0.0.1:10131")
+            .await
+            .unwrap();
+        let content = r#"
+- listen_port: 10080
+  service_config:
+    server_type: Http
+    routes:
+      - route_id: route1
+        route_cluster:
+          type: PollRoute
+          routes:
+            - base_route:
+                endpoint: http://127.0.0.1:10131
+            - base_route:
+                endpoint: http://127.0.0.1:10132
+            - base_route:
+                endpoint: http://127.0.0.1:10131/other
+"#;
+        let warnings = check_upstream_reachable(content, ConfigFormat::Yaml).await;
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].contains("http://127.0.0.1:10132") && warnings[0].contains("unreachable")
+        );
+
+        let path = write_config_file(content).await;
+        assert_eq!(handle_validate_command(Some(path.clone()), true).await, 0);
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+}
@@ -0,0 +1,181 @@
+use crate::configuration_service::config_format::ConfigFormat;
+use crate::vojo::app_error::{AppError, AppErrorKind};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use regex::Captures;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_yaml::Value;
+use std::env;
+
+lazy_static! {
+    static ref ENV_VAR_REGEX: Regex =
+        Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").unwrap();
+    static ref INTERPOLATED_VALUES: DashMap<String, String> = DashMap::new();
+}
+pub fn interpolate_str(value: &str) -> Result<String, AppError> {
+    let mut missing_vars = vec![];
+    let res = ENV_VAR_REGEX.replace_all(value, |caps: &Captures| {
+        match (env::var(&caps[1]), caps.get(2)) {
+            (Ok(env_value), _) => env_value,
+            (Err(_), Some(default_value)) => default_value.as_str().to_string(),
+            (Err(_), None) => {
+                missing_vars.push(caps[1].to_string());
+                String::new()
+            }
+        }
+    });
+    if !missing_vars.is_empty() {
+        return Err(AppError::new(
+            AppErrorKind::Config,
+            format!(
+                "The environment variable {} is not set!",
+                missing_vars.join(",")
+            ),
+        ));
+    }
+    Ok(res.to_string())
+}
+fn interpolate_value(value: &mut Value) -> Result<(), AppError> {
+    match value {
+        Value::String(item) if ENV_VAR_REGEX.is_match(item) => {
+            let placeholder = item.clone();
+            *item = interpolate_str(item)?;
+            INTERPOLATED_VALUES.insert(item.clone(), placeholder);
+        }
+        Value::Sequence(items) => {
+            for item in items.iter_mut() {
+                interpolate_value(item)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                interpolate_value(item)?;
+            }
+        }
+        Value::Tagged(tagged) => interpolate_value(&mut tagged.value)?,
+        _ => {}
+    }
+    Ok(())
+}
+fn restore_placeholders(value: &mut Value) {
+    match value {
+        Value::String(item) => {
+            if let Some(placeholder) = INTERPOLATED_VALUES.get(item.as_str()) {
+                *item = placeholder.clone();
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(restore_placeholders),
+        Value::Mapping(mapping) => mapping
+            .iter_mut()
+            .for_each(|(_, item)| restore_placeholders(item)),
+        Value::Tagged(tagged) => restore_placeholders(&mut tagged.value),
+        _ => {}
+    }
+}
+/**
+*The interpolated strings are written back as their `${VAR}` text,so the values of the environment are not exposed.
+*/
+pub fn to_value_with_placeholders<T: Serialize>(value: &T) -> Result<Value, AppError> {
+    let mut value = serde_yaml::to_value(value).map_err(|e| AppError::from(e.to_string()))?;
+    restore_placeholders(&mut value);
+    Ok(value)
+}
+/**
+*Only the string values are interpolated, so `${VAR}` can not inject config structure.
+*/
+pub fn from_str_with_env<T: DeserializeOwned>(
+    content: &str,
+    format: ConfigFormat,
+) -> Result<T, AppError> {
+    let mut value = format.parse_value(content)?;
+    interpolate_value(&mut value)?;
+    serde_yaml::from_value(value).map_err(|e| {
+        AppError::new(
+            AppErrorKind::Config,
+            format!("Can not parse the config, the error is {}!", e),
+        )
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct TestConfig {
+        secret: String,
+        endpoints: Vec<String>,
+        port: i32,
+    }
+    #[test]
+    fn test_interpolate_str() {
+        env::set_var("SILVERWIND_TEST_HOST", "backend.local");
+        assert_eq!(
+            interpolate_str("http://${SILVERWIND_TEST_HOST}:8080").unwrap(),
+            "http://backend.local:8080"
+        );
+        assert_eq!(
+            interpolate_str("${SILVERWIND_TEST_UNSET:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(
+            interpolate_str("${SILVERWIND_TEST_HOST:-fallback}").unwrap(),
+            "backend.local"
+        );
+        assert_eq!(interpolate_str("plain $HOME").unwrap(), "plain $HOME");
+        let err = interpolate_str("${SILVERWIND_TEST_UNSET}").unwrap_err();
+        assert!(err.to_string().contains("SILVERWIND_TEST_UNSET"));
+    }
+    #[test]
+    fn test_from_yaml_with_env() {
+        env::set_var("SILVERWIND_TEST_SECRET", "s3cr3t\nport: 1");
+        let config: TestConfig = from_str_with_env(
+            r#"
+secret: ${SILVERWIND_TEST_SECRET}
+endpoints:
+  - http://${SILVERWIND_TEST_UPSTREAM:-127.0.0.1}:9000
+port: 8080
+"#,
+            ConfigFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            TestConfig {
+                secret: String::from("s3cr3t\nport: 1"),
+                endpoints: vec![String::from("http://127.0.0.1:9000")],
+                port: 8080,
+            }
+        );
+        let err = from_str_with_env::<TestConfig>(
+            "secret: ${SILVERWIND_TEST_MISSING}\nendpoints: []\nport: 1",
+            ConfigFormat::Yaml,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("SILVERWIND_TEST_MISSING"));
+    }
+    #[test]
+    fn test_to_value_with_placeholders() {
+        env::set_var("SILVERWIND_TEST_TOKEN", "t0ken-value");
+        let content = r#"
+secret: ${SILVERWIND_TEST_TOKEN}
+endpoints:
+  - http://${SILVERWIND_TEST_BACKEND:-127.0.0.2}:9000
+  - http://127.0.0.3:9000
+port: 8080
+"#;
+        let config: TestConfig = from_str_with_env(content, ConfigFormat::Yaml).unwrap();
+        assert_eq!(config.secret, "t0ken-value");
+        let value = to_value_with_placeholders(&config).unwrap();
+        assert_eq!(
+            value,
+            serde_yaml::from_str::<Value>(content).unwrap(),
+            "{:?}",
+            value
+        );
+        assert!(!serde_yaml::to_string(&value)
+            .unwrap()
+            .contains("t0ken-value"));
+    }
+}
@@ -1,4 +1,8 @@
+use crate::vojo::app_config::LogLevel;
+use crate::vojo::app_error::AppError;
 use env_logger::{Env, Logger as Logger2};
+use lazy_static::lazy_static;
+use log::LevelFilter;
 use log4rs::append::console::ConsoleAppender;
 use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
 use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
@@ -6,9 +10,32 @@ use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
 use log4rs::append::rolling_file::RollingFileAppender;
 use log4rs::config::{Appender, Config, Logger, Root};
 use log4rs::encode::pattern::PatternEncoder;
+use log4rs::Handle;
+use std::sync::Mutex;
+lazy_static! {
+    static ref LOGGER_HANDLE: Mutex<Option<Handle>> = Mutex::new(None);
+}
 pub fn start_logger() {
     let env = Env::new().filter_or("RUST_LOG", "info");
     let level_filter = Logger2::from_env(env).filter();
+    let config = build_config(level_filter).unwrap();
+    if let Ok(handle) = log4rs::init_config(config) {
+        *LOGGER_HANDLE.lock().unwrap() = Some(handle);
+    }
+}
+pub fn set_log_level(log_level: LogLevel) -> Result<(), AppError> {
+    let level_filter = LevelFilter::from(log_level);
+    if let Some(handle) = LOGGER_HANDLE
+        .lock()
+        .map_err(|e| AppError::from(e.to_string()))?
+        .as_ref()
+    {
+        handle.set_config(build_config(level_filter)?);
+    }
+    log::set_max_level(level_filter);
+    Ok(())
+}
+fn build_config(level_filter: LevelFilter) -> Result<Config, AppError> {
     let stdout = ConsoleAppender::builder()
         .encoder(Box::new(PatternEncoder::new(
             "{d(%Y-%m-%d %H:%M:%S)(local)} - {h({l})}: {m}{n}",
@@ -18,7 +45,7 @@ pub fn start_logger() {
     let window_size = 10;
     let fixed_window_roller = FixedWindowRoller::builder()
         .build("log/app-{}", window_size)
-        .unwrap();
+        .map_err(|e| AppError::from(e.to_string()))?;
 
     let size_limit = 10 * 1024 * 1024;
     let size_trigger = SizeTrigger::new(size_limit);
@@ -34,13 +61,13 @@ pub fn start_logger() {
             "{d(%Y-%m-%d %H:%M:%S)(local)} - {h({l})}$${m}{n}",
         )))
         .build("log/app.log", Box::new(compound_policy1))
-        .unwrap();
+        .map_err(|e| AppError::from(e.to_string()))?;
     let common = RollingFileAppender::builder()
         .encoder(Box::new(PatternEncoder::new(
             "{d(%Y-%m-%d %H:%M:%S)(local)} - {h({l})}$${m}{n}",
         )))
         .build("log/common.log", Box::new(compound_policy2))
-        .unwrap();
+        .map_err(|e| AppError::from(e.to_string()))?;
 
     let config = Config::builder()
         .appender(Appender::builder().build("stdout", Box::new(stdout)))
@@ -59,11 +86,8 @@ pub fn start_logger() {
                 .appender("common")
                 .build(level_filter),
         )
-        .unwrap();
-
-    let _handle = log4rs::init_config(config);
-
-    // use handle to change logger configuration at runtime
+        .map_err(|e| AppError::from(e.to_string()))?;
+    Ok(config)
 }
 #[cfg(test)]
 mod tests {
@@ -1,2 +1,9 @@
 pub mod app_config_service;
+pub mod config_diff;
+pub mod config_format;
+pub mod config_schema;
+pub mod config_validator;
+pub mod env_interpolation;
 pub mod logger;
+pub mod readiness;
+pub mod route_tester;
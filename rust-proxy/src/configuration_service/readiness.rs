@@ -0,0 +1,85 @@
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+lazy_static! {
+    static ref CONFIG_LOADED: AtomicBool = AtomicBool::new(false);
+    static ref GLOBAL_LISTENER_STATE: DashMap<String, ListenerState> = Default::default();
+}
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum ListenerState {
+    Starting,
+    Bound,
+    Failed,
+}
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub config_loaded: bool,
+    pub listeners: BTreeMap<String, ListenerState>,
+}
+pub fn mark_config_loaded() {
+    CONFIG_LOADED.store(true, Ordering::SeqCst);
+}
+pub fn mark_listener_starting(mapping_key: &str) {
+    GLOBAL_LISTENER_STATE.insert(mapping_key.to_string(), ListenerState::Starting);
+}
+/**
+ *Only the listeners started from the config mapping are tracked, so a removed listener is not added back.
+ */
+fn update_listener_state(mapping_key: &str, listener_state: ListenerState) {
+    if let Some(mut item) = GLOBAL_LISTENER_STATE.get_mut(mapping_key) {
+        *item = listener_state;
+    }
+}
+pub fn mark_listener_bound(mapping_key: &str) {
+    update_listener_state(mapping_key, ListenerState::Bound);
+}
+pub fn mark_listener_failed(mapping_key: &str) {
+    update_listener_state(mapping_key, ListenerState::Failed);
+}
+pub fn remove_listener_state(mapping_key: &str) {
+    GLOBAL_LISTENER_STATE.remove(mapping_key);
+}
+fn build_readiness_report(
+    config_loaded: bool,
+    listener_states: &DashMap<String, ListenerState>,
+) -> ReadinessReport {
+    let listeners = listener_states
+        .iter()
+        .map(|item| (item.key().clone(), *item.value()))
+        .collect::<BTreeMap<String, ListenerState>>();
+    ReadinessReport {
+        ready: config_loaded && listeners.values().all(|item| *item == ListenerState::Bound),
+        config_loaded,
+        listeners,
+    }
+}
+pub fn get_readiness_report() -> ReadinessReport {
+    build_readiness_report(CONFIG_LOADED.load(Ordering::SeqCst), &GLOBAL_LISTENER_STATE)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_build_readiness_report() {
+        let listener_states = DashMap::new();
+        assert!(!build_readiness_report(false, &listener_states).ready);
+        assert!(build_readiness_report(true, &listener_states).ready);
+        listener_states.insert(String::from("8080-Http"), ListenerState::Starting);
+        listener_states.insert(String::from("8443-Https"), ListenerState::Bound);
+        let report = build_readiness_report(true, &listener_states);
+        assert!(!report.ready);
+        assert_eq!(
+            report.listeners.get("8080-Http"),
+            Some(&ListenerState::Starting)
+        );
+        listener_states.insert(String::from("8080-Http"), ListenerState::Failed);
+        assert!(!build_readiness_report(true, &listener_states).ready);
+        listener_states.insert(String::from("8080-Http"), ListenerState::Bound);
+        assert!(build_readiness_report(true, &listener_states).ready);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::configuration_service::app_config_service::parse_api_services;
+use crate::configuration_service::config_format::ConfigFormat;
+use crate::constants::common_constants::ENV_CONFIG_FILE_PATH;
+use crate::proxy::proxy_trait::match_request;
+use crate::vojo::app_error::AppError;
+use http::header::HeaderName;
+use http::HeaderMap;
+use http::HeaderValue;
+use http::Method;
+use std::env;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteTestRequest {
+    pub method: Method,
+    pub path: String,
+    pub headers: HeaderMap,
+    pub port: Option<i32>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteTestResult {
+    pub listen_port: i32,
+    pub route_id: String,
+    pub endpoint: String,
+    pub request_path: String,
+}
+impl RouteTestRequest {
+    /**
+     *Parse `--method GET --path /api --header Host:example.com --port 8080`, the other argument is the config file.
+     */
+    pub fn parse(args: &[String]) -> Result<(Option<String>, Self), AppError> {
+        let mut config_file_path = None;
+        let mut request = RouteTestRequest {
+            method: Method::GET,
+            path: String::from("/"),
+            headers: HeaderMap::new(),
+            port: None,
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if !arg.starts_with("--") {
+                config_file_path = Some(arg.clone());
+                continue;
+            }
+            let value = iter
+                .next()
+                .ok_or(AppError::from(format!("The value of {} is missing!", arg)))?;
+            match arg.as_str() {
+                "--method" => {
+                    request.method = Method::from_bytes(value.to_ascii_uppercase().as_bytes())
+                        .map_err(|e| AppError::from(e.to_string()))?
+                }
+                "--path" => request.path = value.clone(),
+                "--header" => {
+                    let (name, header_value) = value.split_once(':').ok_or(AppError::from(
+                        format!("The header {} should be like name:value!", value),
+                    ))?;
+                    request.headers.append(
+                        HeaderName::from_bytes(name.trim().as_bytes())
+                            .map_err(|e| AppError::from(e.to_string()))?,
+                        HeaderValue::from_str(header_value.trim())
+                            .map_err(|e| AppError::from(e.to_string()))?,
+                    );
+                }
+                "--port" => {
+                    request.port =
+                        Some(value.parse().map_err(|_| {
+                            AppError::from(format!("The port {} is invalid!", value))
+                        })?)
+                }
+                _ => return Err(AppError::from(format!("Unknown option {}!", arg))),
+            }
+        }
+        Ok((config_file_path, request))
+    }
+}
+impl std::fmt::Display for RouteTestResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "port: {}", self.listen_port)?;
+        writeln!(f, "route_id: {}", self.route_id)?;
+        writeln!(f, "endpoint: {}", self.endpoint)?;
+        write!(f, "rewritten: {}", self.request_path)
+    }
+}
+pub async fn handle_test_command(args: &[String]) -> i32 {
+    let result = async {
+        let (config_file_path, request) = RouteTestRequest::parse(args)?;
+        let config_file_path = config_file_path
+            .or(env::var(ENV_CONFIG_FILE_PATH).ok())
+            .ok_or(AppError::from(String::from(
+                "The config file path is not set!",
+            )))?;
+        let content = tokio::fs::read_to_string(&config_file_path)
+            .await
+            .map_err(|e| {
+                AppError::from(format!(
+                    "Can not read the config file {},the error is {}!",
+                    config_file_path, e
+                ))
+            })?;
+        test_route(
+            &content,
+            ConfigFormat::from_path(&config_file_path),
+            &request,
+        )
+        .await
+    }
+    .await;
+    match result {
+        Ok(Some(route_test_result)) => {
+            println!("{}", route_test_result);
+            0
+        }
+        Ok(None) => {
+            println!("no match");
+            1
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            2
+        }
+    }
+}
+/**
+ *Run the matcher and the load balancer of the proxy without sending the request to the upstream.
+ */
+pub async fn test_route(
+    content: &str,
+    format: ConfigFormat,
+    request: &RouteTestRequest,
+) -> Result<Option<RouteTestResult>, AppError> {
+    let api_services = parse_api_services(content, format).await?;
+    let peer_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+    for api_service in api_services
+        .into_iter()
+        .filter(|item| request.port.is_none_or(|port| port == item.listen_port))
+    {
+        let check_result = match_request(
+            api_service.service_config.routes,
+            &api_service.service_config.server_type,
+            request.method.clone(),
+            request.headers.clone(),
+            request.path.clone(),
+            peer_addr,
+        )
+        .await?;
+        if let Some(check_result) = check_result {
+            return Ok(Some(RouteTestResult {
+                listen_port: api_service.listen_port,
+                route_id: check_result.route.route_id,
+                endpoint: check_result.base_route.endpoint,
+                request_path: check_result.request_path,
+            }));
+        }
+    }
+    Ok(None)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::app_config_vistor::new_uuid;
+    const CONFIG: &str = r#"
+- listen_port: 10080
+  service_config:
+    server_type: Http
+    routes:
+      - route_id: api_route
+        host_name: example.com
+        matcher:
+          prefix: /api/
+          prefix_rewrite: /v1/
+          methods:
+            - GET
+        route_cluster:
+          type: PollRoute
+          routes:
+            - base_route:
+                endpoint: http://127.0.0.1:9001
+      - route_id: static_route
+        matcher:
+          prefix: /static
+          prefix_rewrite: /
+        route_cluster:
+          type: RandomRoute
+          routes:
+            - base_route:
+                endpoint: config
+"#;
+    fn parse_request(args: &[&str]) -> RouteTestRequest {
+        let args = args.iter().map(|item| item.to_string()).collect::<Vec<_>>();
+        RouteTestRequest::parse(&args).unwrap().1
+    }
+    #[tokio::test]
+    async fn test_route_matched() {
+        let request = parse_request(&[
+            "--method",
+            "get",
+            "--path",
+            "/api/foo?a=1",
+            "--header",
+            "Host: example.com",
+        ]);
+        let result = test_route(CONFIG, ConfigFormat::Yaml, &request)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.listen_port, 10080);
+        assert_eq!(result.route_id, "api_route");
+        assert_eq!(result.endpoint, "http://127.0.0.1:9001");
+        assert_eq!(result.request_path, "http://127.0.0.1:9001/v1/foo?a=1");
+
+        let request = parse_request(&["--path", "/static/app.js", "--port", "10080"]);
+        let result = test_route(CONFIG, ConfigFormat::Yaml, &request)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.route_id, "static_route");
+        assert_eq!(result.endpoint, "config");
+    }
+    #[tokio::test]
+    async fn test_route_not_matched() {
+        for args in [
+            vec!["--path", "/other"],
+            vec!["--path", "/api/foo", "--header", "Host:other.com"],
+            vec![
+                "--method",
+                "POST",
+                "--path",
+                "/api/foo",
+                "--header",
+                "Host:example.com",
+            ],
+            vec!["--path", "/static/app.js", "--port", "10081"],
+        ] {
+            let request = parse_request(&args);
+            assert_eq!(
+                test_route(CONFIG, ConfigFormat::Yaml, &request)
+                    .await
+                    .unwrap(),
+                None,
+                "{:?}",
+                args
+            );
+        }
+        let args = vec![String::from("--header"), String::from("invalid")];
+        assert!(RouteTestRequest::parse(&args).is_err());
+    }
+    #[tokio::test]
+    async fn test_handle_test_command_exit_code() {
+        let path = env::temp_dir().join(format!("silverwind-{}.yaml", new_uuid()));
+        tokio::fs::write(&path, CONFIG).await.unwrap();
+        let path = path.to_string_lossy().to_string();
+        let args = |request_path: &str| {
+            vec![
+                path.clone(),
+                String::from("--path"),
+                request_path.to_string(),
+            ]
+        };
+        assert_eq!(handle_test_command(&args("/static/app.js")).await, 0);
+        assert_eq!(handle_test_command(&args("/other")).await, 1);
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert_eq!(handle_test_command(&args("/other")).await, 2);
+    }
+}
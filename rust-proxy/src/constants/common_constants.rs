@@ -7,13 +7,45 @@ pub const NOT_FOUND: &str = r#"{
     "response_code": -1,
     "response_object": "The route could not be found in the Proxy!"
 }"#;
+pub const MAINTENANCE_RESPONSE: &str = r#"{
+    "response_code": -1,
+    "response_object": "The route is under maintenance!"
+}"#;
 pub const DEFAULT_FIXEDWINDOW_MAP_SIZE: i32 = 3;
 pub const ENV_ADMIN_PORT: &str = "ADMIN_PORT";
 pub const ENV_DATABASE_URL: &str = "DATABASE_URL";
 pub const ENV_ACCESS_LOG: &str = "ACCESS_LOG";
 pub const ENV_CONFIG_FILE_PATH: &str = "CONFIG_FILE_PATH";
+pub const ENV_BODY_SIZE_BUCKETS: &str = "BODY_SIZE_BUCKETS";
 pub const TIMER_WAIT_SECONDS: u64 = 5;
 pub const DEFAULT_HTTP_TIMEOUT: u64 = 10;
+pub const DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT: u64 = 30;
+pub const DEFAULT_UDP_IDLE_TIMEOUT: u64 = 60;
+pub const DEFAULT_CLIENT_HELLO_TIMEOUT: u64 = 5;
+pub const DEFAULT_UDP_MAX_SESSIONS: usize = 10000;
+pub const UPSTREAM_CHECK_TIMEOUT_MILLIS: u64 = 2000;
+pub const DEFAULT_WEBSOCKET_IDLE_TIMEOUT: u64 = 300;
+pub const DEFAULT_UPSTREAM_IDLE_TIMEOUT: u64 = 4;
+pub const DEFAULT_MIRROR_MAX_BODY_SIZE: u64 = 1024 * 1024;
+pub const DEFAULT_CACHE_MAX_BODY_SIZE: u64 = 1024 * 1024;
+pub const DEFAULT_BODY_LOGGING_MAX_BYTES: usize = 4096;
+pub const MINIMUM_MAX_BUF_SIZE: usize = 8192;
+pub const REDACTED_VALUE: &str = "***";
 pub const DEFAULT_TEMPORARY_DIR: &str = "temporary";
+pub const DEFAULT_INDEX_FILE: &str = "index.html";
+pub const DEFAULT_ACME_CHALLENGE_PORT: u16 = 80;
+pub const ACME_CHALLENGE_PATH_PREFIX: &str = "/.well-known/acme-challenge/";
 pub const GRPC_STATUS_HEADER: &str = "grpc-status";
 pub const GRPC_STATUS_OK: &str = "0";
+pub const GRPC_MESSAGE_HEADER: &str = "grpc-message";
+pub const X_FORWARDED_FOR: &str = "x-forwarded-for";
+pub const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+pub const X_FORWARDED_HOST: &str = "x-forwarded-host";
+pub const MAX_HEDGE_REQUESTS: usize = 3;
+pub const X_RATELIMIT_REASON: &str = "x-ratelimit-reason";
+pub const X_REQUEST_ID: &str = "x-request-id";
+pub const X_CANARY: &str = "x-canary";
+pub const X_CACHE: &str = "x-cache";
+pub const X_HTTP_METHOD_OVERRIDE: &str = "x-http-method-override";
+pub const TRACEPARENT: &str = "traceparent";
+pub const MAX_REQUEST_ID_LENGTH: usize = 128;
@@ -1,14 +1,30 @@
+use crate::configuration_service::app_config_service::diff_mapping_keys;
+use crate::configuration_service::app_config_service::parse_api_services;
+use crate::configuration_service::app_config_service::reload_service_config;
+use crate::configuration_service::app_config_service::update_mapping_from_global_appconfig;
+use crate::configuration_service::app_config_service::MappingDiff;
 use crate::configuration_service::app_config_service::GLOBAL_APP_CONFIG;
+use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
+use crate::configuration_service::config_format::ConfigFormat;
+use crate::configuration_service::config_validator::{validate_config, validate_service};
+use crate::configuration_service::env_interpolation::{
+    from_str_with_env, to_value_with_placeholders,
+};
+use crate::configuration_service::logger::set_log_level;
+use crate::configuration_service::readiness::get_readiness_report;
 use crate::constants::common_constants::DEFAULT_TEMPORARY_DIR;
 use crate::control_plane::lets_encrypt::lets_encrypt_certificate;
 use crate::vojo::app_config::ApiService;
+use crate::vojo::app_config::LogLevel;
 use crate::vojo::app_config::Route;
+use crate::vojo::app_config::ServiceConfig;
 use crate::vojo::app_config::ServiceType;
 use crate::vojo::app_config_vistor::from_api_service;
 use crate::vojo::app_config_vistor::ApiServiceVistor;
 use crate::vojo::app_config_vistor::AppConfigVistor;
 use crate::vojo::app_config_vistor::RouteVistor;
-use crate::vojo::app_error::AppError;
+use crate::vojo::app_config_vistor::ServiceConfigVistor;
+use crate::vojo::app_error::{AppError, AppErrorKind};
 use crate::vojo::base_response::BaseResponse;
 use crate::vojo::route::BaseRoute;
 use axum::response::IntoResponse;
@@ -16,7 +32,9 @@ use axum::routing::delete;
 use axum::routing::{get, post, put};
 use axum::Router;
 use http::header;
+use http::HeaderMap;
 use prometheus::{Encoder, TextEncoder};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::env;
@@ -31,7 +49,9 @@ struct MethodError;
 async fn get_app_config() -> Result<impl axum::response::IntoResponse, Infallible> {
     let app_config = GLOBAL_APP_CONFIG.read().await;
 
-    let app_config_vistor_result = AppConfigVistor::from(app_config.clone()).await;
+    let app_config_vistor_result = AppConfigVistor::from(app_config.clone())
+        .await
+        .and_then(|item| to_value_with_placeholders(&item));
     if app_config_vistor_result.is_err() {
         return Ok((
             axum::http::StatusCode::INTERNAL_SERVER_ERROR,
@@ -91,14 +111,21 @@ async fn post_app_config_with_error(
         .iter_mut()
         .find(|item| item.listen_port == api_service.listen_port)
     {
-        Some(data) => data.service_config.routes.push(
-            api_service
+        Some(data) => {
+            let route = api_service
                 .service_config
                 .routes
                 .first()
-                .ok_or(AppError(String::from("The route is empty!")))?
-                .clone(),
-        ),
+                .ok_or(AppError::from(String::from("The route is empty!")))?
+                .clone();
+            data.service_config
+                .upstream_policy
+                .clone()
+                .unwrap_or_default()
+                .check_route(&route)
+                .await?;
+            data.service_config.routes.push(route)
+        }
         None => rw_global_lock.api_service_config.push(api_service),
     };
     tokio::spawn(async {
@@ -153,16 +180,26 @@ async fn put_route(
 async fn put_route_with_error(route_vistor: RouteVistor) -> Result<String, AppError> {
     let mut rw_global_lock = GLOBAL_APP_CONFIG.write().await;
 
-    let old_route = rw_global_lock
+    let (old_route, upstream_policy) = rw_global_lock
         .api_service_config
-        .iter_mut()
-        .flat_map(|item| item.service_config.routes.clone())
-        .find(|item| item.route_id == route_vistor.route_id)
-        .ok_or(AppError(String::from(
+        .iter()
+        .find_map(|item| {
+            item.service_config
+                .routes
+                .iter()
+                .find(|route| route.route_id == route_vistor.route_id)
+                .map(|route| (route.clone(), item.service_config.upstream_policy.clone()))
+        })
+        .ok_or(AppError::new(
+            AppErrorKind::NotFound,
             "Can not find the route by route id!",
-        )))?;
+        ))?;
 
     let mut new_route = Route::from(route_vistor.clone()).await?;
+    upstream_policy
+        .unwrap_or_default()
+        .check_route(&new_route)
+        .await?;
     let mut new_liveness_status = new_route.liveness_status.write().await;
     *new_liveness_status = old_route.liveness_status.write().await.clone();
 
@@ -177,6 +214,7 @@ async fn put_route_with_error(route_vistor: RouteVistor) -> Result<String, AppEr
             let old_base_route = hashmap.get(&new_base_route.endpoint).unwrap();
             let mut alive = new_base_route.is_alive.write().await;
             *alive = *old_base_route.is_alive.write().await;
+            *new_base_route.recovered_at.write().await = *old_base_route.recovered_at.read().await;
             let mut anomaly_detection_status =
                 new_base_route.anomaly_detection_status.write().await;
             *anomaly_detection_status = old_base_route
@@ -211,9 +249,9 @@ async fn save_config_to_file() -> Result<(), AppError> {
     let api_services_vistor = from_api_service(data.api_service_config.clone()).await?;
     let result: bool = Path::new(DEFAULT_TEMPORARY_DIR).is_dir();
     if !result {
-        let path = env::current_dir().map_err(|e| AppError(e.to_string()))?;
+        let path = env::current_dir().map_err(|e| AppError::from(e.to_string()))?;
         let absolute_path = path.join(DEFAULT_TEMPORARY_DIR);
-        std::fs::create_dir_all(absolute_path).map_err(|e| AppError(e.to_string()))?;
+        std::fs::create_dir_all(absolute_path).map_err(|e| AppError::from(e.to_string()))?;
     }
 
     let mut f = tokio::fs::OpenOptions::new()
@@ -222,12 +260,238 @@ async fn save_config_to_file() -> Result<(), AppError> {
         .truncate(true)
         .open("temporary/new_silverwind_config.yml")
         .await
-        .map_err(|e| AppError(e.to_string()))?;
-    let api_service_str =
-        serde_yaml::to_string(&api_services_vistor).map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
+    let api_service_str = serde_yaml::to_string(&to_value_with_placeholders(&api_services_vistor)?)
+        .map_err(|e| AppError::from(e.to_string()))?;
     f.write_all(api_service_str.as_bytes())
         .await
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
+    Ok(())
+}
+async fn reload_app_config(
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl axum::response::IntoResponse, Infallible> {
+    match reload_app_config_with_error(headers, body).await {
+        Ok(r) => Ok((axum::http::StatusCode::OK, r)),
+        Err(e) => Ok((axum::http::StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+async fn load_reload_content(
+    headers: HeaderMap,
+    body: String,
+) -> Result<(String, ConfigFormat), AppError> {
+    if body.trim().is_empty() {
+        let config_file_path = GLOBAL_APP_CONFIG
+            .read()
+            .await
+            .static_config
+            .config_file_path
+            .clone()
+            .ok_or(AppError::from(String::from(
+                "The config file path is not set!",
+            )))?;
+        let content = tokio::fs::read_to_string(&config_file_path)
+            .await
+            .map_err(|e| AppError::from(e.to_string()))?;
+        Ok((content, ConfigFormat::from_path(&config_file_path)))
+    } else {
+        Ok((body, ConfigFormat::from_content_type(&headers)))
+    }
+}
+async fn reload_app_config_with_error(
+    headers: HeaderMap,
+    body: String,
+) -> Result<String, AppError> {
+    let (content, format) = load_reload_content(headers, body).await?;
+    let errors = validate_config(&content, format).await;
+    if !errors.is_empty() {
+        return Err(AppError::new(AppErrorKind::Config, errors.join("\n")));
+    }
+    let api_services = parse_api_services(&content, format).await?;
+    for api_service in api_services.iter() {
+        validate_service_config(&api_service.service_config).await?;
+    }
+    let mut rw_global_lock = GLOBAL_APP_CONFIG.write().await;
+    rw_global_lock.api_service_config = api_services;
+    drop(rw_global_lock);
+    let mapping_diff = update_mapping_from_global_appconfig()
+        .await
+        .unwrap_or_else(|err| {
+            error!("Apply the reloaded config later,the error is {}!", err);
+            MappingDiff::default()
+        });
+    info!(
+        "Reload the config successfully,the diff is {:?}.",
+        mapping_diff
+    );
+    tokio::spawn(async {
+        if let Err(err) = save_config_to_file().await {
+            error!("Save file error,the error is {}!", err);
+        }
+    });
+    let data = BaseResponse {
+        response_code: 0,
+        response_object: mapping_diff,
+    };
+    Ok(serde_json::to_string(&data).unwrap())
+}
+#[derive(Debug, Clone, Default, Serialize)]
+struct DryRunReport {
+    valid: bool,
+    errors: Vec<String>,
+    mapping_diff: MappingDiff,
+}
+async fn reload_dry_run(
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl axum::response::IntoResponse, Infallible> {
+    let report = match load_reload_content(headers, body).await {
+        Ok((content, format)) => get_dry_run_report(&content, format).await,
+        Err(err) => DryRunReport {
+            valid: false,
+            errors: vec![err.to_string()],
+            mapping_diff: MappingDiff::default(),
+        },
+    };
+    let status_code = if report.valid {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::BAD_REQUEST
+    };
+    let data = BaseResponse {
+        response_code: if report.valid { 0 } else { -1 },
+        response_object: report,
+    };
+    Ok((status_code, serde_json::to_string(&data).unwrap()))
+}
+async fn get_dry_run_report(content: &str, format: ConfigFormat) -> DryRunReport {
+    let mut errors = validate_config(content, format).await;
+    let mut mapping_diff = MappingDiff::default();
+    if errors.is_empty() {
+        match parse_api_services(content, format).await {
+            Ok(api_services) => {
+                for api_service in api_services.iter() {
+                    if let Err(err) = validate_service_config(&api_service.service_config).await {
+                        errors.push(format!(
+                            "The port {} is invalid,the error is {}",
+                            api_service.listen_port, err
+                        ));
+                    }
+                }
+                mapping_diff = diff_mapping_keys(
+                    GLOBAL_CONFIG_MAPPING
+                        .iter()
+                        .map(|item| item.key().clone())
+                        .collect(),
+                    api_services
+                        .iter()
+                        .map(|item| {
+                            format!("{}-{}", item.listen_port, item.service_config.server_type)
+                        })
+                        .collect(),
+                );
+            }
+            Err(err) => errors.push(err.to_string()),
+        }
+    }
+    DryRunReport {
+        valid: errors.is_empty(),
+        errors,
+        mapping_diff,
+    }
+}
+async fn reload_api_service(
+    axum::extract::Path(listen_port): axum::extract::Path<i32>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl axum::response::IntoResponse, Infallible> {
+    match reload_api_service_with_error(
+        listen_port,
+        ConfigFormat::from_content_type(&headers),
+        body,
+    )
+    .await
+    {
+        Ok(r) => Ok((axum::http::StatusCode::OK, r)),
+        Err(e) => Ok((axum::http::StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+async fn reload_api_service_with_error(
+    listen_port: i32,
+    format: ConfigFormat,
+    body: String,
+) -> Result<String, AppError> {
+    let service_config_vistor: ServiceConfigVistor = from_str_with_env(&body, format)?;
+    let errors = validate_service(listen_port, &service_config_vistor).await;
+    if !errors.is_empty() {
+        return Err(AppError::new(AppErrorKind::Config, errors.join("\n")));
+    }
+    let service_config = ServiceConfig::from(service_config_vistor).await?;
+    validate_service_config(&service_config).await?;
+    reload_service_config(listen_port, service_config).await?;
+    info!(
+        "Reload the config of the port {} successfully.",
+        listen_port
+    );
+    tokio::spawn(async {
+        if let Err(err) = save_config_to_file().await {
+            error!("Save file error,the error is {}!", err);
+        }
+    });
+    let data = BaseResponse {
+        response_code: 0,
+        response_object: 0,
+    };
+    Ok(serde_json::to_string(&data).unwrap())
+}
+async fn get_healthz() -> Result<impl axum::response::IntoResponse, Infallible> {
+    Ok((axum::http::StatusCode::OK, "OK"))
+}
+async fn get_readyz() -> Result<impl axum::response::IntoResponse, Infallible> {
+    let readiness_report = get_readiness_report();
+    let status = if readiness_report.ready {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    let data = BaseResponse {
+        response_code: if readiness_report.ready { 0 } else { -1 },
+        response_object: readiness_report,
+    };
+    Ok((status, serde_json::to_string(&data).unwrap()))
+}
+async fn post_log_level(
+    axum::extract::Json(log_level): axum::extract::Json<LogLevel>,
+) -> Result<impl axum::response::IntoResponse, Infallible> {
+    match set_log_level(log_level) {
+        Ok(()) => {
+            info!("The log level is changed to {:?}.", log_level);
+            let data = BaseResponse {
+                response_code: 0,
+                response_object: 0,
+            };
+            Ok((
+                axum::http::StatusCode::OK,
+                serde_json::to_string(&data).unwrap(),
+            ))
+        }
+        Err(e) => Ok((axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+async fn validate_service_config(service_config: &ServiceConfig) -> Result<(), AppError> {
+    if service_config.server_type == ServiceType::Https
+        || service_config.server_type == ServiceType::Http2Tls
+    {
+        validate_tls_config(
+            service_config.cert_str.clone(),
+            service_config.key_str.clone(),
+        )?;
+    }
+    let upstream_policy = service_config.upstream_policy.clone().unwrap_or_default();
+    for route in service_config.routes.iter() {
+        upstream_policy.check_route(route).await?;
+    }
     Ok(())
 }
 fn validate_tls_config(
@@ -235,18 +499,18 @@ fn validate_tls_config(
     key_pem_option: Option<String>,
 ) -> Result<(), AppError> {
     if cert_pem_option.is_none() || key_pem_option.is_none() {
-        return Err(AppError(String::from("Cert or key is none")));
+        return Err(AppError::from(String::from("Cert or key is none")));
     }
     let cert_pem = cert_pem_option.unwrap();
     let mut cer_reader = std::io::BufReader::new(cert_pem.as_bytes());
     let result_certs = rustls_pemfile::certs(&mut cer_reader).next();
     if result_certs.is_none() || result_certs.unwrap().is_err() {
-        return Err(AppError(String::from("Can not parse the certs pem.")));
+        return Err(AppError::from(String::from("Can not parse the certs pem.")));
     }
     let key_pem = key_pem_option.unwrap();
     let key_pem_result = pkcs8::PrivateKeyDocument::from_pem(key_pem.as_str());
     if key_pem_result.is_err() {
-        return Err(AppError(String::from("Can not parse the key pem.")));
+        return Err(AppError::from(String::from("Can not parse the key pem.")));
     }
     Ok(())
 }
@@ -256,7 +520,13 @@ pub fn get_router() -> Router {
         .route("/metrics", get(get_prometheus_metrics))
         .route("/route/:id", delete(delete_route))
         .route("/route", put(put_route))
+        .route("/reload", post(reload_app_config))
+        .route("/reload/dryRun", post(reload_dry_run))
+        .route("/reload/:port", post(reload_api_service))
         .route("/letsEncryptCertificate", post(lets_encrypt_certificate))
+        .route("/logLevel", post(post_log_level))
+        .route("/healthz", get(get_healthz))
+        .route("/readyz", get(get_readyz))
         .layer(TraceLayer::new_for_http())
         .layer(CorsLayer::permissive())
 }
@@ -267,15 +537,18 @@ pub async fn start_control_plane(port: i32) -> Result<(), AppError> {
 
     let listener = tokio::net::TcpListener::bind(addr)
         .await
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
     axum::serve(listener, app)
         .await
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
     Ok(())
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::configuration_service::readiness::mark_config_loaded;
+    use crate::vojo::api_service_manager::ApiServiceManager;
+    use crate::vojo::route::LoadbalancerStrategy;
     use axum::http::StatusCode;
     use axum::{
         body::Body,
@@ -285,7 +558,10 @@ mod tests {
     use lazy_static::lazy_static;
     use serde_json::json;
     use std::env;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
     use tokio::runtime::{Builder, Runtime};
+    use tokio::time::sleep;
     use tower::ServiceExt; // for `call`, `oneshot`, and `ready`
     lazy_static! {
         pub static ref TOKIO_RUNTIME: Runtime = Builder::new_multi_thread()
@@ -476,6 +752,63 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
     #[tokio::test]
+    async fn test_get_app_config_keeps_env_placeholders() {
+        env::set_var("SILVERWIND_TEST_UPSTREAM_HOST", "127.0.0.9");
+        let api_services = parse_api_services(
+            r#"
+- listen_port: 4491
+  service_config:
+    server_type: Http
+    routes:
+      - matcher:
+          prefix: /
+          prefix_rewrite: /
+        route_cluster:
+          type: RandomRoute
+          routes:
+            - base_route:
+                endpoint: http://${SILVERWIND_TEST_UPSTREAM_HOST}:8000
+"#,
+            ConfigFormat::Yaml,
+        )
+        .await
+        .unwrap();
+        GLOBAL_APP_CONFIG
+            .write()
+            .await
+            .api_service_config
+            .extend(api_services);
+        let response = get_router()
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/appConfig")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let base_response: BaseResponse<AppConfigVistor> =
+            serde_json::from_slice(&body_bytes).unwrap();
+        let api_service = base_response
+            .response_object
+            .api_service_config
+            .into_iter()
+            .find(|item| item.listen_port == 4491)
+            .unwrap();
+        let body = String::from_utf8_lossy(&body_bytes);
+        assert!(body.contains("http://${SILVERWIND_TEST_UPSTREAM_HOST}:8000"));
+        assert!(!body.contains("127.0.0.9"));
+        assert_eq!(api_service.service_config.routes.len(), 1);
+        GLOBAL_APP_CONFIG
+            .write()
+            .await
+            .api_service_config
+            .retain(|item| item.listen_port != 4491);
+    }
+    #[tokio::test]
     async fn test_put_route_ok() {
         let body = r#"{
             "route_id": "90c66439-5c87-4902-aebb-1c2c9443c154",
@@ -519,6 +852,60 @@ mod tests {
             .unwrap();
         assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
+    async fn post_app_config_json(body: String) -> StatusCode {
+        get_router()
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/appConfig")
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+    #[tokio::test]
+    async fn test_post_route_uses_service_upstream_policy() {
+        let create_body = |upstream_policy: serde_json::Value, endpoint: &str| {
+            json!({
+                "listen_port": 4490,
+                "service_config": {
+                    "server_type": "Http",
+                    "upstream_policy": upstream_policy,
+                    "routes": [{
+                        "matcher": {"prefix": "/", "prefix_rewrite": "/"},
+                        "route_cluster": {
+                            "type": "RandomRoute",
+                            "routes": [{"base_route": {"endpoint": endpoint}}]
+                        }
+                    }]
+                }
+            })
+            .to_string()
+        };
+        let status = post_app_config_json(create_body(
+            json!({"denied_hosts": ["10.0.0.0/8"]}),
+            "http://127.0.0.1:8000",
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let status =
+            post_app_config_json(create_body(serde_json::Value::Null, "http://10.0.0.1:8000"))
+                .await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        let mut rw_global_lock = GLOBAL_APP_CONFIG.write().await;
+        let api_service = rw_global_lock
+            .api_service_config
+            .iter()
+            .find(|item| item.listen_port == 4490)
+            .unwrap();
+        assert_eq!(api_service.service_config.routes.len(), 1);
+        rw_global_lock
+            .api_service_config
+            .retain(|item| item.listen_port != 4490);
+    }
     #[tokio::test]
     async fn test_delete_route_ok() {
         let app = get_router();
@@ -536,4 +923,339 @@ mod tests {
             .unwrap();
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
+    async fn post_reload(body: &'static str) -> (StatusCode, String) {
+        post_reload_to("/reload", body).await
+    }
+    async fn post_reload_to(uri: &str, body: &'static str) -> (StatusCode, String) {
+        let response = get_router()
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(uri)
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (status, String::from_utf8_lossy(&body_bytes).to_string())
+    }
+    #[tokio::test]
+    async fn test_readyz_after_config_loaded() {
+        let get_status = |uri: &'static str| async move {
+            get_router()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap()
+                .status()
+        };
+        assert_eq!(get_status("/healthz").await, StatusCode::OK);
+        assert_eq!(get_status("/readyz").await, StatusCode::SERVICE_UNAVAILABLE);
+        mark_config_loaded();
+        let mut status = get_status("/readyz").await;
+        for _ in 0..50 {
+            if status == StatusCode::OK {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+            status = get_status("/readyz").await;
+        }
+        assert_eq!(status, StatusCode::OK);
+    }
+    #[tokio::test]
+    async fn test_post_log_level() {
+        let post_log_level = |body: &'static str| async move {
+            get_router()
+                .oneshot(
+                    Request::builder()
+                        .method(http::Method::POST)
+                        .uri("/logLevel")
+                        .header(http::header::CONTENT_TYPE, "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+                .status()
+        };
+        assert_eq!(post_log_level(r#""Debug""#).await, StatusCode::OK);
+        assert_eq!(log::max_level(), log::LevelFilter::Debug);
+        assert_eq!(post_log_level(r#""Info""#).await, StatusCode::OK);
+        assert_eq!(log::max_level(), log::LevelFilter::Info);
+        assert_eq!(
+            post_log_level(r#""Verbose""#).await,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(log::max_level(), log::LevelFilter::Info);
+    }
+    async fn has_listen_port(listen_port: i32) -> bool {
+        GLOBAL_APP_CONFIG
+            .read()
+            .await
+            .api_service_config
+            .iter()
+            .any(|item| item.listen_port == listen_port)
+    }
+    #[tokio::test]
+    async fn test_reload_malformed_yaml_keeps_running_config() {
+        let api_service_vistor: ApiServiceVistor = serde_yaml::from_str(
+            r#"
+listen_port: 10055
+service_config:
+  server_type: Http
+  routes: []
+"#,
+        )
+        .unwrap();
+        GLOBAL_APP_CONFIG
+            .write()
+            .await
+            .api_service_config
+            .push(ApiService::from(api_service_vistor).await.unwrap());
+
+        let (status, body) = post_reload("- listen_port: 4486\n  service_config: [").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("line"));
+        assert!(has_listen_port(10055).await);
+
+        let (status, body) = post_reload(
+            r#"
+- listen_port: 4487
+  service_config:
+    server_type: Http
+    routes:
+      - matcher:
+          prefix: /
+          prefix_rewrite: /
+        route_cluster:
+          type: RandomRoute
+          routes:
+            - base_route:
+                endpoint: http://169.254.169.254/latest
+"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("link-local"));
+        assert!(has_listen_port(10055).await);
+        assert!(!has_listen_port(4487).await);
+    }
+    #[tokio::test]
+    async fn test_reload_rejects_semantically_invalid_config() {
+        let (status, body) = post_reload(
+            r#"
+- listen_port: 4488
+  service_config:
+    server_type: Http
+    routes:
+      - matcher:
+          prefix: "/api/(["
+          prefix_rewrite: /
+          match_type: Regex
+        route_cluster:
+          type: RandomRoute
+          routes:
+            - base_route:
+                endpoint: http://127.0.0.1:9001
+- listen_port: 4488
+  service_config:
+    server_type: Http
+    routes: []
+"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("invalid regex"), "{}", body);
+        assert!(body.contains("duplicated"), "{}", body);
+        assert!(!has_listen_port(4488).await);
+
+        let (status, body) = post_reload_to(
+            "/reload/4489",
+            r#"
+server_type: Http
+routes:
+  - matcher:
+      prefix: /
+      prefix_rewrite: /
+    combined_limit:
+      response:
+        status_code: 200
+    route_cluster:
+      type: RandomRoute
+      routes:
+        - base_route:
+            endpoint: http://127.0.0.1:9001
+"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("ratelimit response status"), "{}", body);
+        assert!(!has_listen_port(4489).await);
+    }
+    #[tokio::test]
+    async fn test_reload_dry_run_does_not_mutate_config() {
+        let (status, body) = post_reload_to(
+            "/reload/dryRun",
+            r#"
+- listen_port: 10079
+  service_config:
+    server_type: Https
+    routes: []
+"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("cert_str"));
+        assert!(!has_listen_port(10079).await);
+
+        let (status, body) = post_reload_to(
+            "/reload/dryRun",
+            r#"
+- listen_port: 10079
+  service_config:
+    server_type: Http
+    routes: []
+"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["response_object"]["valid"], true);
+        assert_eq!(
+            report["response_object"]["mapping_diff"]["added"],
+            json!(["10079-Http"])
+        );
+        assert!(!has_listen_port(10079).await);
+        assert!(!GLOBAL_CONFIG_MAPPING.contains_key("10079-Http"));
+    }
+    fn poll_index(route: &Route) -> usize {
+        match &route.route_cluster {
+            LoadbalancerStrategy::PollRoute(poll_route) => {
+                poll_route.current_index.load(Ordering::SeqCst)
+            }
+            _ => panic!("The route cluster is not a poll route!"),
+        }
+    }
+    fn first_mapping_route(mapping_key: &str) -> Route {
+        GLOBAL_CONFIG_MAPPING
+            .get(mapping_key)
+            .unwrap()
+            .service_config
+            .routes
+            .first()
+            .unwrap()
+            .clone()
+    }
+    #[tokio::test]
+    async fn test_reload_single_port_keeps_other_port_state() {
+        let mut receivers = vec![];
+        for listen_port in [10074, 10075] {
+            let api_service_vistor: ApiServiceVistor = serde_yaml::from_str(&format!(
+                r#"
+listen_port: {}
+service_config:
+  server_type: Http
+  routes:
+    - matcher:
+        prefix: /
+        prefix_rewrite: /
+      route_cluster:
+        type: PollRoute
+        routes:
+          - base_route:
+              endpoint: http://127.0.0.1:9001
+          - base_route:
+              endpoint: http://127.0.0.1:9002
+"#,
+                listen_port
+            ))
+            .unwrap();
+            let api_service = ApiService::from(api_service_vistor).await.unwrap();
+            let (sender, receiver) = tokio::sync::mpsc::channel(10);
+            receivers.push(receiver);
+            GLOBAL_CONFIG_MAPPING.insert(
+                format!("{}-{}", listen_port, ServiceType::Http),
+                ApiServiceManager {
+                    sender,
+                    service_config: api_service.service_config.clone(),
+                },
+            );
+            GLOBAL_APP_CONFIG
+                .write()
+                .await
+                .api_service_config
+                .push(api_service);
+        }
+        let mut other_route = first_mapping_route("10075-Http");
+        let headers = HeaderMap::new();
+        let remote_addr = SocketAddr::from(([127, 0, 0, 1], 8080));
+        for _ in 0..3 {
+            other_route
+                .route_cluster
+                .get_route(headers.clone(), remote_addr, None)
+                .await
+                .unwrap();
+        }
+        let other_base_route = other_route
+            .route_cluster
+            .get_all_route()
+            .await
+            .unwrap()
+            .first()
+            .unwrap()
+            .clone();
+        other_base_route
+            .anomaly_detection_status
+            .write()
+            .await
+            .consecutive_5xx = 2;
+
+        let (status, _) = post_reload_to(
+            "/reload/10074",
+            r#"
+server_type: Http
+routes:
+  - matcher:
+      prefix: /v2
+      prefix_rewrite: /
+    route_cluster:
+      type: PollRoute
+      routes:
+        - base_route:
+            endpoint: http://127.0.0.1:9003
+"#,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let reloaded_route = first_mapping_route("10074-Http");
+        assert_eq!(reloaded_route.matcher.unwrap().prefix, "/v2/");
+        assert!(receivers[0].try_recv().is_err());
+
+        let other_route = first_mapping_route("10075-Http");
+        assert_eq!(poll_index(&other_route), 3);
+        let other_base_route = other_route
+            .clone()
+            .route_cluster
+            .get_all_route()
+            .await
+            .unwrap()
+            .first()
+            .unwrap()
+            .clone();
+        assert_eq!(
+            other_base_route
+                .anomaly_detection_status
+                .read()
+                .await
+                .consecutive_5xx,
+            2
+        );
+
+        let (status, body) = post_reload_to("/reload/10074", "server_type: Tcp\nroutes: []").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("can not be changed"));
+        let (status, _) = post_reload_to("/reload/10076", "server_type: Http\nroutes: []").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
 }
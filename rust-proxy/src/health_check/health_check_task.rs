@@ -4,6 +4,7 @@ use crate::proxy::http1::http_client::HttpClients;
 use crate::vojo::app_config::Route;
 use crate::vojo::app_error::AppError;
 use crate::vojo::health_check::HealthCheckType;
+use crate::vojo::health_check::HealthStatus;
 use crate::vojo::health_check::HttpHealthCheckParam;
 use bytes::Bytes;
 use delay_timer::prelude::*;
@@ -193,9 +194,11 @@ async fn do_http_health_check(
             .method("GET")
             .body(Full::new(Bytes::new()).boxed())
             .unwrap();
-        let task_with_timeout = http_client_shared
-            .clone()
-            .request_http(req, timeout_number as u64);
+        let task_with_timeout = http_client_shared.clone().request_http(
+            req,
+            timeout_number as u64,
+            item.preserve_header_case,
+        );
         set.spawn(async {
             let res = task_with_timeout.await;
             (res, item)
@@ -203,15 +206,33 @@ async fn do_http_health_check(
     }
     while let Some(response_result1) = set.join_next().await {
         if let Ok((response_result2, base_route)) = response_result1 {
-            match response_result2 {
+            let health_status = match response_result2 {
                 Ok(Ok(t)) => {
-                    if t.status() == StatusCode::OK {
-                        base_route
-                            .update_health_check_status_with_ok(route.liveness_status.clone())
-                            .await;
+                    let header_status = http_health_check_param
+                        .header_criteria
+                        .as_ref()
+                        .and_then(|criteria| criteria.get_status(t.headers()));
+                    match header_status {
+                        Some(status) => Some(status),
+                        None if t.status() == StatusCode::OK => Some(HealthStatus::Healthy),
+                        None => None,
                     }
                 }
-                _ => {
+                _ => Some(HealthStatus::Unhealthy),
+            };
+            match health_status {
+                Some(HealthStatus::Healthy) | Some(HealthStatus::Degraded) => {
+                    if health_status == Some(HealthStatus::Degraded) {
+                        warn!(
+                            "The route-{} reports a degraded health status!",
+                            base_route.endpoint.clone()
+                        );
+                    }
+                    base_route
+                        .update_health_check_status_with_ok(route.liveness_status.clone())
+                        .await;
+                }
+                Some(HealthStatus::Unhealthy) => {
                     if let Some(current_liveness_config) = route.liveness_config.clone() {
                         let _update_result = base_route
                             .update_health_check_status_with_fail(
@@ -226,6 +247,7 @@ async fn do_http_health_check(
                         );
                     }
                 }
+                None => {}
             }
         }
     }
@@ -270,9 +292,9 @@ fn submit_task(
             .set_frequency_repeated_by_seconds(base_param.interval as u64)
             .set_maximum_parallel_runnable_num(1)
             .spawn_async_routine(task)
-            .map_err(|err| AppError(err.to_string()));
+            .map_err(|err| AppError::from(err.to_string()));
     }
-    Err(AppError(String::from("Submit task error!")))
+    Err(AppError::from(String::from("Submit task error!")))
 }
 #[cfg(test)]
 mod tests {
@@ -280,16 +302,18 @@ mod tests {
     use crate::vojo::api_service_manager::ApiServiceManager;
     use crate::vojo::app_config::LivenessConfig;
     use crate::vojo::app_config::LivenessStatus;
-    use crate::vojo::app_config::Matcher;
     use crate::vojo::app_config::ServiceConfig;
+    use crate::vojo::app_config::{Matcher, PathMatchType};
     use crate::vojo::health_check::BaseHealthCheckParam;
+    use crate::vojo::health_check::HeaderHealthCriteria;
     use crate::vojo::route::AnomalyDetectionStatus;
     use crate::vojo::route::LoadbalancerStrategy;
     use crate::vojo::route::{BaseRoute, WeightBasedRoute, WeightRoute};
     use lazy_static::lazy_static;
-    use std::sync::atomic::AtomicIsize;
     use std::sync::Arc;
     use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
     use tokio::runtime::{Builder, Runtime};
     use tokio::sync::RwLock;
 
@@ -315,14 +339,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                     weight: 100,
-                    index: Arc::new(AtomicIsize::new(0)),
                 }])),
+                current_weights: Default::default(),
             }),
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
@@ -331,13 +363,34 @@ mod tests {
             health_check: None,
             liveness_config: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             authentication: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let health_check_param = HealthCheckClient::new();
@@ -355,14 +408,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                     weight: 100,
-                    index: Arc::new(AtomicIsize::new(0)),
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -370,6 +431,7 @@ mod tests {
                     interval: 1,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
@@ -377,13 +439,34 @@ mod tests {
             anomaly_detection: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let health_check_param = HealthCheckClient::new();
@@ -406,14 +489,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                     weight: 100,
-                    index: Arc::new(AtomicIsize::new(0)),
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -421,29 +512,50 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             anomaly_detection: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
             })),
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service_manager = ApiServiceManager {
             sender,
             service_config: ServiceConfig {
-                key_str: None,
                 server_type: crate::vojo::app_config::ServiceType::Https,
-                cert_str: None,
                 routes: vec![route],
+                ..Default::default()
             },
         };
         let uuid2 = Uuid::new_v4();
@@ -470,14 +582,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("http://httpbin.org/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                     weight: 100,
-                    index: Arc::new(AtomicIsize::new(0)),
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -485,29 +605,50 @@ mod tests {
                     interval: 3,
                 },
                 path: String::from("/get"),
+                header_criteria: None,
             })),
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             anomaly_detection: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
             })),
             authentication: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service_manager = ApiServiceManager {
             sender,
             service_config: ServiceConfig {
-                key_str: None,
                 server_type: crate::vojo::app_config::ServiceType::Https,
-                cert_str: None,
                 routes: vec![route],
+                ..Default::default()
             },
         };
         let uuid2 = Uuid::new_v4();
@@ -536,14 +677,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("http://127.0.0.1:9394/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                     weight: 100,
-                    index: Arc::new(AtomicIsize::new(0)),
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -551,31 +700,54 @@ mod tests {
                     interval: 3,
                 },
                 path: String::from("/get"),
+                header_criteria: None,
             })),
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             anomaly_detection: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             liveness_config: Some(LivenessConfig {
                 min_liveness_count: 3,
+                panic_threshold: None,
+                slow_start_second: None,
             }),
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
             })),
             authentication: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service_manager = ApiServiceManager {
             sender,
             service_config: ServiceConfig {
-                key_str: None,
                 server_type: crate::vojo::app_config::ServiceType::Https,
-                cert_str: None,
                 routes: vec![route],
+                ..Default::default()
             },
         };
         let uuid2 = Uuid::new_v4();
@@ -599,6 +771,7 @@ mod tests {
                 interval: 10,
             },
             path: String::from("test"),
+            header_criteria: None,
         };
         let id = Uuid::new_v4();
 
@@ -610,14 +783,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                     weight: 100,
-                    index: Arc::new(AtomicIsize::new(0)),
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -625,20 +806,42 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             liveness_config: None,
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
             })),
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             anomaly_detection: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         TOKIO_RUNTIME.block_on(async {
@@ -657,6 +860,7 @@ mod tests {
                 interval: 10,
             },
             path: String::from("test"),
+            header_criteria: None,
         };
         let id = Uuid::new_v4();
 
@@ -668,14 +872,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                     weight: 100,
-                    index: Arc::new(AtomicIsize::new(0)),
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -683,20 +895,42 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             anomaly_detection: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
             })),
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         TOKIO_RUNTIME.block_on(async {
@@ -715,6 +949,7 @@ mod tests {
                 interval: 10,
             },
             path: String::from("test"),
+            header_criteria: None,
         };
         let id = Uuid::new_v4();
 
@@ -726,14 +961,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                     weight: 100,
-                    index: Arc::new(AtomicIsize::new(0)),
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -741,20 +984,42 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("/"),
+                header_criteria: None,
             })),
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
             })),
             anomaly_detection: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             liveness_config: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         TOKIO_RUNTIME.block_on(async {
@@ -764,4 +1029,100 @@ mod tests {
             assert!(result.is_ok());
         });
     }
+    #[test]
+    fn test_do_http_health_check_unhealthy_header() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10041").await.unwrap();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nX-Health: unhealthy\r\ncontent-length: 0\r\n\r\n",
+                    )
+                    .await
+                    .unwrap();
+            });
+            let http_health_check_param = HttpHealthCheckParam {
+                base_health_check_param: BaseHealthCheckParam {
+                    timeout: 5,
+                    interval: 10,
+                },
+                path: String::from("/health"),
+                header_criteria: Some(HeaderHealthCriteria {
+                    name: String::from("X-Health"),
+                    healthy_values: vec![String::from("healthy")],
+                    degraded_values: vec![String::from("degraded")],
+                    unhealthy_values: vec![String::from("unhealthy")],
+                }),
+            };
+            let is_alive = Arc::new(RwLock::new(Some(true)));
+            let route = Route {
+                host_name: None,
+                route_id: Uuid::new_v4().to_string(),
+                route_cluster: LoadbalancerStrategy::WeightBased(WeightBasedRoute {
+                    routes: Arc::new(RwLock::new(vec![WeightRoute {
+                        base_route: BaseRoute {
+                            endpoint: String::from("http://127.0.0.1:10041"),
+                            try_file: None,
+                            index: None,
+                            autoindex: None,
+                            host_header: None,
+                            sni: None,
+                            preserve_header_case: true,
+                            is_alive: is_alive.clone(),
+                            anomaly_detection_status: Arc::new(RwLock::new(
+                                AnomalyDetectionStatus {
+                                    consecutive_5xx: 0,
+                                    recent_responses: Default::default(),
+                                },
+                            )),
+                            circuit_breaker_status: Default::default(),
+                            recovered_at: Default::default(),
+                        },
+                        weight: 100,
+                    }])),
+                    current_weights: Default::default(),
+                }),
+                health_check: None,
+                liveness_config: Some(LivenessConfig {
+                    min_liveness_count: 0,
+                    panic_threshold: None,
+                    slow_start_second: None,
+                }),
+                liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                    current_liveness_count: 1,
+                })),
+                rewrite_headers: None,
+                query_rewrite: None,
+                response_headers: None,
+                forward_headers: None,
+                mirror: None,
+                timeout: None,
+                circuit_breaker: None,
+                hedge: None,
+                retry: None,
+                body_logging: None,
+                maintenance: None,
+                cache: None,
+                single_flight: None,
+                websocket: None,
+                anomaly_detection: None,
+                allow_deny_list: None,
+                allow_deny_default: None,
+                client_ip: None,
+                authentication: None,
+                ratelimit: None,
+                combined_limit: None,
+                bulkhead: None,
+                matcher: None,
+            };
+            let result =
+                do_http_health_check(http_health_check_param, route, 5, HealthCheckClient::new())
+                    .await;
+            assert!(result.is_ok());
+            assert_eq!(*is_alive.read().await, Some(false));
+        });
+    }
 }
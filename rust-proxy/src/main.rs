@@ -19,6 +19,10 @@ use crate::constants::common_constants::ENV_ADMIN_PORT;
 use std::env;
 #[macro_use]
 extern crate log;
+use crate::configuration_service::config_diff::handle_diff_command;
+use crate::configuration_service::config_schema::handle_schema_command;
+use crate::configuration_service::config_validator::handle_validate_command;
+use crate::configuration_service::route_tester::handle_test_command;
 use crate::control_plane::rest_api::start_control_plane;
 use env_logger::Env;
 
@@ -32,6 +36,32 @@ fn main() {
         .build()
         .unwrap();
 
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(|item| item.as_str()) {
+        Some("validate") => {
+            let check_upstreams = args.iter().any(|item| item == "--check-upstreams");
+            let config_file_path = args
+                .iter()
+                .skip(2)
+                .find(|item| !item.starts_with("--"))
+                .cloned();
+            let exit_code = rt.block_on(handle_validate_command(config_file_path, check_upstreams));
+            std::process::exit(exit_code);
+        }
+        Some("diff") => {
+            let exit_code = rt.block_on(handle_diff_command(
+                args.get(2).cloned(),
+                args.get(3).cloned(),
+            ));
+            std::process::exit(exit_code);
+        }
+        Some("schema") => std::process::exit(handle_schema_command()),
+        Some("test") => {
+            let exit_code = rt.block_on(handle_test_command(&args[2..]));
+            std::process::exit(exit_code);
+        }
+        _ => {}
+    }
     rt.block_on(async {
         let admin_port: i32 = env::var(ENV_ADMIN_PORT)
             .unwrap_or(String::from(DEFAULT_ADMIN_PORT))
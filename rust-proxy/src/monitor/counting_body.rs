@@ -0,0 +1,67 @@
+use bytes::Buf;
+use hyper::body::{Body, Frame, SizeHint};
+use prometheus::Histogram;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pub struct CountingBody<B> {
+    inner: B,
+    count: u64,
+    histogram: Histogram,
+}
+impl<B> CountingBody<B> {
+    pub fn new(inner: B, histogram: Histogram) -> Self {
+        CountingBody {
+            inner,
+            count: 0,
+            histogram,
+        }
+    }
+}
+impl<B> Body for CountingBody<B>
+where
+    B: Body + Unpin,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let poll_result = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &poll_result {
+            if let Some(data) = frame.data_ref() {
+                self.count += data.remaining() as u64;
+            }
+        }
+        poll_result
+    }
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+impl<B> Drop for CountingBody<B> {
+    fn drop(&mut self) {
+        self.histogram.observe(self.count as f64);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::prometheus_exporter::get_request_size_histogram;
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, Full};
+    #[tokio::test]
+    async fn test_counting_body_observe_request_size() {
+        let histogram =
+            get_request_size_histogram(String::from("counting-body-test"), String::from("route"));
+        let body = CountingBody::new(Full::new(Bytes::from("hello world")), histogram.clone());
+        let body_bytes = body.collect().await.unwrap().to_bytes();
+        assert_eq!(body_bytes, Bytes::from("hello world"));
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert_eq!(histogram.get_sample_sum(), 11.0);
+    }
+}
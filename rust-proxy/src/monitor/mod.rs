@@ -1 +1,2 @@
+pub mod counting_body;
 pub mod prometheus_exporter;
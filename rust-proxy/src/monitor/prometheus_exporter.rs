@@ -1,6 +1,11 @@
+use crate::constants::common_constants::ENV_BODY_SIZE_BUCKETS;
 use lazy_static::lazy_static;
-use prometheus::{labels, opts, register_counter_vec, register_gauge, register_histogram_vec};
-use prometheus::{CounterVec, Gauge, Histogram, HistogramVec};
+use prometheus::{exponential_buckets, histogram_opts, opts};
+use prometheus::{register_counter_vec, register_histogram_vec};
+use prometheus::{register_int_counter_vec, register_int_gauge_vec};
+use prometheus::{CounterVec, Histogram, HistogramVec};
+use prometheus::{IntCounter, IntCounterVec, IntGauge, IntGaugeVec};
+use std::env;
 
 lazy_static! {
     static ref HTTP_COUNTER: CounterVec = register_counter_vec!(
@@ -11,11 +16,41 @@ lazy_static! {
         &["port", "request_path", "status_code"]
     )
     .unwrap();
-    static ref HTTP_BODY_GAUGE: Gauge = register_gauge!(opts!(
-        "silverwind_http_response_size_bytes",
-        "The HTTP response sizes in bytes.",
-        labels! {"handler" => "all",}
-    ))
+    static ref HTTP_REQUEST_SIZE_HISTOGRAM: HistogramVec = register_histogram_vec!(
+        histogram_opts!(
+            "silverwind_http_request_size_bytes",
+            "The HTTP request body sizes in bytes.",
+            body_size_buckets()
+        ),
+        &["port", "route_id"]
+    )
+    .unwrap();
+    static ref HTTP_RESPONSE_SIZE_HISTOGRAM: HistogramVec = register_histogram_vec!(
+        histogram_opts!(
+            "silverwind_http_response_size_bytes",
+            "The HTTP response body sizes in bytes.",
+            body_size_buckets()
+        ),
+        &["port", "route_id"]
+    )
+    .unwrap();
+    static ref CIRCUIT_BREAKER_STATE: IntGaugeVec = register_int_gauge_vec!(
+        "silverwind_circuit_breaker_state",
+        "The circuit breaker state of the upstream,0 is closed,1 is open and 2 is half open.",
+        &["route_id", "endpoint"]
+    )
+    .unwrap();
+    static ref CIRCUIT_BREAKER_REJECTED_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "silverwind_circuit_breaker_rejected_total",
+        "Number of requests rejected by the circuit breaker.",
+        &["route_id", "endpoint"]
+    )
+    .unwrap();
+    static ref ROUTE_LIMIT_REJECTED_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "silverwind_route_limit_rejected_total",
+        "Number of requests rejected by the combined limit of the route.",
+        &["route_id", "reason"]
+    )
     .unwrap();
     static ref HTTP_REQ_HISTOGRAM: HistogramVec = register_histogram_vec!(
         "silverwind_http_request_duration_seconds",
@@ -40,3 +75,50 @@ pub fn get_timer_list(key: String, path: String) -> Vec<Histogram> {
         HTTP_REQ_HISTOGRAM.with_label_values(&["all", "all"]),
     ]
 }
+fn parse_buckets(buckets: &str) -> Option<Vec<f64>> {
+    let mut res = buckets
+        .split(',')
+        .map(|item| item.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .ok()?;
+    res.sort_by(|a, b| a.total_cmp(b));
+    res.dedup();
+    if res.is_empty() {
+        return None;
+    }
+    Some(res)
+}
+fn body_size_buckets() -> Vec<f64> {
+    env::var(ENV_BODY_SIZE_BUCKETS)
+        .ok()
+        .and_then(|buckets| parse_buckets(&buckets))
+        .unwrap_or_else(|| exponential_buckets(64.0, 4.0, 10).unwrap())
+}
+pub fn get_request_size_histogram(key: String, route_id: String) -> Histogram {
+    HTTP_REQUEST_SIZE_HISTOGRAM.with_label_values(&[key.as_str(), route_id.as_str()])
+}
+pub fn get_response_size_histogram(key: String, route_id: String) -> Histogram {
+    HTTP_RESPONSE_SIZE_HISTOGRAM.with_label_values(&[key.as_str(), route_id.as_str()])
+}
+pub fn get_circuit_breaker_state_gauge(route_id: String, endpoint: String) -> IntGauge {
+    CIRCUIT_BREAKER_STATE.with_label_values(&[route_id.as_str(), endpoint.as_str()])
+}
+pub fn get_circuit_breaker_rejected_counter(route_id: String, endpoint: String) -> IntCounter {
+    CIRCUIT_BREAKER_REJECTED_COUNTER.with_label_values(&[route_id.as_str(), endpoint.as_str()])
+}
+pub fn get_route_limit_rejected_counter(route_id: String, reason: &str) -> IntCounter {
+    ROUTE_LIMIT_REJECTED_COUNTER.with_label_values(&[route_id.as_str(), reason])
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_buckets() {
+        assert_eq!(
+            parse_buckets("1024, 64,256"),
+            Some(vec![64.0, 256.0, 1024.0])
+        );
+        assert_eq!(parse_buckets("64,abc"), None);
+        assert_eq!(parse_buckets(""), None);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
+use crate::constants::common_constants::TIMER_WAIT_SECONDS;
+use crate::vojo::app_error::AppError;
+use crate::vojo::tls_policy::SniCertResolver;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+/**
+ *Return None when the listener has been removed from the config mapping.
+ */
+fn reload_from_mapping(
+    mapping_key: &str,
+    cert_resolver: &SniCertResolver,
+) -> Option<Result<Vec<String>, AppError>> {
+    let service_config = GLOBAL_CONFIG_MAPPING
+        .get(mapping_key)?
+        .service_config
+        .clone();
+    let (Some(cert_str), Some(key_str)) = (service_config.cert_str, service_config.key_str) else {
+        return Some(Err(AppError::from(String::from(
+            "The cert_str or key_str is missing!",
+        ))));
+    };
+    Some(cert_resolver.reload(
+        &cert_str,
+        &key_str,
+        &service_config.sni_certs.unwrap_or_default(),
+    ))
+}
+/**
+ *The watcher is aborted when the guard is dropped,so it stops with the listener which owns it.
+ */
+pub struct CertWatcherGuard {
+    join_handle: JoinHandle<()>,
+}
+impl Drop for CertWatcherGuard {
+    fn drop(&mut self) {
+        self.join_handle.abort();
+    }
+}
+pub fn spawn_cert_watcher(
+    mapping_key: String,
+    cert_resolver: Arc<SniCertResolver>,
+) -> CertWatcherGuard {
+    CertWatcherGuard {
+        join_handle: tokio::spawn(watch_for_certificate_changes(mapping_key, cert_resolver)),
+    }
+}
+async fn watch_for_certificate_changes(mapping_key: String, cert_resolver: Arc<SniCertResolver>) {
+    loop {
+        sleep(std::time::Duration::from_secs(TIMER_WAIT_SECONDS)).await;
+        match reload_from_mapping(&mapping_key, &cert_resolver) {
+            None => break,
+            Some(Ok(updated)) if updated.is_empty() => {}
+            Some(Ok(updated)) => info!(
+                "Reload the certificates of {:?} on {} successfully!",
+                updated, mapping_key
+            ),
+            Some(Err(err)) => error!(
+                "Reload the certificates on {} error,the error is {}",
+                mapping_key, err
+            ),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::api_service_manager::ApiServiceManager;
+    use crate::vojo::app_config::ServiceConfig;
+    use crate::vojo::app_config::ServiceType;
+    use crate::vojo::tls_policy::SniCert;
+    use std::env;
+    fn read_test_pem(file_name: &str) -> String {
+        std::fs::read_to_string(env::current_dir().unwrap().join("config").join(file_name)).unwrap()
+    }
+    fn sni_cert(server_name: &str, name: &str) -> SniCert {
+        SniCert {
+            server_name: server_name.to_string(),
+            cert_str: read_test_pem(&format!("test_sni_{}_cert.pem", name)),
+            key_str: read_test_pem(&format!("test_sni_{}_key.pem", name)),
+        }
+    }
+    fn insert_mapping(mapping_key: &str, sni_certs: Vec<SniCert>) {
+        let (sender, _) = tokio::sync::mpsc::channel(1);
+        GLOBAL_CONFIG_MAPPING.insert(
+            mapping_key.to_string(),
+            ApiServiceManager {
+                service_config: ServiceConfig {
+                    server_type: ServiceType::Https,
+                    cert_str: Some(read_test_pem("test_cert.pem")),
+                    key_str: Some(read_test_pem("test_key.pem")),
+                    sni_certs: Some(sni_certs),
+                    ..Default::default()
+                },
+                sender,
+            },
+        );
+    }
+    #[test]
+    fn test_reload_second_domain_from_mapping() {
+        let mapping_key = "10087-HTTPS-cert-watcher";
+        let sni_certs = vec![
+            sni_cert("a.example.com", "a"),
+            sni_cert("b.example.com", "b"),
+        ];
+        insert_mapping(mapping_key, sni_certs.clone());
+        let cert_resolver = SniCertResolver::new(
+            &read_test_pem("test_cert.pem"),
+            &read_test_pem("test_key.pem"),
+            &sni_certs,
+        )
+        .unwrap();
+        assert_eq!(
+            reload_from_mapping(mapping_key, &cert_resolver)
+                .unwrap()
+                .unwrap(),
+            Vec::<String>::new()
+        );
+        let cert_a = cert_resolver.get_cert(Some("a.example.com")).unwrap();
+        let cert_b = cert_resolver.get_cert(Some("b.example.com")).unwrap();
+        assert_ne!(cert_a.cert, cert_b.cert);
+
+        insert_mapping(
+            mapping_key,
+            vec![
+                sni_cert("a.example.com", "a"),
+                sni_cert("b.example.com", "a"),
+            ],
+        );
+        assert_eq!(
+            reload_from_mapping(mapping_key, &cert_resolver)
+                .unwrap()
+                .unwrap(),
+            vec![String::from("b.example.com")]
+        );
+        let new_cert_b = cert_resolver.get_cert(Some("b.example.com")).unwrap();
+        assert_eq!(new_cert_b.cert, cert_a.cert);
+        assert!(Arc::ptr_eq(
+            &cert_resolver.get_cert(Some("a.example.com")).unwrap(),
+            &cert_a
+        ));
+
+        let mut invalid_cert = sni_cert("b.example.com", "b");
+        invalid_cert.cert_str = String::from("invalid");
+        insert_mapping(
+            mapping_key,
+            vec![sni_cert("a.example.com", "a"), invalid_cert],
+        );
+        assert!(reload_from_mapping(mapping_key, &cert_resolver)
+            .unwrap()
+            .is_err());
+        assert!(Arc::ptr_eq(
+            &cert_resolver.get_cert(Some("b.example.com")).unwrap(),
+            &new_cert_b
+        ));
+
+        GLOBAL_CONFIG_MAPPING.remove(mapping_key);
+        assert!(reload_from_mapping(mapping_key, &cert_resolver).is_none());
+    }
+    #[tokio::test]
+    async fn test_cert_watcher_stopped_with_guard() {
+        let mapping_key = "10088-HTTPS-cert-watcher";
+        insert_mapping(mapping_key, vec![]);
+        let cert_resolver = Arc::new(
+            SniCertResolver::new(
+                &read_test_pem("test_cert.pem"),
+                &read_test_pem("test_key.pem"),
+                &[],
+            )
+            .unwrap(),
+        );
+        let cert_watcher = spawn_cert_watcher(mapping_key.to_string(), cert_resolver.clone());
+        assert_eq!(Arc::strong_count(&cert_resolver), 2);
+        drop(cert_watcher);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(Arc::strong_count(&cert_resolver), 1);
+        GLOBAL_CONFIG_MAPPING.remove(mapping_key);
+    }
+}
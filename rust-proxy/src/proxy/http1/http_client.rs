@@ -1,58 +1,912 @@
+use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
+use crate::constants::common_constants::{DEFAULT_HTTP_TIMEOUT, DEFAULT_UPSTREAM_IDLE_TIMEOUT};
+use crate::vojo::app_config::{TimeoutConfig, UpstreamPoolConfig, UpstreamTlsConfig};
+use crate::vojo::app_error::{AppError, AppErrorKind};
 use bytes::Bytes;
+use dashmap::DashMap;
+use http::Extensions;
 use http_body_util::combinators::BoxBody;
-
-use hyper::Request;
-use hyper_util::client::legacy::connect::HttpConnector;
+use http_body_util::BodyExt;
+use hyper::body::{Body, Frame, Incoming, SizeHint};
+use hyper::{Request, Response};
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use hyper_util::client::legacy::connect::{capture_connection, CaptureConnection};
+use hyper_util::client::legacy::connect::{HttpConnector, HttpInfo};
+use hyper_util::client::legacy::Error;
 use hyper_util::client::legacy::ResponseFuture;
+use hyper_util::rt::TokioTimer;
 use hyper_util::{client::legacy::Client, rt::TokioExecutor};
-use rustls::RootCertStore;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::ring::default_provider;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature};
+use rustls::{DigitallySignedStruct, RootCertStore, SignatureScheme};
+use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
 use std::convert::Infallible;
+use std::future::Future;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
+use tokio::time::error::Elapsed;
 use tokio::time::timeout;
 use tokio::time::Timeout;
+use tokio::time::{sleep_until, timeout_at, Instant, Sleep};
+use tower::Service;
 
 #[derive(Clone)]
 pub struct HttpClients {
-    pub http_client: Client<HttpConnector, BoxBody<Bytes, Infallible>>,
-    pub https_client:
-        Client<hyper_rustls::HttpsConnector<HttpConnector>, BoxBody<Bytes, Infallible>>,
+    pub http_client: Client<HttpConnector<UpstreamResolver>, BoxBody<Bytes, Infallible>>,
+    pub https_client: Client<
+        hyper_rustls::HttpsConnector<HttpConnector<UpstreamResolver>>,
+        BoxBody<Bytes, Infallible>,
+    >,
+    pub lowercase_http_client: Client<HttpConnector<UpstreamResolver>, BoxBody<Bytes, Infallible>>,
+    pub lowercase_https_client: Client<
+        hyper_rustls::HttpsConnector<HttpConnector<UpstreamResolver>>,
+        BoxBody<Bytes, Infallible>,
+    >,
+    pool_config: UpstreamPoolConfig,
+    tls_config: rustls::ClientConfig,
+    server_name: Option<String>,
+    mapping_key: Option<String>,
+    connect_timeout_clients: Arc<DashMap<u64, HttpClients>>,
+    server_name_clients: Arc<DashMap<String, HttpClients>>,
+    connection_created_at: Arc<DashMap<(SocketAddr, SocketAddr), Instant>>,
+}
+/**
+ *Only used when the upstream tls verification is skipped, the handshake signatures are still verified.
+ */
+#[derive(Debug)]
+struct SkipServerVerification;
+impl ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &default_provider().signature_verification_algorithms,
+        )
+    }
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &default_provider().signature_verification_algorithms,
+        )
+    }
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+/**
+ *The certificates in the ca file are trusted besides the webpki roots.
+ */
+fn build_tls_config(
+    upstream_tls: Option<&UpstreamTlsConfig>,
+) -> Result<rustls::ClientConfig, AppError> {
+    if upstream_tls.is_some_and(|item| item.insecure_skip_verify) {
+        warn!("The certificate verification of the upstream is skipped!");
+        return Ok(rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(SkipServerVerification))
+            .with_no_client_auth());
+    }
+    let mut root_store = RootCertStore::empty();
+    root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    if let Some(ca_file) = upstream_tls.and_then(|item| item.ca_file.as_ref()) {
+        let ca_str = std::fs::read_to_string(ca_file).map_err(|e| {
+            AppError::new(
+                AppErrorKind::Tls,
+                format!("Can not read the ca file {},{}", ca_file, e),
+            )
+        })?;
+        let certs = rustls_pemfile::certs(&mut BufReader::new(ca_str.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::new(AppErrorKind::Tls, e.to_string()))?;
+        if certs.is_empty() {
+            return Err(AppError::new(
+                AppErrorKind::Tls,
+                format!("Can not find any certificate in the ca file {}", ca_file),
+            ));
+        }
+        for cert in certs {
+            root_store
+                .add(cert)
+                .map_err(|e| AppError::new(AppErrorKind::Tls, e.to_string()))?;
+        }
+    }
+    Ok(rustls::ClientConfig::builder()
+        .with_root_certificates(root_store)
+        .with_no_client_auth())
+}
+/**
+ *The resolved addresses are checked against the upstream policy of the service before the connector uses them,
+ *so a domain could not be pointed to a denied address after the route is checked.
+ */
+#[derive(Clone)]
+pub struct UpstreamResolver {
+    resolver: GaiResolver,
+    mapping_key: Option<String>,
+}
+impl Service<Name> for UpstreamResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = std::io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.resolver.poll_ready(cx)
+    }
+    fn call(&mut self, name: Name) -> Self::Future {
+        let resolving = self.resolver.call(name.clone());
+        let mapping_key = self.mapping_key.clone();
+        Box::pin(async move {
+            let addrs = resolving.await?.collect::<Vec<SocketAddr>>();
+            let upstream_policy = mapping_key
+                .and_then(|key| {
+                    GLOBAL_CONFIG_MAPPING
+                        .get(&key)
+                        .and_then(|item| item.service_config.upstream_policy.clone())
+                })
+                .unwrap_or_default();
+            for addr in addrs.iter() {
+                upstream_policy
+                    .check_address(name.as_str(), addr.ip())
+                    .map_err(|e| {
+                        std::io::Error::new(std::io::ErrorKind::PermissionDenied, e.to_string())
+                    })?;
+            }
+            Ok(addrs.into_iter())
+        })
+    }
+}
+fn build_http_connector(
+    connect_timeout: Option<Duration>,
+    pool_config: &UpstreamPoolConfig,
+    mapping_key: Option<String>,
+) -> HttpConnector<UpstreamResolver> {
+    let mut http_connector = HttpConnector::new_with_resolver(UpstreamResolver {
+        resolver: GaiResolver::new(),
+        mapping_key,
+    });
+    http_connector.set_connect_timeout(connect_timeout);
+    if let Some(tcp_nodelay) = pool_config.tcp_nodelay {
+        http_connector.set_nodelay(tcp_nodelay);
+    }
+    http_connector.set_keepalive(pool_config.tcp_keepalive.map(Duration::from_secs));
+    http_connector
+        .set_keepalive_interval(pool_config.tcp_keepalive_interval.map(Duration::from_secs));
+    http_connector
 }
 impl HttpClients {
     pub fn new() -> HttpClients {
-        let http_client = Client::builder(TokioExecutor::new())
-            .http1_title_case_headers(true)
+        HttpClients::build(
+            None,
+            UpstreamPoolConfig::default(),
+            build_tls_config(None).unwrap(),
+            None,
+            None,
+        )
+    }
+    /**
+     *The upstream policy of the service on the mapping key is applied to the resolved addresses.
+     */
+    pub fn with_config(
+        pool_config: UpstreamPoolConfig,
+        upstream_tls: Option<&UpstreamTlsConfig>,
+        mapping_key: Option<String>,
+    ) -> Result<HttpClients, AppError> {
+        Ok(HttpClients::build(
+            None,
+            pool_config,
+            build_tls_config(upstream_tls)?,
+            upstream_tls.and_then(|item| item.server_name.clone()),
+            mapping_key,
+        ))
+    }
+    fn build(
+        connect_timeout: Option<Duration>,
+        pool_config: UpstreamPoolConfig,
+        tls_config: rustls::ClientConfig,
+        server_name: Option<String>,
+        mapping_key: Option<String>,
+    ) -> HttpClients {
+        let mut http_connector =
+            build_http_connector(connect_timeout, &pool_config, mapping_key.clone());
+        let mut builder = Client::builder(TokioExecutor::new());
+        builder.pool_timer(TokioTimer::new());
+        if let Some(max_idle_per_host) = pool_config.max_idle_per_host {
+            builder.pool_max_idle_per_host(max_idle_per_host);
+        }
+        let idle_timeout = pool_config
+            .idle_timeout
+            .unwrap_or(DEFAULT_UPSTREAM_IDLE_TIMEOUT);
+        builder.pool_idle_timeout(Duration::from_secs(idle_timeout));
+        let http_client = builder
+            .clone()
+            .http1_preserve_header_case(true)
+            .build(http_connector.clone());
+        let lowercase_http_client = builder.clone().build(http_connector.clone());
+        http_connector.enforce_http(false);
+        let mut https_builder = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_tls_config(tls_config.clone())
+            .https_or_http();
+        if let Some(server_name) = server_name.clone() {
+            https_builder = https_builder.with_server_name(server_name);
+        }
+        let https = https_builder.enable_http1().wrap_connector(http_connector);
+        let https_client = builder
+            .clone()
             .http1_preserve_header_case(true)
-            .build_http();
-        let mut root_store = RootCertStore::empty();
-        root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
-        let tls = rustls::ClientConfig::builder()
-            .with_root_certificates(root_store)
-            .with_no_client_auth();
-        let https = hyper_rustls::HttpsConnectorBuilder::new()
-            .with_tls_config(tls)
-            .https_or_http()
-            .enable_http1()
-            .build();
-        let https_client = Client::builder(TokioExecutor::new()).build(https);
+            .build(https.clone());
+        let lowercase_https_client = builder.build(https);
         HttpClients {
             http_client,
             https_client,
+            lowercase_http_client,
+            lowercase_https_client,
+            pool_config,
+            tls_config,
+            server_name,
+            mapping_key,
+            connect_timeout_clients: Arc::new(DashMap::new()),
+            server_name_clients: Arc::new(DashMap::new()),
+            connection_created_at: Arc::new(DashMap::new()),
+        }
+    }
+    /**
+     *The client which sends the given server name in the tls handshake and verifies the certificate against it.
+     */
+    pub fn with_server_name(&self, server_name: &str) -> HttpClients {
+        self.server_name_clients
+            .entry(server_name.to_string())
+            .or_insert_with(|| {
+                HttpClients::build(
+                    None,
+                    self.pool_config.clone(),
+                    self.tls_config.clone(),
+                    Some(server_name.to_string()),
+                    self.mapping_key.clone(),
+                )
+            })
+            .clone()
+    }
+    fn with_connect_timeout(&self, connect_timeout: Option<u64>) -> HttpClients {
+        match connect_timeout {
+            Some(connect_timeout) => self
+                .connect_timeout_clients
+                .entry(connect_timeout)
+                .or_insert_with(|| {
+                    HttpClients::build(
+                        Some(Duration::from_millis(connect_timeout)),
+                        self.pool_config.clone(),
+                        self.tls_config.clone(),
+                        self.server_name.clone(),
+                        self.mapping_key.clone(),
+                    )
+                })
+                .clone(),
+            None => self.clone(),
+        }
+    }
+    fn send(
+        &self,
+        req: Request<BoxBody<Bytes, Infallible>>,
+        preserve_header_case: bool,
+    ) -> ResponseFuture {
+        let is_https = req.uri().scheme_str() == Some("https");
+        match (is_https, preserve_header_case) {
+            (true, true) => self.https_client.request(req),
+            (true, false) => self.lowercase_https_client.request(req),
+            (false, true) => self.http_client.request(req),
+            (false, false) => self.lowercase_http_client.request(req),
         }
     }
     pub fn request_http(
         &self,
         req: Request<BoxBody<Bytes, Infallible>>,
         time_out: u64,
+        preserve_header_case: bool,
     ) -> Timeout<ResponseFuture> {
-        let request_future = self.http_client.request(req);
+        let request_future = if preserve_header_case {
+            self.http_client.request(req)
+        } else {
+            self.lowercase_http_client.request(req)
+        };
         timeout(Duration::from_secs(time_out), request_future)
     }
     pub fn request_https(
         &self,
         req: Request<BoxBody<Bytes, Infallible>>,
         time_out: u64,
+        preserve_header_case: bool,
     ) -> Timeout<ResponseFuture> {
-        let request_future = self.https_client.request(req);
+        let request_future = if preserve_header_case {
+            self.https_client.request(req)
+        } else {
+            self.lowercase_https_client.request(req)
+        };
         timeout(Duration::from_secs(time_out), request_future)
     }
+    /**
+     *The connection older than the max lifetime is not returned to the pool after the current response.
+     */
+    fn expire_connection(&self, captured_connection: &CaptureConnection) {
+        let Some(max_lifetime) = self.pool_config.max_lifetime.map(Duration::from_secs) else {
+            return;
+        };
+        let connection_metadata = captured_connection.connection_metadata();
+        let Some(connected) = connection_metadata.as_ref() else {
+            return;
+        };
+        let mut extensions = Extensions::new();
+        connected.get_extras(&mut extensions);
+        let Some(http_info) = extensions.get::<HttpInfo>() else {
+            return;
+        };
+        let key = (http_info.local_addr(), http_info.remote_addr());
+        if !self.connection_created_at.contains_key(&key) {
+            let idle_timeout = Duration::from_secs(
+                self.pool_config
+                    .idle_timeout
+                    .unwrap_or(DEFAULT_UPSTREAM_IDLE_TIMEOUT),
+            );
+            self.connection_created_at
+                .retain(|_, created_at| created_at.elapsed() < max_lifetime + idle_timeout);
+        }
+        let created_at = *self
+            .connection_created_at
+            .entry(key)
+            .or_insert_with(Instant::now);
+        if created_at.elapsed() >= max_lifetime {
+            connected.poison();
+            self.connection_created_at.remove(&key);
+        }
+    }
+    pub async fn request_with_timeout(
+        &self,
+        mut req: Request<BoxBody<Bytes, Infallible>>,
+        timeout_config: &TimeoutConfig,
+        preserve_header_case: bool,
+    ) -> Result<Result<Response<BoxBody<Bytes, hyper::Error>>, Error>, Elapsed> {
+        let deadline = timeout_config
+            .request_timeout
+            .map(|item| Instant::now() + Duration::from_millis(item));
+        let mut read_deadline = Instant::now()
+            + timeout_config
+                .read_timeout
+                .map(Duration::from_millis)
+                .unwrap_or(Duration::from_secs(DEFAULT_HTTP_TIMEOUT));
+        if let Some(deadline) = deadline {
+            read_deadline = read_deadline.min(deadline);
+        }
+        let captured_connection = self
+            .pool_config
+            .max_lifetime
+            .map(|_| capture_connection(&mut req));
+        let request_future = self
+            .with_connect_timeout(timeout_config.connect_timeout)
+            .send(req, preserve_header_case);
+        let response_result = timeout_at(read_deadline, request_future).await?;
+        if let Some(captured_connection) = captured_connection {
+            self.expire_connection(&captured_connection);
+        }
+        Ok(response_result.map(|response| match deadline {
+            Some(deadline) => response.map(|body| TimeoutBody::new(body, deadline).boxed()),
+            None => response.map(|body| body.boxed()),
+        }))
+    }
+}
+pub struct TimeoutBody {
+    inner: Incoming,
+    sleep: Pin<Box<Sleep>>,
+}
+impl TimeoutBody {
+    pub fn new(inner: Incoming, deadline: Instant) -> Self {
+        TimeoutBody {
+            inner,
+            sleep: Box::pin(sleep_until(deadline)),
+        }
+    }
+}
+impl Body for TimeoutBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if self.sleep.as_mut().poll(cx).is_ready() {
+            error!("The upstream response exceeded the request timeout and has been cut off!");
+            return Poll::Ready(None);
+        }
+        Pin::new(&mut self.inner).poll_frame(cx)
+    }
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::api_service_manager::ApiServiceManager;
+    use crate::vojo::app_config::ServiceConfig;
+    use crate::vojo::upstream_policy::UpstreamPolicy;
+    use http_body_util::BodyExt;
+    use http_body_util::Full;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+    use tower::{Service, ServiceExt};
+
+    async fn send_and_capture_request(port: u16, preserve_header_case: bool) -> String {
+        let backend = TcpListener::bind(format!("127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let len = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..len]).to_string()
+        });
+        let request = Request::builder()
+            .uri(format!("http://127.0.0.1:{}/", port))
+            .header("x-custom-header", "value")
+            .body(Full::new(Bytes::new()).boxed())
+            .unwrap();
+        let response = HttpClients::new()
+            .request_http(request, 5, preserve_header_case)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        handle.await.unwrap()
+    }
+    #[tokio::test]
+    async fn test_request_http_preserve_header_case() {
+        let raw_request = send_and_capture_request(10043, true).await;
+        assert!(raw_request.contains("x-custom-header: value"));
+    }
+    #[tokio::test]
+    async fn test_request_http_lowercase_header() {
+        let raw_request = send_and_capture_request(10044, false).await;
+        assert!(raw_request.contains("x-custom-header: value"));
+    }
+    fn create_request(port: u16) -> Request<BoxBody<Bytes, Infallible>> {
+        Request::builder()
+            .uri(format!("http://127.0.0.1:{}/", port))
+            .body(Full::new(Bytes::new()).boxed())
+            .unwrap()
+    }
+    #[tokio::test]
+    async fn test_request_with_connect_timeout() {
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:10048".parse().unwrap()).unwrap();
+        let _listener = socket.listen(0).unwrap();
+        let mut backlog = vec![];
+        for _ in 0..8 {
+            if let Ok(Ok(stream)) = tokio::time::timeout(
+                Duration::from_millis(100),
+                tokio::net::TcpStream::connect("127.0.0.1:10048"),
+            )
+            .await
+            {
+                backlog.push(stream);
+            }
+        }
+        let timeout_config = TimeoutConfig {
+            connect_timeout: Some(200),
+            read_timeout: Some(5000),
+            request_timeout: None,
+        };
+        let start = Instant::now();
+        let response_result = HttpClients::new()
+            .request_with_timeout(create_request(10048), &timeout_config, true)
+            .await;
+        assert!(matches!(response_result, Ok(Err(_))));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+    #[tokio::test]
+    async fn test_request_with_read_timeout() {
+        let backend = TcpListener::bind("127.0.0.1:10049").await.unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(3)).await;
+        });
+        let timeout_config = TimeoutConfig {
+            connect_timeout: Some(1000),
+            read_timeout: Some(200),
+            request_timeout: None,
+        };
+        let start = Instant::now();
+        let response_result = HttpClients::new()
+            .request_with_timeout(create_request(10049), &timeout_config, true)
+            .await;
+        assert!(response_result.is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+    #[tokio::test]
+    async fn test_request_with_request_timeout() {
+        let backend = TcpListener::bind("127.0.0.1:10050").await.unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\nhello")
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_secs(3)).await;
+        });
+        let timeout_config = TimeoutConfig {
+            connect_timeout: None,
+            read_timeout: Some(1000),
+            request_timeout: Some(500),
+        };
+        let start = Instant::now();
+        let response = HttpClients::new()
+            .request_with_timeout(create_request(10050), &timeout_config, true)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, Bytes::from("hello"));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+    async fn count_upstream_connections(port: u16, pool_config: UpstreamPoolConfig) -> usize {
+        let backend = TcpListener::bind(format!("127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        let accept_count = Arc::new(AtomicUsize::new(0));
+        let accept_count_clone = accept_count.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                accept_count_clone.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = [0; 1024];
+                    while let Ok(len) = stream.read(&mut buf).await {
+                        if len == 0 {
+                            break;
+                        }
+                        let _ = stream
+                            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                            .await;
+                    }
+                });
+            }
+        });
+        let client = HttpClients::with_config(pool_config, None, None).unwrap();
+        for _ in 0..3 {
+            let response = client
+                .request_http(create_request(port), 5, true)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(response.status(), 200);
+            response.into_body().collect().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        accept_count.load(Ordering::SeqCst)
+    }
+    #[tokio::test]
+    async fn test_pooled_client_reuses_connection() {
+        let accept_count = count_upstream_connections(10053, UpstreamPoolConfig::default()).await;
+        assert_eq!(accept_count, 1);
+    }
+    #[tokio::test]
+    async fn test_pool_disabled_opens_new_connections() {
+        let pool_config = UpstreamPoolConfig {
+            max_idle_per_host: Some(0),
+            idle_timeout: None,
+            ..Default::default()
+        };
+        let accept_count = count_upstream_connections(10054, pool_config).await;
+        assert_eq!(accept_count, 3);
+    }
+    #[tokio::test]
+    async fn test_resolved_address_denied_by_upstream_policy() {
+        let (sender, _) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10159-HTTP"),
+            ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    upstream_policy: Some(UpstreamPolicy {
+                        denied_hosts: Some(vec![String::from("127.0.0.0/8")]),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            },
+        );
+        let client = HttpClients::with_config(
+            UpstreamPoolConfig::default(),
+            None,
+            Some(String::from("10159-HTTP")),
+        )
+        .unwrap();
+        let request = Request::builder()
+            .uri("http://localhost:10159/")
+            .body(Full::new(Bytes::new()).boxed())
+            .unwrap();
+        let err = client
+            .request_http(request, 5, true)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(
+            format!("{:?}", err).contains("PermissionDenied"),
+            "{:?}",
+            err
+        );
+        GLOBAL_CONFIG_MAPPING.remove("10159-HTTP");
+    }
+    #[tokio::test]
+    async fn test_upstream_socket_options() {
+        let backend = TcpListener::bind("127.0.0.1:10125").await.unwrap();
+        tokio::spawn(async move {
+            let _ = backend.accept().await;
+        });
+        let pool_config = UpstreamPoolConfig {
+            tcp_nodelay: Some(true),
+            tcp_keepalive: Some(30),
+            tcp_keepalive_interval: Some(5),
+            ..Default::default()
+        };
+        let mut http_connector = build_http_connector(None, &pool_config, None);
+        let io = http_connector
+            .ready()
+            .await
+            .unwrap()
+            .call("http://127.0.0.1:10125".parse().unwrap())
+            .await
+            .unwrap();
+        let stream = io.into_inner();
+        assert!(stream.nodelay().unwrap());
+        let socket = tokio::net::TcpSocket::from_std_stream(stream.into_std().unwrap());
+        assert!(socket.keepalive().unwrap());
+    }
+    #[tokio::test]
+    async fn test_upstream_connection_max_lifetime() {
+        let backend = TcpListener::bind("127.0.0.1:10126").await.unwrap();
+        let accept_count = Arc::new(AtomicUsize::new(0));
+        let accept_count_clone = accept_count.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = backend.accept().await {
+                accept_count_clone.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = [0; 1024];
+                    while let Ok(len) = stream.read(&mut buf).await {
+                        if len == 0 {
+                            break;
+                        }
+                        let _ = stream
+                            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                            .await;
+                    }
+                });
+            }
+        });
+        let client = HttpClients::with_config(
+            UpstreamPoolConfig {
+                max_lifetime: Some(1),
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        let send_request = || async {
+            let response = client
+                .request_with_timeout(create_request(10126), &TimeoutConfig::default(), true)
+                .await
+                .unwrap()
+                .unwrap();
+            response.into_body().collect().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+        send_request().await;
+        send_request().await;
+        assert_eq!(accept_count.load(Ordering::SeqCst), 1);
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        send_request().await;
+        send_request().await;
+        assert_eq!(accept_count.load(Ordering::SeqCst), 2);
+    }
+    #[tokio::test]
+    async fn test_idle_upstream_connection_closed() {
+        let backend = TcpListener::bind("127.0.0.1:10056").await.unwrap();
+        let (closed_sender, closed_receiver) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            while let Ok(len) = stream.read(&mut buf).await {
+                if len == 0 {
+                    break;
+                }
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                    .await;
+            }
+            let _ = closed_sender.send(());
+        });
+        let client = HttpClients::with_config(
+            UpstreamPoolConfig {
+                max_idle_per_host: None,
+                idle_timeout: Some(1),
+                ..Default::default()
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        let response = client
+            .request_http(create_request(10056), 5, true)
+            .await
+            .unwrap()
+            .unwrap();
+        response.into_body().collect().await.unwrap();
+        let start = Instant::now();
+        let closed_result = tokio::time::timeout(Duration::from_secs(4), closed_receiver).await;
+        assert!(closed_result.is_ok());
+        assert!(start.elapsed() >= Duration::from_millis(900));
+    }
+    fn test_pem_path(file_name: &str) -> String {
+        std::env::current_dir()
+            .unwrap()
+            .join("config")
+            .join(file_name)
+            .to_string_lossy()
+            .to_string()
+    }
+    async fn start_tls_backend(port: u16) {
+        let cert_str = std::fs::read_to_string(test_pem_path("test_upstream_cert.pem")).unwrap();
+        let key_str = std::fs::read_to_string(test_pem_path("test_upstream_key.pem")).unwrap();
+        let certs = rustls_pemfile::certs(&mut BufReader::new(cert_str.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = rustls_pemfile::private_key(&mut BufReader::new(key_str.as_bytes()))
+            .unwrap()
+            .unwrap();
+        let server_config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(server_config));
+        let backend = TcpListener::bind(format!("127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = backend.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 2\r\n\r\nok",
+                        )
+                        .await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+    }
+    async fn request_tls_backend(port: u16, upstream_tls: Option<UpstreamTlsConfig>) -> bool {
+        let client =
+            HttpClients::with_config(UpstreamPoolConfig::default(), upstream_tls.as_ref(), None)
+                .unwrap();
+        let request = Request::builder()
+            .uri(format!("https://127.0.0.1:{}/", port))
+            .body(Full::new(Bytes::new()).boxed())
+            .unwrap();
+        matches!(
+            client.request_https(request, 5, true).await,
+            Ok(Ok(response)) if response.status() == 200
+        )
+    }
+    #[tokio::test]
+    async fn test_upstream_tls_verification() {
+        start_tls_backend(10111).await;
+        assert!(!request_tls_backend(10111, None).await);
+        let trusted = UpstreamTlsConfig {
+            ca_file: Some(test_pem_path("test_upstream_ca.pem")),
+            insecure_skip_verify: false,
+            server_name: None,
+        };
+        assert!(request_tls_backend(10111, Some(trusted.clone())).await);
+        let skip_verify = UpstreamTlsConfig {
+            ca_file: None,
+            insecure_skip_verify: true,
+            server_name: None,
+        };
+        assert!(request_tls_backend(10111, Some(skip_verify)).await);
+
+        let matched_server_name = UpstreamTlsConfig {
+            server_name: Some(String::from("upstream.test")),
+            ..trusted.clone()
+        };
+        assert!(request_tls_backend(10111, Some(matched_server_name)).await);
+        let mismatched_server_name = UpstreamTlsConfig {
+            server_name: Some(String::from("other.test")),
+            ..trusted
+        };
+        assert!(!request_tls_backend(10111, Some(mismatched_server_name)).await);
+    }
+    #[tokio::test]
+    async fn test_request_with_server_name() {
+        start_tls_backend(10113).await;
+        let upstream_tls = UpstreamTlsConfig {
+            ca_file: Some(test_pem_path("test_upstream_ca.pem")),
+            ..Default::default()
+        };
+        let client =
+            HttpClients::with_config(UpstreamPoolConfig::default(), Some(&upstream_tls), None)
+                .unwrap();
+        for (server_name, is_ok) in [("upstream.test", true), ("other.test", false)] {
+            let request = Request::builder()
+                .uri("https://127.0.0.1:10113/")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let response_result = client
+                .with_server_name(server_name)
+                .request_with_timeout(request, &TimeoutConfig::default(), true)
+                .await
+                .unwrap();
+            assert_eq!(response_result.is_ok(), is_ok);
+        }
+    }
+    #[test]
+    fn test_upstream_tls_invalid_ca_file() {
+        let upstream_tls = UpstreamTlsConfig {
+            ca_file: Some(test_pem_path("app_config.yaml")),
+            ..Default::default()
+        };
+        assert!(build_tls_config(Some(&upstream_tls)).is_err());
+        let upstream_tls = UpstreamTlsConfig {
+            ca_file: Some(test_pem_path("not_exist.pem")),
+            ..Default::default()
+        };
+        assert!(build_tls_config(Some(&upstream_tls)).is_err());
+    }
 }
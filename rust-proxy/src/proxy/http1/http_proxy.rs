@@ -1,73 +1,160 @@
+use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
+use crate::configuration_service::readiness::mark_listener_bound;
 use crate::constants::common_constants;
 use crate::constants::common_constants::DEFAULT_HTTP_TIMEOUT;
-use crate::monitor::prometheus_exporter::{get_timer_list, inc};
+use crate::constants::common_constants::MINIMUM_MAX_BUF_SIZE;
+use crate::constants::common_constants::TRACEPARENT;
+use crate::constants::common_constants::X_CACHE;
+use crate::constants::common_constants::X_HTTP_METHOD_OVERRIDE;
+use crate::constants::common_constants::X_RATELIMIT_REASON;
+use crate::constants::common_constants::X_REQUEST_ID;
+use crate::constants::common_constants::{X_FORWARDED_FOR, X_FORWARDED_HOST, X_FORWARDED_PROTO};
+use crate::monitor::counting_body::CountingBody;
+use crate::monitor::prometheus_exporter::{
+    get_request_size_histogram, get_response_size_histogram, get_route_limit_rejected_counter,
+    get_timer_list, inc,
+};
+use crate::proxy::cert_watcher::spawn_cert_watcher;
 use crate::proxy::http1::http_client::HttpClients;
+use crate::proxy::http1::static_file::{
+    accepted_encodings, encode_path_segment, find_index_file, render_directory_listing,
+    resolve_directory,
+};
+use crate::proxy::listener::{apply_stream_options, listen_addrs, MultiListener};
 
 use crate::vojo::anomaly_detection::AnomalyDetectionType;
-use crate::vojo::app_config::{LivenessConfig, LivenessStatus};
-use crate::vojo::app_error::AppError;
-use crate::vojo::route::BaseRoute;
+use crate::vojo::app_config::{
+    AccessLogFormat, BodyLoggingConfig, HeaderLimitConfig, LivenessConfig, LivenessStatus,
+    MaintenanceConfig, MirrorConfig, Route, ServiceType, SocketOptions, TimeoutConfig,
+    UpstreamPoolConfig, UpstreamTlsConfig,
+};
+use crate::vojo::app_config_vistor::default_preserve_header_case;
+use crate::vojo::app_error::{AppError, AppErrorKind};
+use crate::vojo::combined_limit::{LimitReason, LimitResult, RatelimitResponse};
+use crate::vojo::lets_encrypt::get_challenge_proof;
+use crate::vojo::response_cache::CacheConfig;
+use crate::vojo::route::{BaseRoute, LoadbalancerStrategy};
+use crate::vojo::single_flight::{
+    SharedResponse, SingleFlight, SingleFlightConfig, SingleFlightLeader,
+};
+use crate::vojo::tls_policy::build_server_config;
+use crate::vojo::tls_policy::SniCert;
+use crate::vojo::tls_policy::SniCertResolver;
+use crate::vojo::tls_policy::TlsPolicy;
+use crate::vojo::trace_context::TraceContext;
 use bytes::Bytes;
 use http::uri::InvalidUri;
+use http::HeaderMap;
+use http::Method;
 use http::Uri;
+use http::Version;
+use hyper::body::Body;
 use hyper::body::Incoming;
-use hyper::header::{CONNECTION, SEC_WEBSOCKET_KEY};
+use hyper::header::{
+    HeaderValue, AUTHORIZATION, CONNECTION, CONTENT_LENGTH, CONTENT_TYPE, ETAG, HOST,
+    IF_MODIFIED_SINCE, IF_NONE_MATCH, LOCATION, RETRY_AFTER, SEC_WEBSOCKET_KEY, VARY,
+};
 use hyper::StatusCode;
 
 use crate::proxy::http1::websocket_proxy::server_upgrade;
+use crate::proxy::http2::grpc_status::{grpc_error_response, is_grpc_request};
 use crate::proxy::proxy_trait::CheckTrait;
 use crate::proxy::proxy_trait::CommonCheckRequest;
 use http::uri::PathAndQuery;
 use http_body_util::{combinators::BoxBody, BodyExt, Full};
-use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Request, Response};
 use hyper_staticfile::Static;
-use hyper_util::rt::TokioIo;
+use hyper_util::client::legacy::Error as ClientError;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
 use log::Level;
 use prometheus::HistogramTimer;
-use rustls_pki_types::CertificateDer;
 use serde_json::json;
+use std::collections::HashSet;
 use std::convert::Infallible;
-use std::io::BufReader;
+use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use std::time::SystemTime;
-use tokio::net::TcpListener;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 use tokio::sync::RwLock;
+use tokio::task::JoinSet;
+use tokio::time::error::Elapsed;
+use tokio::time::{sleep, timeout};
 use tokio_rustls::TlsAcceptor;
+use url::Url;
 #[derive(Debug)]
 pub struct HttpProxy {
     pub port: i32,
     pub channel: mpsc::Receiver<()>,
     pub mapping_key: String,
+    pub graceful_shutdown_timeout: u64,
+    pub socket_options: SocketOptions,
+    pub upstream_pool: UpstreamPoolConfig,
+    pub upstream_tls: Option<UpstreamTlsConfig>,
+    pub tls_policy: Option<TlsPolicy>,
+    pub sni_certs: Option<Vec<SniCert>>,
+    pub header_limit: HeaderLimitConfig,
+    pub bind_addresses: Option<Vec<IpAddr>>,
+}
+/**
+ *Hyper answers the request with 431 when the headers exceed the limits,the header size is raised to the minimum buffer size of http1.
+ */
+fn apply_header_limit(
+    builder: &mut auto::Builder<TokioExecutor>,
+    header_limit: &HeaderLimitConfig,
+) {
+    if let Some(max_header_size) = header_limit.max_header_size {
+        builder
+            .http1()
+            .max_buf_size(max_header_size.max(MINIMUM_MAX_BUF_SIZE));
+        builder
+            .http2()
+            .max_header_list_size(max_header_size.min(u32::MAX as usize) as u32);
+    }
+    if let Some(max_header_count) = header_limit.max_header_count {
+        builder.http1().max_headers(max_header_count);
+    }
 }
 
 impl HttpProxy {
     pub async fn start_http_server(&mut self) -> Result<(), AppError> {
-        let port_clone = self.port;
-        let addr = SocketAddr::from(([0, 0, 0, 0], port_clone as u16));
-        let client = HttpClients::new();
+        let addrs = listen_addrs(self.bind_addresses.as_deref(), self.port as u16);
+        let client = HttpClients::with_config(
+            self.upstream_pool.clone(),
+            self.upstream_tls.as_ref(),
+            Some(self.mapping_key.clone()),
+        )?;
         let mapping_key_clone1 = self.mapping_key.clone();
         let reveiver = &mut self.channel;
 
-        let listener = TcpListener::bind(addr)
-            .await
-            .map_err(|e| AppError(e.to_string()))?;
-        info!("Listening on http://{}", addr);
+        let mut listener = MultiListener::bind(&addrs, &self.socket_options)?;
+        mark_listener_bound(&self.mapping_key);
+        info!("Listening on http://{:?}", addrs);
+        let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+        let mut connection_tasks = JoinSet::new();
         loop {
             tokio::select! {
-               Ok((stream,addr))= listener.accept()=>{
+               Some((stream,addr))= listener.accept()=>{
+                apply_stream_options(&stream, &self.socket_options);
                 let client_cloned = client.clone();
                 let mapping_key2 = mapping_key_clone1.clone();
-                tokio::spawn(async move {
+                let mut shutdown_receiver = shutdown_receiver.clone();
+                let header_limit = self.header_limit.clone();
+                connection_tasks.spawn(async move {
                     let io = TokioIo::new(stream);
 
-                    if let Err(err) = http1::Builder::new()
-                    .preserve_header_case(true)
-                    .title_case_headers(true)
+                    let mut builder = auto::Builder::new(TokioExecutor::new());
+                    builder
+                        .http1()
+                        .preserve_header_case(true)
+                        .title_case_headers(true);
+                    apply_header_limit(&mut builder, &header_limit);
+                    let connection = builder
                         .serve_connection(
                             io,
                             service_fn(move |req: Request<Incoming>| {
@@ -76,20 +163,34 @@ impl HttpProxy {
                                 });
                                 proxy_adapter(client_cloned.clone(), req, mapping_key2.clone(), addr)
                             }),
-                        )
-                        .await
-                    {
+                        );
+                    tokio::pin!(connection);
+                    let result = tokio::select! {
+                        result = connection.as_mut() => result,
+                        _ = shutdown_receiver.changed() => {
+                            connection.as_mut().graceful_shutdown();
+                            connection.as_mut().await
+                        }
+                    };
+                    if let Err(err) = result {
                         error!("Error serving connection: {:?}", err);
                     }
                 });
                 },
+                Some(_) = connection_tasks.join_next(), if !connection_tasks.is_empty() => {},
                 _ = reveiver.recv() => {
                     info!("http server stoped");
                     break;
                 }
             }
         }
-
+        drop(listener);
+        drain_connections(
+            connection_tasks,
+            shutdown_sender,
+            self.graceful_shutdown_timeout,
+        )
+        .await;
         Ok(())
     }
     pub async fn start_https_server(
@@ -97,43 +198,45 @@ impl HttpProxy {
         pem_str: String,
         key_str: String,
     ) -> Result<(), AppError> {
-        let port_clone = self.port;
-        let addr = SocketAddr::from(([0, 0, 0, 0], port_clone as u16));
-        let client = HttpClients::new();
+        let addrs = listen_addrs(self.bind_addresses.as_deref(), self.port as u16);
+        let client = HttpClients::with_config(
+            self.upstream_pool.clone(),
+            self.upstream_tls.as_ref(),
+            Some(self.mapping_key.clone()),
+        )?;
         let mapping_key_clone1 = self.mapping_key.clone();
 
-        let mut cer_reader = BufReader::new(pem_str.as_bytes());
-        let certs: Vec<CertificateDer<'_>> = rustls_pemfile::certs(&mut cer_reader)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| AppError(e.to_string()))?;
-
-        let mut key_reader = BufReader::new(key_str.as_bytes());
-        let key_der = rustls_pemfile::private_key(&mut key_reader)
-            .map(|key| key.unwrap())
-            .map_err(|e| AppError(e.to_string()))?;
-
-        let tls_cfg = {
-            let cfg = rustls::ServerConfig::builder()
-                .with_no_client_auth()
-                .with_single_cert(certs, key_der)
-                .unwrap();
-            Arc::new(cfg)
-        };
+        let cert_resolver = SniCertResolver::new(
+            &pem_str,
+            &key_str,
+            self.sni_certs.as_deref().unwrap_or_default(),
+        )?;
+        let cert_resolver = Arc::new(cert_resolver);
+        let tls_cfg = Arc::new(build_server_config(
+            self.tls_policy.as_ref(),
+            cert_resolver.clone(),
+            vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        )?);
         let tls_acceptor = TlsAcceptor::from(tls_cfg);
         let reveiver = &mut self.channel;
 
-        let listener = TcpListener::bind(addr)
-            .await
-            .map_err(|e| AppError(e.to_string()))?;
-        info!("Listening on http://{}", addr);
+        let mut listener = MultiListener::bind(&addrs, &self.socket_options)?;
+        mark_listener_bound(&self.mapping_key);
+        let _cert_watcher = spawn_cert_watcher(self.mapping_key.clone(), cert_resolver);
+        info!("Listening on https://{:?}", addrs);
+        let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+        let mut connection_tasks = JoinSet::new();
         loop {
             tokio::select! {
-                    Ok((tcp_stream,addr))= listener.accept()=>{
+                    Some((tcp_stream,addr))= listener.accept()=>{
+                        apply_stream_options(&tcp_stream, &self.socket_options);
                 let tls_acceptor = tls_acceptor.clone();
 
                 let client = client.clone();
                 let mapping_key2 = mapping_key_clone1.clone();
-                tokio::spawn(async move {
+                let mut shutdown_receiver = shutdown_receiver.clone();
+                let header_limit = self.header_limit.clone();
+                connection_tasks.spawn(async move {
                     let tls_stream = match tls_acceptor.accept(tcp_stream).await {
                         Ok(tls_stream) => tls_stream,
                         Err(err) => {
@@ -148,28 +251,73 @@ impl HttpProxy {
 
                         proxy_adapter(client.clone(), req, mapping_key2.clone(), addr)
                     });
-                    if let Err(err) = http1::Builder::new().serve_connection(io, service).await {
+                    let mut builder = auto::Builder::new(TokioExecutor::new());
+                    apply_header_limit(&mut builder, &header_limit);
+                    let connection = builder.serve_connection(io, service);
+                    tokio::pin!(connection);
+                    let result = tokio::select! {
+                        result = connection.as_mut() => result,
+                        _ = shutdown_receiver.changed() => {
+                            connection.as_mut().graceful_shutdown();
+                            connection.as_mut().await
+                        }
+                    };
+                    if let Err(err) = result {
                         error!("Error serving connection: {:?}", err);
                     }
                 });
             },
+                    Some(_) = connection_tasks.join_next(), if !connection_tasks.is_empty() => {},
                     _ = reveiver.recv() => {
                         info!("https server stoped");
                         break;
                     }
                 }
         }
-
+        drop(listener);
+        drain_connections(
+            connection_tasks,
+            shutdown_sender,
+            self.graceful_shutdown_timeout,
+        )
+        .await;
         Ok(())
     }
 }
+async fn drain_connections(
+    mut connection_tasks: JoinSet<()>,
+    shutdown_sender: watch::Sender<bool>,
+    graceful_shutdown_timeout: u64,
+) {
+    if connection_tasks.is_empty() {
+        return;
+    }
+    info!(
+        "Waiting up to {} seconds for {} connections to finish",
+        graceful_shutdown_timeout,
+        connection_tasks.len()
+    );
+    let _ = shutdown_sender.send(true);
+    let drain = async { while connection_tasks.join_next().await.is_some() {} };
+    if timeout(Duration::from_secs(graceful_shutdown_timeout), drain)
+        .await
+        .is_err()
+    {
+        warn!(
+            "Graceful shutdown timed out,force closing {} connections",
+            connection_tasks.len()
+        );
+        connection_tasks.shutdown().await;
+    }
+}
 async fn proxy_adapter(
     client: HttpClients,
     req: Request<BoxBody<Bytes, Infallible>>,
     mapping_key: String,
     remote_addr: SocketAddr,
 ) -> Result<Response<BoxBody<Bytes, Infallible>>, Infallible> {
-    let result = proxy_adapter_with_error(client, req, mapping_key, remote_addr).await;
+    let request_path = req.uri().path().to_string();
+    let result = proxy_adapter_with_error(client, req, mapping_key.clone(), remote_addr).await;
     match result {
         Ok(res) => Ok(res),
         Err(err) => {
@@ -177,19 +325,241 @@ async fn proxy_adapter(
             let json_value = json!({
                 "error": err.to_string(),
             });
-            return Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Full::new(Bytes::copy_from_slice(json_value.to_string().as_bytes())).boxed())
-                .unwrap());
+            return Ok(error_response(
+                &mapping_key,
+                err.status_code(),
+                &request_path,
+                json_value.to_string(),
+            ));
+        }
+    }
+}
+fn error_response(
+    mapping_key: &str,
+    status: StatusCode,
+    path: &str,
+    default_body: String,
+) -> Response<BoxBody<Bytes, Infallible>> {
+    let error_page = GLOBAL_CONFIG_MAPPING.get(mapping_key).and_then(|item| {
+        item.service_config
+            .error_pages
+            .as_ref()
+            .and_then(|error_pages| error_pages.get(&status.as_u16()).cloned())
+    });
+    let Some(error_page) = error_page else {
+        return Response::builder()
+            .status(status)
+            .body(Full::new(Bytes::from(default_body)).boxed())
+            .unwrap();
+    };
+    let body = error_page.render(status.as_u16(), path);
+    let mut res = Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::from(body)).boxed())
+        .unwrap();
+    match HeaderValue::from_str(&error_page.content_type) {
+        Ok(content_type) => {
+            res.headers_mut().insert(CONTENT_TYPE, content_type);
+        }
+        Err(err) => error!("The content type of the error page is invalid,{}", err),
+    }
+    res
+}
+/**
+ *The custom body is sent as it is,otherwise the error page of the status is used.
+ */
+fn limited_response(
+    mapping_key: &str,
+    path: &str,
+    reason: LimitReason,
+    ratelimit_response: &RatelimitResponse,
+) -> Response<BoxBody<Bytes, Infallible>> {
+    let status = ratelimit_response.status_code();
+    let mut res = match ratelimit_response.body.as_ref() {
+        Some(body) => Response::builder()
+            .status(status)
+            .body(Full::new(Bytes::from(body.clone())).boxed())
+            .unwrap(),
+        None => {
+            let message = format!("The request is limited by the {} limit!", reason.as_str());
+            let json_value = json!({
+                "response_code": -1,
+                "response_object": message
+            });
+            error_response(mapping_key, status, path, json_value.to_string())
+        }
+    };
+    if let Some(content_type) = ratelimit_response.content_type.as_ref() {
+        match HeaderValue::from_str(content_type) {
+            Ok(content_type) => {
+                res.headers_mut().insert(CONTENT_TYPE, content_type);
+            }
+            Err(err) => error!(
+                "The content type of the ratelimit response is invalid,{}",
+                err
+            ),
+        }
+    }
+    if !ratelimit_response.omit_headers {
+        res.headers_mut().insert(
+            X_RATELIMIT_REASON,
+            HeaderValue::from_static(reason.as_str()),
+        );
+    }
+    res
+}
+fn find_matched_route<T>(
+    mapping_key: &str,
+    method: &Method,
+    path: String,
+    headers: &HeaderMap,
+    remote_addr: SocketAddr,
+    map: impl FnOnce(&Route) -> T,
+) -> Option<T> {
+    GLOBAL_CONFIG_MAPPING.get(mapping_key).and_then(|item| {
+        let server_type = &item.service_config.server_type;
+        item.service_config
+            .routes
+            .iter()
+            .find(|route| {
+                route.is_method_matched(method)
+                    && route.is_scheme_matched(server_type, headers, remote_addr.ip())
+                    && matches!(
+                        route.is_matched(path.clone(), Some(headers.clone())),
+                        Ok(Some(_))
+                    )
+            })
+            .map(map)
+    })
+}
+fn matched_route_id(
+    mapping_key: &str,
+    method: &Method,
+    path: String,
+    headers: &HeaderMap,
+    remote_addr: SocketAddr,
+) -> Option<String> {
+    find_matched_route(mapping_key, method, path, headers, remote_addr, |route| {
+        route.route_id.clone()
+    })
+}
+/**
+ *The route in maintenance is answered with 503 before the upstream is selected.
+ */
+fn maintenance_response(
+    mapping_key: &str,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    remote_addr: SocketAddr,
+) -> Option<Response<BoxBody<Bytes, Infallible>>> {
+    let backend_path = uri
+        .path_and_query()
+        .map(|item| item.to_string())
+        .unwrap_or_default();
+    let maintenance: MaintenanceConfig = find_matched_route(
+        mapping_key,
+        method,
+        backend_path,
+        headers,
+        remote_addr,
+        |route| route.maintenance.clone(),
+    )
+    .flatten()
+    .filter(|item| item.enabled)?;
+    let mut res = match maintenance.body {
+        Some(body) => {
+            let mut res = Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .body(Full::new(Bytes::from(body)).boxed())
+                .unwrap();
+            match HeaderValue::from_str(&maintenance.content_type) {
+                Ok(content_type) => {
+                    res.headers_mut().insert(CONTENT_TYPE, content_type);
+                }
+                Err(err) => error!("The content type of the maintenance is invalid,{}", err),
+            }
+            res
         }
+        None => error_response(
+            mapping_key,
+            StatusCode::SERVICE_UNAVAILABLE,
+            uri.path(),
+            String::from(common_constants::MAINTENANCE_RESPONSE),
+        ),
+    };
+    if let Some(retry_after) = maintenance.retry_after {
+        res.headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    }
+    Some(res)
+}
+fn has_matched_route(
+    mapping_key: &str,
+    method: &Method,
+    path: String,
+    headers: &HeaderMap,
+    remote_addr: SocketAddr,
+) -> bool {
+    matched_route_id(mapping_key, method, path, headers, remote_addr).is_some()
+}
+struct AccessLog<'a> {
+    method: &'a Method,
+    path: &'a str,
+    status: u16,
+    latency_ms: u128,
+    remote_addr: SocketAddr,
+    route_id: Option<String>,
+    bytes: Option<u64>,
+    request_id: Option<String>,
+    trace_id: Option<String>,
+    span_id: Option<String>,
+}
+impl AccessLog<'_> {
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "method": self.method.as_str(),
+            "path": self.path,
+            "status": self.status,
+            "latency_ms": self.latency_ms as u64,
+            "remote_addr": self.remote_addr.to_string(),
+            "route_id": self.route_id,
+            "bytes": self.bytes,
+            "request_id": self.request_id,
+            "trace_id": self.trace_id,
+            "span_id": self.span_id,
+        })
     }
 }
 async fn proxy_adapter_with_error(
     client: HttpClients,
-    req: Request<BoxBody<Bytes, Infallible>>,
+    mut req: Request<BoxBody<Bytes, Infallible>>,
     mapping_key: String,
     remote_addr: SocketAddr,
 ) -> Result<Response<BoxBody<Bytes, Infallible>>, AppError> {
+    let (access_log_format, request_id_config, trace_context_enabled) = GLOBAL_CONFIG_MAPPING
+        .get(&mapping_key)
+        .map(|item| {
+            (
+                item.service_config.access_log_format.clone(),
+                item.service_config.request_id.clone(),
+                item.service_config.trace_context.unwrap_or(false),
+            )
+        })
+        .unwrap_or_default();
+    let request_id = request_id_config
+        .map(|item| item.get_request_id(req.headers()))
+        .and_then(|item| HeaderValue::from_str(&item).ok());
+    if let Some(request_id) = request_id.clone() {
+        req.headers_mut().insert(X_REQUEST_ID, request_id);
+    }
+    let trace_context = trace_context_enabled.then(|| TraceContext::from_headers(req.headers()));
+    if let Some(traceparent) = trace_context
+        .as_ref()
+        .and_then(|item| HeaderValue::from_str(&item.to_traceparent()).ok())
+    {
+        req.headers_mut().insert(TRACEPARENT, traceparent);
+    }
     let method = req.method().clone();
     let uri = req.uri().clone();
     let path = uri
@@ -202,7 +572,7 @@ async fn proxy_adapter_with_error(
         .iter()
         .map(|item| item.start_timer())
         .collect::<Vec<HistogramTimer>>();
-    let res = proxy(
+    let mut res = proxy(
         client,
         req,
         mapping_key.clone(),
@@ -216,11 +586,16 @@ async fn proxy_adapter_with_error(
             "response_code": -1,
             "response_object": format!("{}", err)
         });
-        Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(Full::new(Bytes::copy_from_slice(json_value.to_string().as_bytes())).boxed())
-            .unwrap()
+        error_response(
+            &mapping_key,
+            err.status_code(),
+            uri.path(),
+            json_value.to_string(),
+        )
     });
+    if let Some(request_id) = request_id.clone() {
+        res.headers_mut().insert(X_REQUEST_ID, request_id);
+    }
     let mut elapsed_time = 0;
     let elapsed_time_res = current_time.elapsed();
     if let Ok(elapsed_times) = elapsed_time_res {
@@ -228,21 +603,65 @@ async fn proxy_adapter_with_error(
     }
 
     let status = res.status().as_u16();
+    if let Some(trace_context) = trace_context.as_ref() {
+        debug!(target: "app",
+            "The span {} of the trace {} with the parent {} took {} ms, the status is {}.",
+            trace_context.span_id,
+            trace_context.trace_id,
+            trace_context.parent_span_id.as_deref().unwrap_or("none"),
+            elapsed_time,
+            status
+        );
+    }
     let json_value: serde_json::Value = format!("{:?}", headers).into();
     monitor_timer_list
         .into_iter()
         .for_each(|item| item.observe_duration());
     inc(mapping_key.clone(), path.clone(), status);
 
-    if log_enabled!(Level::Debug) {
+    if access_log_format.unwrap_or_default() == AccessLogFormat::Json {
+        let bytes = res
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|item| item.to_str().ok())
+            .and_then(|item| item.parse().ok())
+            .or(res.body().size_hint().exact());
+        let access_log = AccessLog {
+            method: &method,
+            path: &path,
+            status,
+            latency_ms: elapsed_time,
+            remote_addr,
+            route_id: matched_route_id(&mapping_key, &method, path.clone(), &headers, remote_addr),
+            bytes,
+            request_id: request_id.and_then(|item| item.to_str().ok().map(|item| item.to_string())),
+            trace_id: trace_context.as_ref().map(|item| item.trace_id.clone()),
+            span_id: trace_context.map(|item| item.span_id),
+        };
+        info!(target: "app", "{}", access_log.to_json());
+        Ok(res)
+    } else if log_enabled!(Level::Debug) && is_event_stream(res.headers()) {
+        debug!(target: "app",
+           "{}$${}$${}$${}$${}$${}$${}$${:?}",
+           remote_addr,
+           elapsed_time,
+           status,
+           method,
+           path,
+           json_value,
+           "",
+           res.headers().clone()
+        );
+        Ok(res)
+    } else if log_enabled!(Level::Debug) {
         let (parts, body) = res.into_parts();
         let response_bytes = body
             .collect()
             .await
-            .map_err(|_| AppError(String::from("Can not get bytes from body")))?
+            .map_err(|_| AppError::from(String::from("Can not get bytes from body")))?
             .to_bytes();
-        let response_str =
-            String::from_utf8(response_bytes.to_vec()).map_err(|e| AppError(e.to_string()))?;
+        let response_str = String::from_utf8(response_bytes.to_vec())
+            .map_err(|e| AppError::from(e.to_string()))?;
         debug!(target: "app",
            "{}$${}$${}$${}$${}$${}$${}$${:?}",
            remote_addr.to_string(),
@@ -261,29 +680,144 @@ async fn proxy_adapter_with_error(
     }
 }
 
+fn is_event_stream(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim_start().starts_with("text/event-stream"))
+        .unwrap_or(false)
+}
+/**
+ *Only the POST request could be overridden, as the other methods are not blocked by the firewall.
+ */
+fn apply_method_override(req: &mut Request<BoxBody<Bytes, Infallible>>) {
+    if req.method() != Method::POST {
+        return;
+    }
+    let Some(method_override) = req.headers_mut().remove(X_HTTP_METHOD_OVERRIDE) else {
+        return;
+    };
+    match method_override
+        .to_str()
+        .ok()
+        .and_then(|item| Method::from_bytes(item.trim().to_ascii_uppercase().as_bytes()).ok())
+    {
+        Some(method) => *req.method_mut() = method,
+        None => error!(
+            "The method override {:?} is invalid and has been ignored!",
+            method_override
+        ),
+    }
+}
 async fn proxy(
     client: HttpClients,
     mut req: Request<BoxBody<Bytes, Infallible>>,
     mapping_key: String,
     remote_addr: SocketAddr,
     check_trait: impl CheckTrait,
+) -> Result<Response<BoxBody<Bytes, Infallible>>, AppError> {
+    let method_override = GLOBAL_CONFIG_MAPPING
+        .get(&mapping_key)
+        .and_then(|item| item.service_config.method_override)
+        .unwrap_or(false);
+    if method_override {
+        apply_method_override(&mut req);
+    }
+    let is_head = req.method() == Method::HEAD;
+    let mut res = proxy_request(client, req, mapping_key, remote_addr, check_trait).await?;
+    if is_head {
+        *res.body_mut() = Full::new(Bytes::new()).boxed();
+    }
+    Ok(res)
+}
+async fn proxy_request(
+    client: HttpClients,
+    mut req: Request<BoxBody<Bytes, Infallible>>,
+    mapping_key: String,
+    remote_addr: SocketAddr,
+    check_trait: impl CheckTrait,
 ) -> Result<Response<BoxBody<Bytes, Infallible>>, AppError> {
     debug!("req: {:?}", req);
+    if let Some(proof) = get_challenge_proof(req.uri().path()) {
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .body(Full::new(Bytes::from(proof)).boxed())
+            .unwrap());
+    }
+    let trailing_slash = GLOBAL_CONFIG_MAPPING
+        .get(&mapping_key)
+        .and_then(|item| item.service_config.trailing_slash.clone())
+        .unwrap_or_default();
+    if let Some(location) = trailing_slash.redirect_location(req.uri()) {
+        return Ok(Response::builder()
+            .status(StatusCode::MOVED_PERMANENTLY)
+            .header(LOCATION, location)
+            .body(Full::new(Bytes::new()).boxed())
+            .unwrap());
+    }
+    if let Some(res) = maintenance_response(
+        &mapping_key,
+        req.method(),
+        req.uri(),
+        req.headers(),
+        remote_addr,
+    ) {
+        return Ok(res);
+    }
     let inbound_headers = req.headers().clone();
     let uri = req.uri().clone();
+    let uri_path = uri.path().to_string();
     let check_result = check_trait
         .check_before_request(
             mapping_key.clone(),
+            req.method().clone(),
             inbound_headers.clone(),
-            uri,
+            uri.clone(),
             remote_addr,
         )
         .await?;
     if check_result.is_none() {
-        return Ok(Response::builder()
-            .status(StatusCode::FORBIDDEN)
-            .body(Full::new(Bytes::from(common_constants::DENY_RESPONSE)).boxed())
-            .unwrap());
+        let backend_path = uri
+            .path_and_query()
+            .map(|item| item.to_string())
+            .unwrap_or_default();
+        if !has_matched_route(
+            &mapping_key,
+            req.method(),
+            backend_path,
+            &inbound_headers,
+            remote_addr,
+        ) {
+            return Ok(error_response(
+                &mapping_key,
+                StatusCode::NOT_FOUND,
+                &uri_path,
+                String::from(common_constants::NOT_FOUND),
+            ));
+        }
+        return Ok(error_response(
+            &mapping_key,
+            StatusCode::FORBIDDEN,
+            &uri_path,
+            String::from(common_constants::DENY_RESPONSE),
+        ));
+    }
+    if let Some(check_request) = check_result.as_ref().filter(|item| item.rate_limited) {
+        let route = &check_request.route;
+        get_route_limit_rejected_counter(route.route_id.clone(), LimitReason::Rate.as_str()).inc();
+        let ratelimit_response = route
+            .ratelimit
+            .as_ref()
+            .and_then(|item| item.get_response())
+            .unwrap_or_default();
+        let mut res = limited_response(
+            &mapping_key,
+            &uri_path,
+            LimitReason::Rate,
+            &ratelimit_response,
+        );
+        handle_before_response(route, &mut res);
+        return Ok(res);
     }
     if inbound_headers.clone().contains_key(CONNECTION)
         && inbound_headers.contains_key(SEC_WEBSOCKET_KEY)
@@ -292,7 +826,21 @@ async fn proxy(
             "The request has been updated to websocket,the req is {:?}!",
             req
         );
-        return server_upgrade(req, check_result, client).await;
+        let failover_uris = match check_result.as_ref() {
+            Some(check_request) => {
+                get_failover_uris(
+                    &check_request.route,
+                    &check_request.base_route,
+                    uri.path_and_query()
+                        .map(|item| item.to_string())
+                        .unwrap_or_default(),
+                    &inbound_headers,
+                )
+                .await
+            }
+            None => vec![],
+        };
+        return server_upgrade(req, check_result, failover_uris, client).await;
     }
 
     if let Some(check_request) = check_result {
@@ -303,143 +851,956 @@ async fn proxy(
             let mut parts = req.uri().clone().into_parts();
             parts.path_and_query = Some(request_path.try_into().unwrap());
             *req.uri_mut() = Uri::from_parts(parts).unwrap();
-            return route_file(base_route, req).await;
+            let mut res = route_file(base_route, req).await?;
+            handle_before_response(&route, &mut res);
+            return Ok(res);
+        }
+        let (upstream_policy, server_type) = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key)
+            .map(|item| {
+                (
+                    item.service_config.upstream_policy.clone(),
+                    item.service_config.server_type.clone(),
+                )
+            })
+            .unwrap_or_default();
+        if let Err(err) = upstream_policy.unwrap_or_default().check(&request_path) {
+            error!("{}", err);
+            let mut res = error_response(
+                &mapping_key,
+                StatusCode::FORBIDDEN,
+                &uri_path,
+                String::from(common_constants::DENY_RESPONSE),
+            );
+            handle_before_response(&route, &mut res);
+            return Ok(res);
+        }
+        let cache_key = route.cache.as_ref().and_then(|cache| {
+            let path_and_query = uri
+                .path_and_query()
+                .map(|item| item.as_str())
+                .unwrap_or_default();
+            cache.cache_key(req.method(), path_and_query, &inbound_headers)
+        });
+        if let (Some(cache), Some(key)) = (route.cache.as_ref(), cache_key.as_ref()) {
+            if let Some(cached_response) = cache.get(&route.route_id, key) {
+                let mut res = Response::builder()
+                    .status(cached_response.status)
+                    .body(Full::new(cached_response.body).boxed())
+                    .unwrap();
+                *res.headers_mut() = cached_response.headers;
+                res.headers_mut()
+                    .insert(X_CACHE, HeaderValue::from_static("HIT"));
+                handle_before_response(&route, &mut res);
+                return Ok(res);
+            }
+        }
+        let mut single_flight_leader = None;
+        if let Some(single_flight) = route.single_flight.as_ref() {
+            let path_and_query = uri
+                .path_and_query()
+                .map(|item| item.as_str())
+                .unwrap_or_default();
+            let flight_key = single_flight.flight_key(
+                &route.route_id,
+                req.method(),
+                path_and_query,
+                &inbound_headers,
+            );
+            match flight_key.map(|key| single_flight.join(key)) {
+                Some(SingleFlight::Leader(leader)) => single_flight_leader = Some(leader),
+                Some(SingleFlight::Follower(in_flight_response)) => {
+                    if let Some(shared_response) = in_flight_response.await {
+                        let mut res = Response::builder()
+                            .status(shared_response.status)
+                            .body(Full::new(shared_response.body).boxed())
+                            .unwrap();
+                        *res.headers_mut() = shared_response.headers;
+                        return Ok(res);
+                    }
+                }
+                None => {}
+            }
         }
         *req.uri_mut() = request_path
             .parse()
-            .map_err(|err: InvalidUri| AppError(err.to_string()))?;
-        let request_future = if request_path.contains("https") {
-            client.request_https(req, DEFAULT_HTTP_TIMEOUT)
-        } else {
-            client.request_http(req, DEFAULT_HTTP_TIMEOUT)
+            .map_err(|err: InvalidUri| AppError::from(err.to_string()))?;
+        *req.version_mut() = Version::HTTP_11;
+        handle_forward_headers(&route, req.headers_mut(), remote_addr, server_type);
+        if let Some(host_header) = base_route.host_header.as_ref() {
+            req.headers_mut().insert(
+                HOST,
+                HeaderValue::from_str(host_header).map_err(|e| AppError::from(e.to_string()))?,
+            );
+        }
+        let client = match base_route.sni.as_ref() {
+            Some(sni) => client.with_server_name(sni),
+            None => client,
         };
-        let response_result = match request_future.await {
-            Ok(response) => response.map_err(|e| AppError(String::from(e.to_string()))),
-            _ => {
-                return Err(AppError(format!(
-                    "Request time out,the uri is {}",
-                    request_path
-                )))
+        let mut concurrency_permit = None;
+        if let Some(combined_limit) = route.combined_limit.as_ref() {
+            match combined_limit
+                .check(inbound_headers.clone(), remote_addr.ip().to_string())
+                .await?
+            {
+                LimitResult::Allowed(permit) => concurrency_permit = Some(permit),
+                LimitResult::Limited(reason) => {
+                    get_route_limit_rejected_counter(route.route_id.clone(), reason.as_str()).inc();
+                    let mut res = limited_response(
+                        &mapping_key,
+                        &uri_path,
+                        reason,
+                        &combined_limit.response.clone().unwrap_or_default(),
+                    );
+                    handle_before_response(&route, &mut res);
+                    return Ok(res);
+                }
             }
-        };
-        if let (Some(anomaly_detection), Some(liveness_config)) = (
-            route.clone().anomaly_detection,
-            route.clone().liveness_config,
-        ) {
-            let is_5xx = match response_result.as_ref() {
-                Ok(response) => {
-                    let status_code = response.status();
-                    status_code.clone().as_u16() >= StatusCode::INTERNAL_SERVER_ERROR.as_u16()
+        }
+        let mut bulkhead_permit = None;
+        if let Some(bulkhead) = route.bulkhead.as_ref() {
+            match bulkhead.acquire().await {
+                Some(permit) => bulkhead_permit = Some(permit),
+                None => {
+                    get_route_limit_rejected_counter(route.route_id.clone(), "bulkhead").inc();
+                    let json_value = json!({
+                        "response_code": -1,
+                        "response_object": "The bulkhead of the route is full!"
+                    });
+                    let mut res = error_response(
+                        &mapping_key,
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        &uri_path,
+                        json_value.to_string(),
+                    );
+                    handle_before_response(&route, &mut res);
+                    return Ok(res);
                 }
+            }
+        }
+        if let Some(circuit_breaker) = route.circuit_breaker.as_ref() {
+            if !base_route
+                .try_acquire_circuit(&route.route_id, circuit_breaker)
+                .await
+            {
+                let message = format!("The circuit breaker of {} is open!", base_route.endpoint);
+                let json_value = json!({
+                    "response_code": -1,
+                    "response_object": message
+                });
+                let mut res = error_response(
+                    &mapping_key,
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    &uri_path,
+                    json_value.to_string(),
+                );
+                handle_before_response(&route, &mut res);
+                return Ok(res);
+            }
+        }
+        if let Some(mirror) = route.mirror.clone() {
+            req = mirror_request(client.clone(), mirror, req).await;
+        }
+        if let Some(body_logging) = route.body_logging.as_ref() {
+            req = log_request_body(body_logging, &route.route_id, req).await;
+        }
+        let is_replayable =
+            req.method().is_idempotent() && req.body().size_hint().exact() == Some(0);
+        let hedge = route.hedge.clone().filter(|_| is_replayable);
+        let retry = route.retry.clone().filter(|_| is_replayable);
+        let request_size_histogram =
+            get_request_size_histogram(mapping_key.clone(), route.route_id.clone());
+        let req = req.map(|body| CountingBody::new(body, request_size_histogram).boxed());
+        let timeout_config = route.timeout.clone().unwrap_or_default();
+        let path_and_query = uri
+            .path_and_query()
+            .map(|item| item.to_string())
+            .unwrap_or_default();
+        let request_result = match (hedge, retry) {
+            (Some(hedge), _) => {
+                let hedge_uris = get_hedge_uris(
+                    &route,
+                    &base_route,
+                    path_and_query,
+                    &inbound_headers,
+                    hedge.get_max_hedges(),
+                )
+                .await;
+                request_with_hedging(
+                    client,
+                    req,
+                    Duration::from_millis(hedge.delay_millis),
+                    hedge_uris,
+                    timeout_config,
+                    base_route.preserve_header_case,
+                )
+                .await
+            }
+            (None, Some(retry)) => {
+                let retry_uris = get_retry_uris(
+                    &route,
+                    &base_route,
+                    path_and_query,
+                    &inbound_headers,
+                    retry.max_retries,
+                )
+                .await;
+                request_with_retries(
+                    client,
+                    req,
+                    retry_uris,
+                    retry.retry_on_5xx,
+                    timeout_config,
+                    base_route.preserve_header_case,
+                )
+                .await
+            }
+            (None, None) => {
+                client
+                    .request_with_timeout(req, &timeout_config, base_route.preserve_header_case)
+                    .await
+            }
+        };
+        let response_result = match request_result {
+            Ok(response) => response.map_err(upstream_error),
+            _ => Err(AppError::new(
+                AppErrorKind::Timeout,
+                format!("Request time out,the uri is {}", request_path),
+            )),
+        };
+        if let Some(circuit_breaker) = route.circuit_breaker.as_ref() {
+            let is_failure = match response_result.as_ref() {
+                Ok(response) => response.status().is_server_error(),
                 Err(_) => true,
             };
-            let temporary_base_route = base_route.clone();
-            let anomaly_detection_status_lock =
-                temporary_base_route.anomaly_detection_status.read().await;
-            let consecutive_5xx = anomaly_detection_status_lock.consecutive_5xx;
-            if is_5xx || consecutive_5xx > 0 {
-                if let Err(err) = trigger_anomaly_detection(
-                    anomaly_detection,
-                    route.liveness_status.clone(),
-                    base_route,
-                    is_5xx,
-                    liveness_config,
+            base_route
+                .record_outcome(&route.route_id, circuit_breaker, is_failure)
+                .await;
+        }
+        let is_5xx = match response_result.as_ref() {
+            Ok(response) => {
+                let status_code = response.status();
+                status_code.clone().as_u16() >= StatusCode::INTERNAL_SERVER_ERROR.as_u16()
+            }
+            Err(_) => true,
+        };
+        record_anomaly_detection(&route, base_route, is_5xx).await;
+        let mut res = match response_result {
+            Ok(response) => response
+                .map(|b| b.boxed())
+                .map(|item| item.map_err(|_| -> Infallible { unreachable!() }).boxed()),
+            Err(err) => {
+                error!("The error is {}.", err);
+                let json_value = json!({
+                    "response_code": -1,
+                    "response_object": format!("{}", err)
+                });
+                error_response(
+                    &mapping_key,
+                    err.status_code(),
+                    &uri_path,
+                    json_value.to_string(),
                 )
-                .await
-                {
-                    error!("{}", err);
-                }
             }
+        };
+        if !is_grpc_request(&inbound_headers) {
+            if let Some((status, body)) = grpc_error_response(res.headers()) {
+                res = error_response(&mapping_key, status, &uri_path, body);
+            }
+        }
+        if let (Some(cache), Some(key)) = (route.cache.as_ref(), cache_key) {
+            let authorized = inbound_headers.contains_key(AUTHORIZATION);
+            res = store_response(cache, &route.route_id, key, authorized, res).await;
         }
-        let res = response_result?
-            .map(|b| b.boxed())
-            .map(|item| item.map_err(|_| -> Infallible { unreachable!() }).boxed());
+        if let Some(body_logging) = route.body_logging.as_ref() {
+            res = log_response_body(body_logging, &route.route_id, res).await;
+        }
+        handle_before_response(&route, &mut res);
+        if let (Some(single_flight), Some(leader)) =
+            (route.single_flight.as_ref(), single_flight_leader)
+        {
+            res = share_response(single_flight, leader, res).await;
+        }
+        let response_size_histogram =
+            get_response_size_histogram(mapping_key.clone(), route.route_id.clone());
+        let res = res.map(|body| {
+            CountingBody::new(body, response_size_histogram)
+                .map_frame(move |frame| {
+                    let _concurrency_permit = &concurrency_permit;
+                    let _bulkhead_permit = &bulkhead_permit;
+                    frame
+                })
+                .boxed()
+        });
         return Ok(res);
     }
-    Ok(Response::builder()
-        .status(StatusCode::NOT_FOUND)
-        .body(Full::new(Bytes::from(common_constants::NOT_FOUND)).boxed())
-        .unwrap())
+    Ok(error_response(
+        &mapping_key,
+        StatusCode::NOT_FOUND,
+        &uri_path,
+        String::from(common_constants::NOT_FOUND),
+    ))
 }
-async fn trigger_anomaly_detection(
-    anomaly_detection: AnomalyDetectionType,
-    liveness_status_lock: Arc<RwLock<LivenessStatus>>,
-    base_route: BaseRoute,
-    is_5xx: bool,
-    liveness_config: LivenessConfig,
-) -> Result<(), AppError> {
-    let AnomalyDetectionType::Http(http_anomaly_detection_param) = anomaly_detection;
-    let res = base_route
-        .trigger_http_anomaly_detection(
-            http_anomaly_detection_param,
-            liveness_status_lock,
-            is_5xx,
-            liveness_config,
-        )
-        .await;
-    if res.is_err() {
-        error!(
-            "trigger_http_anomaly_detection error,the error is {}",
-            res.unwrap_err()
-        );
+/**
+ *The connect timeout is a gateway timeout, the address denied by the upstream policy is forbidden,
+ *the other failures like the refused connection mean the upstream is unavailable.
+ */
+fn upstream_error(err: ClientError) -> AppError {
+    let kind = match io_error_kind(&err) {
+        Some(std::io::ErrorKind::TimedOut) => AppErrorKind::Timeout,
+        Some(std::io::ErrorKind::PermissionDenied) => AppErrorKind::Forbidden,
+        _ => AppErrorKind::UpstreamUnavailable,
+    };
+    AppError::new(kind, err.to_string())
+}
+fn io_error_kind(err: &(dyn std::error::Error + 'static)) -> Option<std::io::ErrorKind> {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(io_error) = err.downcast_ref::<std::io::Error>() {
+            return Some(io_error.kind());
+        }
+        source = err.source();
     }
-
-    Ok(())
+    None
 }
-async fn route_file(
-    base_route: BaseRoute,
-    req: Request<BoxBody<Bytes, Infallible>>,
-) -> Result<Response<BoxBody<Bytes, Infallible>>, AppError> {
-    let static_ = Static::new(Path::new(base_route.endpoint.as_str()));
-    let current_res = static_.clone().serve(req).await;
-    if current_res.is_ok() {
-        let res = current_res.unwrap();
-        if res.status() == StatusCode::NOT_FOUND {
-            let mut request: Request<()> = Request::default();
-            if base_route.try_file.is_none() {
-                return Err(AppError(String::from("Please config the try_file!")));
-            }
-            *request.uri_mut() = base_route.try_file.unwrap().parse().unwrap();
-            return static_
-                .clone()
-                .serve(request)
-                .await
-                .map(|item| {
-                    item.map(|body| {
-                        body.boxed()
-                            .map_err(|_| -> Infallible { unreachable!() })
-                            .boxed()
-                    })
-                })
-                .map_err(|e| AppError(e.to_string()));
-        } else {
-            return Ok(res.map(|body| {
-                body.boxed()
-                    .map_err(|_| -> Infallible { unreachable!() })
-                    .boxed()
-            }));
+async fn get_hedge_uris(
+    route: &Route,
+    base_route: &BaseRoute,
+    path_and_query: String,
+    headers: &HeaderMap,
+    max_hedges: usize,
+) -> Vec<Uri> {
+    let Ok(Some(rest_path)) = route.is_matched(path_and_query, Some(headers.clone())) else {
+        return vec![];
+    };
+    let rest_path = route.rewrite_query(rest_path);
+    get_available_endpoints(route)
+        .await
+        .into_iter()
+        .filter(|endpoint| *endpoint != base_route.endpoint)
+        .filter_map(|endpoint| build_backend_uri(&endpoint, &rest_path))
+        .take(max_hedges)
+        .collect()
+}
+async fn get_available_endpoints(route: &Route) -> Vec<String> {
+    let base_routes = route
+        .route_cluster
+        .clone()
+        .get_all_route()
+        .await
+        .unwrap_or_default();
+    let mut endpoints = vec![];
+    for item in base_routes {
+        if !item.endpoint.starts_with("http")
+            || !item.is_alive.read().await.unwrap_or(true)
+            || item.is_circuit_open().await
+        {
+            continue;
         }
+        endpoints.push(item.endpoint);
     }
-    let mut request: Request<()> = Request::default();
-    if base_route.try_file.is_none() {
-        return Err(AppError(String::from("Please config the try_file!")));
+    endpoints
+}
+fn build_backend_uri(endpoint: &str, rest_path: &str) -> Option<Uri> {
+    match Url::parse(endpoint)
+        .and_then(|url| url.join(rest_path))
+        .map(|url| url.to_string().parse::<Uri>())
+    {
+        Ok(Ok(uri)) => Some(uri),
+        _ => {
+            error!("Can not build the backend uri of {}.", endpoint);
+            None
+        }
     }
-    *request.uri_mut() = base_route.try_file.unwrap().parse().unwrap();
-    static_
-        .clone()
-        .serve(request)
+}
+async fn get_retry_uris(
+    route: &Route,
+    base_route: &BaseRoute,
+    path_and_query: String,
+    headers: &HeaderMap,
+    max_retries: usize,
+) -> Vec<Uri> {
+    let Ok(Some(rest_path)) = route.is_matched(path_and_query, Some(headers.clone())) else {
+        return vec![];
+    };
+    let rest_path = route.rewrite_query(rest_path);
+    let endpoints = get_available_endpoints(route).await;
+    select_retry_endpoints(&endpoints, &base_route.endpoint, max_retries)
+        .iter()
+        .filter_map(|endpoint| build_backend_uri(endpoint, &rest_path))
+        .collect()
+}
+/**
+ *The other available backends of the route, which are tried in order when the upgrade is rejected.
+ */
+async fn get_failover_uris(
+    route: &Route,
+    base_route: &BaseRoute,
+    path_and_query: String,
+    headers: &HeaderMap,
+) -> Vec<Uri> {
+    let Ok(Some(rest_path)) = route.is_matched(path_and_query, Some(headers.clone())) else {
+        return vec![];
+    };
+    let rest_path = route.rewrite_query(rest_path);
+    get_available_endpoints(route)
         .await
-        .map(|item| {
-            item.map(|body| {
-                body.boxed()
-                    .map_err(|_| -> Infallible { unreachable!() })
-                    .boxed()
-            })
-        })
-        .map_err(|e| AppError(e.to_string()))
+        .iter()
+        .filter(|item| **item != base_route.endpoint)
+        .filter_map(|endpoint| build_backend_uri(endpoint, &rest_path))
+        .collect()
 }
-
-#[cfg(test)]
+fn select_retry_endpoints(
+    endpoints: &[String],
+    first_endpoint: &str,
+    max_retries: usize,
+) -> Vec<String> {
+    let mut tried = HashSet::from([first_endpoint]);
+    let mut last_endpoint = first_endpoint;
+    let mut selected = vec![];
+    for _ in 0..max_retries {
+        if endpoints.iter().all(|item| tried.contains(item.as_str())) {
+            tried = HashSet::from([last_endpoint]);
+        }
+        let start = endpoints
+            .iter()
+            .position(|item| item == last_endpoint)
+            .map_or(0, |index| index + 1);
+        let next_endpoint = endpoints[start..]
+            .iter()
+            .chain(endpoints[..start].iter())
+            .map(|item| item.as_str())
+            .find(|item| !tried.contains(item))
+            .unwrap_or(last_endpoint);
+        tried.insert(next_endpoint);
+        selected.push(next_endpoint.to_string());
+        last_endpoint = next_endpoint;
+    }
+    selected
+}
+fn rebuild_request(parts: &http::request::Parts, uri: Uri) -> Request<BoxBody<Bytes, Infallible>> {
+    let mut request = Request::builder()
+        .method(parts.method.clone())
+        .uri(uri)
+        .version(parts.version)
+        .body(Full::new(Bytes::new()).boxed())
+        .unwrap();
+    *request.headers_mut() = parts.headers.clone();
+    request
+}
+async fn request_with_retries(
+    client: HttpClients,
+    req: Request<BoxBody<Bytes, Infallible>>,
+    retry_uris: Vec<Uri>,
+    retry_on_5xx: bool,
+    timeout_config: TimeoutConfig,
+    preserve_header_case: bool,
+) -> Result<Result<Response<BoxBody<Bytes, hyper::Error>>, ClientError>, Elapsed> {
+    let (parts, _) = req.into_parts();
+    let mut uris = std::iter::once(parts.uri.clone())
+        .chain(retry_uris)
+        .peekable();
+    loop {
+        let uri = uris.next().unwrap();
+        let result = client
+            .request_with_timeout(
+                rebuild_request(&parts, uri.clone()),
+                &timeout_config,
+                preserve_header_case,
+            )
+            .await;
+        let should_retry = match result.as_ref() {
+            Ok(Ok(response)) => retry_on_5xx && response.status().is_server_error(),
+            _ => true,
+        };
+        if !should_retry || uris.peek().is_none() {
+            return result;
+        }
+        debug!("The request to {} failed,retry with the next backend.", uri);
+    }
+}
+async fn request_with_hedging(
+    client: HttpClients,
+    req: Request<BoxBody<Bytes, Infallible>>,
+    delay: Duration,
+    hedge_uris: Vec<Uri>,
+    timeout_config: TimeoutConfig,
+    preserve_header_case: bool,
+) -> Result<Result<Response<BoxBody<Bytes, hyper::Error>>, ClientError>, Elapsed> {
+    let (parts, _) = req.into_parts();
+    let mut uris = std::iter::once(parts.uri.clone())
+        .chain(hedge_uris)
+        .peekable();
+    let mut request_tasks = JoinSet::new();
+    let spawn_request = |request_tasks: &mut JoinSet<_>, uri: Uri| {
+        let request = rebuild_request(&parts, uri);
+        let client = client.clone();
+        let timeout_config = timeout_config.clone();
+        request_tasks.spawn(async move {
+            client
+                .request_with_timeout(request, &timeout_config, preserve_header_case)
+                .await
+        });
+    };
+    if let Some(uri) = uris.next() {
+        spawn_request(&mut request_tasks, uri);
+    }
+    loop {
+        let has_hedge = uris.peek().is_some();
+        tokio::select! {
+            joined = request_tasks.join_next() => {
+                let result = match joined {
+                    Some(Ok(result)) => result,
+                    Some(Err(err)) => std::panic::resume_unwind(err.into_panic()),
+                    None => unreachable!(),
+                };
+                if matches!(result, Ok(Ok(_))) {
+                    return result;
+                }
+                match uris.next() {
+                    Some(uri) => spawn_request(&mut request_tasks, uri),
+                    None if request_tasks.is_empty() => return result,
+                    None => {}
+                }
+            }
+            _ = sleep(delay), if has_hedge => {
+                if let Some(uri) = uris.next() {
+                    debug!("Send the hedged request to {}.", uri);
+                    spawn_request(&mut request_tasks, uri);
+                }
+            }
+        }
+    }
+}
+async fn log_request_body(
+    body_logging: &BodyLoggingConfig,
+    route_id: &str,
+    req: Request<BoxBody<Bytes, Infallible>>,
+) -> Request<BoxBody<Bytes, Infallible>> {
+    let (parts, body) = req.into_parts();
+    let body_bytes = body
+        .collect()
+        .await
+        .map(|item| item.to_bytes())
+        .unwrap_or_default();
+    info!(target: "app",
+        "The request of route {} is {} {}, headers: [{}], body: {}",
+        route_id,
+        parts.method,
+        parts.uri,
+        body_logging.format_headers(&parts.headers),
+        body_logging.format_body(&body_bytes)
+    );
+    Request::from_parts(parts, Full::new(body_bytes).boxed())
+}
+/**
+ *The event stream never ends, so it is passed through without being buffered.
+ */
+async fn log_response_body(
+    body_logging: &BodyLoggingConfig,
+    route_id: &str,
+    res: Response<BoxBody<Bytes, Infallible>>,
+) -> Response<BoxBody<Bytes, Infallible>> {
+    if is_event_stream(res.headers()) {
+        return res;
+    }
+    let (parts, body) = res.into_parts();
+    let body_bytes = body
+        .collect()
+        .await
+        .map(|item| item.to_bytes())
+        .unwrap_or_default();
+    info!(target: "app",
+        "The response of route {} is {}, headers: [{}], body: {}",
+        route_id,
+        parts.status,
+        body_logging.format_headers(&parts.headers),
+        body_logging.format_body(&body_bytes)
+    );
+    Response::from_parts(parts, Full::new(body_bytes).boxed())
+}
+async fn mirror_request(
+    client: HttpClients,
+    mirror: MirrorConfig,
+    req: Request<BoxBody<Bytes, Infallible>>,
+) -> Request<BoxBody<Bytes, Infallible>> {
+    let is_buffered = req
+        .body()
+        .size_hint()
+        .upper()
+        .map(|size| size <= mirror.max_body_size)
+        .unwrap_or(false);
+    if !is_buffered || !mirror.is_sampled() {
+        return req;
+    }
+    let (parts, body) = req.into_parts();
+    let body_bytes = body
+        .collect()
+        .await
+        .map(|item| item.to_bytes())
+        .unwrap_or_default();
+    let path_and_query = parts
+        .uri
+        .path_and_query()
+        .map(|item| item.as_str())
+        .unwrap_or("/");
+    match Url::parse(mirror.endpoint.as_str()).and_then(|item| item.join(path_and_query)) {
+        Ok(mirror_uri) => {
+            let mut mirror_req = Request::builder()
+                .method(parts.method.clone())
+                .uri(mirror_uri.to_string())
+                .body(Full::new(body_bytes.clone()).boxed())
+                .unwrap();
+            *mirror_req.headers_mut() = parts.headers.clone();
+            mirror_req.headers_mut().remove(HOST);
+            let request_future = if mirror_uri.scheme() == "https" {
+                client.request_https(
+                    mirror_req,
+                    DEFAULT_HTTP_TIMEOUT,
+                    default_preserve_header_case(),
+                )
+            } else {
+                client.request_http(
+                    mirror_req,
+                    DEFAULT_HTTP_TIMEOUT,
+                    default_preserve_header_case(),
+                )
+            };
+            tokio::spawn(async move {
+                match request_future.await {
+                    Ok(Ok(response)) => {
+                        debug!("The mirror response status is {}.", response.status())
+                    }
+                    Ok(Err(err)) => debug!("The mirror request failed,the error is {}.", err),
+                    Err(_) => debug!("The mirror request timed out."),
+                }
+            });
+        }
+        Err(err) => error!("Can not build the mirror uri,the error is {}.", err),
+    }
+    Request::from_parts(parts, Full::new(body_bytes).boxed())
+}
+fn handle_forward_headers(
+    route: &Route,
+    headers: &mut HeaderMap,
+    remote_addr: SocketAddr,
+    server_type: ServiceType,
+) {
+    let forward_headers = match &route.forward_headers {
+        Some(forward_headers) => forward_headers,
+        None => return,
+    };
+    let client_ip = remote_addr.ip().to_string();
+    let existing_chain = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|item| item.to_str().ok())
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    let forwarded_for = if forward_headers.trust_existing && !existing_chain.is_empty() {
+        format!("{}, {}", existing_chain, client_ip)
+    } else {
+        client_ip
+    };
+    if let Ok(header_value) = HeaderValue::from_str(&forwarded_for) {
+        headers.insert(X_FORWARDED_FOR, header_value);
+    }
+    if !forward_headers.trust_existing || !headers.contains_key(X_FORWARDED_PROTO) {
+        let scheme = route.get_scheme(&server_type, headers, remote_addr.ip());
+        headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static(scheme));
+    }
+    if !forward_headers.trust_existing || !headers.contains_key(X_FORWARDED_HOST) {
+        match headers.get(HOST).cloned() {
+            Some(host) => {
+                headers.insert(X_FORWARDED_HOST, host);
+            }
+            None => {
+                headers.remove(X_FORWARDED_HOST);
+            }
+        }
+    }
+}
+/**
+ *The response is buffered only when it is cacheable and its size is known to be within the limit.
+ */
+async fn store_response(
+    cache: &CacheConfig,
+    route_id: &str,
+    key: String,
+    authorized: bool,
+    mut res: Response<BoxBody<Bytes, Infallible>>,
+) -> Response<BoxBody<Bytes, Infallible>> {
+    res.headers_mut()
+        .insert(X_CACHE, HeaderValue::from_static("MISS"));
+    let Some(ttl) = cache.get_ttl(res.status(), res.headers(), authorized) else {
+        return res;
+    };
+    let content_length = res
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|item| item.to_str().ok())
+        .and_then(|item| item.parse::<u64>().ok())
+        .or(res.body().size_hint().exact());
+    if content_length.is_none_or(|item| item > cache.max_body_bytes) {
+        return res;
+    }
+    let (parts, body) = res.into_parts();
+    let Ok(collected) = body.collect().await;
+    let body = collected.to_bytes();
+    let mut headers = parts.headers.clone();
+    headers.remove(X_CACHE);
+    cache.insert(route_id, key, parts.status, headers, body.clone(), ttl);
+    Response::from_parts(parts, Full::new(body).boxed())
+}
+/**
+ *The response is shared with the followers only when its size is known to be within the limit.
+ */
+async fn share_response(
+    single_flight: &SingleFlightConfig,
+    leader: SingleFlightLeader,
+    res: Response<BoxBody<Bytes, Infallible>>,
+) -> Response<BoxBody<Bytes, Infallible>> {
+    let content_length = res
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|item| item.to_str().ok())
+        .and_then(|item| item.parse::<u64>().ok())
+        .or(res.body().size_hint().exact());
+    if is_event_stream(res.headers())
+        || !single_flight.is_shareable(res.headers())
+        || content_length.is_none_or(|item| item > single_flight.max_body_bytes)
+    {
+        leader.publish(None);
+        return res;
+    }
+    let (parts, body) = res.into_parts();
+    let Ok(collected) = body.collect().await;
+    let body = collected.to_bytes();
+    leader.publish(Some(SharedResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    }));
+    Response::from_parts(parts, Full::new(body).boxed())
+}
+fn handle_before_response(route: &Route, res: &mut Response<BoxBody<Bytes, Infallible>>) {
+    if let Some(response_headers) = &route.response_headers {
+        response_headers.apply(res.headers_mut());
+    }
+}
+/**
+ *Count the failure of the backend, and eject it from the liveness count once the threshold is reached.
+ */
+async fn record_anomaly_detection(route: &Route, base_route: BaseRoute, is_5xx: bool) {
+    let (Some(anomaly_detection), Some(liveness_config)) = (
+        route.anomaly_detection.clone(),
+        route.liveness_config.clone(),
+    ) else {
+        return;
+    };
+    let consecutive_5xx = base_route
+        .anomaly_detection_status
+        .read()
+        .await
+        .consecutive_5xx;
+    let AnomalyDetectionType::Http(http_anomaly_detection_param) = &anomaly_detection;
+    let is_windowed = http_anomaly_detection_param
+        .base_anomaly_detection_param
+        .is_windowed();
+    if is_5xx || consecutive_5xx > 0 || is_windowed {
+        if let Err(err) = trigger_anomaly_detection(
+            anomaly_detection,
+            route.liveness_status.clone(),
+            route.route_cluster.clone(),
+            base_route,
+            is_5xx,
+            liveness_config,
+        )
+        .await
+        {
+            error!("{}", err);
+        }
+    }
+}
+async fn trigger_anomaly_detection(
+    anomaly_detection: AnomalyDetectionType,
+    liveness_status_lock: Arc<RwLock<LivenessStatus>>,
+    mut route_cluster: LoadbalancerStrategy,
+    base_route: BaseRoute,
+    is_5xx: bool,
+    mut liveness_config: LivenessConfig,
+) -> Result<(), AppError> {
+    let AnomalyDetectionType::Http(http_anomaly_detection_param) = anomaly_detection;
+    let base_anomaly_detection_param = &http_anomaly_detection_param.base_anomaly_detection_param;
+    if is_5xx
+        && (base_anomaly_detection_param.max_ejection_percent.is_some()
+            || liveness_config.panic_threshold.is_some())
+    {
+        let cluster_size = route_cluster.get_all_route().await?.len();
+        liveness_config.min_liveness_count = base_anomaly_detection_param
+            .min_liveness_count(cluster_size, liveness_config.min_liveness_count)
+            .max(liveness_config.min_healthy_count(cluster_size));
+    }
+    let res = base_route
+        .trigger_http_anomaly_detection(
+            http_anomaly_detection_param,
+            liveness_status_lock,
+            is_5xx,
+            liveness_config,
+        )
+        .await;
+    if res.is_err() {
+        error!(
+            "trigger_http_anomaly_detection error,the error is {}",
+            res.unwrap_err()
+        );
+    }
+
+    Ok(())
+}
+fn is_entity_tag_matched(if_none_match: &str, entity_tag: &str) -> bool {
+    let entity_tag = entity_tag.trim_start_matches("W/");
+    if_none_match
+        .split(',')
+        .map(|item| item.trim())
+        .any(|item| item == "*" || item.trim_start_matches("W/") == entity_tag)
+}
+/**
+ *The ETag of hyper_staticfile is weak and the If-None-Match is not handled,
+ *so the ETag is made strong and the If-None-Match takes precedence over the If-Modified-Since.
+ */
+async fn route_file(
+    base_route: BaseRoute,
+    mut req: Request<BoxBody<Bytes, Infallible>>,
+) -> Result<Response<BoxBody<Bytes, Infallible>>, AppError> {
+    if let Some(directory) = resolve_directory(&base_route.endpoint, req.uri().path()) {
+        match find_index_file(&directory, base_route.index.as_ref()).await {
+            Some(index_file) => {
+                let path = format!("{}{}", req.uri().path(), encode_path_segment(&index_file));
+                let path_and_query = match req.uri().query() {
+                    Some(query) => format!("{}?{}", path, query),
+                    None => path,
+                };
+                let mut parts = req.uri().clone().into_parts();
+                parts.path_and_query = Some(
+                    path_and_query
+                        .parse()
+                        .map_err(|err: InvalidUri| AppError::from(err.to_string()))?,
+                );
+                *req.uri_mut() =
+                    Uri::from_parts(parts).map_err(|e| AppError::from(e.to_string()))?;
+            }
+            None if base_route.autoindex.unwrap_or(false) => {
+                match render_directory_listing(&directory, req.uri().path()).await {
+                    Ok(body) => {
+                        return Ok(Response::builder()
+                            .status(StatusCode::OK)
+                            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+                            .body(Full::new(Bytes::from(body)).boxed())
+                            .unwrap());
+                    }
+                    Err(err) => debug!("Render the directory listing error,the error is {}", err),
+                }
+            }
+            None => {}
+        }
+    }
+    let if_none_match = req
+        .headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|item| item.to_str().ok())
+        .map(|item| item.to_string());
+    if if_none_match.is_some() {
+        req.headers_mut().remove(IF_MODIFIED_SINCE);
+    }
+    let mut res = serve_file(base_route, req).await?;
+    if res.status().is_success() {
+        res.headers_mut()
+            .insert(VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    let Some(entity_tag) = res
+        .headers()
+        .get(ETAG)
+        .and_then(|item| item.to_str().ok())
+        .map(|item| item.trim_start_matches("W/").to_string())
+    else {
+        return Ok(res);
+    };
+    if let Ok(strong_entity_tag) = HeaderValue::from_str(&entity_tag) {
+        res.headers_mut().insert(ETAG, strong_entity_tag);
+    }
+    let is_matched = if_none_match.is_some_and(|item| is_entity_tag_matched(&item, &entity_tag));
+    if res.status() == StatusCode::OK && is_matched {
+        *res.status_mut() = StatusCode::NOT_MODIFIED;
+        res.headers_mut().remove(CONTENT_LENGTH);
+        res.headers_mut().remove(CONTENT_TYPE);
+        *res.body_mut() = Full::new(Bytes::new()).boxed();
+    }
+    Ok(res)
+}
+async fn serve_file(
+    base_route: BaseRoute,
+    req: Request<BoxBody<Bytes, Infallible>>,
+) -> Result<Response<BoxBody<Bytes, Infallible>>, AppError> {
+    let mut static_ = Static::new(Path::new(base_route.endpoint.as_str()));
+    static_.allowed_encodings(accepted_encodings(req.headers()));
+    let current_res = static_.clone().serve(req).await;
+    if current_res.is_ok() {
+        let res = current_res.unwrap();
+        if res.status() == StatusCode::NOT_FOUND {
+            let mut request: Request<()> = Request::default();
+            if base_route.try_file.is_none() {
+                return Err(AppError::new(
+                    AppErrorKind::Config,
+                    "Please config the try_file!",
+                ));
+            }
+            *request.uri_mut() = base_route.try_file.unwrap().parse().unwrap();
+            return static_
+                .clone()
+                .serve(request)
+                .await
+                .map(|item| {
+                    item.map(|body| {
+                        body.boxed()
+                            .map_err(|_| -> Infallible { unreachable!() })
+                            .boxed()
+                    })
+                })
+                .map_err(|e| AppError::from(e.to_string()));
+        } else {
+            return Ok(res.map(|body| {
+                body.boxed()
+                    .map_err(|_| -> Infallible { unreachable!() })
+                    .boxed()
+            }));
+        }
+    }
+    let mut request: Request<()> = Request::default();
+    if base_route.try_file.is_none() {
+        return Err(AppError::new(
+            AppErrorKind::Config,
+            "Please config the try_file!",
+        ));
+    }
+    *request.uri_mut() = base_route.try_file.unwrap().parse().unwrap();
+    static_
+        .clone()
+        .serve(request)
+        .await
+        .map(|item| {
+            item.map(|body| {
+                body.boxed()
+                    .map_err(|_| -> Infallible { unreachable!() })
+                    .boxed()
+            })
+        })
+        .map_err(|e| AppError::from(e.to_string()))
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
     use crate::configuration_service::app_config_service::GLOBAL_APP_CONFIG;
@@ -452,21 +1813,43 @@ mod tests {
     use crate::vojo::anomaly_detection::HttpAnomalyDetectionParam;
     use crate::vojo::api_service_manager::ApiServiceManager;
     use crate::vojo::app_config::ApiService;
+    use crate::vojo::app_config::BodyLoggingConfig;
+    use crate::vojo::app_config::ErrorPage;
+    use crate::vojo::app_config::ForwardHeaders;
+    use crate::vojo::app_config::HedgeConfig;
     use crate::vojo::app_config::LivenessStatus;
-    use crate::vojo::app_config::Matcher;
+    use crate::vojo::app_config::RequestIdConfig;
+    use crate::vojo::app_config::ResponseHeaders;
+    use crate::vojo::app_config::RetryConfig;
     use crate::vojo::app_config::Route;
     use crate::vojo::app_config::ServiceConfig;
+    use crate::vojo::app_config::TrailingSlashPolicy;
+    use crate::vojo::app_config::{Matcher, PathMatchType};
     use crate::vojo::base_response::BaseResponse;
+    use crate::vojo::bulkhead::Bulkhead;
+    use crate::vojo::combined_limit::CombinedLimit;
+    use crate::vojo::lets_encrypt::GLOBAL_ACME_CHALLENGE_TOKENS;
+    use crate::vojo::rate_limit::{
+        FixedWindowRateLimit, IPBasedRatelimit, LimitLocation, TimeUnit,
+    };
     use crate::vojo::route::AnomalyDetectionStatus;
     use crate::vojo::route::{BaseRoute, LoadbalancerStrategy, RandomBaseRoute, RandomRoute};
+    use crate::vojo::route::{PollBaseRoute, PollRoute};
+    use hyper::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
     use lazy_static::lazy_static;
     use regex::Regex;
+    use std::collections::HashMap;
     use std::env;
     use std::fs::File;
     use std::io::BufReader;
     use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
     use std::sync::Arc;
+    use std::time::Instant;
     use std::{thread, time};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
     use tokio::runtime::{Builder, Runtime};
     use tokio::sync::RwLock;
 
@@ -522,6 +1905,14 @@ mod tests {
             let (_, receiver) = tokio::sync::mpsc::channel(10);
 
             let mut http_proxy = HttpProxy {
+                graceful_shutdown_timeout: common_constants::DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT,
+                socket_options: Default::default(),
+                upstream_pool: Default::default(),
+                upstream_tls: None,
+                tls_policy: None,
+                sni_certs: None,
+                header_limit: Default::default(),
+                bind_addresses: None,
                 port: 9987,
                 channel: receiver,
                 mapping_key: String::from("random key"),
@@ -536,7 +1927,7 @@ mod tests {
                 .uri("http://127.0.0.1:9987/get")
                 .body(Full::new(Bytes::from("value")).boxed())
                 .unwrap();
-            let response_result = client.request_http(request, 5).await;
+            let response_result = client.request_http(request, 5, true).await;
             assert!(response_result.is_ok());
             let response = response_result.unwrap().unwrap();
             assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
@@ -566,6 +1957,14 @@ mod tests {
             let (_, receiver) = tokio::sync::mpsc::channel(10);
 
             let mut http_proxy = HttpProxy {
+                graceful_shutdown_timeout: common_constants::DEFAULT_GRACEFUL_SHUTDOWN_TIMEOUT,
+                socket_options: Default::default(),
+                upstream_pool: Default::default(),
+                upstream_tls: None,
+                tls_policy: None,
+                sni_certs: None,
+                header_limit: Default::default(),
+                bind_addresses: None,
                 port: 4450,
                 channel: receiver,
                 mapping_key: String::from("random key"),
@@ -582,7 +1981,7 @@ mod tests {
                 .uri("https://localhost:4450/get")
                 .body(Full::new(Bytes::new()).boxed())
                 .unwrap();
-            let response_result = client.request_https(request, 5).await;
+            let response_result = client.request_https(request, 5, true).await;
             assert!(response_result.is_ok());
             let response = response_result.unwrap().unwrap();
             assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
@@ -632,10 +2031,18 @@ mod tests {
             let base_route = BaseRoute {
                 endpoint: String::from("not_found"),
                 try_file: None,
+                index: None,
+                autoindex: None,
+                host_header: None,
+                sni: None,
+                preserve_header_case: true,
                 is_alive: Arc::new(RwLock::new(None)),
                 anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                     consecutive_5xx: 100,
+                    recent_responses: Default::default(),
                 })),
+                circuit_breaker_status: Default::default(),
+                recovered_at: Default::default(),
             };
             let res = route_file(base_route, request).await;
             assert!(res.is_err());
@@ -654,16 +2061,175 @@ mod tests {
             let base_route = BaseRoute {
                 endpoint: String::from("config"),
                 try_file: None,
+                index: None,
+                autoindex: None,
+                host_header: None,
+                sni: None,
+                preserve_header_case: true,
                 is_alive: Arc::new(RwLock::new(None)),
                 anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                     consecutive_5xx: 100,
+                    recent_responses: Default::default(),
                 })),
+                circuit_breaker_status: Default::default(),
+                recovered_at: Default::default(),
             };
             let res = route_file(base_route, request).await;
             assert!(res.is_ok());
         });
     }
     #[test]
+    fn test_route_file_if_none_match() {
+        TOKIO_RUNTIME.block_on(async {
+            let base_route = BaseRoute {
+                endpoint: String::from("config"),
+                ..Default::default()
+            };
+            let new_request = |if_none_match: Option<&str>| {
+                let mut request = Request::builder().uri("http://localhost:4450/test_cert.pem");
+                if let Some(if_none_match) = if_none_match {
+                    request = request
+                        .header(IF_NONE_MATCH, if_none_match)
+                        .header(IF_MODIFIED_SINCE, "Fri, 01 Jan 2100 00:00:00 GMT");
+                }
+                request.body(Full::new(Bytes::new()).boxed()).unwrap()
+            };
+            let res = route_file(base_route.clone(), new_request(None))
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let entity_tag = res
+                .headers()
+                .get(ETAG)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            assert!(entity_tag.starts_with('"'));
+
+            let res = route_file(
+                base_route.clone(),
+                new_request(Some(&format!("\"other\", W/{}", entity_tag))),
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(res.headers().get(ETAG).unwrap(), entity_tag.as_str());
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert!(body.is_empty());
+
+            let res = route_file(base_route, new_request(Some("\"other\"")))
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(
+                body,
+                Bytes::from(std::fs::read("config/test_cert.pem").unwrap())
+            );
+        });
+    }
+    #[test]
+    fn test_route_file_index_and_autoindex() {
+        TOKIO_RUNTIME.block_on(async {
+            let root = env::temp_dir().join(format!("silverwind-autoindex-{}", get_uuid()));
+            std::fs::create_dir_all(root.join("site")).unwrap();
+            std::fs::create_dir_all(root.join("files").join("nested")).unwrap();
+            std::fs::write(root.join("site").join("home.html"), "<h1>home</h1>").unwrap();
+            std::fs::write(root.join("files").join("a<b>.txt"), "a").unwrap();
+            let base_route = BaseRoute {
+                endpoint: root.to_string_lossy().to_string(),
+                index: Some(vec![String::from("index.html"), String::from("home.html")]),
+                autoindex: Some(true),
+                ..Default::default()
+            };
+            let new_request = |uri: &str| {
+                Request::builder()
+                    .uri(uri)
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap()
+            };
+            let res = route_file(base_route.clone(), new_request("/site/?a=1"))
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("<h1>home</h1>"));
+
+            let res = route_file(base_route.clone(), new_request("/files/"))
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(
+                res.headers().get(CONTENT_TYPE).unwrap(),
+                "text/html; charset=utf-8"
+            );
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            let body = String::from_utf8(body.to_vec()).unwrap();
+            assert!(body.contains("<li><a href=\"nested/\">nested/</a></li>"));
+            assert!(body.contains("<li><a href=\"a%3Cb%3E.txt\">a&lt;b&gt;.txt</a></li>"));
+            assert!(body.find("nested/").unwrap() < body.find("a&lt;b&gt;.txt").unwrap());
+
+            let base_route = BaseRoute {
+                autoindex: None,
+                try_file: Some(String::from("/site/home.html")),
+                ..base_route
+            };
+            let res = route_file(base_route, new_request("/files/"))
+                .await
+                .unwrap();
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("<h1>home</h1>"));
+            std::fs::remove_dir_all(root).unwrap();
+        });
+    }
+    #[test]
+    fn test_route_file_precompressed() {
+        TOKIO_RUNTIME.block_on(async {
+            let root = env::temp_dir().join(format!("silverwind-precompressed-{}", get_uuid()));
+            std::fs::create_dir_all(&root).unwrap();
+            std::fs::write(root.join("foo.js"), "console.log('plain');").unwrap();
+            std::fs::write(root.join("foo.js.gz"), [0x1f, 0x8b, 0x08, 0x00]).unwrap();
+            let base_route = BaseRoute {
+                endpoint: root.to_string_lossy().to_string(),
+                ..Default::default()
+            };
+            let new_request = |accept_encoding: Option<&str>| {
+                let mut request = Request::builder().uri("/foo.js");
+                if let Some(accept_encoding) = accept_encoding {
+                    request = request.header(ACCEPT_ENCODING, accept_encoding);
+                }
+                request.body(Full::new(Bytes::new()).boxed()).unwrap()
+            };
+            let res = route_file(base_route.clone(), new_request(Some("br, gzip")))
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+            assert_eq!(res.headers().get(VARY).unwrap(), "accept-encoding");
+            assert!(res
+                .headers()
+                .get(CONTENT_TYPE)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .contains("javascript"));
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from_static(&[0x1f, 0x8b, 0x08, 0x00]));
+
+            for accept_encoding in [None, Some("gzip;q=0")] {
+                let res = route_file(base_route.clone(), new_request(accept_encoding))
+                    .await
+                    .unwrap();
+                assert_eq!(res.status(), StatusCode::OK);
+                assert!(res.headers().get(CONTENT_ENCODING).is_none());
+                let body = res.into_body().collect().await.unwrap().to_bytes();
+                assert_eq!(body, Bytes::from("console.log('plain');"));
+            }
+            std::fs::remove_dir_all(root).unwrap();
+        });
+    }
+    #[test]
     fn test_route_file_with_try_file_ok() {
         TOKIO_RUNTIME.spawn(async {
             let request = Request::builder()
@@ -673,10 +2239,18 @@ mod tests {
             let base_route = BaseRoute {
                 endpoint: String::from("config"),
                 try_file: Some(String::from("app_config.yaml")),
+                index: None,
+                autoindex: None,
+                host_header: None,
+                sni: None,
+                preserve_header_case: true,
                 is_alive: Arc::new(RwLock::new(None)),
                 anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                     consecutive_5xx: 100,
+                    recent_responses: Default::default(),
                 })),
+                circuit_breaker_status: Default::default(),
+                recovered_at: Default::default(),
             };
             let res = route_file(base_route, request).await;
             assert!(res.is_ok());
@@ -691,10 +2265,18 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("http://httpbin.org:80"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                 }],
             });
@@ -703,22 +2285,39 @@ mod tests {
             let api_service_manager = ApiServiceManager {
                 sender,
                 service_config: ServiceConfig {
-                    key_str: None,
                     server_type: crate::vojo::app_config::ServiceType::Http,
-                    cert_str: None,
                     routes: vec![Route {
                         rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: None,
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
                         host_name: None,
                         route_id: get_uuid(),
                         matcher: Some(Matcher {
                             prefix: String::from("/"),
                             prefix_rewrite: String::from("test"),
+                            match_type: PathMatchType::Prefix,
+                            paths: None,
+                            methods: None,
+                            schemes: None,
                         }),
                         route_cluster: route,
                         allow_deny_list: Some(vec![AllowDenyObject {
                             limit_type: AllowType::AllowAll,
                             value: None,
                         }]),
+                        allow_deny_default: None,
+                        client_ip: None,
                         authentication: None,
                         anomaly_detection: None,
                         liveness_config: None,
@@ -726,8 +2325,11 @@ mod tests {
                             current_liveness_count: 0,
                         })),
                         ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
                         health_check: None,
                     }],
+                    ..Default::default()
                 },
             };
             let mut write = GLOBAL_APP_CONFIG.write().await;
@@ -762,10 +2364,18 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("httpbin.org:80"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                 }],
             });
@@ -774,24 +2384,43 @@ mod tests {
             let api_service_manager = ApiServiceManager {
                 sender,
                 service_config: ServiceConfig {
-                    key_str: None,
                     server_type: crate::vojo::app_config::ServiceType::Tcp,
-                    cert_str: None,
                     routes: vec![Route {
                         rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: None,
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
                         route_id: get_uuid(),
                         host_name: None,
                         matcher: Some(Matcher {
                             prefix: String::from("/"),
                             prefix_rewrite: String::from("test"),
+                            match_type: PathMatchType::Prefix,
+                            paths: None,
+                            methods: None,
+                            schemes: None,
                         }),
                         route_cluster: route,
                         allow_deny_list: Some(vec![AllowDenyObject {
                             limit_type: AllowType::Deny,
                             value: Some(String::from("127.0.0.1")),
                         }]),
+                        allow_deny_default: None,
+                        client_ip: None,
                         authentication: None,
                         ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
                         liveness_status: Arc::new(RwLock::new(LivenessStatus {
                             current_liveness_count: 0,
                         })),
@@ -799,6 +2428,7 @@ mod tests {
                         anomaly_detection: None,
                         liveness_config: None,
                     }],
+                    ..Default::default()
                 },
             };
             let mut write = GLOBAL_APP_CONFIG.write().await;
@@ -835,10 +2465,18 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("http://127.0.0.1:9851"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
                 }],
             });
@@ -847,37 +2485,62 @@ mod tests {
             let api_service_manager = ApiServiceManager {
                 sender,
                 service_config: ServiceConfig {
-                    key_str: None,
                     server_type: crate::vojo::app_config::ServiceType::Http,
-                    cert_str: None,
                     routes: vec![Route {
                         rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: None,
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
                         host_name: None,
                         route_id: get_uuid(),
                         matcher: Some(Matcher {
                             prefix: String::from("/"),
                             prefix_rewrite: String::from("test"),
+                            match_type: PathMatchType::Prefix,
+                            paths: None,
+                            methods: None,
+                            schemes: None,
                         }),
                         route_cluster: route,
                         allow_deny_list: None,
+                        allow_deny_default: None,
+                        client_ip: None,
                         authentication: None,
                         anomaly_detection: Some(AnomalyDetectionType::Http(
                             HttpAnomalyDetectionParam {
                                 consecutive_5xx: 3,
                                 base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                                    max_ejection_percent: None,
+                                    window_second: None,
+                                    window_size: None,
                                     ejection_second: 10,
                                 },
                             },
                         )),
                         liveness_config: Some(LivenessConfig {
                             min_liveness_count: 1,
+                            panic_threshold: None,
+                            slow_start_second: None,
                         }),
                         liveness_status: Arc::new(RwLock::new(LivenessStatus {
                             current_liveness_count: 0,
                         })),
                         ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
                         health_check: None,
                     }],
+                    ..Default::default()
                 },
             };
             let mut write = GLOBAL_APP_CONFIG.write().await;
@@ -901,7 +2564,2317 @@ mod tests {
                 CommonCheckRequest {},
             )
             .await;
-            assert!(res.is_err());
+            assert!(res.is_ok());
+            assert_eq!(res.unwrap().status(), StatusCode::BAD_GATEWAY);
+        });
+    }
+    #[test]
+    fn test_proxy_error_response_with_response_headers() {
+        TOKIO_RUNTIME.block_on(async {
+            let route = LoadbalancerStrategy::Random(RandomRoute {
+                routes: vec![RandomBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://127.0.0.1:10034"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
+                            consecutive_5xx: 0,
+                            recent_responses: Default::default(),
+                        })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                }],
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let response_headers = ResponseHeaders {
+                add: HashMap::from([(
+                    String::from("Access-Control-Allow-Origin"),
+                    String::from("*"),
+                )]),
+                remove: vec![],
+            };
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    routes: vec![Route {
+                        rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: Some(response_headers),
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
+                        host_name: None,
+                        route_id: get_uuid(),
+                        matcher: Some(Matcher {
+                            prefix: String::from("/"),
+                            prefix_rewrite: String::from("test"),
+                            match_type: PathMatchType::Prefix,
+                            paths: None,
+                            methods: None,
+                            schemes: None,
+                        }),
+                        route_cluster: route,
+                        allow_deny_list: None,
+                        allow_deny_default: None,
+                        client_ip: None,
+                        authentication: None,
+                        anomaly_detection: None,
+                        liveness_config: None,
+                        liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                            current_liveness_count: 0,
+                        })),
+                        ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
+                        health_check: None,
+                    }],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10033-HTTP"), api_service_manager);
+            let client = HttpClients::new();
+            let request = Request::builder()
+                .uri("http://localhost:10033/get")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let res = proxy(
+                client,
+                request,
+                String::from("10033-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+            assert_eq!(
+                res.headers().get("Access-Control-Allow-Origin").unwrap(),
+                "*"
+            );
+        });
+    }
+    #[test]
+    fn test_graceful_shutdown_wait_for_inflight_request() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10031").await.unwrap();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(1000)).await;
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\nslow")
+                    .await
+                    .unwrap();
+            });
+            let route = LoadbalancerStrategy::Random(RandomRoute {
+                routes: vec![RandomBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://127.0.0.1:10031"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
+                            consecutive_5xx: 0,
+                            recent_responses: Default::default(),
+                        })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                }],
+            });
+            let (sender, receiver) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender: sender.clone(),
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    graceful_shutdown_timeout: Some(5),
+                    routes: vec![Route {
+                        rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: None,
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
+                        host_name: None,
+                        route_id: get_uuid(),
+                        matcher: Some(Matcher {
+                            prefix: String::from("/"),
+                            prefix_rewrite: String::from("/"),
+                            match_type: PathMatchType::Prefix,
+                            paths: None,
+                            methods: None,
+                            schemes: None,
+                        }),
+                        route_cluster: route,
+                        allow_deny_list: None,
+                        allow_deny_default: None,
+                        client_ip: None,
+                        authentication: None,
+                        anomaly_detection: None,
+                        liveness_config: None,
+                        liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                            current_liveness_count: 0,
+                        })),
+                        ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
+                        health_check: None,
+                    }],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10032-HTTP"), api_service_manager);
+            let server = tokio::spawn(async {
+                let mut http_proxy = HttpProxy {
+                    port: 10032,
+                    channel: receiver,
+                    mapping_key: String::from("10032-HTTP"),
+                    graceful_shutdown_timeout: 5,
+                    socket_options: Default::default(),
+                    upstream_pool: Default::default(),
+                    upstream_tls: None,
+                    tls_policy: None,
+                    sni_certs: None,
+                    header_limit: Default::default(),
+                    bind_addresses: None,
+                };
+                http_proxy.start_http_server().await
+            });
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let request_task = tokio::spawn(async {
+                let client = HttpClients::new();
+                let request = Request::builder()
+                    .uri("http://127.0.0.1:10032/slow")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                client.request_http(request, 5, true).await
+            });
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let shutdown_time = SystemTime::now();
+            sender.send(()).await.unwrap();
+            let server_result = server.await.unwrap();
+            assert!(server_result.is_ok());
+            assert!(shutdown_time.elapsed().unwrap() >= Duration::from_millis(500));
+
+            let response = request_task.await.unwrap().unwrap().unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body_bytes = response.collect().await.unwrap().to_bytes();
+            assert_eq!(body_bytes, Bytes::from("slow"));
+        });
+    }
+    #[test]
+    fn test_http2_multiplexed_requests_ok() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10035").await.unwrap();
+            tokio::spawn(async move {
+                loop {
+                    let (stream, _) = backend.accept().await.unwrap();
+                    tokio::spawn(async move {
+                        let service = service_fn(|req: Request<Incoming>| async move {
+                            Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(
+                                req.uri().path().to_string(),
+                            ))))
+                        });
+                        let _ = hyper::server::conn::http1::Builder::new()
+                            .serve_connection(TokioIo::new(stream), service)
+                            .await;
+                    });
+                }
+            });
+            let route = LoadbalancerStrategy::Random(RandomRoute {
+                routes: vec![RandomBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://127.0.0.1:10035"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
+                            consecutive_5xx: 0,
+                            recent_responses: Default::default(),
+                        })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                }],
+            });
+            let (sender, receiver) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    routes: vec![Route {
+                        rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: None,
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
+                        host_name: None,
+                        route_id: get_uuid(),
+                        matcher: Some(Matcher {
+                            prefix: String::from("/"),
+                            prefix_rewrite: String::from("/"),
+                            match_type: PathMatchType::Prefix,
+                            paths: None,
+                            methods: None,
+                            schemes: None,
+                        }),
+                        route_cluster: route,
+                        allow_deny_list: None,
+                        allow_deny_default: None,
+                        client_ip: None,
+                        authentication: None,
+                        anomaly_detection: None,
+                        liveness_config: None,
+                        liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                            current_liveness_count: 0,
+                        })),
+                        ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
+                        health_check: None,
+                    }],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10036-HTTP"), api_service_manager);
+            tokio::spawn(async {
+                let mut http_proxy = HttpProxy {
+                    port: 10036,
+                    channel: receiver,
+                    mapping_key: String::from("10036-HTTP"),
+                    graceful_shutdown_timeout: 1,
+                    socket_options: Default::default(),
+                    upstream_pool: Default::default(),
+                    upstream_tls: None,
+                    tls_policy: None,
+                    sni_certs: None,
+                    header_limit: Default::default(),
+                    bind_addresses: None,
+                };
+                http_proxy.start_http_server().await
+            });
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let stream = tokio::net::TcpStream::connect("127.0.0.1:10036")
+                .await
+                .unwrap();
+            let (send_request, connection) =
+                hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(stream))
+                    .await
+                    .unwrap();
+            tokio::spawn(connection);
+            let mut tasks = vec![];
+            for path in ["/first", "/second", "/third"] {
+                let mut send_request = send_request.clone();
+                tasks.push(tokio::spawn(async move {
+                    let request = Request::builder()
+                        .uri(format!("http://127.0.0.1:10036{}", path))
+                        .body(Full::new(Bytes::new()))
+                        .unwrap();
+                    let response = send_request.send_request(request).await.unwrap();
+                    assert_eq!(response.version(), Version::HTTP_2);
+                    assert_eq!(response.status(), StatusCode::OK);
+                    let body_bytes = response.collect().await.unwrap().to_bytes();
+                    assert_eq!(body_bytes, Bytes::from(path));
+                }));
+            }
+            for task in tasks {
+                task.await.unwrap();
+            }
+        });
+    }
+    #[test]
+    fn test_proxy_streams_event_stream_response() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10037").await.unwrap();
+            let (first_event_sender, first_event_receiver) = tokio::sync::oneshot::channel::<()>();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ntransfer-encoding: chunked\r\n\r\n")
+                    .await
+                    .unwrap();
+                stream.write_all(b"d\r\ndata: first\n\n\r\n").await.unwrap();
+                let _ = first_event_receiver.await;
+                stream.write_all(b"e\r\ndata: second\n\n\r\n").await.unwrap();
+                stream.write_all(b"0\r\n\r\n").await.unwrap();
+            });
+            let route = LoadbalancerStrategy::Random(RandomRoute {
+                routes: vec![RandomBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://127.0.0.1:10037"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
+                            consecutive_5xx: 0,
+                            recent_responses: Default::default(),
+                        })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                }],
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    routes: vec![Route {
+                        rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: None,
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
+                        host_name: None,
+                        route_id: get_uuid(),
+                        matcher: Some(Matcher {
+                            prefix: String::from("/"),
+                            prefix_rewrite: String::from("/"),
+                            match_type: PathMatchType::Prefix,
+                            paths: None,
+                            methods: None,
+                            schemes: None,
+                        }),
+                        route_cluster: route,
+                        allow_deny_list: None,
+                        allow_deny_default: None,
+                        client_ip: None,
+                        authentication: None,
+                        anomaly_detection: None,
+                        liveness_config: None,
+                        liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                            current_liveness_count: 0,
+                        })),
+                        ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
+                        health_check: None,
+                    }],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10038-HTTP"), api_service_manager);
+            let request = Request::builder()
+                .uri("http://localhost:10038/events")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let response = proxy_adapter(
+                HttpClients::new(),
+                request,
+                String::from("10038-HTTP"),
+                socket,
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let mut body = response.into_body();
+            let first = timeout(Duration::from_secs(5), body.frame())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap()
+                .into_data()
+                .unwrap();
+            assert_eq!(first, Bytes::from("data: first\n\n"));
+            first_event_sender.send(()).unwrap();
+            let second = timeout(Duration::from_secs(5), body.frame())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap()
+                .into_data()
+                .unwrap();
+            assert_eq!(second, Bytes::from("data: second\n\n"));
+        });
+    }
+    #[test]
+    fn test_proxy_deny_metadata_upstream() {
+        TOKIO_RUNTIME.block_on(async {
+            let route = LoadbalancerStrategy::Random(RandomRoute {
+                routes: vec![RandomBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://169.254.169.254"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
+                            consecutive_5xx: 0,
+                            recent_responses: Default::default(),
+                        })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                }],
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    routes: vec![Route {
+                        rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: None,
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
+                        host_name: None,
+                        route_id: get_uuid(),
+                        matcher: Some(Matcher {
+                            prefix: String::from("/"),
+                            prefix_rewrite: String::from("/"),
+                            match_type: PathMatchType::Prefix,
+                            paths: None,
+                            methods: None,
+                            schemes: None,
+                        }),
+                        route_cluster: route,
+                        allow_deny_list: None,
+                        allow_deny_default: None,
+                        client_ip: None,
+                        authentication: None,
+                        anomaly_detection: None,
+                        liveness_config: None,
+                        liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                            current_liveness_count: 0,
+                        })),
+                        ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
+                        health_check: None,
+                    }],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10039-HTTP"), api_service_manager);
+            let request = Request::builder()
+                .uri("http://localhost:10039/latest/meta-data")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let res = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10039-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        });
+    }
+    fn create_test_route() -> Route {
+        Route {
+            rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
+            host_name: None,
+            route_id: get_uuid(),
+            matcher: Some(Matcher {
+                prefix: String::from("/"),
+                prefix_rewrite: String::from("/"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
+            }),
+            route_cluster: LoadbalancerStrategy::Random(RandomRoute { routes: vec![] }),
+            allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
+            authentication: None,
+            anomaly_detection: None,
+            liveness_config: None,
+            liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                current_liveness_count: 0,
+            })),
+            ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
+            health_check: None,
+        }
+    }
+    /**
+     *Points the route to the endpoint and registers it as the only route of the mapping key.
+     */
+    fn insert_test_route(mapping_key: &str, endpoint: &str, mut route: Route) {
+        route.route_cluster = LoadbalancerStrategy::PollRoute(PollRoute {
+            current_index: Arc::new(AtomicUsize::new(0)),
+            routes: vec![PollBaseRoute {
+                base_route: BaseRoute {
+                    endpoint: endpoint.to_string(),
+                    ..Default::default()
+                },
+            }],
+        });
+        let (sender, _) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            mapping_key.to_string(),
+            ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            },
+        );
+    }
+    #[test]
+    fn test_forward_headers_trusted_append() {
+        let mut route = create_test_route();
+        route.forward_headers = Some(ForwardHeaders {
+            trust_existing: true,
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, "203.0.113.7, 10.0.0.2".parse().unwrap());
+        headers.insert(X_FORWARDED_PROTO, "https".parse().unwrap());
+        headers.insert(HOST, "example.com".parse().unwrap());
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)), 8080);
+        handle_forward_headers(&route, &mut headers, socket, ServiceType::Http);
+        assert_eq!(
+            headers.get(X_FORWARDED_FOR).unwrap(),
+            "203.0.113.7, 10.0.0.2, 10.0.0.3"
+        );
+        assert_eq!(headers.get(X_FORWARDED_PROTO).unwrap(), "https");
+        assert_eq!(headers.get(X_FORWARDED_HOST).unwrap(), "example.com");
+    }
+    #[test]
+    fn test_forward_headers_untrusted_replace() {
+        let mut route = create_test_route();
+        route.forward_headers = Some(ForwardHeaders {
+            trust_existing: false,
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, "1.2.3.4".parse().unwrap());
+        headers.insert(X_FORWARDED_PROTO, "https".parse().unwrap());
+        headers.insert(X_FORWARDED_HOST, "spoofed.com".parse().unwrap());
+        headers.insert(HOST, "example.com".parse().unwrap());
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 3)), 8080);
+        handle_forward_headers(&route, &mut headers, socket, ServiceType::Http);
+        assert_eq!(headers.get(X_FORWARDED_FOR).unwrap(), "10.0.0.3");
+        assert_eq!(headers.get(X_FORWARDED_PROTO).unwrap(), "http");
+        assert_eq!(headers.get(X_FORWARDED_HOST).unwrap(), "example.com");
+
+        let mut headers = HeaderMap::new();
+        handle_forward_headers(&route, &mut headers, socket, ServiceType::Https);
+        assert_eq!(headers.get(X_FORWARDED_FOR).unwrap(), "10.0.0.3");
+        assert_eq!(headers.get(X_FORWARDED_PROTO).unwrap(), "https");
+    }
+    #[test]
+    fn test_proxy_trailing_slash_redirect() {
+        TOKIO_RUNTIME.block_on(async {
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    trailing_slash: Some(TrailingSlashPolicy::Strip),
+                    routes: vec![create_test_route()],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10040-HTTP"), api_service_manager);
+            let request = Request::builder()
+                .uri("http://localhost:10040/a/?name=1")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let res = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10040-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+            assert_eq!(res.headers().get(LOCATION).unwrap(), "/a?name=1");
+        });
+    }
+    #[test]
+    fn test_proxy_custom_not_found_page() {
+        TOKIO_RUNTIME.block_on(async {
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let mut route = create_test_route();
+            route.matcher = Some(Matcher {
+                prefix: String::from("/api"),
+                prefix_rewrite: String::from("/"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
+            });
+            let mut error_pages = HashMap::new();
+            error_pages.insert(
+                404,
+                ErrorPage {
+                    content_type: String::from("text/html; charset=utf-8"),
+                    body: String::from("<h1>{status}</h1><p>{path} is not found</p>"),
+                },
+            );
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    error_pages: Some(error_pages),
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10057-HTTP"), api_service_manager);
+            let request = Request::builder()
+                .uri("http://localhost:10057/missing%3Cb%3E?name=1")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let res = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10057-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+            assert_eq!(
+                res.headers().get(CONTENT_TYPE).unwrap(),
+                "text/html; charset=utf-8"
+            );
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(
+                body,
+                Bytes::from("<h1>404</h1><p>/missing%3Cb%3E is not found</p>")
+            );
+        });
+    }
+    #[test]
+    fn test_proxy_mirror_request_to_shadow() {
+        TOKIO_RUNTIME.block_on(async {
+            let primary = TcpListener::bind("127.0.0.1:10045").await.unwrap();
+            tokio::spawn(async move {
+                let (mut stream, _) = primary.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 7\r\n\r\nprimary")
+                    .await
+                    .unwrap();
+            });
+            let shadow = TcpListener::bind("127.0.0.1:10046").await.unwrap();
+            let (shadow_sender, shadow_receiver) = tokio::sync::oneshot::channel::<String>();
+            tokio::spawn(async move {
+                let (mut stream, _) = shadow.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let len = stream.read(&mut buf).await.unwrap();
+                let _ = shadow_sender.send(String::from_utf8_lossy(&buf[..len]).to_string());
+                stream
+                    .write_all(b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+            });
+            let mut route = create_test_route();
+            route.route_cluster = LoadbalancerStrategy::Random(RandomRoute {
+                routes: vec![RandomBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://127.0.0.1:10045"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
+                            consecutive_5xx: 0,
+                            recent_responses: Default::default(),
+                        })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                }],
+            });
+            route.mirror = Some(MirrorConfig {
+                endpoint: String::from("http://127.0.0.1:10046"),
+                percentage: 100.0,
+                max_body_size: 1024,
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10047-HTTP"), api_service_manager);
+            let request = Request::builder()
+                .method("POST")
+                .uri("http://localhost:10047/orders?id=1")
+                .body(Full::new(Bytes::from("hello")).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let response = proxy_adapter(
+                HttpClients::new(),
+                request,
+                String::from("10047-HTTP"),
+                socket,
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("primary"));
+            let mirrored = timeout(Duration::from_secs(5), shadow_receiver)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(mirrored.starts_with("POST /orders?id=1 HTTP/1.1"));
+            assert!(mirrored.ends_with("hello"));
+        });
+    }
+    #[test]
+    fn test_proxy_request_id_generated_and_passed_through() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10083").await.unwrap();
+            let (request_sender, mut request_receiver) = tokio::sync::mpsc::channel(2);
+            tokio::spawn(async move {
+                for _ in 0..2 {
+                    let (mut stream, _) = backend.accept().await.unwrap();
+                    let mut buf = [0; 1024];
+                    let len = stream.read(&mut buf).await.unwrap();
+                    let request = String::from_utf8_lossy(&buf[..len]).to_lowercase();
+                    let request_id = request
+                        .lines()
+                        .find_map(|line| line.strip_prefix("x-request-id: "))
+                        .map(|item| item.trim().to_string());
+                    let _ = request_sender.send(request_id).await;
+                    stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: 2\r\n\r\nok",
+                        )
+                        .await
+                        .unwrap();
+                }
+            });
+            insert_test_route("10084-HTTP", "http://127.0.0.1:10083", create_test_route());
+            GLOBAL_CONFIG_MAPPING
+                .get_mut("10084-HTTP")
+                .unwrap()
+                .service_config
+                .request_id = Some(RequestIdConfig {
+                trust_incoming: true,
+            });
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let request = Request::builder()
+                .uri("http://localhost:10084/orders")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let response = proxy_adapter(
+                HttpClients::new(),
+                request,
+                String::from("10084-HTTP"),
+                socket,
+            )
+            .await
+            .unwrap();
+            let generated_id = response
+                .headers()
+                .get(X_REQUEST_ID)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            assert_eq!(generated_id.len(), 36);
+            assert_eq!(request_receiver.recv().await.unwrap(), Some(generated_id));
+
+            let request = Request::builder()
+                .uri("http://localhost:10084/orders")
+                .header(X_REQUEST_ID, "incoming-id")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let response = proxy_adapter(
+                HttpClients::new(),
+                request,
+                String::from("10084-HTTP"),
+                socket,
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.headers().get(X_REQUEST_ID).unwrap(), "incoming-id");
+            assert_eq!(
+                request_receiver.recv().await.unwrap(),
+                Some(String::from("incoming-id"))
+            );
+        });
+    }
+    #[test]
+    fn test_proxy_forward_traceparent() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10085").await.unwrap();
+            let (request_sender, request_receiver) = tokio::sync::oneshot::channel();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let len = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..len]).to_lowercase();
+                let traceparent = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("traceparent: "))
+                    .map(|item| item.trim().to_string());
+                let _ = request_sender.send(traceparent);
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                    .await
+                    .unwrap();
+            });
+            insert_test_route("10086-HTTP", "http://127.0.0.1:10085", create_test_route());
+            GLOBAL_CONFIG_MAPPING
+                .get_mut("10086-HTTP")
+                .unwrap()
+                .service_config
+                .trace_context = Some(true);
+            let incoming = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+            let request = Request::builder()
+                .uri("http://localhost:10086/orders")
+                .header(TRACEPARENT, incoming)
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let response = proxy_adapter(
+                HttpClients::new(),
+                request,
+                String::from("10086-HTTP"),
+                socket,
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let forwarded = request_receiver.await.unwrap().unwrap();
+            assert_ne!(forwarded, incoming);
+            let (trace_id, span_id, trace_flags) = TraceContext::parse(&forwarded).unwrap();
+            assert_eq!(trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+            assert_ne!(span_id, "00f067aa0ba902b7");
+            assert_eq!(trace_flags, 1);
+        });
+    }
+    #[test]
+    fn test_proxy_override_host_header() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10112").await.unwrap();
+            let (request_sender, request_receiver) = tokio::sync::oneshot::channel();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let len = stream.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..len]).to_lowercase();
+                let _ = request_sender.send(request);
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                    .await
+                    .unwrap();
+            });
+            let mut route = create_test_route();
+            route.forward_headers = Some(ForwardHeaders {
+                trust_existing: false,
+            });
+            route.route_cluster = LoadbalancerStrategy::PollRoute(PollRoute {
+                current_index: Arc::new(AtomicUsize::new(0)),
+                routes: vec![PollBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://127.0.0.1:10112"),
+                        host_header: Some(String::from("backend.internal")),
+                        ..Default::default()
+                    },
+                }],
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10114-HTTP"), api_service_manager);
+            let request = Request::builder()
+                .uri("http://localhost:10114/orders")
+                .header(HOST, "localhost:10114")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let response = proxy_adapter(
+                HttpClients::new(),
+                request,
+                String::from("10114-HTTP"),
+                socket,
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let request = request_receiver.await.unwrap();
+            assert!(request.contains("host: backend.internal\r\n"));
+            assert!(request.contains("x-forwarded-host: localhost:10114\r\n"));
+            assert!(request.starts_with("get /orders http/1.1"));
+        });
+    }
+    #[test]
+    fn test_proxy_map_grpc_status() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10116").await.unwrap();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: application/grpc\r\ngrpc-status: 14\r\ngrpc-message: backend%20down\r\ncontent-length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+            });
+            insert_test_route("10117-HTTP", "http://127.0.0.1:10116", create_test_route());
+            let request = Request::builder()
+                .uri("http://localhost:10117/users/42")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let response = proxy_adapter(
+                HttpClients::new(),
+                request,
+                String::from("10117-HTTP"),
+                socket,
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], 14);
+            assert_eq!(body["message"], "backend down");
+        });
+    }
+    #[test]
+    fn test_proxy_eject_timed_out_backend() {
+        TOKIO_RUNTIME.block_on(async {
+            let slow = TcpListener::bind("127.0.0.1:10120").await.unwrap();
+            let fast = TcpListener::bind("127.0.0.1:10121").await.unwrap();
+            tokio::spawn(async move {
+                let mut streams = vec![];
+                while let Ok((stream, _)) = slow.accept().await {
+                    streams.push(stream);
+                }
+            });
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = fast.accept().await {
+                    tokio::spawn(async move {
+                        let mut buf = [0; 1024];
+                        while let Ok(len) = stream.read(&mut buf).await {
+                            if len == 0 {
+                                break;
+                            }
+                            let _ = stream
+                                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                                .await;
+                        }
+                    });
+                }
+            });
+            let slow_route = BaseRoute {
+                endpoint: String::from("http://127.0.0.1:10120"),
+                ..Default::default()
+            };
+            let is_alive = slow_route.is_alive.clone();
+            let mut route = create_test_route();
+            route.timeout = Some(TimeoutConfig {
+                connect_timeout: None,
+                read_timeout: None,
+                request_timeout: Some(200),
+            });
+            route.anomaly_detection = Some(AnomalyDetectionType::Http(HttpAnomalyDetectionParam {
+                consecutive_5xx: 2,
+                base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                    ejection_second: 1,
+                    window_size: None,
+                    window_second: None,
+                    max_ejection_percent: None,
+                },
+            }));
+            route.liveness_config = Some(LivenessConfig {
+                min_liveness_count: 1,
+                panic_threshold: None,
+                slow_start_second: None,
+            });
+            route.liveness_status = Arc::new(RwLock::new(LivenessStatus {
+                current_liveness_count: 2,
+            }));
+            let liveness_status = route.liveness_status.clone();
+            route.route_cluster = LoadbalancerStrategy::PollRoute(PollRoute {
+                current_index: Arc::new(AtomicUsize::new(0)),
+                routes: vec![
+                    PollBaseRoute {
+                        base_route: slow_route,
+                    },
+                    PollBaseRoute {
+                        base_route: BaseRoute {
+                            endpoint: String::from("http://127.0.0.1:10121"),
+                            ..Default::default()
+                        },
+                    },
+                ],
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10122-HTTP"), api_service_manager);
+            let http_clients = HttpClients::new();
+            let send_request = || async {
+                let request = Request::builder()
+                    .uri("http://localhost:10122/get")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+                proxy_adapter(
+                    http_clients.clone(),
+                    request,
+                    String::from("10122-HTTP"),
+                    socket,
+                )
+                .await
+                .unwrap()
+                .status()
+            };
+            let mut statuses = vec![];
+            for _ in 0..4 {
+                statuses.push(send_request().await);
+            }
+            assert_eq!(
+                statuses
+                    .iter()
+                    .filter(|item| **item == StatusCode::OK)
+                    .count(),
+                2
+            );
+            assert_eq!(*is_alive.read().await, Some(false));
+            assert_eq!(liveness_status.read().await.current_liveness_count, 1);
+            for _ in 0..3 {
+                assert_eq!(send_request().await, StatusCode::OK);
+            }
+
+            sleep(Duration::from_millis(1500)).await;
+            assert_eq!(*is_alive.read().await, Some(true));
+            assert_eq!(liveness_status.read().await.current_liveness_count, 2);
+        });
+    }
+    #[test]
+    fn test_proxy_bulkhead_reject_when_queue_full() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10123").await.unwrap();
+            let (release_sender, release_receiver) = tokio::sync::watch::channel(false);
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = backend.accept().await {
+                    let mut release_receiver = release_receiver.clone();
+                    tokio::spawn(async move {
+                        let mut buf = [0; 1024];
+                        while let Ok(len) = stream.read(&mut buf).await {
+                            if len == 0 {
+                                break;
+                            }
+                            let _ = release_receiver.wait_for(|released| *released).await;
+                            let _ = stream
+                                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                                .await;
+                        }
+                    });
+                }
+            });
+            let bulkhead = Bulkhead {
+                max_concurrent: 1,
+                max_queue: 1,
+                ..Default::default()
+            };
+            let mut route = create_test_route();
+            route.bulkhead = Some(bulkhead.clone());
+            insert_test_route("10124-HTTP", "http://127.0.0.1:10123", route);
+            let send_request = || {
+                let request = Request::builder()
+                    .uri("http://localhost:10124/get")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+                tokio::spawn(async move {
+                    let response = proxy_adapter(
+                        HttpClients::new(),
+                        request,
+                        String::from("10124-HTTP"),
+                        socket,
+                    )
+                    .await
+                    .unwrap();
+                    let status = response.status();
+                    let _ = response.into_body().collect().await;
+                    status
+                })
+            };
+            let first = send_request();
+            sleep(Duration::from_millis(100)).await;
+            let second = send_request();
+            sleep(Duration::from_millis(100)).await;
+            assert_eq!(bulkhead.queued.load(Ordering::SeqCst), 1);
+            let third = send_request();
+            assert_eq!(third.await.unwrap(), StatusCode::SERVICE_UNAVAILABLE);
+
+            release_sender.send(true).unwrap();
+            assert_eq!(first.await.unwrap(), StatusCode::OK);
+            assert_eq!(second.await.unwrap(), StatusCode::OK);
+            assert_eq!(bulkhead.queued.load(Ordering::SeqCst), 0);
+        });
+    }
+    #[test]
+    fn test_http_server_reject_oversized_headers() {
+        TOKIO_RUNTIME.block_on(async {
+            let (_sender, receiver) = tokio::sync::mpsc::channel(10);
+            tokio::spawn(async {
+                let mut http_proxy = HttpProxy {
+                    port: 10127,
+                    channel: receiver,
+                    mapping_key: String::from("10127-HTTP"),
+                    graceful_shutdown_timeout: 5,
+                    socket_options: Default::default(),
+                    upstream_pool: Default::default(),
+                    upstream_tls: None,
+                    tls_policy: None,
+                    sni_certs: None,
+                    header_limit: HeaderLimitConfig {
+                        max_header_size: Some(8192),
+                        max_header_count: Some(10),
+                    },
+                    bind_addresses: None,
+                };
+                http_proxy.start_http_server().await
+            });
+            sleep(Duration::from_millis(100)).await;
+            let send_raw_request = |headers: String| async move {
+                let mut stream = tokio::net::TcpStream::connect("127.0.0.1:10127")
+                    .await
+                    .unwrap();
+                stream
+                    .write_all(
+                        format!("GET /get HTTP/1.1\r\nhost: localhost\r\n{}\r\n", headers)
+                            .as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+                let mut buf = vec![0; 1024];
+                let len = stream.read(&mut buf).await.unwrap();
+                String::from_utf8_lossy(&buf[..len]).to_string()
+            };
+            let oversized = format!("x-large: {}\r\n", "a".repeat(16 * 1024));
+            let response = send_raw_request(oversized).await;
+            assert!(response.starts_with("HTTP/1.1 431"));
+
+            let too_many = (0..20)
+                .map(|index| format!("x-header-{}: value\r\n", index))
+                .collect::<String>();
+            let response = send_raw_request(too_many).await;
+            assert!(response.starts_with("HTTP/1.1 431"));
+
+            let response = send_raw_request(String::from("x-header: value\r\n")).await;
+            assert!(!response.starts_with("HTTP/1.1 431"));
+        });
+    }
+    #[test]
+    fn test_http_server_preserve_mixed_case_header() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10154").await.unwrap();
+            let (request_sender, request_receiver) = tokio::sync::oneshot::channel();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let len = stream.read(&mut buf).await.unwrap();
+                let _ = request_sender.send(String::from_utf8_lossy(&buf[..len]).to_string());
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                    .await
+                    .unwrap();
+            });
+            let mut route = create_test_route();
+            route.route_cluster = LoadbalancerStrategy::PollRoute(PollRoute {
+                current_index: Arc::new(AtomicUsize::new(0)),
+                routes: vec![PollBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://127.0.0.1:10154"),
+                        preserve_header_case: true,
+                        ..Default::default()
+                    },
+                }],
+            });
+            let (sender, receiver) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10153-HTTP"), api_service_manager);
+            tokio::spawn(async {
+                let mut http_proxy = HttpProxy {
+                    port: 10153,
+                    channel: receiver,
+                    mapping_key: String::from("10153-HTTP"),
+                    graceful_shutdown_timeout: 5,
+                    socket_options: Default::default(),
+                    upstream_pool: Default::default(),
+                    upstream_tls: None,
+                    tls_policy: None,
+                    sni_certs: None,
+                    header_limit: Default::default(),
+                    bind_addresses: None,
+                };
+                http_proxy.start_http_server().await
+            });
+            sleep(Duration::from_millis(100)).await;
+            let mut stream = tokio::net::TcpStream::connect("127.0.0.1:10153")
+                .await
+                .unwrap();
+            stream
+                .write_all(b"GET /get HTTP/1.1\r\nhost: localhost\r\nx-CuStOm: 1\r\n\r\n")
+                .await
+                .unwrap();
+            let mut buf = vec![0; 1024];
+            let len = stream.read(&mut buf).await.unwrap();
+            assert!(String::from_utf8_lossy(&buf[..len]).starts_with("HTTP/1.1 200"));
+            let request = request_receiver.await.unwrap();
+            assert!(request.contains("\r\nx-CuStOm: 1\r\n"), "{}", request);
+        });
+    }
+    #[test]
+    fn test_http_server_bind_to_loopback_only() {
+        TOKIO_RUNTIME.block_on(async {
+            let (_sender, receiver) = tokio::sync::mpsc::channel(10);
+            tokio::spawn(async {
+                let mut http_proxy = HttpProxy {
+                    port: 10130,
+                    channel: receiver,
+                    mapping_key: String::from("10130-HTTP"),
+                    graceful_shutdown_timeout: 5,
+                    socket_options: Default::default(),
+                    upstream_pool: Default::default(),
+                    upstream_tls: None,
+                    tls_policy: None,
+                    sni_certs: None,
+                    header_limit: Default::default(),
+                    bind_addresses: Some(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]),
+                };
+                http_proxy.start_http_server().await
+            });
+            sleep(Duration::from_millis(100)).await;
+            assert!(tokio::net::TcpStream::connect("127.0.0.1:10130")
+                .await
+                .is_ok());
+            assert!(tokio::net::TcpStream::connect("127.0.0.2:10130")
+                .await
+                .is_err());
+        });
+    }
+    #[test]
+    fn test_proxy_hedged_request_to_faster_backend() {
+        TOKIO_RUNTIME.block_on(async {
+            let slow = TcpListener::bind("127.0.0.1:10058").await.unwrap();
+            tokio::spawn(async move {
+                let (mut stream, _) = slow.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                sleep(Duration::from_secs(3)).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\nslow")
+                    .await;
+            });
+            let fast = TcpListener::bind("127.0.0.1:10059").await.unwrap();
+            let (fast_sender, fast_receiver) = tokio::sync::oneshot::channel::<String>();
+            tokio::spawn(async move {
+                let (mut stream, _) = fast.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let len = stream.read(&mut buf).await.unwrap();
+                let _ = fast_sender.send(String::from_utf8_lossy(&buf[..len]).to_string());
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\nfast")
+                    .await
+                    .unwrap();
+            });
+            let mut route = create_test_route();
+            route.route_cluster = LoadbalancerStrategy::PollRoute(PollRoute {
+                current_index: Arc::new(AtomicUsize::new(0)),
+                routes: ["http://127.0.0.1:10058", "http://127.0.0.1:10059"]
+                    .iter()
+                    .map(|endpoint| PollBaseRoute {
+                        base_route: BaseRoute {
+                            endpoint: endpoint.to_string(),
+                            ..Default::default()
+                        },
+                    })
+                    .collect(),
+            });
+            route.hedge = Some(HedgeConfig {
+                delay_millis: 100,
+                max_hedges: 1,
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10060-HTTP"), api_service_manager);
+            let request = Request::builder()
+                .uri("http://localhost:10060/orders?id=1")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let start = Instant::now();
+            let response = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10060-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("fast"));
+            assert!(start.elapsed() < Duration::from_secs(2));
+            let hedged = fast_receiver.await.unwrap();
+            assert!(hedged.starts_with("GET /orders?id=1 HTTP/1.1"));
+        });
+    }
+    #[test]
+    fn test_access_log_json_fields() {
+        let access_log = AccessLog {
+            method: &Method::POST,
+            path: "/orders?id=1",
+            status: 201,
+            latency_ms: 12,
+            remote_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080),
+            route_id: Some(String::from("orders")),
+            bytes: Some(42),
+            request_id: Some(String::from("abc")),
+            trace_id: None,
+            span_id: None,
+        };
+        assert_eq!(
+            access_log.to_json(),
+            json!({
+                "method": "POST",
+                "path": "/orders?id=1",
+                "status": 201,
+                "latency_ms": 12,
+                "remote_addr": "127.0.0.1:8080",
+                "route_id": "orders",
+                "bytes": 42,
+                "request_id": "abc",
+                "trace_id": null,
+                "span_id": null,
+            })
+        );
+    }
+    #[test]
+    fn test_select_retry_endpoints_prefer_untried() {
+        let endpoints = ["http://a", "http://b", "http://c"]
+            .iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            select_retry_endpoints(&endpoints, "http://b", 2),
+            vec!["http://c", "http://a"]
+        );
+        assert_eq!(
+            select_retry_endpoints(&endpoints, "http://a", 5),
+            vec!["http://b", "http://c", "http://a", "http://b", "http://c"]
+        );
+        assert_eq!(
+            select_retry_endpoints(&endpoints[..1], "http://a", 2),
+            vec!["http://a", "http://a"]
+        );
+        assert!(select_retry_endpoints(&endpoints, "http://a", 0).is_empty());
+    }
+    #[test]
+    fn test_proxy_retry_with_untried_backend() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10077").await.unwrap();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).await.unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                    .await
+                    .unwrap();
+            });
+            let mut route = create_test_route();
+            route.route_cluster = LoadbalancerStrategy::PollRoute(PollRoute {
+                current_index: Arc::new(AtomicUsize::new(0)),
+                routes: ["http://127.0.0.1:10076", "http://127.0.0.1:10077"]
+                    .iter()
+                    .map(|endpoint| PollBaseRoute {
+                        base_route: BaseRoute {
+                            endpoint: endpoint.to_string(),
+                            ..Default::default()
+                        },
+                    })
+                    .collect(),
+            });
+            route.retry = Some(RetryConfig {
+                max_retries: 1,
+                retry_on_5xx: false,
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            let api_service_manager = ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: crate::vojo::app_config::ServiceType::Http,
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            };
+            GLOBAL_CONFIG_MAPPING.insert(String::from("10078-HTTP"), api_service_manager);
+            let request = Request::builder()
+                .uri("http://localhost:10078/orders")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let response = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10078-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("ok"));
+        });
+    }
+    #[test]
+    fn test_proxy_body_logging_keeps_body() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10081").await.unwrap();
+            let (request_sender, request_receiver) = tokio::sync::oneshot::channel();
+            tokio::spawn(async move {
+                let (mut stream, _) = backend.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let len = stream.read(&mut buf).await.unwrap();
+                let _ = request_sender.send(String::from_utf8_lossy(&buf[..len]).to_string());
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 16\r\n\r\n{\"token\":\"abcd\"}")
+                    .await
+                    .unwrap();
+            });
+            let mut route = create_test_route();
+            route.body_logging = Some(BodyLoggingConfig {
+                max_bytes: 8,
+                redact_headers: vec![String::from("authorization")],
+                redact_fields: vec![String::from("password")],
+            });
+            insert_test_route("10082-HTTP", "http://127.0.0.1:10081", route);
+            let request_body = r#"{"user":"admin","password":"secret"}"#;
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri("http://localhost:10082/orders")
+                .header("authorization", "Bearer secret")
+                .body(Full::new(Bytes::from(request_body)).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let response = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10082-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from(r#"{"token":"abcd"}"#));
+            let upstream_request = request_receiver.await.unwrap();
+            assert!(upstream_request.ends_with(request_body));
+            assert!(upstream_request.contains("Bearer secret"));
+        });
+    }
+    #[test]
+    fn test_proxy_route_maintenance() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10101").await.unwrap();
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = backend.accept().await.unwrap();
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await.unwrap();
+                    stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+                        .await
+                        .unwrap();
+                }
+            });
+            let mut route = create_test_route();
+            route.maintenance = Some(MaintenanceConfig {
+                enabled: true,
+                retry_after: Some(120),
+                body: Some(String::from("<h1>Under maintenance</h1>")),
+                content_type: String::from("text/html; charset=utf-8"),
+            });
+            insert_test_route("10102-HTTP", "http://127.0.0.1:10101", route);
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let new_request = || {
+                Request::builder()
+                    .uri("http://localhost:10102/orders")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap()
+            };
+            let res = proxy(
+                HttpClients::new(),
+                new_request(),
+                String::from("10102-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "120");
+            assert_eq!(
+                res.headers().get(CONTENT_TYPE).unwrap(),
+                "text/html; charset=utf-8"
+            );
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("<h1>Under maintenance</h1>"));
+
+            GLOBAL_CONFIG_MAPPING
+                .get_mut("10102-HTTP")
+                .unwrap()
+                .service_config
+                .routes[0]
+                .maintenance
+                .as_mut()
+                .unwrap()
+                .enabled = false;
+            let res = proxy(
+                HttpClients::new(),
+                new_request(),
+                String::from("10102-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("ok"));
+            GLOBAL_CONFIG_MAPPING.remove("10102-HTTP");
+        });
+    }
+    #[test]
+    fn test_proxy_method_override() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10103").await.unwrap();
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = backend.accept().await.unwrap();
+                    let mut buf = [0; 1024];
+                    let len = stream.read(&mut buf).await.unwrap();
+                    let request = String::from_utf8_lossy(&buf[..len]).to_string();
+                    let method = request.split(' ').next().unwrap_or_default().to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{}",
+                        method.len(),
+                        method
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                }
+            });
+            let mut route = create_test_route();
+            route.matcher.as_mut().unwrap().methods = Some(vec![String::from("PUT")]);
+            insert_test_route("10104-HTTP", "http://127.0.0.1:10103", route);
+            GLOBAL_CONFIG_MAPPING
+                .get_mut("10104-HTTP")
+                .unwrap()
+                .service_config
+                .method_override = Some(true);
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let new_request = || {
+                Request::builder()
+                    .method(Method::POST)
+                    .uri("http://localhost:10104/orders")
+                    .header("X-HTTP-Method-Override", "put")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap()
+            };
+            let res = proxy(
+                HttpClients::new(),
+                new_request(),
+                String::from("10104-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("PUT"));
+
+            GLOBAL_CONFIG_MAPPING
+                .get_mut("10104-HTTP")
+                .unwrap()
+                .service_config
+                .method_override = None;
+            let res = proxy(
+                HttpClients::new(),
+                new_request(),
+                String::from("10104-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+            GLOBAL_CONFIG_MAPPING.remove("10104-HTTP");
+        });
+    }
+    #[test]
+    fn test_proxy_head_request_without_body() {
+        TOKIO_RUNTIME.block_on(async {
+            let mut route = create_test_route();
+            route.matcher.as_mut().unwrap().methods = Some(vec![String::from("GET")]);
+            route.route_cluster = LoadbalancerStrategy::Random(RandomRoute {
+                routes: vec![RandomBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("config"),
+                        ..Default::default()
+                    },
+                }],
+            });
+            let (sender, _) = tokio::sync::mpsc::channel(10);
+            GLOBAL_CONFIG_MAPPING.insert(
+                String::from("10105-HTTP"),
+                ApiServiceManager {
+                    sender,
+                    service_config: ServiceConfig {
+                        server_type: crate::vojo::app_config::ServiceType::Http,
+                        routes: vec![route],
+                        ..Default::default()
+                    },
+                },
+            );
+            let request = Request::builder()
+                .method(Method::HEAD)
+                .uri("http://localhost:10105/test_cert.pem")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let res = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10105-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let content_length: u64 = res
+                .headers()
+                .get(CONTENT_LENGTH)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!(content_length > 0);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert!(body.is_empty());
+            GLOBAL_CONFIG_MAPPING.remove("10105-HTTP");
+        });
+    }
+    #[test]
+    fn test_proxy_response_cache_hit_and_expire() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10106").await.unwrap();
+            let upstream_count = Arc::new(AtomicUsize::new(0));
+            let cloned_upstream_count = upstream_count.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = backend.accept().await.unwrap();
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await.unwrap();
+                    let count = cloned_upstream_count.fetch_add(1, Ordering::SeqCst) + 1;
+                    let body = format!("response-{}", count);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                }
+            });
+            let mut route = create_test_route();
+            route.cache = Some(CacheConfig {
+                ttl_seconds: 1,
+                max_entries: 16,
+                key_headers: vec![],
+                max_body_bytes: 1024,
+            });
+            insert_test_route("10107-HTTP", "http://127.0.0.1:10106", route);
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let mut results = vec![];
+            for wait_millis in [0, 0, 1100] {
+                sleep(Duration::from_millis(wait_millis)).await;
+                let request = Request::builder()
+                    .uri("http://localhost:10107/products?page=1")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let res = proxy(
+                    HttpClients::new(),
+                    request,
+                    String::from("10107-HTTP"),
+                    socket,
+                    CommonCheckRequest {},
+                )
+                .await
+                .unwrap();
+                assert_eq!(res.status(), StatusCode::OK);
+                let cache_status = res.headers().get(X_CACHE).unwrap().clone();
+                let body = res.into_body().collect().await.unwrap().to_bytes();
+                results.push((cache_status, body));
+            }
+            assert_eq!(
+                results[0],
+                (HeaderValue::from_static("MISS"), Bytes::from("response-1"))
+            );
+            assert_eq!(
+                results[1],
+                (HeaderValue::from_static("HIT"), Bytes::from("response-1"))
+            );
+            assert_eq!(
+                results[2],
+                (HeaderValue::from_static("MISS"), Bytes::from("response-2"))
+            );
+            assert_eq!(upstream_count.load(Ordering::SeqCst), 2);
+            GLOBAL_CONFIG_MAPPING.remove("10107-HTTP");
+        });
+    }
+    #[test]
+    fn test_proxy_single_flight_coalesce_identical_requests() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10133").await.unwrap();
+            let upstream_count = Arc::new(AtomicUsize::new(0));
+            let cloned_upstream_count = upstream_count.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = backend.accept().await.unwrap();
+                    let count = cloned_upstream_count.clone();
+                    tokio::spawn(async move {
+                        let mut buf = [0; 1024];
+                        let _ = stream.read(&mut buf).await.unwrap();
+                        let count = count.fetch_add(1, Ordering::SeqCst) + 1;
+                        sleep(Duration::from_millis(300)).await;
+                        let body = format!("response-{}", count);
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        );
+                        stream.write_all(response.as_bytes()).await.unwrap();
+                    });
+                }
+            });
+            let mut route = create_test_route();
+            route.single_flight = Some(SingleFlightConfig {
+                key_headers: vec![],
+                max_body_bytes: 1024,
+            });
+            insert_test_route("10134-HTTP", "http://127.0.0.1:10133", route);
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let requests = (0..10).map(|_| async {
+                let request = Request::builder()
+                    .uri("http://localhost:10134/products?page=1")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let res = proxy(
+                    HttpClients::new(),
+                    request,
+                    String::from("10134-HTTP"),
+                    socket,
+                    CommonCheckRequest {},
+                )
+                .await
+                .unwrap();
+                assert_eq!(res.status(), StatusCode::OK);
+                res.into_body().collect().await.unwrap().to_bytes()
+            });
+            for body in futures::future::join_all(requests).await {
+                assert_eq!(body, Bytes::from("response-1"));
+            }
+            assert_eq!(upstream_count.load(Ordering::SeqCst), 1);
+
+            let request = Request::builder()
+                .uri("http://localhost:10134/products?page=1")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let res = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10134-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("response-2"));
+
+            let requests = ["Bearer alice", "Bearer bob"].map(|authorization| async move {
+                let request = Request::builder()
+                    .uri("http://localhost:10134/products?page=1")
+                    .header(AUTHORIZATION, authorization)
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let res = proxy(
+                    HttpClients::new(),
+                    request,
+                    String::from("10134-HTTP"),
+                    socket,
+                    CommonCheckRequest {},
+                )
+                .await
+                .unwrap();
+                res.into_body().collect().await.unwrap().to_bytes()
+            });
+            let bodies = futures::future::join_all(requests).await;
+            assert_ne!(bodies[0], bodies[1]);
+            assert_eq!(upstream_count.load(Ordering::SeqCst), 4);
+            GLOBAL_CONFIG_MAPPING.remove("10134-HTTP");
+        });
+    }
+    #[test]
+    fn test_proxy_adapter_error_status_by_kind() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10135").await.unwrap();
+            tokio::spawn(async move {
+                let mut streams = vec![];
+                loop {
+                    let (stream, _) = backend.accept().await.unwrap();
+                    streams.push(stream);
+                }
+            });
+            let mut route = create_test_route();
+            route.timeout = Some(TimeoutConfig {
+                read_timeout: Some(200),
+                ..Default::default()
+            });
+            insert_test_route("10136-HTTP", "http://127.0.0.1:10135", route);
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            for (mapping_key, expected) in [
+                ("10136-HTTP", StatusCode::GATEWAY_TIMEOUT),
+                ("10137-HTTP", StatusCode::INTERNAL_SERVER_ERROR),
+            ] {
+                let request = Request::builder()
+                    .uri("http://localhost:10136/get")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let res = proxy_adapter_with_error(
+                    HttpClients::new(),
+                    request,
+                    String::from(mapping_key),
+                    socket,
+                )
+                .await
+                .unwrap();
+                assert_eq!(res.status(), expected, "{}", mapping_key);
+            }
+            GLOBAL_CONFIG_MAPPING.remove("10136-HTTP");
+        });
+    }
+    #[test]
+    fn test_proxy_gateway_timeout_with_response_headers() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10157").await.unwrap();
+            tokio::spawn(async move {
+                let mut streams = vec![];
+                loop {
+                    let (stream, _) = backend.accept().await.unwrap();
+                    streams.push(stream);
+                }
+            });
+            let mut route = create_test_route();
+            route.timeout = Some(TimeoutConfig {
+                read_timeout: Some(200),
+                ..Default::default()
+            });
+            route.response_headers = Some(ResponseHeaders {
+                add: HashMap::from([(
+                    String::from("Access-Control-Allow-Origin"),
+                    String::from("*"),
+                )]),
+                remove: vec![],
+            });
+            insert_test_route("10158-HTTP", "http://127.0.0.1:10157", route);
+            let request = Request::builder()
+                .uri("http://localhost:10158/get")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let res = proxy_adapter(
+                HttpClients::new(),
+                request,
+                String::from("10158-HTTP"),
+                socket,
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+            assert_eq!(
+                res.headers().get("Access-Control-Allow-Origin").unwrap(),
+                "*"
+            );
+            GLOBAL_CONFIG_MAPPING.remove("10158-HTTP");
+        });
+    }
+    #[test]
+    fn test_proxy_upstream_timeout_and_refused_status() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10139").await.unwrap();
+            tokio::spawn(async move {
+                let mut streams = vec![];
+                loop {
+                    let (stream, _) = backend.accept().await.unwrap();
+                    streams.push(stream);
+                }
+            });
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            for (endpoint, expected) in [
+                ("http://127.0.0.1:10139", StatusCode::GATEWAY_TIMEOUT),
+                ("http://127.0.0.1:10138", StatusCode::BAD_GATEWAY),
+            ] {
+                let mut route = create_test_route();
+                route.timeout = Some(TimeoutConfig {
+                    read_timeout: Some(200),
+                    ..Default::default()
+                });
+                insert_test_route("10140-HTTP", endpoint, route);
+                let request = Request::builder()
+                    .uri("http://localhost:10140/get")
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let res = proxy_adapter_with_error(
+                    HttpClients::new(),
+                    request,
+                    String::from("10140-HTTP"),
+                    socket,
+                )
+                .await
+                .unwrap();
+                assert_eq!(res.status(), expected, "{}", endpoint);
+            }
+            GLOBAL_CONFIG_MAPPING.remove("10140-HTTP");
+        });
+    }
+    #[test]
+    fn test_io_error_kind() {
+        let timed_out = std::io::Error::new(std::io::ErrorKind::TimedOut, "connect timeout");
+        assert_eq!(
+            io_error_kind(&timed_out),
+            Some(std::io::ErrorKind::TimedOut)
+        );
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        assert_eq!(
+            io_error_kind(&refused),
+            Some(std::io::ErrorKind::ConnectionRefused)
+        );
+    }
+    #[test]
+    fn test_proxy_integrated_acme_challenge() {
+        TOKIO_RUNTIME.block_on(async {
+            let backend = TcpListener::bind("127.0.0.1:10088").await.unwrap();
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = backend.accept().await.unwrap();
+                    let mut buf = [0; 1024];
+                    let _ = stream.read(&mut buf).await.unwrap();
+                    stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 7\r\n\r\nbackend")
+                        .await
+                        .unwrap();
+                }
+            });
+            insert_test_route("10089-HTTP", "http://127.0.0.1:10088", create_test_route());
+            GLOBAL_ACME_CHALLENGE_TOKENS.insert(
+                String::from("integrated-token"),
+                String::from("integrated-token.proof"),
+            );
+            for (path, expected) in [
+                (
+                    "/.well-known/acme-challenge/integrated-token",
+                    "integrated-token.proof",
+                ),
+                ("/.well-known/acme-challenge/unknown-token", "backend"),
+                ("/orders", "backend"),
+            ] {
+                let request = Request::builder()
+                    .uri(format!("http://localhost:10089{}", path))
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+                let response = proxy(
+                    HttpClients::new(),
+                    request,
+                    String::from("10089-HTTP"),
+                    socket,
+                    CommonCheckRequest {},
+                )
+                .await
+                .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = response.into_body().collect().await.unwrap().to_bytes();
+                assert_eq!(body, Bytes::from(expected), "{}", path);
+            }
+            GLOBAL_ACME_CHALLENGE_TOKENS.remove("integrated-token");
+        });
+    }
+    #[test]
+    fn test_proxy_combined_limit_reason() {
+        TOKIO_RUNTIME.block_on(async {
+            let concurrency_limit = CombinedLimit {
+                max_concurrent_requests: Some(1),
+                ratelimit: None,
+                response: None,
+                in_flight: Arc::new(AtomicUsize::new(1)),
+            };
+            let rate_limit = CombinedLimit {
+                max_concurrent_requests: Some(1),
+                ratelimit: Some(Box::new(FixedWindowRateLimit {
+                    rate_per_unit: 0,
+                    unit: TimeUnit::Minute,
+                    limit_location: LimitLocation::IP(IPBasedRatelimit {
+                        value: String::from("127.0.0.1"),
+                    }),
+                    response: None,
+                    count_map: Default::default(),
+                    lock: Default::default(),
+                })),
+                response: None,
+                in_flight: Arc::new(AtomicUsize::new(0)),
+            };
+            for (port, combined_limit, reason) in [
+                (10061, concurrency_limit, "concurrency"),
+                (10062, rate_limit, "rate"),
+            ] {
+                let mut route = create_test_route();
+                route.combined_limit = Some(combined_limit);
+                let mapping_key = format!("{}-HTTP", port);
+                insert_test_route(&mapping_key, "http://127.0.0.1:10063", route);
+                let request = Request::builder()
+                    .uri(format!("http://localhost:{}/get", port))
+                    .body(Full::new(Bytes::new()).boxed())
+                    .unwrap();
+                let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+                let res = proxy(
+                    HttpClients::new(),
+                    request,
+                    mapping_key,
+                    socket,
+                    CommonCheckRequest {},
+                )
+                .await
+                .unwrap();
+                assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+                assert_eq!(res.headers().get(X_RATELIMIT_REASON).unwrap(), reason);
+            }
+        });
+    }
+    #[test]
+    fn test_proxy_route_ratelimit_custom_response() {
+        TOKIO_RUNTIME.block_on(async {
+            let mut route = create_test_route();
+            route.ratelimit = Some(Box::new(FixedWindowRateLimit {
+                rate_per_unit: 0,
+                unit: TimeUnit::Minute,
+                limit_location: LimitLocation::IP(IPBasedRatelimit {
+                    value: String::from("127.0.0.1"),
+                }),
+                response: Some(RatelimitResponse {
+                    status_code: Some(429),
+                    body: Some(String::from("Too many requests!")),
+                    content_type: Some(String::from("text/plain")),
+                    omit_headers: false,
+                }),
+                count_map: Default::default(),
+                lock: Default::default(),
+            }));
+            insert_test_route("10155-HTTP", "http://127.0.0.1:10156", route);
+            let request = Request::builder()
+                .uri("http://localhost:10155/get")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let res = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10155-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "text/plain");
+            assert_eq!(res.headers().get(X_RATELIMIT_REASON).unwrap(), "rate");
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("Too many requests!"));
+            GLOBAL_CONFIG_MAPPING.remove("10155-HTTP");
+        });
+    }
+    #[test]
+    fn test_proxy_combined_limit_custom_response() {
+        TOKIO_RUNTIME.block_on(async {
+            let mut route = create_test_route();
+            route.combined_limit = Some(CombinedLimit {
+                max_concurrent_requests: None,
+                ratelimit: Some(Box::new(FixedWindowRateLimit {
+                    rate_per_unit: 0,
+                    unit: TimeUnit::Minute,
+                    limit_location: LimitLocation::IP(IPBasedRatelimit {
+                        value: String::from("127.0.0.1"),
+                    }),
+                    response: None,
+                    count_map: Default::default(),
+                    lock: Default::default(),
+                })),
+                response: Some(RatelimitResponse {
+                    status_code: Some(503),
+                    body: Some(String::from("Slow down!")),
+                    content_type: Some(String::from("text/plain")),
+                    omit_headers: true,
+                }),
+                in_flight: Arc::new(AtomicUsize::new(0)),
+            });
+            insert_test_route("10142-HTTP", "http://127.0.0.1:10143", route);
+            let request = Request::builder()
+                .uri("http://localhost:10142/get")
+                .body(Full::new(Bytes::new()).boxed())
+                .unwrap();
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let res = proxy(
+                HttpClients::new(),
+                request,
+                String::from("10142-HTTP"),
+                socket,
+                CommonCheckRequest {},
+            )
+            .await
+            .unwrap();
+            assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "text/plain");
+            assert!(res.headers().get(X_RATELIMIT_REASON).is_none());
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, Bytes::from("Slow down!"));
+            GLOBAL_CONFIG_MAPPING.remove("10142-HTTP");
         });
     }
 }
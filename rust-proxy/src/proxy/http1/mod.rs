@@ -2,4 +2,8 @@ pub mod http_client;
 
 pub mod http_proxy;
 
+pub mod redirect_proxy;
+
+pub mod static_file;
+
 pub mod websocket_proxy;
@@ -0,0 +1,259 @@
+use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
+use crate::configuration_service::readiness::mark_listener_bound;
+use crate::proxy::listener::{apply_stream_options, listen_addrs, MultiListener};
+use crate::vojo::app_config::RedirectConfig;
+use crate::vojo::app_error::AppError;
+use crate::vojo::lets_encrypt::get_challenge_proof;
+use bytes::Bytes;
+use http::uri::Authority;
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::header::{HOST, LOCATION};
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+
+const DEFAULT_HTTPS_PORT: u16 = 443;
+pub struct RedirectProxy {
+    pub port: i32,
+    pub mapping_key: String,
+    pub channel: mpsc::Receiver<()>,
+}
+impl RedirectProxy {
+    pub async fn start_proxy(&mut self) -> Result<(), AppError> {
+        let mapping_key = self.mapping_key.clone();
+        let (socket_options, bind_addresses) = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key)
+            .map(|item| {
+                (
+                    item.service_config
+                        .socket_options
+                        .clone()
+                        .unwrap_or_default(),
+                    item.service_config.bind_addresses.clone(),
+                )
+            })
+            .unwrap_or_default();
+        let addrs = listen_addrs(bind_addresses.as_deref(), self.port as u16);
+        let mut listener = MultiListener::bind(&addrs, &socket_options)?;
+        mark_listener_bound(&mapping_key);
+        info!("Listening on http://{:?} and redirecting to https", addrs);
+        let reveiver = &mut self.channel;
+        loop {
+            tokio::select! {
+                Some((stream, _)) = listener.accept() => {
+                    apply_stream_options(&stream, &socket_options);
+                    let mapping_key = mapping_key.clone();
+                    tokio::spawn(async move {
+                        let result = auto::Builder::new(TokioExecutor::new())
+                            .serve_connection(
+                                TokioIo::new(stream),
+                                service_fn(move |req: Request<Incoming>| {
+                                    let redirect = GLOBAL_CONFIG_MAPPING
+                                        .get(&mapping_key)
+                                        .and_then(|item| item.service_config.redirect.clone())
+                                        .unwrap_or_default();
+                                    async move {
+                                        Ok::<_, Infallible>(redirect_response(&redirect, &req))
+                                    }
+                                }),
+                            )
+                            .await;
+                        if let Err(err) = result {
+                            error!("Error serving connection: {:?}", err);
+                        }
+                    });
+                },
+                _ = reveiver.recv() => {
+                    info!("redirect server stoped");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+/**
+ *The acme challenge is answered instead of redirected,so the certificate could be issued on the same port.
+ */
+pub fn redirect_response<B>(redirect: &RedirectConfig, req: &Request<B>) -> Response<Full<Bytes>> {
+    if let Some(proof) = get_challenge_proof(req.uri().path()) {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .body(Full::new(Bytes::from(proof)))
+            .unwrap();
+    }
+    match https_location(redirect, req) {
+        Some(location) => Response::builder()
+            .status(redirect.status_code())
+            .header(LOCATION, location)
+            .body(Full::new(Bytes::new()))
+            .unwrap(),
+        None => Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(Full::new(Bytes::from(
+                "The host of the request is missing!",
+            )))
+            .unwrap(),
+    }
+}
+/**
+ *The port of the host is replaced by the https port,the path and the query are kept.
+ */
+fn https_location<B>(redirect: &RedirectConfig, req: &Request<B>) -> Option<String> {
+    let authority = req
+        .headers()
+        .get(HOST)
+        .and_then(|item| item.to_str().ok())
+        .and_then(|item| item.parse::<Authority>().ok())
+        .or(req.uri().authority().cloned())?;
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|item| item.as_str())
+        .unwrap_or("/");
+    match redirect.https_port {
+        Some(port) if port != DEFAULT_HTTPS_PORT => Some(format!(
+            "https://{}:{}{}",
+            authority.host(),
+            port,
+            path_and_query
+        )),
+        _ => Some(format!("https://{}{}", authority.host(), path_and_query)),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::api_service_manager::ApiServiceManager;
+    use crate::vojo::app_config::{ServiceConfig, ServiceType};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio::time::sleep;
+
+    fn create_request(host: Option<&str>, uri: &str) -> Request<()> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(host) = host {
+            builder = builder.header(HOST, host);
+        }
+        builder.body(()).unwrap()
+    }
+    #[test]
+    fn test_redirect_response_location() {
+        let permanent = RedirectConfig::default();
+        let response = redirect_response(
+            &permanent,
+            &create_request(Some("example.com:8080"), "/a/b?c=1"),
+        );
+        assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(
+            response.headers().get(LOCATION).unwrap(),
+            "https://example.com/a/b?c=1"
+        );
+
+        let preserve_method = RedirectConfig {
+            preserve_method: true,
+            https_port: Some(8443),
+            ..Default::default()
+        };
+        let response = redirect_response(&preserve_method, &create_request(Some("[::1]:80"), "/"));
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers().get(LOCATION).unwrap(),
+            "https://[::1]:8443/"
+        );
+
+        let response = redirect_response(
+            &permanent,
+            &create_request(None, "http://example.org/index.html"),
+        );
+        assert_eq!(
+            response.headers().get(LOCATION).unwrap(),
+            "https://example.org/index.html"
+        );
+        let response = redirect_response(&permanent, &create_request(None, "/"));
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    #[test]
+    fn test_redirect_status_code() {
+        for (permanent, preserve_method, expected) in [
+            (true, false, StatusCode::MOVED_PERMANENTLY),
+            (true, true, StatusCode::PERMANENT_REDIRECT),
+            (false, false, StatusCode::FOUND),
+            (false, true, StatusCode::TEMPORARY_REDIRECT),
+        ] {
+            let redirect = RedirectConfig {
+                permanent,
+                preserve_method,
+                https_port: None,
+            };
+            assert_eq!(redirect.status_code(), expected);
+        }
+    }
+    #[tokio::test]
+    async fn test_redirect_proxy_listener() {
+        let mapping_key = String::from("10141-Redirect");
+        let (sender, receiver) = mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            mapping_key.clone(),
+            ApiServiceManager {
+                sender: sender.clone(),
+                service_config: ServiceConfig {
+                    server_type: ServiceType::Redirect,
+                    redirect: Some(RedirectConfig::default()),
+                    ..Default::default()
+                },
+            },
+        );
+        let mut redirect_proxy = RedirectProxy {
+            port: 10141,
+            mapping_key: mapping_key.clone(),
+            channel: receiver,
+        };
+        let task = tokio::spawn(async move { redirect_proxy.start_proxy().await });
+        sleep(Duration::from_millis(100)).await;
+        for (redirect, status_line, location) in [
+            (
+                RedirectConfig::default(),
+                "HTTP/1.1 301 Moved Permanently",
+                "https://example.com/login?next=%2F",
+            ),
+            (
+                RedirectConfig {
+                    preserve_method: true,
+                    https_port: Some(8443),
+                    ..Default::default()
+                },
+                "HTTP/1.1 308 Permanent Redirect",
+                "https://example.com:8443/login?next=%2F",
+            ),
+        ] {
+            GLOBAL_CONFIG_MAPPING
+                .get_mut(&mapping_key)
+                .unwrap()
+                .service_config
+                .redirect = Some(redirect);
+            let mut stream = TcpStream::connect("127.0.0.1:10141").await.unwrap();
+            stream
+                .write_all(
+                    b"POST /login?next=%2F HTTP/1.1\r\nHost: example.com:10141\r\nConnection: close\r\ncontent-length: 0\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with(status_line), "{}", response);
+            assert!(
+                response.contains(&format!("location: {}\r\n", location)),
+                "{}",
+                response
+            );
+        }
+        sender.send(()).await.unwrap();
+        assert!(task.await.unwrap().is_ok());
+        GLOBAL_CONFIG_MAPPING.remove(&mapping_key);
+    }
+}
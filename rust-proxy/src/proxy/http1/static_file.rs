@@ -0,0 +1,183 @@
+use crate::constants::common_constants::DEFAULT_INDEX_FILE;
+use crate::vojo::app_error::AppError;
+use http::header::ACCEPT_ENCODING;
+use http::HeaderMap;
+use hyper_staticfile::AcceptEncoding;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+pub fn decode_percents(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|item| std::str::from_utf8(item).ok())
+            .and_then(|item| u8::from_str_radix(item, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(item)) => {
+                decoded.push(item);
+                index += 3;
+            }
+            (item, _) => {
+                decoded.push(item);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+/**
+ *Return the directory under the root for the request path ending with a slash, the parent components are dropped.
+ */
+pub fn resolve_directory(root: &str, request_path: &str) -> Option<PathBuf> {
+    if !request_path.ends_with('/') {
+        return None;
+    }
+    let mut directory = PathBuf::from(root);
+    for component in Path::new(&decode_percents(request_path)).components() {
+        if let Component::Normal(item) = component {
+            directory.push(item);
+        }
+    }
+    Some(directory)
+}
+
+/**
+ *Return the first configured index file which exists in the directory.
+ */
+pub async fn find_index_file(directory: &Path, index: Option<&Vec<String>>) -> Option<String> {
+    let default_index = vec![String::from(DEFAULT_INDEX_FILE)];
+    for item in index.unwrap_or(&default_index) {
+        let is_file = tokio::fs::metadata(directory.join(item))
+            .await
+            .is_ok_and(|metadata| metadata.is_file());
+        if is_file {
+            return Some(item.clone());
+        }
+    }
+    None
+}
+/**
+ *The encoding with q=0 is refused, which is ignored by hyper_staticfile.
+ */
+pub fn accepted_encodings(headers: &HeaderMap) -> AcceptEncoding {
+    let mut accept_encoding = AcceptEncoding::none();
+    for value in headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|item| item.to_str().ok())
+    {
+        for item in value.split(',') {
+            let mut params = item.split(';').map(|param| param.trim());
+            let encoding = params.next().unwrap_or_default().to_ascii_lowercase();
+            let is_refused = params.any(|param| {
+                param
+                    .strip_prefix("q=")
+                    .and_then(|quality| quality.parse::<f32>().ok())
+                    .is_some_and(|quality| quality <= 0.0)
+            });
+            match encoding.as_str() {
+                "gzip" => accept_encoding.gzip = !is_refused,
+                "br" => accept_encoding.br = !is_refused,
+                "*" if !is_refused => {
+                    accept_encoding.gzip = true;
+                    accept_encoding.br = true;
+                }
+                _ => {}
+            }
+        }
+    }
+    accept_encoding
+}
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+pub fn encode_path_segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|item| match item {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (item as char).to_string()
+            }
+            _ => format!("%{:02X}", item),
+        })
+        .collect()
+}
+/**
+ *The directories are listed before the files, and both of them are sorted by name.
+ */
+pub async fn render_directory_listing(
+    directory: &Path,
+    request_path: &str,
+) -> Result<String, AppError> {
+    let mut read_dir = tokio::fs::read_dir(directory)
+        .await
+        .map_err(|e| AppError::from(e.to_string()))?;
+    let mut entries = vec![];
+    while let Some(entry) = read_dir
+        .next_entry()
+        .await
+        .map_err(|e| AppError::from(e.to_string()))?
+    {
+        let is_dir = entry.file_type().await.is_ok_and(|item| item.is_dir());
+        entries.push((!is_dir, entry.file_name().to_string_lossy().to_string()));
+    }
+    entries.sort();
+    let title = escape_html(request_path);
+    let mut body = format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>Index of {0}</title></head>\n<body>\n<h1>Index of {0}</h1>\n<ul>\n",
+        title
+    );
+    if request_path != "/" {
+        body.push_str("<li><a href=\"../\">../</a></li>\n");
+    }
+    for (is_file, name) in entries {
+        let suffix = if is_file { "" } else { "/" };
+        body.push_str(&format!(
+            "<li><a href=\"{}{}\">{}{}</a></li>\n",
+            encode_path_segment(&name),
+            suffix,
+            escape_html(&name),
+            suffix
+        ));
+    }
+    body.push_str("</ul>\n</body>\n</html>\n");
+    Ok(body)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_resolve_directory_and_escape_name() {
+        assert_eq!(
+            escape_html("<a href='x'>&</a>"),
+            "&lt;a href=&#39;x&#39;&gt;&amp;&lt;/a&gt;"
+        );
+        assert_eq!(encode_path_segment("a b#c.txt"), "a%20b%23c.txt");
+        assert_eq!(
+            resolve_directory("static", "/a/../%2e%2e/b%20c/"),
+            Some(PathBuf::from("static/a/b c"))
+        );
+        assert_eq!(resolve_directory("static", "/a/b.txt"), None);
+    }
+    #[test]
+    fn test_accepted_encodings() {
+        let mut headers = HeaderMap::new();
+        let accept_encoding = accepted_encodings(&headers);
+        assert!(!accept_encoding.gzip && !accept_encoding.br);
+        headers.insert(ACCEPT_ENCODING, "gzip, deflate, br;q=0".parse().unwrap());
+        let accept_encoding = accepted_encodings(&headers);
+        assert!(accept_encoding.gzip && !accept_encoding.br);
+        headers.insert(ACCEPT_ENCODING, "GZIP;q=0, br;q=0.5".parse().unwrap());
+        let accept_encoding = accepted_encodings(&headers);
+        assert!(!accept_encoding.gzip && accept_encoding.br);
+    }
+}
@@ -1,57 +1,316 @@
 use hyper::body::Incoming;
 use tokio::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
 
 use crate::constants::common_constants::DEFAULT_HTTP_TIMEOUT;
 use crate::proxy::http1::http_client::HttpClients;
-use crate::vojo::app_error::AppError;
+use crate::vojo::app_error::{AppError, AppErrorKind};
 use base64::{engine::general_purpose, Engine as _};
 use bytes::Bytes;
 use http_body_util::{combinators::BoxBody, BodyExt, Full};
-use hyper::header::{HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE};
-use hyper::{Request, Response, StatusCode};
+use hyper::header::{
+    HeaderName, HeaderValue, CONNECTION, HOST, PROXY_AUTHORIZATION, SEC_WEBSOCKET_ACCEPT,
+    SEC_WEBSOCKET_EXTENSIONS, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_PROTOCOL, TE, TRAILER,
+    TRANSFER_ENCODING, UPGRADE,
+};
+use hyper::{HeaderMap, Method, Request, Response, StatusCode, Uri};
 use hyper_util::rt::TokioIo;
 use sha1::{Digest, Sha1};
 use std::convert::Infallible;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::io::AsyncWriteExt;
+use tokio::time::sleep_until;
 
 use crate::proxy::proxy_trait::CheckResult;
+use crate::vojo::app_config::WebsocketConfig;
+
+static COPY_BUFFER_SIZE: usize = 8192;
+static HOP_BY_HOP_HEADERS: [HeaderName; 7] = [
+    CONNECTION,
+    UPGRADE,
+    TE,
+    TRAILER,
+    TRANSFER_ENCODING,
+    PROXY_AUTHORIZATION,
+    HeaderName::from_static("keep-alive"),
+];
+fn offered_subprotocols(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(SEC_WEBSOCKET_PROTOCOL)
+        .iter()
+        .filter_map(|item| item.to_str().ok())
+        .flat_map(|item| item.split(','))
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+/**
+ *The hop-by-hop headers and the ones listed in the Connection header are not forwarded, only the subprotocols allowed by the config are offered to the upstream.
+ */
+fn create_upstream_headers(
+    headers: &HeaderMap,
+    upgrade_value: &HeaderValue,
+    allowed_subprotocols: Option<&Vec<String>>,
+) -> Result<(HeaderMap, Vec<String>), AppError> {
+    let connection_tokens: Vec<String> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|item| item.to_str().ok())
+        .flat_map(|item| item.split(','))
+        .map(|item| item.trim().to_ascii_lowercase())
+        .collect();
+    let mut upstream_headers = HeaderMap::new();
+    for (key, value) in headers.iter() {
+        if HOP_BY_HOP_HEADERS.contains(key)
+            || key == SEC_WEBSOCKET_PROTOCOL
+            || connection_tokens.iter().any(|item| item == key.as_str())
+        {
+            continue;
+        }
+        upstream_headers.append(key, value.clone());
+    }
+    upstream_headers.insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+    upstream_headers.insert(UPGRADE, upgrade_value.clone());
+
+    let subprotocols: Vec<String> = offered_subprotocols(headers)
+        .into_iter()
+        .filter(|item| allowed_subprotocols.is_none_or(|allowed| allowed.contains(item)))
+        .collect();
+    if !subprotocols.is_empty() {
+        upstream_headers.insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_str(&subprotocols.join(", "))
+                .map_err(|e| AppError::from(e.to_string()))?,
+        );
+    }
+    Ok((upstream_headers, subprotocols))
+}
+/**
+ *The subprotocol selected by the upstream must be one of the offered ones.
+ */
+fn selected_subprotocol(
+    headers: &HeaderMap,
+    subprotocols: &[String],
+) -> Result<Option<HeaderValue>, AppError> {
+    let Some(value) = headers.get(SEC_WEBSOCKET_PROTOCOL) else {
+        return Ok(None);
+    };
+    let selected = value
+        .to_str()
+        .map_err(|e| AppError::from(e.to_string()))?
+        .trim();
+    if !subprotocols.iter().any(|item| item == selected) {
+        return Err(AppError::from(format!(
+            "The upstream selected the subprotocol {} which is not offered!",
+            selected
+        )));
+    }
+    Ok(Some(value.clone()))
+}
+/**
+ *Track the websocket frame boundaries of one direction, the payloads of the fragmented message are summed up.
+ */
+struct MessageSizeLimit {
+    max_message_size: u64,
+    header: Vec<u8>,
+    remaining_payload: u64,
+    message_size: u64,
+}
+impl MessageSizeLimit {
+    fn new(max_message_size: u64) -> Self {
+        MessageSizeLimit {
+            max_message_size,
+            header: vec![],
+            remaining_payload: 0,
+            message_size: 0,
+        }
+    }
+    fn check(&mut self, data: &[u8]) -> Result<(), AppError> {
+        let mut index = 0;
+        while index < data.len() {
+            if self.remaining_payload > 0 {
+                let len = self.remaining_payload.min((data.len() - index) as u64);
+                self.remaining_payload -= len;
+                index += len as usize;
+                continue;
+            }
+            self.header.push(data[index]);
+            index += 1;
+            self.check_header()?;
+        }
+        Ok(())
+    }
+    fn check_header(&mut self) -> Result<(), AppError> {
+        if self.header.len() < 2 {
+            return Ok(());
+        }
+        let is_masked = self.header[1] & 0x80 != 0;
+        let extended_len = match self.header[1] & 0x7f {
+            126 => 2,
+            127 => 8,
+            _ => 0,
+        };
+        let header_len = 2 + extended_len + if is_masked { 4 } else { 0 };
+        if self.header.len() < header_len {
+            return Ok(());
+        }
+        let payload_len = match extended_len {
+            0 => (self.header[1] & 0x7f) as u64,
+            _ => self.header[2..2 + extended_len]
+                .iter()
+                .fold(0, |acc, item| (acc << 8) | *item as u64),
+        };
+        let is_fin = self.header[0] & 0x80 != 0;
+        let is_control = self.header[0] & 0x08 != 0;
+        self.header.clear();
+        self.remaining_payload = payload_len;
+        let message_size = if is_control {
+            payload_len
+        } else {
+            self.message_size.saturating_add(payload_len)
+        };
+        if message_size > self.max_message_size {
+            return Err(AppError::from(format!(
+                "The websocket message size {} exceeds the limit {}!",
+                message_size, self.max_message_size
+            )));
+        }
+        if !is_control {
+            self.message_size = if is_fin { 0 } else { message_size };
+        }
+        Ok(())
+    }
+}
+async fn copy_with_limit<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    last_active: &Mutex<Instant>,
+    max_message_size: Option<u64>,
+) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut message_size_limit = max_message_size.map(MessageSizeLimit::new);
+    let mut buf = vec![0; COPY_BUFFER_SIZE];
+    loop {
+        let len = reader.read(&mut buf).await?;
+        if len == 0 {
+            return writer.shutdown().await;
+        }
+        if let Some(limit) = message_size_limit.as_mut() {
+            limit
+                .check(&buf[..len])
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.message))?;
+        }
+        writer.write_all(&buf[..len]).await?;
+        *last_active.lock().unwrap() = Instant::now();
+    }
+}
+async fn wait_idle_timeout(last_active: &Mutex<Instant>, idle_timeout: Option<Duration>) {
+    let Some(idle_timeout) = idle_timeout else {
+        return std::future::pending().await;
+    };
+    loop {
+        let deadline = *last_active.lock().unwrap() + idle_timeout;
+        sleep_until(deadline.into()).await;
+        if *last_active.lock().unwrap() + idle_timeout <= Instant::now() {
+            return;
+        }
+    }
+}
+/**
+ *The data in either direction keeps the connection alive, both sides are shut down once it is idle or the message is too large.
+ */
+async fn relay_websocket<I, O>(
+    inbound: I,
+    outbound: O,
+    websocket_config: Option<WebsocketConfig>,
+) -> Result<(), AppError>
+where
+    I: AsyncRead + AsyncWrite,
+    O: AsyncRead + AsyncWrite,
+{
+    let idle_timeout = websocket_config
+        .as_ref()
+        .map(|item| Duration::from_secs(item.idle_timeout));
+    let max_message_size = websocket_config.and_then(|item| item.max_message_size);
+    let last_active = Mutex::new(Instant::now());
+
+    let (mut ri, mut wi) = tokio::io::split(inbound);
+    let (mut ro, mut wo) = tokio::io::split(outbound);
+    let client_to_server = copy_with_limit(&mut ri, &mut wo, &last_active, max_message_size);
+    let server_to_client = copy_with_limit(&mut ro, &mut wi, &last_active, max_message_size);
+    let result = tokio::select! {
+        result = async { tokio::try_join!(client_to_server, server_to_client) } => result.map(|_| ()),
+        _ = wait_idle_timeout(&last_active, idle_timeout) => {
+            debug!("The websocket connection is idle timeout.");
+            Ok(())
+        }
+    };
+    let _ = wi.shutdown().await;
+    let _ = wo.shutdown().await;
+    result.map_err(|e| AppError::from(format!("Copy stream error,the error is {}", e)))
+}
 async fn server_upgraded_io(
     inbound_req: Request<BoxBody<Bytes, Infallible>>,
     outbound_res: Response<Incoming>,
+    websocket_config: Option<WebsocketConfig>,
 ) -> Result<(), AppError> {
     let upgraded_inbound = hyper::upgrade::on(inbound_req)
         .await
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
     let inbound = TokioIo::new(upgraded_inbound);
 
     let upgraded_outbound = hyper::upgrade::on(outbound_res)
         .await
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
     let outbound = TokioIo::new(upgraded_outbound);
 
-    let (mut ri, mut wi) = tokio::io::split(inbound);
-    let (mut ro, mut wo) = tokio::io::split(outbound);
-    let client_to_server = async {
-        io::copy(&mut ri, &mut wo).await?;
-        wo.shutdown().await
-    };
+    relay_websocket(inbound, outbound, websocket_config).await
+}
+async fn request_upgrade(
+    http_client: &HttpClients,
+    method: &Method,
+    request_uri: &str,
+    upstream_headers: HeaderMap,
+    preserve_header_case: bool,
+) -> Result<Response<Incoming>, AppError> {
+    let mut new_request = Request::builder()
+        .method(method.clone())
+        .uri(request_uri)
+        .body(Full::new(Bytes::new()).boxed())
+        .map_err(|e| AppError::from(e.to_string()))?;
+    *new_request.headers_mut() = upstream_headers;
+    debug!("The new request is:{:?}", new_request);
 
-    let server_to_client = async {
-        io::copy(&mut ro, &mut wi).await?;
-        wi.shutdown().await
+    let request_future = if request_uri.contains("https") {
+        http_client.request_https(new_request, DEFAULT_HTTP_TIMEOUT, preserve_header_case)
+    } else {
+        http_client.request_http(new_request, DEFAULT_HTTP_TIMEOUT, preserve_header_case)
     };
-
-    let result = tokio::try_join!(client_to_server, server_to_client);
-
-    if result.is_err() {
-        error!("Copy stream error!");
+    let outbound_res = match request_future.await {
+        Ok(response) => response.map_err(|e| AppError::from(e.to_string())),
+        Err(_) => Err(AppError::new(
+            AppErrorKind::Timeout,
+            format!("Request time out,the uri is {}", request_uri),
+        )),
+    }?;
+    if outbound_res.status() != StatusCode::SWITCHING_PROTOCOLS {
+        return Err(AppError::from(format!(
+            "The backend {} rejected the websocket upgrade with {}",
+            request_uri,
+            outbound_res.status()
+        )));
     }
-
-    Ok(())
+    Ok(outbound_res)
 }
 pub async fn server_upgrade(
     req: Request<BoxBody<Bytes, Infallible>>,
     check_result: Option<CheckResult>,
+    failover_uris: Vec<Uri>,
     http_client: HttpClients,
 ) -> Result<Response<BoxBody<Bytes, Infallible>>, AppError> {
     debug!("The source request:{:?}.", req);
@@ -65,41 +324,64 @@ pub async fn server_upgrade(
     let upgrade_value = header_map.get(UPGRADE).unwrap();
     let sec_websocke_key = header_map
         .get(SEC_WEBSOCKET_KEY)
-        .ok_or(AppError(String::from("Can not get the websocket key!")))?
+        .ok_or(AppError::from(String::from(
+            "Can not get the websocket key!",
+        )))?
         .to_str()
-        .map_err(|e| AppError(e.to_string()))?
+        .map_err(|e| AppError::from(e.to_string()))?
         .to_string();
 
-    let request_path = check_result.unwrap().request_path;
-    let mut new_request = Request::builder()
-        .method(req.method().clone())
-        .uri(request_path.clone())
-        .body(Full::new(Bytes::new()).boxed())
-        .map_err(|e| AppError(e.to_string()))?;
-
-    let new_header = new_request.headers_mut();
-    header_map.iter().for_each(|(key, value)| {
-        new_header.insert(key, value.clone());
-    });
-    debug!("The new request is:{:?}", new_request);
-
-    let request_future = if new_request.uri().to_string().contains("https") {
-        http_client.request_https(new_request, DEFAULT_HTTP_TIMEOUT)
-    } else {
-        http_client.request_http(new_request, DEFAULT_HTTP_TIMEOUT)
+    let check_request = check_result.unwrap();
+    let request_path = check_request.request_path;
+    let preserve_header_case = check_request.base_route.preserve_header_case;
+    let websocket_config = check_request.route.websocket;
+    let (mut upstream_headers, subprotocols) = create_upstream_headers(
+        &header_map,
+        upgrade_value,
+        websocket_config
+            .as_ref()
+            .and_then(|item| item.subprotocols.as_ref()),
+    )?;
+    if let Some(host_header) = check_request.base_route.host_header.as_ref() {
+        upstream_headers.insert(
+            HOST,
+            HeaderValue::from_str(host_header).map_err(|e| AppError::from(e.to_string()))?,
+        );
+    }
+    let http_client = match check_request.base_route.sni.as_ref() {
+        Some(sni) => http_client.with_server_name(sni),
+        None => http_client,
     };
-    let outbound_res = match request_future.await {
-        Ok(response) => response.map_err(|e| AppError(e.to_string())),
-        Err(_) => Err(AppError(format!(
-            "Request time out,the uri is {}",
-            request_path
-        ))),
-    }?;
-    if outbound_res.status() != StatusCode::SWITCHING_PROTOCOLS {
-        return Err(AppError(String::from("Request error!")));
+    let mut outbound_res = None;
+    let request_uris =
+        std::iter::once(request_path).chain(failover_uris.iter().map(|item| item.to_string()));
+    for request_uri in request_uris {
+        match request_upgrade(
+            &http_client,
+            req.method(),
+            &request_uri,
+            upstream_headers.clone(),
+            preserve_header_case,
+        )
+        .await
+        {
+            Ok(response) => {
+                outbound_res = Some(response);
+                break;
+            }
+            Err(err) => error!("{},try the next backend.", err),
+        }
     }
+    let outbound_res = outbound_res.ok_or(AppError::from(String::from(
+        "All the backends rejected the websocket upgrade!",
+    )))?;
+    let subprotocol = selected_subprotocol(outbound_res.headers(), &subprotocols)?;
+    let extensions = outbound_res
+        .headers()
+        .get(SEC_WEBSOCKET_EXTENSIONS)
+        .cloned();
     tokio::task::spawn(async move {
-        let res = server_upgraded_io(req, outbound_res).await;
+        let res = server_upgraded_io(req, outbound_res, websocket_config).await;
         if let Err(err) = res {
             error!("{}", err);
         }
@@ -113,11 +395,312 @@ pub async fn server_upgrade(
     res.headers_mut().insert(UPGRADE, upgrade_value.clone());
     res.headers_mut().insert(
         SEC_WEBSOCKET_ACCEPT,
-        HeaderValue::from_str(encoded.as_str()).map_err(|e| AppError(e.to_string()))?,
+        HeaderValue::from_str(encoded.as_str()).map_err(|e| AppError::from(e.to_string()))?,
     );
     res.headers_mut().insert(
         CONNECTION,
-        HeaderValue::from_str("Upgrade").map_err(|e| AppError(e.to_string()))?,
+        HeaderValue::from_str("Upgrade").map_err(|e| AppError::from(e.to_string()))?,
     );
+    if let Some(subprotocol) = subprotocol {
+        res.headers_mut()
+            .insert(SEC_WEBSOCKET_PROTOCOL, subprotocol);
+    }
+    if let Some(extensions) = extensions {
+        res.headers_mut()
+            .insert(SEC_WEBSOCKET_EXTENSIONS, extensions);
+    }
     Ok(res)
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::app_config::LivenessStatus;
+    use crate::vojo::app_config::Route;
+    use crate::vojo::route::{BaseRoute, LoadbalancerStrategy, RandomBaseRoute, RandomRoute};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio::sync::RwLock;
+    fn create_check_result(
+        endpoint: &str,
+        websocket_config: Option<WebsocketConfig>,
+    ) -> CheckResult {
+        let base_route = BaseRoute {
+            endpoint: String::from(endpoint),
+            ..Default::default()
+        };
+        CheckResult {
+            request_path: format!("{}/ws", endpoint),
+            base_route: base_route.clone(),
+            rate_limited: false,
+            route: Route {
+                host_name: None,
+                route_id: String::from("websocket-route"),
+                matcher: Default::default(),
+                route_cluster: LoadbalancerStrategy::Random(RandomRoute {
+                    routes: vec![RandomBaseRoute { base_route }],
+                }),
+                allow_deny_list: None,
+                allow_deny_default: None,
+                client_ip: None,
+                authentication: None,
+                ratelimit: None,
+                combined_limit: None,
+                bulkhead: None,
+                health_check: None,
+                anomaly_detection: None,
+                rewrite_headers: None,
+                query_rewrite: None,
+                response_headers: None,
+                forward_headers: None,
+                mirror: None,
+                timeout: None,
+                circuit_breaker: None,
+                hedge: None,
+                retry: None,
+                body_logging: None,
+                maintenance: None,
+                cache: None,
+                single_flight: None,
+                websocket: websocket_config,
+                liveness_config: None,
+                liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                    current_liveness_count: 0,
+                })),
+            },
+        }
+    }
+    #[test]
+    fn test_create_upstream_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONNECTION, HeaderValue::from_static("Upgrade, X-Hop"));
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert("x-hop", HeaderValue::from_static("1"));
+        headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+        headers.insert(TE, HeaderValue::from_static("trailers"));
+        headers.append("x-multi", HeaderValue::from_static("a"));
+        headers.append("x-multi", HeaderValue::from_static("b"));
+        headers.insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("v1, chat"));
+        headers.append(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static("superchat"),
+        );
+        let upgrade_value = HeaderValue::from_static("websocket");
+
+        let (upstream_headers, subprotocols) =
+            create_upstream_headers(&headers, &upgrade_value, None).unwrap();
+        assert_eq!(subprotocols, vec!["v1", "chat", "superchat"]);
+        assert_eq!(upstream_headers.get(CONNECTION).unwrap(), "Upgrade");
+        assert_eq!(upstream_headers.get(UPGRADE).unwrap(), "websocket");
+        assert!(!upstream_headers.contains_key("x-hop"));
+        assert!(!upstream_headers.contains_key("keep-alive"));
+        assert!(!upstream_headers.contains_key(TE));
+        assert_eq!(upstream_headers.get_all("x-multi").iter().count(), 2);
+
+        let allowed = vec![String::from("chat"), String::from("superchat")];
+        let (upstream_headers, subprotocols) =
+            create_upstream_headers(&headers, &upgrade_value, Some(&allowed)).unwrap();
+        assert_eq!(subprotocols, allowed);
+        assert_eq!(
+            upstream_headers.get(SEC_WEBSOCKET_PROTOCOL).unwrap(),
+            "chat, superchat"
+        );
+        let mut response_headers = HeaderMap::new();
+        response_headers.insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("v1"));
+        assert!(selected_subprotocol(&response_headers, &subprotocols).is_err());
+        assert_eq!(
+            selected_subprotocol(&HeaderMap::new(), &subprotocols).unwrap(),
+            None
+        );
+    }
+    #[tokio::test]
+    async fn test_server_upgrade_negotiate_subprotocol() {
+        let backend = TcpListener::bind("127.0.0.1:10108").await.unwrap();
+        let backend_task = tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let len = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..len]).to_lowercase();
+            stream
+                .write_all(
+                    b"HTTP/1.1 101 Switching Protocols\r\nupgrade: websocket\r\nconnection: Upgrade\r\nsec-websocket-accept: test\r\nsec-websocket-protocol: chat\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            request
+        });
+        let websocket_config = WebsocketConfig {
+            idle_timeout: 10,
+            max_message_size: None,
+            subprotocols: Some(vec![String::from("chat")]),
+        };
+        let req = Request::builder()
+            .method("GET")
+            .uri("/ws")
+            .header(CONNECTION, "keep-alive, Upgrade")
+            .header(UPGRADE, "websocket")
+            .header("keep-alive", "timeout=5")
+            .header(SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .header(SEC_WEBSOCKET_PROTOCOL, "v1, chat")
+            .body(Full::new(Bytes::new()).boxed())
+            .unwrap();
+        let res = server_upgrade(
+            req,
+            Some(create_check_result(
+                "http://127.0.0.1:10108",
+                Some(websocket_config),
+            )),
+            vec![],
+            HttpClients::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(res.headers().get(SEC_WEBSOCKET_PROTOCOL).unwrap(), "chat");
+        assert_eq!(
+            res.headers().get(SEC_WEBSOCKET_ACCEPT).unwrap(),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+
+        let request = backend_task.await.unwrap();
+        assert!(request.contains("sec-websocket-protocol: chat\r\n"));
+        assert!(request.contains("connection: upgrade\r\n"));
+        assert!(!request.contains("keep-alive: timeout=5"));
+    }
+    #[tokio::test]
+    async fn test_server_upgrade_failover_to_next_backend() {
+        let rejected_backend = TcpListener::bind("127.0.0.1:10109").await.unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = rejected_backend.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let accepted_backend = TcpListener::bind("127.0.0.1:10110").await.unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = accepted_backend.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream
+                .write_all(
+                    b"HTTP/1.1 101 Switching Protocols\r\nupgrade: websocket\r\nconnection: Upgrade\r\n\r\n",
+                )
+                .await
+                .unwrap();
+        });
+        let req = Request::builder()
+            .method("GET")
+            .uri("/ws")
+            .header(CONNECTION, "Upgrade")
+            .header(UPGRADE, "websocket")
+            .header(SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .body(Full::new(Bytes::new()).boxed())
+            .unwrap();
+        let res = server_upgrade(
+            req,
+            Some(create_check_result("http://127.0.0.1:10109", None)),
+            vec![Uri::from_static("http://127.0.0.1:10110/ws")],
+            HttpClients::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        let req = Request::builder()
+            .method("GET")
+            .uri("/ws")
+            .header(CONNECTION, "Upgrade")
+            .header(UPGRADE, "websocket")
+            .header(SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .body(Full::new(Bytes::new()).boxed())
+            .unwrap();
+        let res = server_upgrade(
+            req,
+            Some(create_check_result("http://127.0.0.1:10109", None)),
+            vec![],
+            HttpClients::new(),
+        )
+        .await;
+        assert!(res.is_err());
+    }
+    fn create_frame(first_byte: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![first_byte];
+        match payload.len() {
+            len if len < 126 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&[1, 2, 3, 4]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+    #[test]
+    fn test_message_size_limit() {
+        let mut limit = MessageSizeLimit::new(200);
+        let frame = create_frame(0x81, &[0; 150]);
+        for item in frame.chunks(3) {
+            assert!(limit.check(item).is_ok());
+        }
+        assert!(limit.check(&create_frame(0x89, &[0; 10])).is_ok());
+        assert!(limit.check(&create_frame(0x01, &[0; 150])).is_ok());
+        assert!(limit.check(&create_frame(0x80, &[0; 100])).is_err());
+        assert!(MessageSizeLimit::new(200)
+            .check(&create_frame(0x82, &[0; 201]))
+            .is_err());
+    }
+    #[tokio::test]
+    async fn test_relay_websocket_idle_timeout() {
+        let (mut client, inbound) = tokio::io::duplex(1024);
+        let (outbound, mut backend) = tokio::io::duplex(1024);
+        let websocket_config = WebsocketConfig {
+            idle_timeout: 1,
+            max_message_size: None,
+            subprotocols: None,
+        };
+        let relay_task = tokio::spawn(relay_websocket(inbound, outbound, Some(websocket_config)));
+
+        let frame = create_frame(0x81, b"hello");
+        client.write_all(&frame).await.unwrap();
+        let mut buf = vec![0; frame.len()];
+        backend.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, frame);
+
+        let now = Instant::now();
+        let mut buf = vec![0; 64];
+        let len = tokio::time::timeout(Duration::from_secs(3), client.read(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(len, 0);
+        assert!(now.elapsed() >= Duration::from_millis(900));
+        let len = backend.read(&mut buf).await.unwrap();
+        assert_eq!(len, 0);
+        assert!(relay_task.await.unwrap().is_ok());
+    }
+    #[tokio::test]
+    async fn test_relay_websocket_message_too_large() {
+        let (mut client, inbound) = tokio::io::duplex(1024);
+        let (outbound, mut backend) = tokio::io::duplex(1024);
+        let websocket_config = WebsocketConfig {
+            idle_timeout: 10,
+            max_message_size: Some(16),
+            subprotocols: None,
+        };
+        let relay_task = tokio::spawn(relay_websocket(inbound, outbound, Some(websocket_config)));
+
+        client
+            .write_all(&create_frame(0x82, &[0; 64]))
+            .await
+            .unwrap();
+        let mut buf = vec![0; 64];
+        let len = backend.read(&mut buf).await.unwrap();
+        assert_eq!(len, 0);
+        let len = client.read(&mut buf).await.unwrap();
+        assert_eq!(len, 0);
+        assert!(relay_task.await.unwrap().is_err());
+    }
+}
@@ -1,8 +1,14 @@
+use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
+use crate::configuration_service::readiness::mark_listener_bound;
 use crate::constants::common_constants::GRPC_STATUS_HEADER;
 use crate::constants::common_constants::GRPC_STATUS_OK;
+use crate::proxy::cert_watcher::spawn_cert_watcher;
+use crate::proxy::listener::{listen_addrs, MultiListener};
 use crate::proxy::proxy_trait::CheckTrait;
 use crate::proxy::proxy_trait::CommonCheckRequest;
 use crate::vojo::app_error::AppError;
+use crate::vojo::tls_policy::build_server_config;
+use crate::vojo::tls_policy::SniCertResolver;
 use h2::client;
 use h2::server;
 use h2::server::SendResponse;
@@ -13,12 +19,10 @@ use http::Response;
 use http::{Method, Request};
 use hyper::body::Bytes;
 
-use rustls_pki_types::CertificateDer;
-use std::io::BufReader;
+use std::future::poll_fn;
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
 use std::sync::Arc;
-use tokio::net::TcpListener;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 use tokio_rustls::server::TlsStream;
@@ -38,7 +42,7 @@ pub async fn start_task(
 ) -> Result<(), AppError> {
     let mut connection = server::handshake(tcp_stream)
         .await
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
     while let Some(request_result) = connection.accept().await {
         if let Ok((request, respond)) = request_result {
             let mapping_key_cloned = mapping_key.clone();
@@ -63,7 +67,7 @@ pub async fn start_tls_task(
 ) -> Result<(), AppError> {
     let mut connection = server::handshake(tcp_stream)
         .await
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
     while let Some(request_result) = connection.accept().await {
         if let Ok((request, respond)) = request_result {
             let mapping_key_cloned = mapping_key.clone();
@@ -97,21 +101,34 @@ async fn request_outbound_adapter(
     .await
 }
 impl GrpcProxy {
+    fn bind(&self) -> Result<(MultiListener, Vec<SocketAddr>), AppError> {
+        let (socket_options, bind_addresses) = GLOBAL_CONFIG_MAPPING
+            .get(&self.mapping_key)
+            .map(|item| {
+                (
+                    item.service_config
+                        .socket_options
+                        .clone()
+                        .unwrap_or_default(),
+                    item.service_config.bind_addresses.clone(),
+                )
+            })
+            .unwrap_or_default();
+        let addrs = listen_addrs(bind_addresses.as_deref(), self.port as u16);
+        let listener = MultiListener::bind(&addrs, &socket_options)?;
+        Ok((listener, addrs))
+    }
     pub async fn start_proxy(&mut self) -> Result<(), AppError> {
-        let port_clone = self.port;
-        let addr = SocketAddr::from(([0, 0, 0, 0], port_clone as u16));
-        info!("Listening on grpc://{}", addr);
-        let listener = TcpListener::bind(addr).await.unwrap();
+        let (mut listener, addrs) = self.bind()?;
+        info!("Listening on grpc://{:?}", addrs);
+        mark_listener_bound(&self.mapping_key);
         let mapping_key = self.mapping_key.clone();
         let reveiver = &mut self.channel;
 
         loop {
-            let accept_future = listener.accept();
             tokio::select! {
-               accept_result=accept_future=>{
-                if let Ok((socket, peer_addr))=accept_result{
-                    tokio::spawn(start_task(socket, mapping_key.clone(), peer_addr));
-                }
+               Some((socket, peer_addr))=listener.accept()=>{
+                tokio::spawn(start_task(socket, mapping_key.clone(), peer_addr));
                },
                _=reveiver.recv()=>{
                 info!("close the socket of grpc!");
@@ -125,45 +142,36 @@ impl GrpcProxy {
         pem_str: String,
         key_str: String,
     ) -> Result<(), AppError> {
-        let port_clone = self.port;
-        let addr = SocketAddr::from(([0, 0, 0, 0], port_clone as u16));
-        let mut cer_reader = BufReader::new(pem_str.as_bytes());
-        // let certs = rustls_pemfile::certs(&mut cer_reader)
-        //     .unwrap()
-        //     .iter()
-        //     .map(|s| rustls::Certificate((*s).clone()))
-        //     .collect();
-        let certs: Vec<CertificateDer<'_>> = rustls_pemfile::certs(&mut cer_reader)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| AppError(e.to_string()))?;
-
-        let mut key_reader = BufReader::new(key_str.as_bytes());
-        let key_der = rustls_pemfile::private_key(&mut key_reader)
-            .map(|key| key.unwrap())
-            .map_err(|e| AppError(e.to_string()))?;
-
-        let tls_cfg = {
-            let cfg = rustls::ServerConfig::builder()
-                .with_no_client_auth()
-                .with_single_cert(certs, key_der)
-                .unwrap();
-            Arc::new(cfg)
-        };
+        let (tls_policy, sni_certs) = GLOBAL_CONFIG_MAPPING
+            .get(&self.mapping_key)
+            .map(|item| {
+                (
+                    item.service_config.tls_policy.clone(),
+                    item.service_config.sni_certs.clone().unwrap_or_default(),
+                )
+            })
+            .unwrap_or_default();
+        let cert_resolver = SniCertResolver::new(&pem_str, &key_str, &sni_certs)?;
+        let cert_resolver = Arc::new(cert_resolver);
+        let tls_cfg = Arc::new(build_server_config(
+            tls_policy.as_ref(),
+            cert_resolver.clone(),
+            vec![],
+        )?);
         let tls_acceptor = TlsAcceptor::from(tls_cfg);
 
-        info!("Listening on grpc with tls://{}", addr);
-        let listener = TcpListener::bind(addr).await.unwrap();
+        let (mut listener, addrs) = self.bind()?;
+        info!("Listening on grpc with tls://{:?}", addrs);
+        mark_listener_bound(&self.mapping_key);
+        let _cert_watcher = spawn_cert_watcher(self.mapping_key.clone(), cert_resolver);
         let mapping_key = self.mapping_key.clone();
         let reveiver = &mut self.channel;
 
         loop {
-            let accept_future = listener.accept();
             tokio::select! {
-               accept_result=accept_future=>{
-                if let Ok((tcp_stream, peer_addr))=accept_result{
-                    if let Ok(tls_streams) = tls_acceptor.accept(tcp_stream).await {
-                        tokio::spawn(start_tls_task(tls_streams, mapping_key.clone(), peer_addr));
-                    }
+               Some((tcp_stream, peer_addr))=listener.accept()=>{
+                if let Ok(tls_streams) = tls_acceptor.accept(tcp_stream).await {
+                    tokio::spawn(start_tls_task(tls_streams, mapping_key.clone(), peer_addr));
                 }
                },
                _=reveiver.recv()=>{
@@ -175,25 +183,51 @@ impl GrpcProxy {
     }
 }
 
+/**
+ *Wait for the send capacity before sending each part, the received data is only released after it has been sent,
+ *so the streaming messages are relayed one by one without buffering the whole body.
+ */
+async fn send_with_capacity(
+    send_stream: &mut SendStream<Bytes>,
+    mut chunk_bytes: Bytes,
+) -> Result<(), AppError> {
+    while !chunk_bytes.is_empty() {
+        send_stream.reserve_capacity(chunk_bytes.len());
+        let capacity = poll_fn(|cx| send_stream.poll_capacity(cx))
+            .await
+            .ok_or(AppError::from(String::from("The stream has been closed!")))?
+            .map_err(|e| AppError::from(e.to_string()))?;
+        if capacity == 0 {
+            continue;
+        }
+        let data = chunk_bytes.split_to(capacity.min(chunk_bytes.len()));
+        send_stream
+            .send_data(data, false)
+            .map_err(|e| AppError::from(e.to_string()))?;
+    }
+    Ok(())
+}
 async fn copy_io(
     mut send_stream: SendStream<Bytes>,
     mut recv_stream: RecvStream,
 ) -> Result<(), AppError> {
     let mut flow_control = recv_stream.flow_control().clone();
     while let Some(chunk_result) = recv_stream.data().await {
-        let chunk_bytes = chunk_result.map_err(|e| AppError(e.to_string()))?;
+        let chunk_bytes = chunk_result.map_err(|e| AppError::from(e.to_string()))?;
         debug!("Data from outbound: {:?}", chunk_bytes.clone());
-        send_stream
-            .send_data(chunk_bytes.clone(), false)
-            .map_err(|e| AppError(e.to_string()))?;
+        let len = chunk_bytes.len();
+        send_with_capacity(&mut send_stream, chunk_bytes).await?;
         flow_control
-            .release_capacity(chunk_bytes.len())
-            .map_err(|e| AppError(e.to_string()))?;
+            .release_capacity(len)
+            .map_err(|e| AppError::from(e.to_string()))?;
     }
-    if let Ok(Some(header)) = recv_stream.trailers().await {
-        send_stream
+    match recv_stream.trailers().await {
+        Ok(Some(header)) => send_stream
             .send_trailers(header)
-            .map_err(|e| AppError(e.to_string()))?;
+            .map_err(|e| AppError::from(e.to_string()))?,
+        _ => send_stream
+            .send_data(Bytes::new(), true)
+            .map_err(|e| AppError::from(e.to_string()))?,
     }
     Ok(())
 }
@@ -210,29 +244,35 @@ async fn request_outbound(
     let inbound_headers = inbound_parts.headers.clone();
     let uri = inbound_parts.uri.clone();
     let check_result = check_trait
-        .check_before_request(mapping_key.clone(), inbound_headers, uri, peer_addr)
+        .check_before_request(
+            mapping_key.clone(),
+            inbound_parts.method.clone(),
+            inbound_headers,
+            uri,
+            peer_addr,
+        )
         .await?;
-    if check_result.is_none() {
-        return Err(AppError(String::from(
+    let Some(check_result) = check_result.filter(|item| !item.rate_limited) else {
+        return Err(AppError::from(String::from(
             "The request has been denied by the proxy!",
         )));
-    }
-    let request_path = check_result.unwrap().request_path;
-    let url = Url::parse(&request_path).map_err(|e| AppError(e.to_string()))?;
+    };
+    let request_path = check_result.request_path;
+    let url = Url::parse(&request_path).map_err(|e| AppError::from(e.to_string()))?;
     let cloned_url = url.clone();
     let host = cloned_url
         .host()
-        .ok_or(AppError(String::from("Parse host error!")))?;
+        .ok_or(AppError::from(String::from("Parse host error!")))?;
     let port = cloned_url
         .port()
-        .ok_or(AppError(String::from("Parse host error!")))?;
+        .ok_or(AppError::from(String::from("Parse host error!")))?;
     debug!("The host is {}", host);
 
     let addr = format!("{}:{}", host, port)
         .to_socket_addrs()
-        .map_err(|e| AppError(e.to_string()))?
+        .map_err(|e| AppError::from(e.to_string()))?
         .next()
-        .ok_or(AppError(String::from("Parse the domain error!")))?;
+        .ok_or(AppError::from(String::from("Parse the domain error!")))?;
     debug!("The addr is {}", addr);
     let host_str = host.to_string();
 
@@ -247,18 +287,18 @@ async fn request_outbound(
         let tls_connector = TlsConnector::from(Arc::new(config));
         let stream = TcpStream::connect(&addr)
             .await
-            .map_err(|e| AppError(e.to_string()))?;
+            .map_err(|e| AppError::from(e.to_string()))?;
         let domain = rustls_pki_types::ServerName::try_from(host_str.as_str())
-            .map_err(|e| AppError(e.to_string()))?
+            .map_err(|e| AppError::from(e.to_string()))?
             .to_owned();
         debug!("The domain name is {}", host);
         let stream = tls_connector
             .connect(domain, stream)
             .await
-            .map_err(|e| AppError(e.to_string()))?;
+            .map_err(|e| AppError::from(e.to_string()))?;
         let (send_request, connection) = client::handshake(stream)
             .await
-            .map_err(|e| AppError(e.to_string()))?;
+            .map_err(|e| AppError::from(e.to_string()))?;
         tokio::spawn(async move {
             let connection_result = connection.await;
             if let Err(err) = connection_result {
@@ -271,10 +311,10 @@ async fn request_outbound(
     } else {
         let tcpstream = TcpStream::connect(addr)
             .await
-            .map_err(|e| AppError(e.to_string()))?;
+            .map_err(|e| AppError::from(e.to_string()))?;
         let (send_request, connection) = client::handshake(tcpstream)
             .await
-            .map_err(|e| AppError(e.to_string()))?;
+            .map_err(|e| AppError::from(e.to_string()))?;
         tokio::spawn(async move {
             connection.await.unwrap();
             debug!("The connection has closed!");
@@ -286,7 +326,7 @@ async fn request_outbound(
     let mut send_request = send_request_poll
         .ready()
         .await
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
     let request = Request::builder()
         .method(Method::POST)
         .version(Version::HTTP_2)
@@ -298,7 +338,7 @@ async fn request_outbound(
     debug!("Our bound request is {:?}", request);
     let (response, outbound_send_stream) = send_request
         .send_request(request, false)
-        .map_err(|e| AppError(e.to_string()))?;
+        .map_err(|e| AppError::from(e.to_string()))?;
     tokio::spawn(async {
         if let Err(err) = copy_io(outbound_send_stream, inbound_body).await {
             error!("Copy from inbound to outboud error,the error is {}", err);
@@ -307,7 +347,7 @@ async fn request_outbound(
 
     let (head, outboud_response_body) = response
         .await
-        .map_err(|e| AppError(e.to_string()))?
+        .map_err(|e| AppError::from(e.to_string()))?
         .into_parts();
 
     debug!("Received response: {:?}", head);
@@ -321,8 +361,10 @@ async fn request_outbound(
 
     let send_stream = inbound_respond
         .send_response(inbound_response, is_grpc_status_ok)
-        .map_err(|e| AppError(e.to_string()))?;
-
+        .map_err(|e| AppError::from(e.to_string()))?;
+    if is_grpc_status_ok {
+        return Ok(());
+    }
     tokio::spawn(async {
         if let Err(err) = copy_io(send_stream, outboud_response_body).await {
             error!("Copy from outbound to inbound error,the error is {}", err);
@@ -335,7 +377,12 @@ mod tests {
     use super::*;
     use crate::proxy::http1::http_client::HttpClients;
     use crate::proxy::proxy_trait::CheckResult;
-    use crate::vojo::app_config::Route;
+    use crate::vojo::api_service_manager::ApiServiceManager;
+    use crate::vojo::app_config::{Matcher, Route, ServiceConfig, ServiceType};
+    use crate::vojo::app_config_vistor::{
+        BaseRouteVistor, LoadbalancerStrategyVistor, RandomBaseRouteVistor, RandomRouteVistor,
+        RouteVistor,
+    };
     use async_trait::async_trait;
     use hyper::HeaderMap;
     use hyper::Uri;
@@ -346,6 +393,7 @@ mod tests {
     use lazy_static::lazy_static;
     use std::env;
     use std::time::Duration;
+    use tokio::net::TcpListener;
     use tokio::runtime::{Builder, Runtime};
     use tokio::time::sleep;
     struct MockProvider();
@@ -354,6 +402,7 @@ mod tests {
         async fn check_before_request(
             &self,
             _mapping_key: String,
+            _method: Method,
             _headers: HeaderMap,
             _uri: Uri,
             _peer_addr: SocketAddr,
@@ -363,6 +412,7 @@ mod tests {
                 request_path: String::from("http://127.0.0.1:50051"),
                 route,
                 base_route: Default::default(),
+                rate_limited: false,
             }))
         }
     }
@@ -401,7 +451,7 @@ mod tests {
             .body(Full::new(Bytes::new()).boxed())
             .unwrap();
         let http_clients = HttpClients::new();
-        let outbound_res = http_clients.request_http(request, 3).await;
+        let outbound_res = http_clients.request_http(request, 3, true).await;
 
         if let Ok(Ok(response)) = outbound_res {
             assert_eq!(response.status(), StatusCode::OK);
@@ -444,9 +494,169 @@ mod tests {
             .body(Full::new(Bytes::new()).boxed())
             .unwrap();
         let http_clients = HttpClients::new();
-        let outbound_res = http_clients.request_http(request, 3).await;
+        let outbound_res = http_clients.request_http(request, 3, true).await;
         if let Ok(Ok(response)) = outbound_res {
             assert_eq!(response.status(), StatusCode::OK);
         }
     }
+    fn create_grpc_message(payload: &[u8]) -> Bytes {
+        let mut message = vec![0];
+        message.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        message.extend_from_slice(payload);
+        Bytes::from(message)
+    }
+    fn count_grpc_messages(buf: &mut Vec<u8>) -> usize {
+        let mut count = 0;
+        while buf.len() >= 5 {
+            let len = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+            if buf.len() < 5 + len {
+                break;
+            }
+            buf.drain(..5 + len);
+            count += 1;
+        }
+        count
+    }
+    async fn start_counting_backend(port: u16) {
+        let listener = TcpListener::bind(format!("127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut connection = server::handshake(socket).await.unwrap();
+            while let Some(Ok((request, mut respond))) = connection.accept().await {
+                tokio::spawn(async move {
+                    let mut body = request.into_body();
+                    let mut buf = vec![];
+                    let mut count = 0;
+                    let mut send_stream = None;
+                    while let Some(Ok(chunk)) = body.data().await {
+                        let _ = body.flow_control().release_capacity(chunk.len());
+                        buf.extend_from_slice(&chunk);
+                        count += count_grpc_messages(&mut buf);
+                        if send_stream.is_none() {
+                            let response = Response::builder()
+                                .header("content-type", "application/grpc")
+                                .body(())
+                                .unwrap();
+                            send_stream = Some(respond.send_response(response, false).unwrap());
+                        }
+                    }
+                    let mut send_stream = send_stream.unwrap();
+                    send_stream
+                        .send_data(create_grpc_message(count.to_string().as_bytes()), false)
+                        .unwrap();
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert(GRPC_STATUS_HEADER, GRPC_STATUS_OK.parse().unwrap());
+                    send_stream.send_trailers(trailers).unwrap();
+                });
+            }
+        });
+    }
+    #[tokio::test]
+    async fn test_grpc_client_streaming_messages() {
+        start_counting_backend(10118).await;
+        let route = Route::from(RouteVistor {
+            route_cluster: LoadbalancerStrategyVistor::RandomRoute(RandomRouteVistor {
+                routes: vec![RandomBaseRouteVistor {
+                    base_route: BaseRouteVistor {
+                        endpoint: String::from("http://127.0.0.1:10118"),
+                        ..Default::default()
+                    },
+                }],
+            }),
+            matcher: Some(Matcher {
+                prefix: String::from("/"),
+                prefix_rewrite: String::from("/"),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10119-Http2"),
+            ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: ServiceType::Http2,
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            },
+        );
+        let mut grpc_proxy = GrpcProxy {
+            port: 10119,
+            channel: receiver,
+            mapping_key: String::from("10119-Http2"),
+        };
+        tokio::spawn(async move { grpc_proxy.start_proxy().await });
+        sleep(Duration::from_millis(100)).await;
+
+        let stream = TcpStream::connect("127.0.0.1:10119").await.unwrap();
+        let (send_request, connection) = client::handshake(stream).await.unwrap();
+        tokio::spawn(connection);
+        let mut send_request = send_request.ready().await.unwrap();
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("http://127.0.0.1:10119/test.Counter/Count")
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .body(())
+            .unwrap();
+        let (response, mut send_stream) = send_request.send_request(request, false).unwrap();
+        send_stream
+            .send_data(create_grpc_message(b"first"), false)
+            .unwrap();
+        let response = tokio::time::timeout(Duration::from_secs(3), response)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        send_stream
+            .send_data(create_grpc_message(b"second"), false)
+            .unwrap();
+        send_stream
+            .send_data(create_grpc_message(b"third"), true)
+            .unwrap();
+
+        let mut body = response.into_body();
+        let mut buf = vec![];
+        while let Some(chunk) = body.data().await {
+            buf.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(&buf[5..], b"3");
+        let trailers = body.trailers().await.unwrap().unwrap();
+        assert_eq!(trailers.get(GRPC_STATUS_HEADER).unwrap(), GRPC_STATUS_OK);
+        GLOBAL_CONFIG_MAPPING.remove("10119-Http2");
+    }
+    #[tokio::test]
+    async fn test_grpc_bind_addresses() {
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10144-Http2"),
+            ApiServiceManager {
+                sender: sender.clone(),
+                service_config: ServiceConfig {
+                    server_type: ServiceType::Http2,
+                    bind_addresses: Some(vec!["127.0.0.1".parse().unwrap()]),
+                    ..Default::default()
+                },
+            },
+        );
+        let mut grpc_proxy = GrpcProxy {
+            port: 10144,
+            channel: receiver,
+            mapping_key: String::from("10144-Http2"),
+        };
+        let task = tokio::spawn(async move { grpc_proxy.start_proxy().await });
+        sleep(Duration::from_millis(100)).await;
+
+        assert!(TcpStream::connect("127.0.0.1:10144").await.is_ok());
+        assert!(TcpListener::bind("127.0.0.2:10144").await.is_ok());
+        sender.send(()).await.unwrap();
+        assert!(task.await.unwrap().is_ok());
+        GLOBAL_CONFIG_MAPPING.remove("10144-Http2");
+    }
 }
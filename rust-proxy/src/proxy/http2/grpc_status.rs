@@ -0,0 +1,100 @@
+use crate::constants::common_constants::GRPC_MESSAGE_HEADER;
+use crate::constants::common_constants::GRPC_STATUS_HEADER;
+use crate::constants::common_constants::GRPC_STATUS_OK;
+use crate::proxy::http1::static_file::decode_percents;
+use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+use http::StatusCode;
+use serde_json::json;
+
+/**
+ *The mapping follows the one of grpc-gateway, the unknown code is treated as an internal error.
+ */
+pub fn grpc_status_to_http(code: u32) -> StatusCode {
+    match code {
+        0 => StatusCode::OK,
+        1 => StatusCode::from_u16(499).unwrap_or(StatusCode::BAD_REQUEST),
+        3 | 9 | 11 => StatusCode::BAD_REQUEST,
+        4 => StatusCode::GATEWAY_TIMEOUT,
+        5 => StatusCode::NOT_FOUND,
+        6 | 10 => StatusCode::CONFLICT,
+        7 => StatusCode::FORBIDDEN,
+        8 => StatusCode::TOO_MANY_REQUESTS,
+        12 => StatusCode::NOT_IMPLEMENTED,
+        14 => StatusCode::SERVICE_UNAVAILABLE,
+        16 => StatusCode::UNAUTHORIZED,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+pub fn is_grpc_request(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|item| item.to_str().ok())
+        .is_some_and(|item| item.starts_with("application/grpc"))
+}
+/**
+ *Return the http status and the json body when the headers carry a failed grpc status, which is sent in a trailers-only response.
+ */
+pub fn grpc_error_response(headers: &HeaderMap) -> Option<(StatusCode, String)> {
+    let grpc_status = headers.get(GRPC_STATUS_HEADER)?.to_str().ok()?.trim();
+    if grpc_status == GRPC_STATUS_OK {
+        return None;
+    }
+    let code = grpc_status.parse::<u32>().unwrap_or(2);
+    let message = headers
+        .get(GRPC_MESSAGE_HEADER)
+        .and_then(|item| item.to_str().ok())
+        .map(decode_percents)
+        .unwrap_or_default();
+    let json_value = json!({
+        "code": code,
+        "message": message
+    });
+    Some((grpc_status_to_http(code), json_value.to_string()))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+    #[test]
+    fn test_grpc_status_to_http() {
+        for (code, status) in [
+            (0, 200),
+            (1, 499),
+            (3, 400),
+            (4, 504),
+            (5, 404),
+            (7, 403),
+            (8, 429),
+            (12, 501),
+            (14, 503),
+            (16, 401),
+            (99, 500),
+        ] {
+            assert_eq!(grpc_status_to_http(code).as_u16(), status);
+        }
+    }
+    #[test]
+    fn test_grpc_error_response() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(grpc_error_response(&headers), None);
+        headers.insert(GRPC_STATUS_HEADER, HeaderValue::from_static("0"));
+        assert_eq!(grpc_error_response(&headers), None);
+        headers.insert(GRPC_STATUS_HEADER, HeaderValue::from_static("5"));
+        headers.insert(
+            GRPC_MESSAGE_HEADER,
+            HeaderValue::from_static("user%2042%20not%20found"),
+        );
+        let (status, body) = grpc_error_response(&headers).unwrap();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["code"], 5);
+        assert_eq!(body["message"], "user 42 not found");
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc+proto"),
+        );
+        assert!(is_grpc_request(&headers));
+    }
+}
@@ -1 +1,3 @@
 pub mod grpc_proxy;
+
+pub mod grpc_status;
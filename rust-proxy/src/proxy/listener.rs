@@ -0,0 +1,142 @@
+use crate::vojo::app_config::SocketOptions;
+use crate::vojo::app_error::AppError;
+use futures::future::select_all;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+
+pub const DEFAULT_LISTEN_BACKLOG: u32 = 1024;
+
+fn build_socket(addr: SocketAddr, socket_options: &SocketOptions) -> std::io::Result<TcpSocket> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(socket_options.reuseaddr.unwrap_or(true))?;
+    if let Some(nodelay) = socket_options.nodelay {
+        socket.set_nodelay(nodelay)?;
+    }
+    socket.bind(addr)?;
+    Ok(socket)
+}
+pub fn bind_listener(
+    addr: SocketAddr,
+    socket_options: &SocketOptions,
+) -> Result<TcpListener, AppError> {
+    build_socket(addr, socket_options)
+        .and_then(|socket| socket.listen(socket_options.backlog.unwrap_or(DEFAULT_LISTEN_BACKLOG)))
+        .map_err(|e| AppError::from(e.to_string()))
+}
+/**
+ *Listen on all the interfaces when the bind addresses are not configured.
+ */
+pub fn listen_addrs(bind_addresses: Option<&[IpAddr]>, port: u16) -> Vec<SocketAddr> {
+    match bind_addresses {
+        Some(bind_addresses) if !bind_addresses.is_empty() => bind_addresses
+            .iter()
+            .map(|ip| SocketAddr::new(*ip, port))
+            .collect(),
+        _ => vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)],
+    }
+}
+/**
+ *The listeners are owned by the multi listener and are closed as soon as it is dropped,
+ *so the same port could be bound again right after the proxy is stopped.
+ */
+pub struct MultiListener {
+    listeners: Vec<TcpListener>,
+}
+impl MultiListener {
+    pub fn bind(addrs: &[SocketAddr], socket_options: &SocketOptions) -> Result<Self, AppError> {
+        let listeners = addrs
+            .iter()
+            .map(|addr| bind_listener(*addr, socket_options))
+            .collect::<Result<Vec<TcpListener>, AppError>>()?;
+        Ok(MultiListener { listeners })
+    }
+    pub async fn accept(&mut self) -> Option<(TcpStream, SocketAddr)> {
+        if self.listeners.is_empty() {
+            return None;
+        }
+        loop {
+            let accept_futures = self
+                .listeners
+                .iter()
+                .map(|listener| Box::pin(listener.accept()));
+            match select_all(accept_futures).await.0 {
+                Ok(item) => return Some(item),
+                Err(err) => error!("Accept the connection error,the error is {}.", err),
+            }
+        }
+    }
+}
+pub fn apply_stream_options(stream: &TcpStream, socket_options: &SocketOptions) {
+    if let Some(nodelay) = socket_options.nodelay {
+        if let Err(err) = stream.set_nodelay(nodelay) {
+            error!("Set the nodelay of the stream error,the error is {}.", err);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[tokio::test]
+    async fn test_build_socket_with_options() {
+        let socket_options = SocketOptions {
+            nodelay: Some(true),
+            reuseaddr: Some(false),
+            backlog: Some(16),
+        };
+        let socket = build_socket("127.0.0.1:10051".parse().unwrap(), &socket_options).unwrap();
+        assert!(socket.nodelay().unwrap());
+        assert!(!socket.reuseaddr().unwrap());
+        let listener = socket.listen(16).unwrap();
+        assert_eq!(listener.local_addr().unwrap().port(), 10051);
+    }
+    #[tokio::test]
+    async fn test_bind_listener_and_accept_with_nodelay() {
+        let socket_options = SocketOptions {
+            nodelay: Some(true),
+            ..Default::default()
+        };
+        let listener = bind_listener("127.0.0.1:10052".parse().unwrap(), &socket_options).unwrap();
+        let client = TcpStream::connect("127.0.0.1:10052").await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        apply_stream_options(&stream, &socket_options);
+        assert!(stream.nodelay().unwrap());
+        drop(client);
+    }
+    #[test]
+    fn test_listen_addrs() {
+        assert_eq!(
+            listen_addrs(None, 8080),
+            vec!["0.0.0.0:8080".parse::<SocketAddr>().unwrap()]
+        );
+        let bind_addresses: Vec<IpAddr> =
+            vec!["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()];
+        assert_eq!(
+            listen_addrs(Some(&bind_addresses), 8080),
+            vec![
+                "127.0.0.1:8080".parse::<SocketAddr>().unwrap(),
+                "[::1]:8080".parse::<SocketAddr>().unwrap()
+            ]
+        );
+    }
+    #[tokio::test]
+    async fn test_multi_listener_accept_on_every_address() {
+        let addrs: Vec<SocketAddr> = vec![
+            "127.0.0.1:10128".parse().unwrap(),
+            "127.0.0.1:10129".parse().unwrap(),
+        ];
+        let mut listener = MultiListener::bind(&addrs, &SocketOptions::default()).unwrap();
+        for addr in addrs.iter() {
+            let client = TcpStream::connect(addr).await.unwrap();
+            let (stream, _) = listener.accept().await.unwrap();
+            assert_eq!(stream.local_addr().unwrap(), *addr);
+            drop(client);
+        }
+        drop(listener);
+        assert!(TcpStream::connect("127.0.0.1:10128").await.is_err());
+        assert!(bind_listener(addrs[1], &SocketOptions::default()).is_ok());
+    }
+}
@@ -1,4 +1,7 @@
+pub mod cert_watcher;
 pub mod http1;
 pub mod http2;
+pub mod listener;
 pub mod proxy_trait;
 pub mod tcp;
+pub mod udp;
@@ -1,9 +1,10 @@
 use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
-use crate::vojo::app_config::Route;
-use crate::vojo::app_error::AppError;
+use crate::vojo::app_config::{Route, RouteAccess, ServiceType};
+use crate::vojo::app_error::{AppError, AppErrorKind};
 use crate::vojo::route::BaseRoute;
 use async_trait::async_trait;
 use http::HeaderMap;
+use http::Method;
 use hyper::Uri;
 use std::net::SocketAddr;
 use std::path::Path;
@@ -13,6 +14,7 @@ pub trait CheckTrait {
     async fn check_before_request(
         &self,
         mapping_key: String,
+        method: Method,
         headers: HeaderMap,
         uri: Uri,
         peer_addr: SocketAddr,
@@ -29,6 +31,7 @@ pub struct CheckResult {
     pub request_path: String,
     pub route: Route,
     pub base_route: BaseRoute,
+    pub rate_limited: bool,
 }
 
 #[async_trait]
@@ -36,74 +39,351 @@ impl CheckTrait for CommonCheckRequest {
     async fn check_before_request(
         &self,
         mapping_key: String,
+        method: Method,
         headers: HeaderMap,
         uri: Uri,
         peer_addr: SocketAddr,
     ) -> Result<Option<CheckResult>, AppError> {
         let backend_path = uri
             .path_and_query()
-            .ok_or(AppError(String::from("")))?
+            .ok_or(AppError::from(String::from("")))?
             .to_string();
         let api_service_manager = GLOBAL_CONFIG_MAPPING
             .get(&mapping_key)
-            .ok_or(AppError(format!(
-                "Can not find the config mapping on the key {}!",
-                mapping_key.clone()
-            )))?
+            .ok_or(AppError::new(
+                AppErrorKind::Config,
+                format!(
+                    "Can not find the config mapping on the key {}!",
+                    mapping_key.clone()
+                ),
+            ))?
             .clone();
-        let addr_string = peer_addr.ip().to_string();
-        for item in api_service_manager.service_config.routes {
-            let back_path_clone = backend_path.clone();
-            let match_result = item.is_matched(back_path_clone, Some(headers.clone()))?;
-            if match_result.clone().is_none() {
-                continue;
-            }
-            let is_allowed = item
-                .is_allowed(addr_string.clone(), Some(headers.clone()))
-                .await?;
-            if !is_allowed {
-                return Ok(None);
-            }
-            let base_route = item
-                .route_cluster
-                .clone()
-                .get_route(headers.clone())
-                .await?;
-            let endpoint = base_route.endpoint.clone();
-            debug!("The endpoint is {}", endpoint);
-            if endpoint.contains("http") {
-                let host = Url::parse(endpoint.as_str()).map_err(|e| AppError(e.to_string()))?;
-                let rest_path = match_result.unwrap();
+        match_request(
+            api_service_manager.service_config.routes,
+            &api_service_manager.service_config.server_type,
+            method,
+            headers,
+            backend_path,
+            peer_addr,
+        )
+        .await
+    }
+}
+/**
+ *Find the first route matching the request and choose the endpoint from its cluster.
+ */
+pub async fn match_request(
+    routes: Vec<Route>,
+    server_type: &ServiceType,
+    method: Method,
+    headers: HeaderMap,
+    backend_path: String,
+    peer_addr: SocketAddr,
+) -> Result<Option<CheckResult>, AppError> {
+    let addr_string = peer_addr.ip().to_string();
+    for item in routes {
+        if !item.is_method_matched(&method)
+            || !item.is_scheme_matched(server_type, &headers, peer_addr.ip())
+        {
+            continue;
+        }
+        let back_path_clone = backend_path.clone();
+        let match_result = item.is_matched(back_path_clone, Some(headers.clone()))?;
+        if match_result.clone().is_none() {
+            continue;
+        }
+        let route_access = item
+            .is_allowed(addr_string.clone(), Some(headers.clone()))
+            .await?;
+        if route_access == RouteAccess::Denied {
+            return Ok(None);
+        }
+        let base_route = item
+            .route_cluster
+            .clone()
+            .get_route(headers.clone(), peer_addr, item.liveness_config.as_ref())
+            .await?;
+        let endpoint = base_route.endpoint.clone();
+        debug!("The endpoint is {}", endpoint);
+        if endpoint.contains("http") {
+            let host = Url::parse(endpoint.as_str()).map_err(|e| AppError::from(e.to_string()))?;
+            let rest_path = item.rewrite_query(match_result.unwrap());
 
-                let request_path = host
-                    .join(rest_path.as_str())
-                    .map_err(|e| AppError(e.to_string()))?
-                    .to_string();
-                return Ok(Some(CheckResult {
-                    request_path,
-                    route: item,
-                    base_route,
-                }));
-            } else {
-                let path = Path::new(&endpoint);
-                let rest_path = match_result.unwrap();
-                let request_path = path.join(rest_path);
-                return Ok(Some(CheckResult {
-                    request_path: String::from(request_path.to_str().unwrap_or_default()),
-                    route: item,
-                    base_route,
-                }));
-            }
+            let request_path = host
+                .join(rest_path.as_str())
+                .map_err(|e| AppError::from(e.to_string()))?
+                .to_string();
+            return Ok(Some(CheckResult {
+                request_path,
+                route: item,
+                base_route,
+                rate_limited: route_access == RouteAccess::RateLimited,
+            }));
+        } else {
+            let path = Path::new(&endpoint);
+            let rest_path = match_result.unwrap();
+            let request_path = path.join(rest_path);
+            return Ok(Some(CheckResult {
+                request_path: String::from(request_path.to_str().unwrap_or_default()),
+                route: item,
+                base_route,
+                rate_limited: route_access == RouteAccess::RateLimited,
+            }));
         }
-        Ok(None)
     }
+    Ok(None)
 }
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::common_constants::X_FORWARDED_PROTO;
+    use crate::vojo::allow_deny_ip::ClientIpConfig;
+    use crate::vojo::api_service_manager::ApiServiceManager;
+    use crate::vojo::app_config::LivenessStatus;
+    use crate::vojo::app_config::{
+        Matcher, PathMatchType, QueryRewrite, ServiceConfig, ServiceType,
+    };
+    use crate::vojo::app_config_vistor::RouteVistor;
+    use crate::vojo::app_config_vistor::{
+        BaseRouteVistor, LoadbalancerStrategyVistor, RandomBaseRouteVistor, RandomRouteVistor,
+    };
+    use crate::vojo::route::AnomalyDetectionStatus;
+    use std::collections::HashMap;
     #[test]
     fn test_url_parse() {
         let host = Url::parse("http://127.0.0.1:8080");
         assert!(host.is_ok());
     }
+    #[tokio::test]
+    async fn test_check_before_request_regex_rewrite_with_query() {
+        let route_vistor = RouteVistor {
+            host_name: None,
+            route_id: String::from("regex-query-route"),
+            route_cluster: LoadbalancerStrategyVistor::RandomRoute(RandomRouteVistor {
+                routes: vec![RandomBaseRouteVistor {
+                    base_route: BaseRouteVistor {
+                        endpoint: String::from("http://127.0.0.1:8080"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: None,
+                        anomaly_detection_status: AnomalyDetectionStatus {
+                            consecutive_5xx: 0,
+                            recent_responses: Default::default(),
+                        },
+                    },
+                }],
+            }),
+            liveness_status: LivenessStatus {
+                current_liveness_count: 0,
+            },
+            anomaly_detection: None,
+            health_check: None,
+            allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
+            authentication: None,
+            liveness_config: None,
+            rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
+            ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
+            matcher: Some(Matcher {
+                prefix: String::from(r"^/users/(\d+)/posts$"),
+                prefix_rewrite: String::from("/v2/posts?user=$1"),
+                match_type: PathMatchType::Regex,
+                paths: None,
+                methods: None,
+                schemes: None,
+            }),
+        };
+        let route = Route::from(route_vistor).await.unwrap();
+        let (sender, _) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10042-HTTP"),
+            ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    server_type: ServiceType::Http,
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            },
+        );
+        let check_result = CommonCheckRequest::new()
+            .check_before_request(
+                String::from("10042-HTTP"),
+                Method::GET,
+                HeaderMap::new(),
+                Uri::from_static("/users/42/posts?page=2"),
+                "127.0.0.1:9000".parse().unwrap(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            check_result.request_path,
+            "http://127.0.0.1:8080/v2/posts?user=42&page=2"
+        );
+    }
+    async fn create_scheme_routes(use_forwarded_proto: bool) -> Vec<Route> {
+        let mut routes = vec![];
+        for (route_id, schemes) in [
+            ("secure", Some(vec![String::from("https")])),
+            ("plain", None),
+        ] {
+            let route_vistor = RouteVistor {
+                route_id: String::from(route_id),
+                route_cluster: LoadbalancerStrategyVistor::RandomRoute(RandomRouteVistor {
+                    routes: vec![RandomBaseRouteVistor {
+                        base_route: BaseRouteVistor {
+                            endpoint: format!("http://127.0.0.1:8080/{}/", route_id),
+                            ..Default::default()
+                        },
+                    }],
+                }),
+                client_ip: Some(ClientIpConfig {
+                    use_forwarded_for: false,
+                    trusted_hops: 1,
+                    use_forwarded_proto,
+                    trusted_proxies: Some(vec![String::from("127.0.0.0/8")]),
+                }),
+                matcher: Some(Matcher {
+                    prefix: String::from("/"),
+                    prefix_rewrite: String::from("/"),
+                    schemes,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            routes.push(Route::from(route_vistor).await.unwrap());
+        }
+        routes
+    }
+    #[tokio::test]
+    async fn test_match_request_scheme_from_trusted_forwarded_proto() {
+        let mut forwarded_https = HeaderMap::new();
+        forwarded_https.insert(X_FORWARDED_PROTO, "https".parse().unwrap());
+        for (use_forwarded_proto, server_type, headers, peer_addr, expected) in [
+            (
+                true,
+                ServiceType::Http,
+                HeaderMap::new(),
+                "127.0.0.1:9000",
+                "plain",
+            ),
+            (
+                true,
+                ServiceType::Http,
+                forwarded_https.clone(),
+                "127.0.0.1:9000",
+                "secure",
+            ),
+            (
+                true,
+                ServiceType::Http,
+                forwarded_https.clone(),
+                "10.0.0.1:9000",
+                "plain",
+            ),
+            (
+                false,
+                ServiceType::Http,
+                forwarded_https.clone(),
+                "127.0.0.1:9000",
+                "plain",
+            ),
+            (
+                false,
+                ServiceType::Https,
+                HeaderMap::new(),
+                "127.0.0.1:9000",
+                "secure",
+            ),
+        ] {
+            let check_result = match_request(
+                create_scheme_routes(use_forwarded_proto).await,
+                &server_type,
+                Method::GET,
+                headers,
+                String::from("/orders"),
+                peer_addr.parse().unwrap(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            assert_eq!(check_result.route.route_id, expected);
+        }
+    }
+    #[tokio::test]
+    async fn test_check_before_request_query_rewrite() {
+        let route_vistor = RouteVistor {
+            route_id: String::from("query-rewrite-route"),
+            route_cluster: LoadbalancerStrategyVistor::RandomRoute(RandomRouteVistor {
+                routes: vec![RandomBaseRouteVistor {
+                    base_route: BaseRouteVistor {
+                        endpoint: String::from("http://127.0.0.1:8080"),
+                        ..Default::default()
+                    },
+                }],
+            }),
+            query_rewrite: Some(QueryRewrite {
+                add: HashMap::from([(String::from("api_version"), String::from("2"))]),
+                remove: vec![String::from("utm_source")],
+                override_params: HashMap::from([(String::from("page"), String::from("1"))]),
+            }),
+            matcher: Some(Matcher {
+                prefix: String::from("/"),
+                prefix_rewrite: String::from("/"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
+            }),
+            ..Default::default()
+        };
+        let route = Route::from(route_vistor).await.unwrap();
+        let (sender, _) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10115-HTTP"),
+            ApiServiceManager {
+                sender,
+                service_config: ServiceConfig {
+                    routes: vec![route],
+                    ..Default::default()
+                },
+            },
+        );
+        let check_result = CommonCheckRequest::new()
+            .check_before_request(
+                String::from("10115-HTTP"),
+                Method::GET,
+                HeaderMap::new(),
+                Uri::from_static("/items?utm_source=mail&page=3&sort=asc"),
+                "127.0.0.1:9000".parse().unwrap(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            check_result.request_path,
+            "http://127.0.0.1:8080/items?page=1&sort=asc&api_version=2"
+        );
+    }
 }
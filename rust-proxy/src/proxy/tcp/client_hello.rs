@@ -0,0 +1,156 @@
+use crate::vojo::app_error::AppError;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+
+static TLS_HANDSHAKE_RECORD: u8 = 0x16;
+static TLS_CLIENT_HELLO: u8 = 0x01;
+static TLS_RECORD_HEADER_LEN: usize = 5;
+static TLS_MAX_RECORD_LEN: usize = 16384 + 2048;
+static SERVER_NAME_EXTENSION: u16 = 0x0000;
+static HOST_NAME_TYPE: u8 = 0x00;
+
+/**
+ *Read the first TLS record without terminating TLS, the bytes are returned so they could be replayed to the backend.
+ */
+pub async fn read_client_hello<R>(reader: &mut R) -> Result<Vec<u8>, AppError>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut record = vec![0; TLS_RECORD_HEADER_LEN];
+    reader
+        .read_exact(&mut record)
+        .await
+        .map_err(|e| AppError::from(e.to_string()))?;
+    if record[0] != TLS_HANDSHAKE_RECORD {
+        return Err(AppError::from(String::from(
+            "The first record is not a TLS handshake!",
+        )));
+    }
+    let record_len = u16::from_be_bytes([record[3], record[4]]) as usize;
+    if record_len > TLS_MAX_RECORD_LEN {
+        return Err(AppError::from(format!(
+            "The TLS record length {} is too large!",
+            record_len
+        )));
+    }
+    record.resize(TLS_RECORD_HEADER_LEN + record_len, 0);
+    reader
+        .read_exact(&mut record[TLS_RECORD_HEADER_LEN..])
+        .await
+        .map_err(|e| AppError::from(e.to_string()))?;
+    Ok(record)
+}
+struct Cursor<'a> {
+    data: &'a [u8],
+}
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Some(head)
+    }
+    fn read_u8(&mut self) -> Option<u8> {
+        self.take(1).map(|item| item[0])
+    }
+    fn read_u16(&mut self) -> Option<u16> {
+        self.take(2)
+            .map(|item| u16::from_be_bytes([item[0], item[1]]))
+    }
+    fn read_vec_u8(&mut self) -> Option<&'a [u8]> {
+        let len = self.read_u8()? as usize;
+        self.take(len)
+    }
+    fn read_vec_u16(&mut self) -> Option<&'a [u8]> {
+        let len = self.read_u16()? as usize;
+        self.take(len)
+    }
+}
+/**
+ *Return the host name of the server_name extension in the ClientHello record.
+ */
+pub fn parse_server_name(record: &[u8]) -> Option<String> {
+    let mut record = Cursor {
+        data: record.get(TLS_RECORD_HEADER_LEN..)?,
+    };
+    if record.read_u8()? != TLS_CLIENT_HELLO {
+        return None;
+    }
+    let handshake_len = record.take(3)?;
+    let handshake_len =
+        u32::from_be_bytes([0, handshake_len[0], handshake_len[1], handshake_len[2]]) as usize;
+    let mut client_hello = Cursor {
+        data: record.take(handshake_len)?,
+    };
+    client_hello.take(2 + 32)?;
+    client_hello.read_vec_u8()?;
+    client_hello.read_vec_u16()?;
+    client_hello.read_vec_u8()?;
+    let mut extensions = Cursor {
+        data: client_hello.read_vec_u16()?,
+    };
+    while !extensions.data.is_empty() {
+        let extension_type = extensions.read_u16()?;
+        let extension_data = extensions.read_vec_u16()?;
+        if extension_type != SERVER_NAME_EXTENSION {
+            continue;
+        }
+        let mut server_names = Cursor {
+            data: Cursor {
+                data: extension_data,
+            }
+            .read_vec_u16()?,
+        };
+        while !server_names.data.is_empty() {
+            let name_type = server_names.read_u8()?;
+            let name = server_names.read_vec_u16()?;
+            if name_type == HOST_NAME_TYPE {
+                return String::from_utf8(name.to_vec())
+                    .ok()
+                    .map(|item| item.to_ascii_lowercase());
+            }
+        }
+    }
+    None
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::tls_policy::tests::AcceptAnyCert;
+    use rustls::ClientConfig;
+    use rustls_pki_types::ServerName;
+    use std::sync::Arc;
+    use tokio_rustls::TlsConnector;
+    async fn capture_client_hello(server_name: &'static str) -> Vec<u8> {
+        let client_config = ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+            .with_no_client_auth();
+        let (client_stream, mut server_stream) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let connector = TlsConnector::from(Arc::new(client_config));
+            let _ = connector
+                .connect(ServerName::try_from(server_name).unwrap(), client_stream)
+                .await;
+        });
+        read_client_hello(&mut server_stream).await.unwrap()
+    }
+    #[tokio::test]
+    async fn test_parse_server_name() {
+        let client_hello = capture_client_hello("Www.Example.com").await;
+        assert_eq!(
+            parse_server_name(&client_hello),
+            Some(String::from("www.example.com"))
+        );
+        let client_hello = capture_client_hello("127.0.0.1").await;
+        assert_eq!(parse_server_name(&client_hello), None);
+        assert_eq!(parse_server_name(&client_hello[..20]), None);
+    }
+    #[tokio::test]
+    async fn test_read_client_hello_not_tls() {
+        let mut reader = &b"GET / HTTP/1.1\r\n\r\n"[..];
+        assert!(read_client_hello(&mut reader).await.is_err());
+    }
+}
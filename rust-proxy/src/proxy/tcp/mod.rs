@@ -1 +1,2 @@
+pub mod client_hello;
 pub mod tcp_proxy;
@@ -1,12 +1,26 @@
 use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
-use crate::vojo::app_error::AppError;
+use crate::configuration_service::readiness::mark_listener_bound;
+use crate::constants::common_constants::DEFAULT_CLIENT_HELLO_TIMEOUT;
+use crate::proxy::listener::{apply_stream_options, listen_addrs, MultiListener};
+use crate::proxy::tcp::client_hello::parse_server_name;
+use crate::proxy::tcp::client_hello::read_client_hello;
+use crate::vojo::app_config::RouteAccess;
+use crate::vojo::app_error::{AppError, AppErrorKind};
 use futures::FutureExt;
 use http::HeaderMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::io;
-use tokio::io::AsyncWriteExt;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
 use tokio::sync::mpsc;
+use tokio::sync::Semaphore;
+use tokio::time::sleep;
+use tokio::time::timeout;
+
+static TCP_COPY_BUFFER_SIZE: u64 = 8 * 1024;
 pub struct TcpProxy {
     pub port: i32,
     pub mapping_key: String,
@@ -14,20 +28,49 @@ pub struct TcpProxy {
 }
 impl TcpProxy {
     pub async fn start_proxy(&mut self) -> Result<(), AppError> {
-        let listen_addr = format!("0.0.0.0:{}", self.port.clone());
         let mapping_key_clone = self.mapping_key.clone();
-        info!("Listening on: {}", listen_addr);
-        let listener = TcpListener::bind(listen_addr)
-            .await
-            .map_err(|e| AppError(e.to_string()))?;
+        let (socket_options, tcp_limit, bind_addresses) = GLOBAL_CONFIG_MAPPING
+            .get(&mapping_key_clone)
+            .map(|item| {
+                (
+                    item.service_config
+                        .socket_options
+                        .clone()
+                        .unwrap_or_default(),
+                    item.service_config
+                        .tcp
+                        .as_ref()
+                        .and_then(|tcp_config| tcp_config.limit.clone())
+                        .unwrap_or_default(),
+                    item.service_config.bind_addresses.clone(),
+                )
+            })
+            .unwrap_or_default();
+        let listen_addrs = listen_addrs(bind_addresses.as_deref(), self.port as u16);
+        info!("Listening on: {:?}", listen_addrs);
+        let connection_limit = tcp_limit
+            .max_connections
+            .map(|max_connections| Arc::new(Semaphore::new(max_connections)));
+        let mut listener = MultiListener::bind(&listen_addrs, &socket_options)?;
+        mark_listener_bound(&mapping_key_clone);
         let reveiver = &mut self.channel;
         loop {
             let accept_future = listener.accept();
             tokio::select! {
                accept_result=accept_future=>{
-                if let Ok((inbound, socket_addr))=accept_result{
+                if let Some((inbound, socket_addr))=accept_result{
+                   let permit = match connection_limit.clone().map(|item| item.try_acquire_owned()) {
+                        Some(Err(_)) => {
+                            warn!("Refuse the connection from {},the connections of {} reach the limit!", socket_addr, mapping_key_clone);
+                            continue;
+                        }
+                        Some(Ok(permit)) => Some(permit),
+                        None => None,
+                   };
+                   apply_stream_options(&inbound, &socket_options);
                    check(mapping_key_clone.clone(),socket_addr).await?;
-                   let transfer = transfer(inbound, mapping_key_clone.clone()).map(|r| {
+                   let transfer = transfer(inbound, mapping_key_clone.clone(), socket_addr).map(|r| {
+                        drop(permit);
                         if let Err(e) = r {
                             println!("Failed to transfer,error is {}", e);
                         }
@@ -44,21 +87,70 @@ impl TcpProxy {
     }
 }
 
-async fn transfer(mut inbound: TcpStream, mapping_key: String) -> Result<(), AppError> {
-    let proxy_addr = get_route_cluster(mapping_key).await?;
+async fn transfer(
+    mut inbound: TcpStream,
+    mapping_key: String,
+    remote_addr: SocketAddr,
+) -> Result<(), AppError> {
+    let tcp_config = GLOBAL_CONFIG_MAPPING
+        .get(&mapping_key)
+        .and_then(|item| item.service_config.tcp.clone())
+        .unwrap_or_default();
+    let bytes_per_second = tcp_config
+        .limit
+        .as_ref()
+        .and_then(|tcp_limit| tcp_limit.bytes_per_second);
+    let client_hello = if let Some(tls_passthrough) = tcp_config.tls_passthrough.as_ref() {
+        let client_hello_timeout = Duration::from_secs(
+            tls_passthrough
+                .client_hello_timeout
+                .unwrap_or(DEFAULT_CLIENT_HELLO_TIMEOUT),
+        );
+        timeout(client_hello_timeout, read_client_hello(&mut inbound))
+            .await
+            .map_err(|_| {
+                AppError::new(
+                    AppErrorKind::Timeout,
+                    format!("Read the client hello from {} timeout!", remote_addr),
+                )
+            })??
+    } else {
+        vec![]
+    };
+    let server_name = parse_server_name(&client_hello);
+    let proxy_addr = get_route_cluster(
+        mapping_key,
+        remote_addr,
+        tcp_config.tls_passthrough.is_some(),
+        server_name.as_deref(),
+    )
+    .await?;
     let mut outbound = TcpStream::connect(proxy_addr)
         .await
-        .map_err(|err| AppError(err.to_string()))?;
+        .map_err(|err| AppError::from(err.to_string()))?;
+    if let Some(proxy_protocol) = tcp_config.proxy_protocol {
+        let local_addr = inbound
+            .local_addr()
+            .map_err(|err| AppError::from(err.to_string()))?;
+        outbound
+            .write_all(&proxy_protocol.build_header(remote_addr, local_addr))
+            .await
+            .map_err(|err| AppError::from(err.to_string()))?;
+    }
+    outbound
+        .write_all(&client_hello)
+        .await
+        .map_err(|err| AppError::from(err.to_string()))?;
 
     let (mut ri, mut wi) = inbound.split();
     let (mut ro, mut wo) = outbound.split();
     let client_to_server = async {
-        io::copy(&mut ri, &mut wo).await?;
+        copy_with_rate_limit(&mut ri, &mut wo, bytes_per_second).await?;
         wo.shutdown().await
     };
 
     let server_to_client = async {
-        io::copy(&mut ro, &mut wi).await?;
+        copy_with_rate_limit(&mut ro, &mut wi, bytes_per_second).await?;
         wi.shutdown().await
     };
 
@@ -70,35 +162,99 @@ async fn transfer(mut inbound: TcpStream, mapping_key: String) -> Result<(), App
 
     Ok(())
 }
+/**
+ *Pace the copy so the bytes sent never run ahead of the configured bytes per second.
+ */
+async fn copy_with_rate_limit<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    bytes_per_second: Option<u64>,
+) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let Some(bytes_per_second) = bytes_per_second.filter(|item| *item > 0) else {
+        return io::copy(reader, writer).await;
+    };
+    let mut buf = vec![0; (bytes_per_second / 10).clamp(1, TCP_COPY_BUFFER_SIZE) as usize];
+    let start_time = Instant::now();
+    let mut transferred = 0;
+    loop {
+        let len = reader.read(&mut buf).await?;
+        if len == 0 {
+            writer.flush().await?;
+            return Ok(transferred);
+        }
+        writer.write_all(&buf[..len]).await?;
+        transferred += len as u64;
+        let expected = Duration::from_secs_f64(transferred as f64 / bytes_per_second as f64);
+        let elapsed = start_time.elapsed();
+        if expected > elapsed {
+            sleep(expected - elapsed).await;
+        }
+    }
+}
 async fn check(mapping_key: String, remote_addr: SocketAddr) -> Result<bool, AppError> {
     let value = GLOBAL_CONFIG_MAPPING
         .get(&mapping_key)
         .ok_or("Can not get apiservice from global_mapping")
-        .map_err(|err| AppError(err.to_string()))?;
+        .map_err(|err| AppError::from(err.to_string()))?;
     let service_config = &value.service_config.routes.clone();
     let service_config_clone = service_config.clone();
     if service_config_clone.is_empty() {
-        return Err(AppError(String::from("The len of routes is 0")));
+        return Err(AppError::from(String::from("The len of routes is 0")));
     }
     let route = service_config_clone.first().unwrap();
-    let is_allowed = route
+    let route_access = route
         .clone()
         .is_allowed(remote_addr.ip().to_string(), None)
         .await?;
-    Ok(is_allowed)
+    Ok(route_access == RouteAccess::Allowed)
 }
-async fn get_route_cluster(mapping_key: String) -> Result<String, AppError> {
+/**
+ *The TLS passthrough selects the first route whose host_name matches the SNI, otherwise the first route is used.
+ */
+async fn get_route_cluster(
+    mapping_key: String,
+    remote_addr: SocketAddr,
+    tls_passthrough: bool,
+    server_name: Option<&str>,
+) -> Result<String, AppError> {
     let value = GLOBAL_CONFIG_MAPPING
         .get(&mapping_key)
         .ok_or("Can not get apiservice from global_mapping")
-        .map_err(|err| AppError(err.to_string()))?;
+        .map_err(|err| AppError::from(err.to_string()))?;
     let service_config = &value.service_config.routes.clone();
     let service_config_clone = service_config.clone();
+    drop(value);
     if service_config_clone.is_empty() {
-        return Err(AppError(String::from("The len of routes is 0")));
+        return Err(AppError::from(String::from("The len of routes is 0")));
     }
-    let mut route = service_config_clone.first().unwrap().route_cluster.clone();
-    route.get_route(HeaderMap::new()).await.map(|s| s.endpoint)
+    let mut matched_route = None;
+    for item in service_config_clone.iter() {
+        if !tls_passthrough || item.match_server_name(server_name)? {
+            matched_route = Some(item);
+            break;
+        }
+    }
+    let matched_route = matched_route.ok_or(AppError::new(
+        AppErrorKind::NotFound,
+        format!(
+            "Can not find the route for the server name {:?}",
+            server_name
+        ),
+    ))?;
+    matched_route
+        .route_cluster
+        .clone()
+        .get_route(
+            HeaderMap::new(),
+            remote_addr,
+            matched_route.liveness_config.as_ref(),
+        )
+        .await
+        .map(|s| s.endpoint)
 }
 
 #[cfg(test)]
@@ -111,16 +267,25 @@ mod tests {
     use crate::vojo::api_service_manager::ApiServiceManager;
     use crate::vojo::app_config::ApiService;
     use crate::vojo::app_config::LivenessStatus;
-    use crate::vojo::app_config::Matcher;
+    use crate::vojo::app_config::{Matcher, PathMatchType};
     use crate::vojo::app_config::{Route, ServiceConfig};
+    use crate::vojo::app_config::{TcpConfig, TcpLimitConfig, TlsPassthroughConfig};
+    use crate::vojo::proxy_protocol::ProxyProtocolVersion;
     use crate::vojo::route::AnomalyDetectionStatus;
     use crate::vojo::route::{BaseRoute, LoadbalancerStrategy, RandomBaseRoute, RandomRoute};
+    use crate::vojo::tls_policy::build_server_config;
+    use crate::vojo::tls_policy::tests::{first_cert_der, read_test_pem, AcceptAnyCert};
+    use crate::vojo::tls_policy::SniCertResolver;
     use lazy_static::lazy_static;
+    use rustls::ClientConfig;
+    use rustls_pki_types::ServerName;
     use std::net::TcpListener;
     use std::net::{IpAddr, Ipv4Addr};
     use std::sync::Arc;
     use std::{thread, time, vec};
+    use tokio::io::AsyncReadExt;
     use tokio::sync::RwLock;
+    use tokio_rustls::{TlsAcceptor, TlsConnector};
 
     use tokio::runtime::{Builder, Runtime};
 
@@ -157,7 +322,8 @@ mod tests {
     fn test_transfer_error() {
         TOKIO_RUNTIME.spawn(async {
             let tcp_stream = TcpStream::connect("httpbin.org:80").await.unwrap();
-            let result = transfer(tcp_stream, String::from("test")).await;
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let result = transfer(tcp_stream, String::from("test"), socket).await;
             assert!(result.is_err());
         });
         let sleep_time = time::Duration::from_millis(2000);
@@ -170,10 +336,18 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("httpbin.org:80"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
             }],
         });
@@ -183,39 +357,357 @@ mod tests {
             let api_service_manager = ApiServiceManager {
                 sender,
                 service_config: ServiceConfig {
-                    key_str: None,
                     server_type: crate::vojo::app_config::ServiceType::Tcp,
-                    cert_str: None,
                     routes: vec![Route {
                         host_name: None,
                         route_id: get_uuid(),
                         matcher: Default::default(),
                         route_cluster: route,
                         allow_deny_list: None,
+                        allow_deny_default: None,
+                        client_ip: None,
                         authentication: None,
                         ratelimit: None,
+                        combined_limit: None,
+                        bulkhead: None,
                         health_check: None,
                         anomaly_detection: None,
                         rewrite_headers: None,
+                        query_rewrite: None,
+                        response_headers: None,
+                        forward_headers: None,
+                        mirror: None,
+                        timeout: None,
+                        circuit_breaker: None,
+                        hedge: None,
+                        retry: None,
+                        body_logging: None,
+                        maintenance: None,
+                        cache: None,
+                        single_flight: None,
+                        websocket: None,
 
                         liveness_config: None,
                         liveness_status: Arc::new(RwLock::new(LivenessStatus {
                             current_liveness_count: 0,
                         })),
                     }],
+                    ..Default::default()
                 },
             };
             GLOBAL_CONFIG_MAPPING.insert(String::from("test123"), api_service_manager);
             let tcp_stream = TcpStream::connect("httpbin.org:80").await.unwrap();
-            let result = transfer(tcp_stream, String::from("test123")).await;
+            let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+            let result = transfer(tcp_stream, String::from("test123"), socket).await;
             assert!(result.is_ok());
         });
         let sleep_time = time::Duration::from_millis(2000);
         thread::sleep(sleep_time);
     }
+    fn create_tcp_service_config(endpoint: &str) -> ServiceConfig {
+        ServiceConfig {
+            server_type: crate::vojo::app_config::ServiceType::Tcp,
+            routes: vec![Route {
+                host_name: None,
+                route_id: get_uuid(),
+                matcher: Default::default(),
+                route_cluster: LoadbalancerStrategy::Random(RandomRoute {
+                    routes: vec![RandomBaseRoute {
+                        base_route: BaseRoute {
+                            endpoint: String::from(endpoint),
+                            ..Default::default()
+                        },
+                    }],
+                }),
+                allow_deny_list: None,
+                allow_deny_default: None,
+                client_ip: None,
+                authentication: None,
+                ratelimit: None,
+                combined_limit: None,
+                bulkhead: None,
+                health_check: None,
+                anomaly_detection: None,
+                rewrite_headers: None,
+                query_rewrite: None,
+                response_headers: None,
+                forward_headers: None,
+                mirror: None,
+                timeout: None,
+                circuit_breaker: None,
+                hedge: None,
+                retry: None,
+                body_logging: None,
+                maintenance: None,
+                cache: None,
+                single_flight: None,
+                websocket: None,
+                liveness_config: None,
+                liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                    current_liveness_count: 0,
+                })),
+            }],
+            ..Default::default()
+        }
+    }
+    #[tokio::test]
+    async fn test_proxy_protocol_header_sent_to_backend() {
+        for (listen_port, backend_port, proxy_protocol) in [
+            (10091, 10090, ProxyProtocolVersion::V1),
+            (10093, 10092, ProxyProtocolVersion::V2),
+        ] {
+            let backend = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", backend_port))
+                .await
+                .unwrap();
+            let mapping_key = format!("{}-TCP", listen_port);
+            let (sender, receiver) = tokio::sync::mpsc::channel(10);
+            let mut service_config =
+                create_tcp_service_config(&format!("127.0.0.1:{}", backend_port));
+            service_config.tcp = Some(TcpConfig {
+                proxy_protocol: Some(proxy_protocol),
+                ..Default::default()
+            });
+            GLOBAL_CONFIG_MAPPING.insert(
+                mapping_key.clone(),
+                ApiServiceManager {
+                    sender: sender.clone(),
+                    service_config,
+                },
+            );
+            let mut tcp_proxy = TcpProxy {
+                port: listen_port,
+                channel: receiver,
+                mapping_key: mapping_key.clone(),
+            };
+            tokio::spawn(async move { tcp_proxy.start_proxy().await });
+            tokio::time::sleep(time::Duration::from_millis(100)).await;
+
+            let mut client = TcpStream::connect(format!("127.0.0.1:{}", listen_port))
+                .await
+                .unwrap();
+            client.write_all(b"hello").await.unwrap();
+            let (mut upstream, _) = backend.accept().await.unwrap();
+            let expected_header = proxy_protocol
+                .build_header(client.local_addr().unwrap(), client.peer_addr().unwrap());
+            let mut received = vec![0; expected_header.len() + 5];
+            upstream.read_exact(&mut received).await.unwrap();
+            assert_eq!(&received[..expected_header.len()], &expected_header[..]);
+            assert_eq!(&received[expected_header.len()..], b"hello");
+            upstream.write_all(b"world").await.unwrap();
+            let mut response = [0; 5];
+            client.read_exact(&mut response).await.unwrap();
+            assert_eq!(&response, b"world");
+
+            sender.send(()).await.unwrap();
+            GLOBAL_CONFIG_MAPPING.remove(&mapping_key);
+        }
+    }
+    #[tokio::test]
+    async fn test_tcp_max_connections() {
+        let backend = tokio::net::TcpListener::bind("127.0.0.1:10094")
+            .await
+            .unwrap();
+        let mut service_config = create_tcp_service_config("127.0.0.1:10094");
+        service_config.tcp = Some(TcpConfig {
+            limit: Some(TcpLimitConfig {
+                max_connections: Some(1),
+                bytes_per_second: None,
+            }),
+            ..Default::default()
+        });
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10095-TCP"),
+            ApiServiceManager {
+                sender: sender.clone(),
+                service_config,
+            },
+        );
+        let mut tcp_proxy = TcpProxy {
+            port: 10095,
+            channel: receiver,
+            mapping_key: String::from("10095-TCP"),
+        };
+        tokio::spawn(async move { tcp_proxy.start_proxy().await });
+        tokio::time::sleep(time::Duration::from_millis(100)).await;
+
+        let mut first_client = TcpStream::connect("127.0.0.1:10095").await.unwrap();
+        let (mut upstream, _) = backend.accept().await.unwrap();
+        let mut second_client = TcpStream::connect("127.0.0.1:10095").await.unwrap();
+        let mut buf = [0; 5];
+        let read_result =
+            tokio::time::timeout(time::Duration::from_secs(2), second_client.read(&mut buf))
+                .await
+                .unwrap();
+        assert!(matches!(read_result, Ok(0) | Err(_)));
+
+        first_client.write_all(b"hello").await.unwrap();
+        upstream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+        drop(first_client);
+        drop(upstream);
+        tokio::time::sleep(time::Duration::from_millis(100)).await;
+        let _third_client = TcpStream::connect("127.0.0.1:10095").await.unwrap();
+        assert!(
+            tokio::time::timeout(time::Duration::from_secs(2), backend.accept())
+                .await
+                .is_ok()
+        );
+
+        sender.send(()).await.unwrap();
+        GLOBAL_CONFIG_MAPPING.remove("10095-TCP");
+    }
+    #[tokio::test]
+    async fn test_copy_with_rate_limit() {
+        let data = vec![7; 40000];
+        let mut reader = &data[..];
+        let mut writer = vec![];
+        let start_time = Instant::now();
+        let transferred = copy_with_rate_limit(&mut reader, &mut writer, Some(20000))
+            .await
+            .unwrap();
+        let elapsed = start_time.elapsed();
+        assert_eq!(transferred, 40000);
+        assert_eq!(writer, data);
+        assert!(elapsed >= Duration::from_millis(1900), "{:?}", elapsed);
+        assert!(transferred as f64 / elapsed.as_secs_f64() <= 20000.0 * 1.05);
+
+        let mut reader = &data[..];
+        let mut writer = vec![];
+        let start_time = Instant::now();
+        copy_with_rate_limit(&mut reader, &mut writer, None)
+            .await
+            .unwrap();
+        assert!(start_time.elapsed() < Duration::from_millis(500));
+        assert_eq!(writer, data);
+    }
+    async fn start_tls_backend(port: u16, name: &'static str) {
+        let cert_resolver = SniCertResolver::new(
+            &read_test_pem(&format!("test_sni_{}_cert.pem", name)),
+            &read_test_pem(&format!("test_sni_{}_key.pem", name)),
+            &[],
+        )
+        .unwrap();
+        let server_config = build_server_config(None, Arc::new(cert_resolver), vec![]).unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(server_config));
+        let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut tls_stream = acceptor.accept(stream).await.unwrap();
+                let mut buf = [0; 4];
+                tls_stream.read_exact(&mut buf).await.unwrap();
+                tls_stream
+                    .write_all(format!("pong-{}", name).as_bytes())
+                    .await
+                    .unwrap();
+                tls_stream.flush().await.unwrap();
+            }
+        });
+    }
+    #[tokio::test]
+    async fn test_tls_passthrough_routes_by_sni() {
+        start_tls_backend(10096, "a").await;
+        start_tls_backend(10097, "b").await;
+        let mut service_config = create_tcp_service_config("127.0.0.1:10096");
+        let mut route_b = create_tcp_service_config("127.0.0.1:10097")
+            .routes
+            .remove(0);
+        service_config.routes[0].host_name = Some(String::from("^a\\.example\\.com$"));
+        route_b.host_name = Some(String::from("^b\\.example\\.com$"));
+        service_config.routes.push(route_b);
+        service_config.tcp = Some(TcpConfig {
+            tls_passthrough: Some(TlsPassthroughConfig::default()),
+            ..Default::default()
+        });
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10098-TCP"),
+            ApiServiceManager {
+                sender: sender.clone(),
+                service_config,
+            },
+        );
+        let mut tcp_proxy = TcpProxy {
+            port: 10098,
+            channel: receiver,
+            mapping_key: String::from("10098-TCP"),
+        };
+        tokio::spawn(async move { tcp_proxy.start_proxy().await });
+        tokio::time::sleep(time::Duration::from_millis(100)).await;
+
+        let client_config = ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+            .with_no_client_auth();
+        let connector = TlsConnector::from(Arc::new(client_config));
+        for (server_name, name) in [("a.example.com", "a"), ("b.example.com", "b")] {
+            let stream = TcpStream::connect("127.0.0.1:10098").await.unwrap();
+            let mut tls_stream = connector
+                .connect(ServerName::try_from(server_name).unwrap(), stream)
+                .await
+                .unwrap();
+            let peer_cert = tls_stream.get_ref().1.peer_certificates().unwrap()[0].clone();
+            assert_eq!(
+                peer_cert,
+                first_cert_der(&read_test_pem(&format!("test_sni_{}_cert.pem", name)))
+            );
+            tls_stream.write_all(b"ping").await.unwrap();
+            let mut buf = [0; 6];
+            tls_stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, format!("pong-{}", name).as_bytes());
+        }
+        let stream = TcpStream::connect("127.0.0.1:10098").await.unwrap();
+        assert!(connector
+            .connect(ServerName::try_from("c.example.com").unwrap(), stream)
+            .await
+            .is_err());
+
+        sender.send(()).await.unwrap();
+        GLOBAL_CONFIG_MAPPING.remove("10098-TCP");
+    }
+    #[tokio::test]
+    async fn test_tls_passthrough_client_hello_timeout() {
+        let mut service_config = create_tcp_service_config("127.0.0.1:10149");
+        service_config.tcp = Some(TcpConfig {
+            tls_passthrough: Some(TlsPassthroughConfig {
+                client_hello_timeout: Some(1),
+            }),
+            ..Default::default()
+        });
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10150-TCP"),
+            ApiServiceManager {
+                sender: sender.clone(),
+                service_config,
+            },
+        );
+        let mut tcp_proxy = TcpProxy {
+            port: 10150,
+            channel: receiver,
+            mapping_key: String::from("10150-TCP"),
+        };
+        tokio::spawn(async move { tcp_proxy.start_proxy().await });
+        tokio::time::sleep(time::Duration::from_millis(100)).await;
+
+        let mut stream = TcpStream::connect("127.0.0.1:10150").await.unwrap();
+        stream.write_all(&[0x16, 0x03, 0x01]).await.unwrap();
+        let mut buf = [0; 16];
+        let read_result = timeout(time::Duration::from_secs(3), stream.read(&mut buf))
+            .await
+            .unwrap();
+        assert!(matches!(read_result, Ok(0) | Err(_)));
+
+        sender.send(()).await.unwrap();
+        GLOBAL_CONFIG_MAPPING.remove("10150-TCP");
+    }
     #[tokio::test]
     async fn test_get_route_cluster_error() {
-        let result = get_route_cluster(String::from("testxxxx")).await;
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let result = get_route_cluster(String::from("testxxxx"), socket, false, None).await;
         assert!(result.is_err());
     }
 
@@ -226,10 +718,18 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("httpbin.org:80"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
             }],
         });
@@ -238,25 +738,44 @@ mod tests {
         let api_service_manager = ApiServiceManager {
             sender,
             service_config: ServiceConfig {
-                key_str: None,
                 server_type: crate::vojo::app_config::ServiceType::Tcp,
-                cert_str: None,
                 routes: vec![Route {
                     host_name: None,
                     route_id: get_uuid(),
                     matcher: Some(Matcher {
                         prefix: String::from("/"),
                         prefix_rewrite: String::from("test"),
+                        match_type: PathMatchType::Prefix,
+                        paths: None,
+                        methods: None,
+                        schemes: None,
                     }),
                     route_cluster: route,
                     allow_deny_list: Some(vec![AllowDenyObject {
                         limit_type: AllowType::DenyAll,
                         value: None,
                     }]),
+                    allow_deny_default: None,
+                    client_ip: None,
                     authentication: None,
                     ratelimit: None,
+                    combined_limit: None,
+                    bulkhead: None,
                     health_check: None,
                     rewrite_headers: None,
+                    query_rewrite: None,
+                    response_headers: None,
+                    forward_headers: None,
+                    mirror: None,
+                    timeout: None,
+                    circuit_breaker: None,
+                    hedge: None,
+                    retry: None,
+                    body_logging: None,
+                    maintenance: None,
+                    cache: None,
+                    single_flight: None,
+                    websocket: None,
 
                     anomaly_detection: None,
                     liveness_status: Arc::new(RwLock::new(LivenessStatus {
@@ -264,6 +783,7 @@ mod tests {
                     })),
                     liveness_config: None,
                 }],
+                ..Default::default()
             },
         };
         let mut write = GLOBAL_APP_CONFIG.write().await;
@@ -285,10 +805,18 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("httpbin.org:80"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
             }],
         });
@@ -297,31 +825,51 @@ mod tests {
         let api_service_manager = ApiServiceManager {
             sender,
             service_config: ServiceConfig {
-                key_str: None,
                 server_type: crate::vojo::app_config::ServiceType::Tcp,
-                cert_str: None,
                 routes: vec![Route {
                     host_name: None,
                     route_id: get_uuid(),
                     matcher: Some(Matcher {
                         prefix: String::from("/"),
                         prefix_rewrite: String::from("test"),
+                        match_type: PathMatchType::Prefix,
+                        paths: None,
+                        methods: None,
+                        schemes: None,
                     }),
                     route_cluster: route,
                     allow_deny_list: Some(vec![AllowDenyObject {
                         limit_type: AllowType::Deny,
                         value: Some(String::from("127.0.0.1")),
                     }]),
+                    allow_deny_default: None,
+                    client_ip: None,
                     authentication: None,
                     health_check: None,
                     ratelimit: None,
+                    combined_limit: None,
+                    bulkhead: None,
                     anomaly_detection: None,
                     rewrite_headers: None,
+                    query_rewrite: None,
+                    response_headers: None,
+                    forward_headers: None,
+                    mirror: None,
+                    timeout: None,
+                    circuit_breaker: None,
+                    hedge: None,
+                    retry: None,
+                    body_logging: None,
+                    maintenance: None,
+                    cache: None,
+                    single_flight: None,
+                    websocket: None,
                     liveness_config: None,
                     liveness_status: Arc::new(RwLock::new(LivenessStatus {
                         current_liveness_count: 0,
                     })),
                 }],
+                ..Default::default()
             },
         };
         let mut write = GLOBAL_APP_CONFIG.write().await;
@@ -0,0 +1 @@
+pub mod udp_proxy;
@@ -0,0 +1,397 @@
+use crate::configuration_service::app_config_service::GLOBAL_CONFIG_MAPPING;
+use crate::configuration_service::readiness::mark_listener_bound;
+use crate::constants::common_constants::DEFAULT_UDP_IDLE_TIMEOUT;
+use crate::constants::common_constants::DEFAULT_UDP_MAX_SESSIONS;
+use crate::proxy::listener::listen_addrs;
+use crate::vojo::app_config::RouteAccess;
+use crate::vojo::app_error::AppError;
+use dashmap::DashMap;
+use http::HeaderMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::task::JoinSet;
+use tokio::time::sleep_until;
+
+static MAX_DATAGRAM_SIZE: usize = 65535;
+static SESSION_CHANNEL_SIZE: usize = 64;
+pub struct UdpProxy {
+    pub port: i32,
+    pub mapping_key: String,
+    pub channel: mpsc::Receiver<()>,
+}
+struct UdpSession {
+    sender: mpsc::Sender<Vec<u8>>,
+    join_handle: JoinHandle<()>,
+}
+#[derive(Clone)]
+struct SessionContext {
+    mapping_key: String,
+    socket: Arc<UdpSocket>,
+    sessions: Arc<DashMap<SocketAddr, UdpSession>>,
+    idle_timeout: Duration,
+    max_sessions: usize,
+}
+impl UdpProxy {
+    pub async fn start_proxy(&mut self) -> Result<(), AppError> {
+        let (udp_config, bind_addresses) = GLOBAL_CONFIG_MAPPING
+            .get(&self.mapping_key)
+            .map(|item| {
+                (
+                    item.service_config.udp.clone().unwrap_or_default(),
+                    item.service_config.bind_addresses.clone(),
+                )
+            })
+            .unwrap_or_default();
+        let idle_timeout =
+            Duration::from_secs(udp_config.idle_timeout.unwrap_or(DEFAULT_UDP_IDLE_TIMEOUT));
+        let max_sessions = udp_config.max_sessions.unwrap_or(DEFAULT_UDP_MAX_SESSIONS);
+        let addrs = listen_addrs(bind_addresses.as_deref(), self.port as u16);
+        let mut sockets = vec![];
+        for addr in addrs.iter() {
+            sockets.push(Arc::new(
+                UdpSocket::bind(addr)
+                    .await
+                    .map_err(|e| AppError::from(e.to_string()))?,
+            ));
+        }
+        mark_listener_bound(&self.mapping_key);
+        info!("Listening on udp://{:?}", addrs);
+        let sessions: Arc<DashMap<SocketAddr, UdpSession>> = Default::default();
+        let mut receive_tasks = JoinSet::new();
+        for socket in sockets {
+            receive_tasks.spawn(receive_datagrams(SessionContext {
+                mapping_key: self.mapping_key.clone(),
+                socket,
+                sessions: sessions.clone(),
+                idle_timeout,
+                max_sessions,
+            }));
+        }
+        self.channel.recv().await;
+        receive_tasks.shutdown().await;
+        let client_addrs: Vec<SocketAddr> = sessions.iter().map(|item| *item.key()).collect();
+        for client_addr in client_addrs {
+            if let Some((_, session)) = sessions.remove(&client_addr) {
+                session.join_handle.abort();
+                let _ = session.join_handle.await;
+            }
+        }
+        info!("close the socket of udp!");
+        Ok(())
+    }
+}
+/**
+ *Every bound address has its own receive loop,the sessions are shared by all of them.
+ *The loop only hands the datagram to the session,so a slow upstream could not block the other clients.
+ */
+async fn receive_datagrams(context: SessionContext) {
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        let (len, client_addr) = match context.socket.recv_from(&mut buf).await {
+            Ok(item) => item,
+            Err(err) => {
+                error!("Receive the datagram error,the error is {}", err);
+                continue;
+            }
+        };
+        let existing = context
+            .sessions
+            .get(&client_addr)
+            .map(|item| item.sender.clone());
+        let sender = match existing {
+            Some(sender) => sender,
+            None if context.sessions.len() >= context.max_sessions => {
+                warn!(
+                    "The udp sessions reach the limit {},the datagram from {} is dropped.",
+                    context.max_sessions, client_addr
+                );
+                continue;
+            }
+            None => create_session(context.clone(), client_addr),
+        };
+        if sender.try_send(buf[..len].to_vec()).is_err() {
+            debug!(
+                "The udp session of {} is busy,the datagram is dropped.",
+                client_addr
+            );
+        }
+    }
+}
+fn create_session(context: SessionContext, client_addr: SocketAddr) -> mpsc::Sender<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel(SESSION_CHANNEL_SIZE);
+    let sessions = context.sessions.clone();
+    let join_handle = tokio::spawn(async move {
+        if let Err(err) = run_session(&context, client_addr, receiver).await {
+            error!(
+                "Relay the datagram from {} error,the error is {}",
+                client_addr, err
+            );
+        }
+        context.sessions.remove(&client_addr);
+    });
+    sessions.insert(
+        client_addr,
+        UdpSession {
+            sender: sender.clone(),
+            join_handle,
+        },
+    );
+    sender
+}
+async fn connect_upstream(
+    mapping_key: &str,
+    client_addr: SocketAddr,
+) -> Result<UdpSocket, AppError> {
+    let route = GLOBAL_CONFIG_MAPPING
+        .get(mapping_key)
+        .and_then(|item| item.service_config.routes.first().cloned())
+        .ok_or(AppError::from(String::from("The len of routes is 0")))?;
+    if route.is_allowed(client_addr.ip().to_string(), None).await? != RouteAccess::Allowed {
+        return Err(AppError::from(String::from("The client is not allowed!")));
+    }
+    let endpoint = route
+        .route_cluster
+        .clone()
+        .get_route(
+            HeaderMap::new(),
+            client_addr,
+            route.liveness_config.as_ref(),
+        )
+        .await?
+        .endpoint;
+    let upstream = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| AppError::from(e.to_string()))?;
+    upstream
+        .connect(&endpoint)
+        .await
+        .map_err(|e| AppError::from(e.to_string()))?;
+    Ok(upstream)
+}
+/**
+ *Each client address gets its own upstream socket, so the replies could be returned to the original client.
+ */
+async fn run_session(
+    context: &SessionContext,
+    client_addr: SocketAddr,
+    mut receiver: mpsc::Receiver<Vec<u8>>,
+) -> Result<(), AppError> {
+    let upstream = connect_upstream(&context.mapping_key, client_addr).await?;
+    let mut buf = vec![0; MAX_DATAGRAM_SIZE];
+    let mut last_active = Instant::now();
+    loop {
+        tokio::select! {
+            Some(datagram) = receiver.recv() => {
+                last_active = Instant::now();
+                upstream
+                    .send(&datagram)
+                    .await
+                    .map_err(|e| AppError::from(e.to_string()))?;
+            },
+            recv_result = upstream.recv(&mut buf) => {
+                let len = recv_result.map_err(|e| AppError::from(e.to_string()))?;
+                last_active = Instant::now();
+                if let Err(err) = context.socket.send_to(&buf[..len], client_addr).await {
+                    error!("Send the datagram to {} error,the error is {}", client_addr, err);
+                }
+            },
+            _ = sleep_until((last_active + context.idle_timeout).into()) => {
+                debug!("The udp session of {} is idle timeout.", client_addr);
+                return Ok(());
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::uuid::get_uuid;
+    use crate::vojo::api_service_manager::ApiServiceManager;
+    use crate::vojo::app_config::LivenessStatus;
+    use crate::vojo::app_config::ServiceType;
+    use crate::vojo::app_config::{Route, ServiceConfig, UdpConfig};
+    use crate::vojo::route::{BaseRoute, LoadbalancerStrategy, RandomBaseRoute, RandomRoute};
+    use tokio::sync::RwLock;
+    fn create_udp_service_config(endpoint: &str) -> ServiceConfig {
+        ServiceConfig {
+            server_type: ServiceType::Udp,
+            udp: Some(UdpConfig {
+                idle_timeout: Some(1),
+                max_sessions: None,
+            }),
+            routes: vec![Route {
+                host_name: None,
+                route_id: get_uuid(),
+                matcher: Default::default(),
+                route_cluster: LoadbalancerStrategy::Random(RandomRoute {
+                    routes: vec![RandomBaseRoute {
+                        base_route: BaseRoute {
+                            endpoint: String::from(endpoint),
+                            ..Default::default()
+                        },
+                    }],
+                }),
+                allow_deny_list: None,
+                allow_deny_default: None,
+                client_ip: None,
+                authentication: None,
+                ratelimit: None,
+                combined_limit: None,
+                bulkhead: None,
+                health_check: None,
+                anomaly_detection: None,
+                rewrite_headers: None,
+                query_rewrite: None,
+                response_headers: None,
+                forward_headers: None,
+                mirror: None,
+                timeout: None,
+                circuit_breaker: None,
+                hedge: None,
+                retry: None,
+                body_logging: None,
+                maintenance: None,
+                cache: None,
+                single_flight: None,
+                websocket: None,
+                liveness_config: None,
+                liveness_status: Arc::new(RwLock::new(LivenessStatus {
+                    current_liveness_count: 0,
+                })),
+            }],
+            ..Default::default()
+        }
+    }
+    #[tokio::test]
+    async fn test_udp_proxy_relay_and_idle_timeout() {
+        let backend = UdpSocket::bind("127.0.0.1:10099").await.unwrap();
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10100-Udp"),
+            ApiServiceManager {
+                sender: sender.clone(),
+                service_config: create_udp_service_config("127.0.0.1:10099"),
+            },
+        );
+        let mut udp_proxy = UdpProxy {
+            port: 10100,
+            channel: receiver,
+            mapping_key: String::from("10100-Udp"),
+        };
+        tokio::spawn(async move { udp_proxy.start_proxy().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.connect("127.0.0.1:10100").await.unwrap();
+        let mut buf = [0; 64];
+        let mut upstream_addrs = vec![];
+        for message in ["hello", "again"] {
+            client.send(message.as_bytes()).await.unwrap();
+            let (len, upstream_addr) = backend.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], message.as_bytes());
+            upstream_addrs.push(upstream_addr);
+            backend
+                .send_to(format!("reply:{}", message).as_bytes(), upstream_addr)
+                .await
+                .unwrap();
+            let len = client.recv(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], format!("reply:{}", message).as_bytes());
+        }
+        assert_eq!(upstream_addrs[0], upstream_addrs[1]);
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        client.send(b"after idle").await.unwrap();
+        let (len, upstream_addr) = backend.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"after idle");
+        assert_ne!(upstream_addr, upstream_addrs[0]);
+
+        sender.send(()).await.unwrap();
+        GLOBAL_CONFIG_MAPPING.remove("10100-Udp");
+    }
+    #[tokio::test]
+    async fn test_udp_proxy_bind_addresses() {
+        let backend = UdpSocket::bind("127.0.0.1:10145").await.unwrap();
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10146-Udp"),
+            ApiServiceManager {
+                sender: sender.clone(),
+                service_config: ServiceConfig {
+                    bind_addresses: Some(vec!["127.0.0.1".parse().unwrap()]),
+                    ..create_udp_service_config("127.0.0.1:10145")
+                },
+            },
+        );
+        let mut udp_proxy = UdpProxy {
+            port: 10146,
+            channel: receiver,
+            mapping_key: String::from("10146-Udp"),
+        };
+        let task = tokio::spawn(async move { udp_proxy.start_proxy().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client.connect("127.0.0.1:10146").await.unwrap();
+        client.send(b"hello").await.unwrap();
+        let mut buf = [0; 64];
+        let (len, _) = backend.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"hello");
+        assert!(UdpSocket::bind("127.0.0.2:10146").await.is_ok());
+
+        sender.send(()).await.unwrap();
+        assert!(task.await.unwrap().is_ok());
+        GLOBAL_CONFIG_MAPPING.remove("10146-Udp");
+    }
+    #[tokio::test]
+    async fn test_udp_proxy_max_sessions() {
+        let backend = UdpSocket::bind("127.0.0.1:10147").await.unwrap();
+        let (sender, receiver) = tokio::sync::mpsc::channel(10);
+        GLOBAL_CONFIG_MAPPING.insert(
+            String::from("10148-Udp"),
+            ApiServiceManager {
+                sender: sender.clone(),
+                service_config: ServiceConfig {
+                    udp: Some(UdpConfig {
+                        idle_timeout: Some(1),
+                        max_sessions: Some(1),
+                    }),
+                    ..create_udp_service_config("127.0.0.1:10147")
+                },
+            },
+        );
+        let mut udp_proxy = UdpProxy {
+            port: 10148,
+            channel: receiver,
+            mapping_key: String::from("10148-Udp"),
+        };
+        tokio::spawn(async move { udp_proxy.start_proxy().await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut buf = [0; 64];
+        let first_client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        first_client.connect("127.0.0.1:10148").await.unwrap();
+        first_client.send(b"first").await.unwrap();
+        let (len, _) = backend.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"first");
+
+        let second_client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        second_client.connect("127.0.0.1:10148").await.unwrap();
+        second_client.send(b"second").await.unwrap();
+        let recv_result =
+            tokio::time::timeout(Duration::from_millis(300), backend.recv_from(&mut buf)).await;
+        assert!(recv_result.is_err());
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        second_client.send(b"after idle").await.unwrap();
+        let (len, _) = backend.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"after idle");
+
+        sender.send(()).await.unwrap();
+        GLOBAL_CONFIG_MAPPING.remove("10148-Udp");
+    }
+}
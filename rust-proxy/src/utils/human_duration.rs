@@ -0,0 +1,283 @@
+use crate::vojo::app_error::AppError;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject, StringValidation};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::time::Duration;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDuration {
+    Number(u64),
+    Text(String),
+}
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+const UNITS: [(&str, Duration); 5] = [
+    ("d", Duration::from_secs(86400)),
+    ("h", Duration::from_secs(3600)),
+    ("m", Duration::from_secs(60)),
+    ("s", Duration::from_secs(1)),
+    ("ms", Duration::from_millis(1)),
+];
+/**
+ *Parse the durations like `100ms`, `10s`, `5m`, `1h`, `1d` and `1h30m15s`, a bare number is counted in the unit of the field.
+ *Each unit could appear once, from the largest to the smallest.
+ */
+pub fn parse_duration(value: &str, unit: Duration) -> Result<Duration, AppError> {
+    let value = value.trim();
+    let invalid = || AppError::from(format!("The duration {} is invalid!", value));
+    if value.is_empty() {
+        return Err(invalid());
+    }
+    if value.chars().all(|item| item.is_ascii_digit()) {
+        return scale(value, unit).ok_or(AppError::from(format!(
+            "The duration {} is too large!",
+            value
+        )));
+    }
+    let mut rest = value;
+    let mut duration = Duration::ZERO;
+    let mut next_unit_index = 0;
+    while !rest.is_empty() {
+        let index = rest
+            .find(|item: char| !item.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (number, suffix) = rest.split_at(index);
+        let unit_len = suffix
+            .find(|item: char| !item.is_ascii_alphabetic())
+            .unwrap_or(suffix.len());
+        let (suffix, remaining) = suffix.split_at(unit_len);
+        if number.is_empty() || suffix.is_empty() {
+            return Err(invalid());
+        }
+        let unit_index =
+            UNITS
+                .iter()
+                .position(|(name, _)| *name == suffix)
+                .ok_or(AppError::from(format!(
+                    "The unit of the duration {} should be one of ms,s,m,h and d!",
+                    value
+                )))?;
+        if unit_index < next_unit_index {
+            return Err(AppError::from(format!(
+                "The units of the duration {} should not be repeated and should be from the largest to the smallest!",
+                value
+            )));
+        }
+        next_unit_index = unit_index + 1;
+        let (_, unit) = UNITS[unit_index];
+        duration = scale(number, unit)
+            .and_then(|item| duration.checked_add(item))
+            .ok_or(AppError::from(format!(
+                "The duration {} is too large!",
+                value
+            )))?;
+        rest = remaining;
+    }
+    Ok(duration)
+}
+fn scale(number: &str, unit: Duration) -> Option<Duration> {
+    let number: u64 = number.parse().ok()?;
+    let nanos = unit.as_nanos().checked_mul(number as u128)?;
+    let seconds = u64::try_from(nanos / NANOS_PER_SECOND).ok()?;
+    Some(Duration::new(seconds, (nanos % NANOS_PER_SECOND) as u32))
+}
+fn to_unit<T: TryFrom<u128>>(value: RawDuration, unit: Duration) -> Result<T, AppError> {
+    let duration = match value {
+        RawDuration::Number(number) => return to_number(number as u128),
+        RawDuration::Text(text) => parse_duration(&text, unit)?,
+    };
+    if duration.as_nanos() % unit.as_nanos() != 0 {
+        return Err(AppError::from(format!(
+            "The duration {:?} is not a whole number of {:?}!",
+            duration, unit
+        )));
+    }
+    to_number(duration.as_nanos() / unit.as_nanos())
+}
+fn to_number<T: TryFrom<u128>>(number: u128) -> Result<T, AppError> {
+    T::try_from(number)
+        .map_err(|_| AppError::from(format!("The duration {} is too large!", number)))
+}
+/**
+ *The schema of the duration fields, which accepts a bare number or a duration string.
+ */
+pub struct HumanDuration;
+impl JsonSchema for HumanDuration {
+    fn schema_name() -> String {
+        String::from("HumanDuration")
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        Schema::Object(SchemaObject {
+            instance_type: Some(vec![InstanceType::Integer, InstanceType::String].into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some(String::from(
+                    r"^\s*([0-9]+|([0-9]+d)?([0-9]+h)?([0-9]+m)?([0-9]+s)?([0-9]+ms)?)\s*$",
+                )),
+                ..Default::default()
+            })),
+            ..Default::default()
+        })
+    }
+}
+macro_rules! human_duration_module {
+    ($name:ident, $unit:expr) => {
+        human_duration_module!($name, $unit, {});
+    };
+    ($name:ident, $unit:expr, option) => {
+        human_duration_module!($name, $unit, {
+            pub mod option {
+                use super::*;
+                pub fn serialize<S: Serializer, T: Serialize>(
+                    value: &Option<T>,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    value.serialize(serializer)
+                }
+                pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<u128>>(
+                    deserializer: D,
+                ) -> Result<Option<T>, D::Error> {
+                    Option::<RawDuration>::deserialize(deserializer)?
+                        .map(|item| to_unit(item, $unit))
+                        .transpose()
+                        .map_err(serde::de::Error::custom)
+                }
+            }
+        });
+    };
+    ($name:ident, $unit:expr, {$($option:item)*}) => {
+        pub mod $name {
+            use super::*;
+            /**
+             *The value is kept in the integer unit of the field,so the output could be read by the older versions.
+             */
+            pub fn serialize<S: Serializer, T: Serialize>(
+                value: &T,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                value.serialize(serializer)
+            }
+            pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<u128>>(
+                deserializer: D,
+            ) -> Result<T, D::Error> {
+                to_unit(RawDuration::deserialize(deserializer)?, $unit)
+                    .map_err(serde::de::Error::custom)
+            }
+            $($option)*
+        }
+    };
+}
+human_duration_module!(seconds, Duration::from_secs(1));
+human_duration_module!(millis, Duration::from_millis(1), option);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_duration() {
+        let second = Duration::from_secs(1);
+        for (value, expected) in [
+            ("30", Duration::from_secs(30)),
+            ("100ms", Duration::from_millis(100)),
+            ("10s", Duration::from_secs(10)),
+            ("5m", Duration::from_secs(300)),
+            (" 2h ", Duration::from_secs(7200)),
+            ("1d", Duration::from_secs(86400)),
+        ] {
+            assert_eq!(parse_duration(value, second).unwrap(), expected);
+        }
+        assert_eq!(
+            parse_duration("30", Duration::from_millis(1)).unwrap(),
+            Duration::from_millis(30)
+        );
+        assert_eq!(
+            parse_duration("1h30m15s", second).unwrap(),
+            Duration::from_secs(5415)
+        );
+        assert_eq!(
+            parse_duration("1d2h500ms", second).unwrap(),
+            Duration::from_millis(93_600_500)
+        );
+        for value in ["", "s", "10 seconds", "-1s", "1.5s", "1h30", "1h 30m", "1w"] {
+            assert!(parse_duration(value, second).is_err(), "{}", value);
+        }
+    }
+    #[test]
+    fn test_parse_duration_repeated_or_unordered_units() {
+        let second = Duration::from_secs(1);
+        for value in ["1h1h", "30m1h", "1s1d", "10ms5s", "1m1m30s"] {
+            assert!(parse_duration(value, second).is_err(), "{}", value);
+        }
+        assert_eq!(
+            parse_duration("1d1h1m1s1ms", second).unwrap(),
+            Duration::from_millis(90_061_001)
+        );
+    }
+    #[test]
+    fn test_parse_duration_large_numbers() {
+        let millis = Duration::from_millis(1);
+        assert_eq!(
+            parse_duration("5000000000ms", millis).unwrap(),
+            Duration::from_millis(5_000_000_000)
+        );
+        assert_eq!(
+            parse_duration("5000000000", millis).unwrap(),
+            Duration::from_millis(5_000_000_000)
+        );
+        assert_eq!(
+            parse_duration("5000000000s", millis).unwrap(),
+            Duration::from_secs(5_000_000_000)
+        );
+        assert!(parse_duration("18446744073709551616s", millis).is_err());
+        assert!(parse_duration("18446744073709551615d", millis).is_err());
+    }
+    #[test]
+    fn test_to_unit() {
+        let second = Duration::from_secs(1);
+        assert_eq!(
+            to_unit::<u64>(RawDuration::Text(String::from("2m")), second).unwrap(),
+            120
+        );
+        assert_eq!(
+            to_unit::<u64>(
+                RawDuration::Text(String::from("1s")),
+                Duration::from_millis(1)
+            )
+            .unwrap(),
+            1000
+        );
+        assert!(to_unit::<u64>(RawDuration::Text(String::from("1500ms")), second).is_err());
+        assert!(to_unit::<i32>(RawDuration::Number(u64::MAX), second).is_err());
+    }
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Cooldown {
+        #[serde(with = "seconds")]
+        cooldown: u64,
+        #[serde(default, with = "millis::option")]
+        jitter: Option<u64>,
+    }
+    #[test]
+    fn test_human_duration_round_trip() {
+        for (value, seconds) in [
+            ("90m", 5400),
+            ("1h30m", 5400),
+            ("2d", 172800),
+            ("5400", 5400),
+            ("0", 0),
+        ] {
+            let cooldown: Cooldown = serde_yaml::from_str(&format!("cooldown: {}", value)).unwrap();
+            assert_eq!(cooldown.cooldown, seconds);
+            assert_eq!(
+                serde_yaml::to_string(&cooldown).unwrap(),
+                format!("cooldown: {}\njitter: null\n", seconds)
+            );
+            let output = serde_yaml::to_string(&cooldown).unwrap();
+            assert_eq!(serde_yaml::from_str::<Cooldown>(&output).unwrap(), cooldown);
+        }
+        let cooldown: Cooldown = serde_yaml::from_str("cooldown: 1d\njitter: 1500").unwrap();
+        assert_eq!(cooldown.jitter, Some(1500));
+        assert_eq!(
+            serde_yaml::to_string(&cooldown).unwrap(),
+            "cooldown: 86400\njitter: 1500\n"
+        );
+    }
+}
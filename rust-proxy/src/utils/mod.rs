@@ -1 +1,2 @@
+pub mod human_duration;
 pub mod uuid;
@@ -1,16 +1,18 @@
-use ipnet::Ipv4Net;
-use iprange::IpRange;
+use crate::constants::common_constants::{X_FORWARDED_FOR, X_FORWARDED_PROTO};
+use http::HeaderMap;
+use ipnet::IpNet;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::net::Ipv4Addr;
+use std::net::IpAddr;
 
 use super::app_error::AppError;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct AllowDenyObject {
     pub limit_type: AllowType,
     pub value: Option<String>,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub enum AllowType {
     #[default]
     AllowAll,
@@ -18,6 +20,12 @@ pub enum AllowType {
     Allow,
     Deny,
 }
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum DefaultPolicy {
+    #[default]
+    Allow,
+    Deny,
+}
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub enum AllowResult {
     #[default]
@@ -25,6 +33,118 @@ pub enum AllowResult {
     Deny,
     Notmapping,
 }
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct ClientIpConfig {
+    #[serde(default)]
+    pub use_forwarded_for: bool,
+    #[serde(default = "default_trusted_hops")]
+    pub trusted_hops: usize,
+    #[serde(default)]
+    pub use_forwarded_proto: bool,
+    pub trusted_proxies: Option<Vec<String>>,
+}
+fn default_trusted_hops() -> usize {
+    1
+}
+impl ClientIpConfig {
+    /**
+     *Each trusted proxy appends the address it received the request from, so only the
+     *entry `trusted_hops` from the right can be trusted and the ones on its left may be spoofed.
+     */
+    pub fn get_client_ip(&self, peer_ip: String, headers: Option<&HeaderMap>) -> String {
+        let Some(headers) = headers.filter(|_| self.use_forwarded_for) else {
+            return peer_ip;
+        };
+        self.trusted_entry(headers, X_FORWARDED_FOR, self.trusted_hops)
+            .and_then(|item| item.parse::<IpAddr>().ok())
+            .map(|item| item.to_string())
+            .unwrap_or(peer_ip)
+    }
+    /**
+     *The scheme of the client is taken from `X-Forwarded-Proto`, so a TLS-terminating proxy in front keeps https.
+     *The header is only honored when the peer is one of the trusted proxies, otherwise any client could set it.
+     *Proxies usually overwrite the header instead of appending to it, so the rightmost value is taken whatever the hops are.
+     */
+    pub fn get_scheme(
+        &self,
+        scheme: &'static str,
+        headers: &HeaderMap,
+        peer_ip: IpAddr,
+    ) -> &'static str {
+        if !self.use_forwarded_proto || !self.is_trusted_proxy(peer_ip) {
+            return scheme;
+        }
+        match self.trusted_entry(headers, X_FORWARDED_PROTO, 1) {
+            Some(item) if item.eq_ignore_ascii_case("https") => "https",
+            Some(item) if item.eq_ignore_ascii_case("http") => "http",
+            _ => scheme,
+        }
+    }
+    fn is_trusted_proxy(&self, peer_ip: IpAddr) -> bool {
+        let peer_ip = peer_ip.to_string();
+        self.trusted_proxies
+            .iter()
+            .flatten()
+            .any(|item| ip_matched(item.trim(), &peer_ip).unwrap_or(false))
+    }
+    pub fn validate(&self) -> Result<(), AppError> {
+        for item in self.trusted_proxies.iter().flatten() {
+            validate_ip_value(item.trim())?;
+        }
+        Ok(())
+    }
+    fn trusted_entry<'a>(
+        &self,
+        headers: &'a HeaderMap,
+        header_name: &str,
+        hops: usize,
+    ) -> Option<&'a str> {
+        if hops == 0 {
+            return None;
+        }
+        let entries = headers
+            .get_all(header_name)
+            .iter()
+            .filter_map(|item| item.to_str().ok())
+            .flat_map(|item| item.split(','))
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<&str>>();
+        entries.len().checked_sub(hops).map(|index| entries[index])
+    }
+}
+/**
+ *The value can be a single ip, a cidr like `10.0.0.0/8` or a range like `10.0.0.1-10.0.0.50`.
+ *The ipv4-mapped client ip like `::ffff:10.0.0.1` from a dual-stack listener is matched as the ipv4 one.
+ */
+fn ip_matched(value: &str, client_ip: &str) -> Result<bool, AppError> {
+    let Ok(source_ip) = client_ip.parse::<IpAddr>().map(|item| item.to_canonical()) else {
+        return Ok(value == client_ip);
+    };
+    if let Some((start, end)) = value.split_once('-') {
+        let (Ok(start_ip), Ok(end_ip)) =
+            (start.trim().parse::<IpAddr>(), end.trim().parse::<IpAddr>())
+        else {
+            return Err(AppError::from(format!(
+                "The ip range {} is invalid!",
+                value
+            )));
+        };
+        let is_same_family =
+            start_ip.is_ipv4() == source_ip.is_ipv4() && end_ip.is_ipv4() == source_ip.is_ipv4();
+        return Ok(is_same_family && start_ip <= source_ip && source_ip <= end_ip);
+    }
+    if value.contains('/') {
+        let ip_net = value
+            .parse::<IpNet>()
+            .map_err(|_| AppError::from(format!("The cidr {} is invalid!", value)))?;
+        return Ok(ip_net.contains(&source_ip));
+    }
+    Ok(value
+        .parse::<IpAddr>()
+        .map(|item| item == source_ip)
+        .unwrap_or(value == client_ip))
+}
 impl AllowDenyObject {
     pub fn is_allow(&self, client_ip: String) -> Result<AllowResult, AppError> {
         if self.limit_type == AllowType::AllowAll {
@@ -34,22 +154,12 @@ impl AllowDenyObject {
             return Ok(AllowResult::Deny);
         }
         if self.value.is_none() {
-            return Err(AppError(String::from(
+            return Err(AppError::from(String::from(
                 "the value counld not be none when the limit_type is not AllowAll or DenyAll!",
             )));
         }
         let config_ip = self.value.clone().unwrap();
-        let value_mapped_ip;
-        if config_ip.contains('/') {
-            let ip_range: IpRange<Ipv4Net> =
-                [config_ip].iter().map(|s| s.parse().unwrap()).collect();
-            let source_ip = client_ip.parse::<Ipv4Addr>().unwrap();
-            value_mapped_ip = ip_range.contains(&source_ip);
-        } else if self.value.clone().unwrap() == client_ip {
-            value_mapped_ip = true;
-        } else {
-            value_mapped_ip = false;
-        }
+        let value_mapped_ip = ip_matched(config_ip.trim(), &client_ip)?;
         if value_mapped_ip && self.limit_type == AllowType::Allow {
             return Ok(AllowResult::Allow);
         }
@@ -59,6 +169,41 @@ impl AllowDenyObject {
 
         Ok(AllowResult::Notmapping)
     }
+    /**
+     *The cidr and the range are checked when the config is loaded,otherwise the mistake only shows up on the first request.
+     */
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.limit_type == AllowType::AllowAll || self.limit_type == AllowType::DenyAll {
+            return Ok(());
+        }
+        let Some(value) = self.value.as_ref().map(|item| item.trim()) else {
+            return Err(AppError::from(String::from(
+                "the value counld not be none when the limit_type is not AllowAll or DenyAll!",
+            )));
+        };
+        validate_ip_value(value)
+    }
+}
+fn validate_ip_value(value: &str) -> Result<(), AppError> {
+    if let Some((start, end)) = value.split_once('-') {
+        return match (start.trim().parse::<IpAddr>(), end.trim().parse::<IpAddr>()) {
+            (Ok(start_ip), Ok(end_ip))
+                if start_ip.is_ipv4() == end_ip.is_ipv4() && start_ip <= end_ip =>
+            {
+                Ok(())
+            }
+            _ => Err(AppError::from(format!(
+                "The ip range {} is invalid!",
+                value
+            ))),
+        };
+    }
+    if value.contains('/') {
+        value
+            .parse::<IpNet>()
+            .map_err(|_| AppError::from(format!("The cidr {} is invalid!", value)))?;
+    }
+    Ok(())
 }
 #[cfg(test)]
 mod tests {
@@ -133,6 +278,237 @@ mod tests {
         assert_eq!(result2.unwrap(), AllowResult::Deny);
     }
 
+    #[test]
+    fn test_get_client_ip_from_forwarded_for() {
+        let client_ip_config = ClientIpConfig {
+            use_forwarded_proto: false,
+            trusted_proxies: None,
+            use_forwarded_for: true,
+            trusted_hops: 1,
+        };
+        let peer_ip = String::from("10.0.0.1");
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            client_ip_config.get_client_ip(peer_ip.clone(), Some(&headers)),
+            "10.0.0.1"
+        );
+        headers.insert(X_FORWARDED_FOR, "192.168.0.1, 203.0.113.7".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_client_ip(peer_ip.clone(), Some(&headers)),
+            "203.0.113.7"
+        );
+        let two_hops_config = ClientIpConfig {
+            use_forwarded_proto: false,
+            trusted_proxies: None,
+            use_forwarded_for: true,
+            trusted_hops: 2,
+        };
+        assert_eq!(
+            two_hops_config.get_client_ip(peer_ip.clone(), Some(&headers)),
+            "192.168.0.1"
+        );
+        headers.insert(X_FORWARDED_FOR, "unknown".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_client_ip(peer_ip.clone(), Some(&headers)),
+            "10.0.0.1"
+        );
+        let disabled_config = ClientIpConfig {
+            use_forwarded_proto: false,
+            trusted_proxies: None,
+            use_forwarded_for: false,
+            trusted_hops: 1,
+        };
+        assert_eq!(
+            disabled_config.get_client_ip(peer_ip, Some(&headers)),
+            "10.0.0.1"
+        );
+    }
+    #[test]
+    fn test_get_scheme_from_forwarded_proto() {
+        let client_ip_config = ClientIpConfig {
+            use_forwarded_for: false,
+            trusted_hops: 1,
+            use_forwarded_proto: true,
+            trusted_proxies: Some(vec![String::from("10.0.0.0/8")]),
+        };
+        let proxy_ip: IpAddr = "10.0.0.2".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            client_ip_config.get_scheme("http", &headers, proxy_ip),
+            "http"
+        );
+        headers.insert(X_FORWARDED_PROTO, "HTTPS".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_scheme("http", &headers, proxy_ip),
+            "https"
+        );
+        headers.insert(X_FORWARDED_PROTO, "https, http".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_scheme("https", &headers, proxy_ip),
+            "http"
+        );
+        headers.insert(X_FORWARDED_PROTO, "ftp".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_scheme("http", &headers, proxy_ip),
+            "http"
+        );
+        headers.insert(X_FORWARDED_PROTO, "https".parse().unwrap());
+        let untrusted_config = ClientIpConfig {
+            use_forwarded_proto: false,
+            ..client_ip_config.clone()
+        };
+        assert_eq!(
+            untrusted_config.get_scheme("http", &headers, proxy_ip),
+            "http"
+        );
+    }
+    #[test]
+    fn test_get_scheme_from_untrusted_peer() {
+        let client_ip_config = ClientIpConfig {
+            use_forwarded_for: false,
+            trusted_hops: 1,
+            use_forwarded_proto: true,
+            trusted_proxies: Some(vec![String::from("10.0.0.1-10.0.0.9")]),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_PROTO, "https".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_scheme("http", &headers, "10.0.0.5".parse().unwrap()),
+            "https"
+        );
+        assert_eq!(
+            client_ip_config.get_scheme("http", &headers, "203.0.113.7".parse().unwrap()),
+            "http"
+        );
+        let no_proxies_config = ClientIpConfig {
+            trusted_proxies: None,
+            ..client_ip_config
+        };
+        assert_eq!(
+            no_proxies_config.get_scheme("http", &headers, "10.0.0.5".parse().unwrap()),
+            "http"
+        );
+    }
+    #[test]
+    fn test_get_scheme_with_two_trusted_hops() {
+        let client_ip_config = ClientIpConfig {
+            use_forwarded_for: true,
+            trusted_hops: 2,
+            use_forwarded_proto: true,
+            trusted_proxies: Some(vec![String::from("10.0.0.3")]),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, "203.0.113.7, 10.0.0.2".parse().unwrap());
+        headers.insert(X_FORWARDED_PROTO, "https".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_scheme("http", &headers, "10.0.0.3".parse().unwrap()),
+            "https"
+        );
+        assert_eq!(
+            client_ip_config.get_client_ip(String::from("10.0.0.3"), Some(&headers)),
+            "203.0.113.7"
+        );
+        headers.insert(X_FORWARDED_PROTO, "http, https".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_scheme("http", &headers, "10.0.0.3".parse().unwrap()),
+            "https"
+        );
+    }
+    #[test]
+    fn test_validate_cidr_and_range() {
+        for (limit_type, value, is_valid) in [
+            (AllowType::Allow, Some("10.0.0.0/8"), true),
+            (AllowType::Deny, Some("10.0.0.1-10.0.0.50"), true),
+            (AllowType::Allow, Some("2001:db8::1"), true),
+            (AllowType::AllowAll, None, true),
+            (AllowType::Allow, Some("10.0.0.0/33"), false),
+            (AllowType::Deny, Some("10.0.0.50-10.0.0.1"), false),
+            (AllowType::Deny, Some("10.0.0.1-2001:db8::1"), false),
+            (AllowType::Allow, Some("10.0.0.a-10.0.0.5"), false),
+            (AllowType::Deny, None, false),
+        ] {
+            let allow_object = AllowDenyObject {
+                limit_type,
+                value: value.map(String::from),
+            };
+            assert_eq!(allow_object.validate().is_ok(), is_valid, "{:?}", value);
+        }
+    }
+    #[test]
+    fn test_is_allow_ipv4_mapped_client_ip() {
+        for (value, client_ip, expected) in [
+            ("10.0.0.0/8", "::ffff:10.0.0.1", AllowResult::Allow),
+            ("10.0.0.1-10.0.0.50", "::ffff:10.0.0.7", AllowResult::Allow),
+            ("10.0.0.1", "::ffff:10.0.0.1", AllowResult::Allow),
+            ("10.0.0.0/8", "::ffff:192.168.0.1", AllowResult::Notmapping),
+        ] {
+            let allow_object = AllowDenyObject {
+                limit_type: AllowType::Allow,
+                value: Some(String::from(value)),
+            };
+            assert_eq!(
+                allow_object.is_allow(String::from(client_ip)).unwrap(),
+                expected,
+                "{} {}",
+                value,
+                client_ip
+            );
+        }
+        let client_ip_config = ClientIpConfig {
+            use_forwarded_for: false,
+            trusted_hops: 1,
+            use_forwarded_proto: true,
+            trusted_proxies: Some(vec![String::from("10.0.0.0/8")]),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_PROTO, "https".parse().unwrap());
+        assert_eq!(
+            client_ip_config.get_scheme("http", &headers, "::ffff:10.0.0.2".parse().unwrap()),
+            "https"
+        );
+    }
+    #[test]
+    fn test_is_allow_ipv6_cidr_and_range() {
+        let allow_object = AllowDenyObject {
+            limit_type: AllowType::Allow,
+            value: Some(String::from("2001:db8::/32")),
+        };
+        assert_eq!(
+            allow_object
+                .is_allow(String::from("2001:db8:1::42"))
+                .unwrap(),
+            AllowResult::Allow
+        );
+        assert_eq!(
+            allow_object.is_allow(String::from("2001:db9::1")).unwrap(),
+            AllowResult::Notmapping
+        );
+        assert_eq!(
+            allow_object.is_allow(String::from("192.168.0.1")).unwrap(),
+            AllowResult::Notmapping
+        );
+        let range_object = AllowDenyObject {
+            limit_type: AllowType::Deny,
+            value: Some(String::from("10.0.0.1-10.0.0.50")),
+        };
+        assert_eq!(
+            range_object.is_allow(String::from("10.0.0.50")).unwrap(),
+            AllowResult::Deny
+        );
+        assert_eq!(
+            range_object.is_allow(String::from("10.0.0.51")).unwrap(),
+            AllowResult::Notmapping
+        );
+        assert_eq!(
+            range_object.is_allow(String::from("::1")).unwrap(),
+            AllowResult::Notmapping
+        );
+        let invalid_object = AllowDenyObject {
+            limit_type: AllowType::Deny,
+            value: Some(String::from("10.0.0.0/33")),
+        };
+        assert!(invalid_object.is_allow(String::from("10.0.0.1")).is_err());
+    }
     #[test]
     fn test_is_allow_not_mapping1() {
         let allow_object = AllowDenyObject {
@@ -1,20 +1,59 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct BaseHealthCheckParam {
     pub timeout: i32,
     pub interval: i32,
 }
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct BaseAnomalyDetectionParam {
     pub ejection_second: u64,
+    pub window_size: Option<usize>,
+    pub window_second: Option<u64>,
+    pub max_ejection_percent: Option<u8>,
 }
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+impl BaseAnomalyDetectionParam {
+    pub fn is_windowed(&self) -> bool {
+        self.window_size.is_some() || self.window_second.is_some()
+    }
+    pub fn min_liveness_count(&self, cluster_size: usize, min_liveness_count: i32) -> i32 {
+        match self.max_ejection_percent {
+            Some(max_ejection_percent) => {
+                let max_ejection_count =
+                    cluster_size * max_ejection_percent.min(100) as usize / 100;
+                min_liveness_count.max((cluster_size - max_ejection_count) as i32)
+            }
+            None => min_liveness_count,
+        }
+    }
+}
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct HttpAnomalyDetectionParam {
     pub consecutive_5xx: i32,
     pub base_anomaly_detection_param: BaseAnomalyDetectionParam,
 }
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum AnomalyDetectionType {
     Http(HttpAnomalyDetectionParam),
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_min_liveness_count_with_max_ejection_percent() {
+        let mut param = BaseAnomalyDetectionParam {
+            ejection_second: 3,
+            window_size: None,
+            window_second: None,
+            max_ejection_percent: None,
+        };
+        assert_eq!(param.min_liveness_count(4, 1), 1);
+        param.max_ejection_percent = Some(50);
+        assert_eq!(param.min_liveness_count(4, 1), 2);
+        assert_eq!(param.min_liveness_count(3, 1), 2);
+        assert_eq!(param.min_liveness_count(4, 3), 3);
+        param.max_ejection_percent = Some(200);
+        assert_eq!(param.min_liveness_count(4, 0), 0);
+    }
+}
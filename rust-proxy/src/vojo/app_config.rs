@@ -1,62 +1,645 @@
 use super::allow_deny_ip::AllowResult;
 use super::app_config_vistor::ApiServiceVistor;
 use super::app_config_vistor::ServiceConfigVistor;
+use crate::constants::common_constants::DEFAULT_BODY_LOGGING_MAX_BYTES;
+use crate::constants::common_constants::DEFAULT_MIRROR_MAX_BODY_SIZE;
+use crate::constants::common_constants::DEFAULT_WEBSOCKET_IDLE_TIMEOUT;
+use crate::constants::common_constants::MAX_HEDGE_REQUESTS;
+use crate::constants::common_constants::MAX_REQUEST_ID_LENGTH;
+use crate::constants::common_constants::REDACTED_VALUE;
+use crate::constants::common_constants::X_REQUEST_ID;
+use crate::utils::human_duration;
+use crate::utils::human_duration::HumanDuration;
+use crate::utils::uuid::get_uuid;
 use crate::vojo::allow_deny_ip::AllowDenyObject;
+use crate::vojo::allow_deny_ip::ClientIpConfig;
+use crate::vojo::allow_deny_ip::DefaultPolicy;
 use crate::vojo::anomaly_detection::AnomalyDetectionType;
 use crate::vojo::app_config_vistor::from_loadbalancer_strategy_vistor;
 use crate::vojo::app_config_vistor::RouteVistor;
-use crate::vojo::app_error::AppError;
+use crate::vojo::app_error::{AppError, AppErrorKind};
 use crate::vojo::authentication::AuthenticationStrategy;
+use crate::vojo::bulkhead::Bulkhead;
+use crate::vojo::circuit_breaker::CircuitBreakerConfig;
+use crate::vojo::combined_limit::CombinedLimit;
 use crate::vojo::health_check::HealthCheckType;
+use crate::vojo::proxy_protocol::ProxyProtocolVersion;
 use crate::vojo::rate_limit::RatelimitStrategy;
+use crate::vojo::response_cache::CacheConfig;
 use crate::vojo::route::LoadbalancerStrategy;
+use crate::vojo::single_flight::SingleFlightConfig;
+use crate::vojo::tls_policy::SniCert;
+use crate::vojo::tls_policy::TlsPolicy;
+use crate::vojo::upstream_policy::UpstreamPolicy;
+use dashmap::DashMap;
 use http::HeaderMap;
+use http::HeaderName;
 use http::HeaderValue;
+use http::Method;
+use http::StatusCode;
+use http::Uri;
+use lazy_static::lazy_static;
+use log::LevelFilter;
+use rand::Rng;
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+lazy_static! {
+    static ref PATH_REGEX_CACHE: DashMap<String, Regex> = DashMap::new();
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct Matcher {
     pub prefix: String,
     pub prefix_rewrite: String,
+    #[serde(default)]
+    pub match_type: PathMatchType,
+    pub paths: Option<Vec<String>>,
+    pub methods: Option<Vec<String>>,
+    pub schemes: Option<Vec<String>>,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum PathMatchType {
+    #[default]
+    Prefix,
+    Exact,
+    Regex,
+}
+impl Matcher {
+    /**
+     *The matcher without methods matches all, and the HEAD request is matched like GET.
+     */
+    pub fn is_method_matched(&self, method: &Method) -> bool {
+        let Some(methods) = self.methods.as_ref() else {
+            return true;
+        };
+        methods.iter().any(|item| {
+            item.eq_ignore_ascii_case(method.as_str())
+                || (method == Method::HEAD && item.eq_ignore_ascii_case(Method::GET.as_str()))
+        })
+    }
+    pub fn is_scheme_matched(&self, scheme: &str) -> bool {
+        self.schemes
+            .as_ref()
+            .is_none_or(|schemes| schemes.iter().any(|item| item.eq_ignore_ascii_case(scheme)))
+    }
+    pub fn match_and_rewrite(&self, path: &str) -> Result<Option<String>, AppError> {
+        if let Some(res) = self.match_pattern(self.prefix.as_str(), path)? {
+            return Ok(Some(res));
+        }
+        for pattern in self.paths.iter().flatten() {
+            if let Some(res) = self.match_pattern(pattern.as_str(), path)? {
+                return Ok(Some(res));
+            }
+        }
+        Ok(None)
+    }
+    fn match_pattern(&self, pattern: &str, path: &str) -> Result<Option<String>, AppError> {
+        match self.match_type {
+            PathMatchType::Prefix => Ok(path
+                .strip_prefix(pattern)
+                .map(|rest| format!("{}{}", self.prefix_rewrite, rest))),
+            PathMatchType::Exact => {
+                let (path_only, query) = split_query(path);
+                if path_only != pattern {
+                    return Ok(None);
+                }
+                Ok(Some(format!("{}{}", self.prefix_rewrite, query)))
+            }
+            PathMatchType::Regex => {
+                let (path_only, query) = split_query(path);
+                let path_regex = compile_path_regex(pattern)?;
+                let captures = match path_regex.captures(path_only) {
+                    Some(captures) => captures,
+                    None => return Ok(None),
+                };
+                let mut expanded = String::new();
+                captures.expand(self.prefix_rewrite.as_str(), &mut expanded);
+                let (final_path, rewritten_query) = split_query(expanded.as_str());
+                Ok(Some(format!(
+                    "{}{}",
+                    final_path,
+                    merge_query(rewritten_query, query)
+                )))
+            }
+        }
+    }
+}
+/**
+ *The regex is compiled when the route is built,the requests only clone it from the cache.
+ */
+fn compile_path_regex(pattern: &str) -> Result<Regex, AppError> {
+    if let Some(path_regex) = PATH_REGEX_CACHE.get(pattern) {
+        return Ok(path_regex.clone());
+    }
+    let path_regex = Regex::new(pattern).map_err(|e| {
+        AppError::new(
+            AppErrorKind::Config,
+            format!("The path regex {} is invalid,the error is {}!", pattern, e),
+        )
+    })?;
+    PATH_REGEX_CACHE.insert(pattern.to_string(), path_regex.clone());
+    Ok(path_regex)
+}
+fn normalize_matcher_path(path: &str, match_type: &PathMatchType) -> String {
+    let mut res = path.to_string();
+    if *match_type == PathMatchType::Prefix && !res.ends_with('/') {
+        res.push('/');
+    }
+    if !res.starts_with('/') {
+        res.insert(0, '/');
+    }
+    res
+}
+fn split_query(path: &str) -> (&str, &str) {
+    match path.find('?') {
+        Some(index) => path.split_at(index),
+        None => (path, ""),
+    }
+}
+fn merge_query(rewritten_query: &str, request_query: &str) -> String {
+    let rewritten = rewritten_query.trim_start_matches('?');
+    let request = request_query.trim_start_matches('?');
+    match (rewritten.is_empty(), request.is_empty()) {
+        (true, true) => String::new(),
+        (false, true) => format!("?{}", rewritten),
+        (true, false) => format!("?{}", request),
+        (false, false) => format!("?{}&{}", rewritten, request),
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct LivenessConfig {
     pub min_liveness_count: i32,
+    pub panic_threshold: Option<u8>,
+    pub slow_start_second: Option<u64>,
+}
+impl LivenessConfig {
+    /**
+     *The panic mode is entered when the percentage of the healthy backends is below the panic threshold.
+     */
+    pub fn is_panic_mode(&self, healthy_count: usize, cluster_size: usize) -> bool {
+        self.panic_threshold.is_some_and(|panic_threshold| {
+            healthy_count * 100 < cluster_size * panic_threshold.min(100) as usize
+        })
+    }
+    pub fn min_healthy_count(&self, cluster_size: usize) -> i32 {
+        self.panic_threshold
+            .map(|panic_threshold| {
+                (cluster_size * panic_threshold.min(100) as usize).div_ceil(100) as i32
+            })
+            .unwrap_or(0)
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct ForwardHeaders {
+    #[serde(default)]
+    pub trust_existing: bool,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct SocketOptions {
+    pub nodelay: Option<bool>,
+    pub reuseaddr: Option<bool>,
+    pub backlog: Option<u32>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct TcpLimitConfig {
+    pub max_connections: Option<usize>,
+    pub bytes_per_second: Option<u64>,
+}
+/**
+ *The settings only used by the tcp listener.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct TcpConfig {
+    pub proxy_protocol: Option<ProxyProtocolVersion>,
+    pub limit: Option<TcpLimitConfig>,
+    pub tls_passthrough: Option<TlsPassthroughConfig>,
+}
+/**
+ *The connection is routed by the server name of the client hello without terminating the tls.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct TlsPassthroughConfig {
+    pub client_hello_timeout: Option<u64>,
+}
+/**
+ *The settings only used by the udp listener.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct UdpConfig {
+    pub idle_timeout: Option<u64>,
+    pub max_sessions: Option<usize>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct UpstreamPoolConfig {
+    pub max_idle_per_host: Option<usize>,
+    pub idle_timeout: Option<u64>,
+    pub tcp_nodelay: Option<bool>,
+    pub tcp_keepalive: Option<u64>,
+    pub tcp_keepalive_interval: Option<u64>,
+    pub max_lifetime: Option<u64>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct UpstreamTlsConfig {
+    pub ca_file: Option<String>,
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+    pub server_name: Option<String>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct HeaderLimitConfig {
+    pub max_header_size: Option<usize>,
+    pub max_header_count: Option<usize>,
+}
+/**
+ *The redirect listener answers every request with a redirect to the https scheme of the same host and path.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RedirectConfig {
+    #[serde(default = "default_redirect_permanent")]
+    pub permanent: bool,
+    #[serde(default)]
+    pub preserve_method: bool,
+    pub https_port: Option<u16>,
+}
+impl Default for RedirectConfig {
+    fn default() -> Self {
+        RedirectConfig {
+            permanent: default_redirect_permanent(),
+            preserve_method: false,
+            https_port: None,
+        }
+    }
+}
+fn default_redirect_permanent() -> bool {
+    true
+}
+impl RedirectConfig {
+    pub fn status_code(&self) -> StatusCode {
+        match (self.permanent, self.preserve_method) {
+            (true, false) => StatusCode::MOVED_PERMANENTLY,
+            (true, true) => StatusCode::PERMANENT_REDIRECT,
+            (false, false) => StatusCode::FOUND,
+            (false, true) => StatusCode::TEMPORARY_REDIRECT,
+        }
+    }
+}
+fn default_error_page_content_type() -> String {
+    String::from("text/html; charset=utf-8")
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ErrorPage {
+    #[serde(default = "default_error_page_content_type")]
+    pub content_type: String,
+    pub body: String,
+}
+impl ErrorPage {
+    pub fn render(&self, status: u16, path: &str) -> String {
+        let path = if self.content_type.contains("html") {
+            path.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\'', "&#39;")
+        } else {
+            path.to_string()
+        };
+        self.body
+            .replace("{status}", &status.to_string())
+            .replace("{path}", &path)
+    }
+}
+/**
+ *The timeouts are in milliseconds, or a duration like `500ms` and `2s`.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct TimeoutConfig {
+    #[serde(default, with = "human_duration::millis::option")]
+    #[schemars(with = "Option<HumanDuration>")]
+    pub connect_timeout: Option<u64>,
+    #[serde(default, with = "human_duration::millis::option")]
+    #[schemars(with = "Option<HumanDuration>")]
+    pub read_timeout: Option<u64>,
+    #[serde(default, with = "human_duration::millis::option")]
+    #[schemars(with = "Option<HumanDuration>")]
+    pub request_timeout: Option<u64>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MirrorConfig {
+    pub endpoint: String,
+    #[serde(default = "default_mirror_percentage")]
+    pub percentage: f64,
+    #[serde(default = "default_mirror_max_body_size")]
+    pub max_body_size: u64,
+}
+fn default_mirror_percentage() -> f64 {
+    100.0
+}
+fn default_mirror_max_body_size() -> u64 {
+    DEFAULT_MIRROR_MAX_BODY_SIZE
+}
+impl MirrorConfig {
+    pub fn is_sampled(&self) -> bool {
+        self.percentage >= 100.0 || rand::thread_rng().gen_range(0.0..100.0) < self.percentage
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct HedgeConfig {
+    #[serde(default = "default_hedge_delay_millis")]
+    pub delay_millis: u64,
+    #[serde(default = "default_max_hedges")]
+    pub max_hedges: usize,
+}
+fn default_hedge_delay_millis() -> u64 {
+    50
+}
+fn default_max_hedges() -> usize {
+    1
+}
+impl HedgeConfig {
+    pub fn get_max_hedges(&self) -> usize {
+        self.max_hedges.min(MAX_HEDGE_REQUESTS)
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RetryConfig {
+    #[serde(default = "default_max_retries")]
+    pub max_retries: usize,
+    #[serde(default)]
+    pub retry_on_5xx: bool,
+}
+fn default_max_retries() -> usize {
+    1
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct MaintenanceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub retry_after: Option<u64>,
+    pub body: Option<String>,
+    #[serde(default = "default_maintenance_content_type")]
+    pub content_type: String,
+}
+fn default_maintenance_content_type() -> String {
+    String::from("text/html; charset=utf-8")
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WebsocketConfig {
+    #[serde(default = "default_websocket_idle_timeout")]
+    pub idle_timeout: u64,
+    pub max_message_size: Option<u64>,
+    pub subprotocols: Option<Vec<String>>,
+}
+fn default_websocket_idle_timeout() -> u64 {
+    DEFAULT_WEBSOCKET_IDLE_TIMEOUT
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct QueryRewrite {
+    #[serde(default)]
+    pub add: HashMap<String, String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+    #[serde(default, rename = "override")]
+    pub override_params: HashMap<String, String>,
+}
+fn query_param_name(param: &str) -> String {
+    let name = param.split('=').next().unwrap_or_default();
+    url::form_urlencoded::parse(name.as_bytes())
+        .next()
+        .map(|(key, _)| key.to_string())
+        .unwrap_or_default()
+}
+fn encode_query_param(name: &str, value: &str) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+        .append_pair(name, value)
+        .finish()
+}
+impl QueryRewrite {
+    /**
+     *The unlisted params are kept as they are, the added ones are appended only when they are absent.
+     */
+    pub fn rewrite(&self, query: Option<&str>) -> Option<String> {
+        let mut params: Vec<String> = vec![];
+        let mut overridden = vec![];
+        for param in query.unwrap_or_default().split('&') {
+            if param.is_empty() {
+                continue;
+            }
+            let name = query_param_name(param);
+            if self.remove.contains(&name) || overridden.contains(&name) {
+                continue;
+            }
+            match self.override_params.get(&name) {
+                Some(value) => {
+                    params.push(encode_query_param(&name, value));
+                    overridden.push(name);
+                }
+                None => params.push(param.to_string()),
+            }
+        }
+        let existing: Vec<String> = params.iter().map(|item| query_param_name(item)).collect();
+        let mut appended: Vec<(&String, &String)> = self
+            .add
+            .iter()
+            .filter(|(name, _)| !self.override_params.contains_key(*name))
+            .chain(self.override_params.iter())
+            .filter(|(name, _)| !existing.contains(name))
+            .collect();
+        appended.sort();
+        for (name, value) in appended {
+            params.push(encode_query_param(name, value));
+        }
+        if params.is_empty() {
+            return None;
+        }
+        Some(params.join("&"))
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BodyLoggingConfig {
+    #[serde(default = "default_body_logging_max_bytes")]
+    pub max_bytes: usize,
+    #[serde(default = "default_redact_headers")]
+    pub redact_headers: Vec<String>,
+    #[serde(default)]
+    pub redact_fields: Vec<String>,
+}
+fn default_body_logging_max_bytes() -> usize {
+    DEFAULT_BODY_LOGGING_MAX_BYTES
+}
+fn default_redact_headers() -> Vec<String> {
+    vec![String::from("authorization"), String::from("cookie")]
+}
+impl BodyLoggingConfig {
+    pub fn format_headers(&self, headers: &HeaderMap) -> String {
+        headers
+            .iter()
+            .map(|(key, value)| {
+                let is_redacted = self
+                    .redact_headers
+                    .iter()
+                    .any(|item| item.eq_ignore_ascii_case(key.as_str()));
+                let value = if is_redacted {
+                    REDACTED_VALUE
+                } else {
+                    value.to_str().unwrap_or_default()
+                };
+                format!("{}: {}", key, value)
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+    /**
+     *The json fields are redacted before the body is truncated, so a secret can not be cut in half.
+     */
+    pub fn format_body(&self, body: &[u8]) -> String {
+        let content = match serde_json::from_slice::<serde_json::Value>(body) {
+            Ok(mut value) if !self.redact_fields.is_empty() => {
+                self.redact_value(&mut value);
+                value.to_string()
+            }
+            _ => String::from_utf8_lossy(body).to_string(),
+        };
+        if content.len() <= self.max_bytes {
+            return content;
+        }
+        let mut end = self.max_bytes;
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!(
+            "{}...({} bytes truncated)",
+            &content[..end],
+            content.len() - end
+        )
+    }
+    fn redact_value(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, item) in map.iter_mut() {
+                    if self
+                        .redact_fields
+                        .iter()
+                        .any(|field| field.eq_ignore_ascii_case(key))
+                    {
+                        *item = serde_json::Value::String(String::from(REDACTED_VALUE));
+                    } else {
+                        self.redact_value(item);
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for item in items.iter_mut() {
+                    self.redact_value(item);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct ResponseHeaders {
+    #[serde(default)]
+    pub add: HashMap<String, String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+impl ResponseHeaders {
+    pub fn apply(&self, headers: &mut HeaderMap<HeaderValue>) {
+        for key in self.remove.iter() {
+            headers.remove(key.as_str());
+        }
+        for (key, value) in self.add.iter() {
+            if let (Ok(header_name), Ok(header_value)) = (
+                HeaderName::from_bytes(key.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(header_name, header_value);
+            }
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+impl From<LogLevel> for LevelFilter {
+    fn from(log_level: LogLevel) -> Self {
+        match log_level {
+            LogLevel::Trace => LevelFilter::Trace,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Error => LevelFilter::Error,
+        }
+    }
 }
 #[derive(Debug, Serialize, Clone, Deserialize, Default)]
 pub struct LivenessStatus {
     pub current_liveness_count: i32,
 }
+/**
+ *A request denied by the ratelimit is told apart from the denied ip,so it could be answered with the ratelimit response.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteAccess {
+    Allowed,
+    Denied,
+    RateLimited,
+}
 #[derive(Debug, Clone)]
 pub struct Route {
     pub route_id: String,
     pub host_name: Option<String>,
     pub matcher: Option<Matcher>,
     pub allow_deny_list: Option<Vec<AllowDenyObject>>,
+    pub allow_deny_default: Option<DefaultPolicy>,
+    pub client_ip: Option<ClientIpConfig>,
     pub authentication: Option<Box<dyn AuthenticationStrategy>>,
     pub anomaly_detection: Option<AnomalyDetectionType>,
     pub liveness_status: Arc<RwLock<LivenessStatus>>,
     pub rewrite_headers: Option<HashMap<String, String>>,
+    pub query_rewrite: Option<QueryRewrite>,
+    pub response_headers: Option<ResponseHeaders>,
+    pub forward_headers: Option<ForwardHeaders>,
+    pub mirror: Option<MirrorConfig>,
+    pub timeout: Option<TimeoutConfig>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    pub hedge: Option<HedgeConfig>,
+    pub retry: Option<RetryConfig>,
+    pub body_logging: Option<BodyLoggingConfig>,
+    pub maintenance: Option<MaintenanceConfig>,
+    pub cache: Option<CacheConfig>,
+    pub single_flight: Option<SingleFlightConfig>,
+    pub websocket: Option<WebsocketConfig>,
     pub liveness_config: Option<LivenessConfig>,
     pub health_check: Option<HealthCheckType>,
     pub ratelimit: Option<Box<dyn RatelimitStrategy>>,
+    pub combined_limit: Option<CombinedLimit>,
+    pub bulkhead: Option<Bulkhead>,
     pub route_cluster: LoadbalancerStrategy,
 }
 impl Route {
     pub async fn from(route_vistor: RouteVistor) -> Result<Route, AppError> {
         let cloned_cluster = route_vistor.route_cluster.clone();
         let new_matcher = route_vistor.matcher.clone().map(|mut item| {
-            let src_prefix = item.prefix.clone();
-            if !src_prefix.ends_with('/') {
-                let src_prefix_len = item.prefix.len();
-                item.prefix.insert(src_prefix_len, '/');
-            }
-            if !src_prefix.starts_with('/') {
-                item.prefix.insert(0, '/')
+            if item.match_type == PathMatchType::Regex {
+                return item;
             }
+            item.prefix = normalize_matcher_path(&item.prefix, &item.match_type);
+            item.paths = item.paths.map(|paths| {
+                paths
+                    .iter()
+                    .map(|path| normalize_matcher_path(path, &item.match_type))
+                    .collect()
+            });
             let path_rewrite = item.prefix_rewrite.clone();
             // if !path_rewrite.ends_with('/') {
             //     let src_prefix_rewrite_len = item.prefix_rewrite.len();
@@ -68,6 +651,14 @@ impl Route {
             item
         });
 
+        if let Some(matcher) = new_matcher
+            .as_ref()
+            .filter(|item| item.match_type == PathMatchType::Regex)
+        {
+            for pattern in std::iter::once(&matcher.prefix).chain(matcher.paths.iter().flatten()) {
+                compile_path_regex(pattern)?;
+            }
+        }
         let count = cloned_cluster.get_routes_len() as i32;
 
         Ok(Route {
@@ -75,21 +666,85 @@ impl Route {
             host_name: route_vistor.host_name,
             matcher: new_matcher,
             allow_deny_list: route_vistor.allow_deny_list,
+            allow_deny_default: route_vistor.allow_deny_default,
+            client_ip: route_vistor.client_ip,
             authentication: route_vistor.authentication,
             anomaly_detection: route_vistor.anomaly_detection,
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: count,
             })),
             rewrite_headers: route_vistor.rewrite_headers,
+            response_headers: route_vistor.response_headers,
+            forward_headers: route_vistor.forward_headers,
+            mirror: route_vistor.mirror,
+            timeout: route_vistor.timeout,
+            circuit_breaker: route_vistor.circuit_breaker,
+            hedge: route_vistor.hedge,
+            retry: route_vistor.retry,
+            body_logging: route_vistor.body_logging,
+            maintenance: route_vistor.maintenance,
+            cache: route_vistor.cache,
+            single_flight: route_vistor.single_flight,
+            websocket: route_vistor.websocket,
+            query_rewrite: route_vistor.query_rewrite,
             liveness_config: route_vistor.liveness_config,
             health_check: route_vistor.health_check,
             ratelimit: route_vistor.ratelimit,
+            combined_limit: route_vistor.combined_limit,
+            bulkhead: route_vistor.bulkhead,
             route_cluster: from_loadbalancer_strategy_vistor(route_vistor.route_cluster),
         })
     }
 }
 
 impl Route {
+    pub fn is_method_matched(&self, method: &Method) -> bool {
+        self.matcher
+            .as_ref()
+            .map(|item| item.is_method_matched(method))
+            .unwrap_or(true)
+    }
+    /**
+     *The effective scheme is the one of the listener, unless the route honors the forwarded proto of the trusted proxies.
+     */
+    pub fn get_scheme(
+        &self,
+        server_type: &ServiceType,
+        headers: &HeaderMap,
+        peer_ip: IpAddr,
+    ) -> &'static str {
+        let scheme = server_type.scheme();
+        match self.client_ip.as_ref() {
+            Some(client_ip_config) => client_ip_config.get_scheme(scheme, headers, peer_ip),
+            None => scheme,
+        }
+    }
+    pub fn is_scheme_matched(
+        &self,
+        server_type: &ServiceType,
+        headers: &HeaderMap,
+        peer_ip: IpAddr,
+    ) -> bool {
+        self.matcher.as_ref().is_none_or(|item| {
+            item.is_scheme_matched(self.get_scheme(server_type, headers, peer_ip))
+        })
+    }
+    /**
+     *Apply the query rewrite of the route to the query part of the matched rest path.
+     */
+    pub fn rewrite_query(&self, rest_path: String) -> String {
+        let Some(query_rewrite) = self.query_rewrite.as_ref() else {
+            return rest_path;
+        };
+        let (path, query) = match rest_path.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest_path.as_str(), None),
+        };
+        match query_rewrite.rewrite(query) {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_string(),
+        }
+    }
     pub fn is_matched(
         &self,
         path: String,
@@ -99,13 +754,13 @@ impl Route {
             .clone()
             .matcher
             .ok_or("The matcher counld not be none for http")
-            .map_err(|err| AppError(err.to_string()))?;
+            .map_err(|err| AppError::from(err.to_string()))?;
 
-        let match_res = path.strip_prefix(matcher.prefix.as_str());
+        let match_res = matcher.match_and_rewrite(path.as_str())?;
         if match_res.is_none() {
             return Ok(None);
         }
-        let final_path = format!("{}{}", matcher.prefix_rewrite, match_res.unwrap());
+        let final_path = match_res.unwrap();
         // info!("final_path:{}", final_path);
         if let Some(real_host_name) = &self.host_name {
             if headers_option.is_none() {
@@ -121,69 +776,83 @@ impl Route {
                 return Ok(None);
             }
             let host_name_regex =
-                Regex::new(real_host_name.as_str()).map_err(|e| AppError(e.to_string()))?;
+                Regex::new(real_host_name.as_str()).map_err(|e| AppError::from(e.to_string()))?;
             return host_name_regex
                 .captures(host_result.unwrap())
                 .map_or(Ok(None), |_| Ok(Some(final_path)));
         }
         Ok(Some(final_path))
     }
+    /**
+     *The host_name is matched against the SNI of the TLS passthrough, the route without host_name matches all.
+     */
+    pub fn match_server_name(&self, server_name: Option<&str>) -> Result<bool, AppError> {
+        let Some(real_host_name) = &self.host_name else {
+            return Ok(true);
+        };
+        let Some(server_name) = server_name else {
+            return Ok(false);
+        };
+        let host_name_regex =
+            Regex::new(real_host_name.as_str()).map_err(|e| AppError::from(e.to_string()))?;
+        Ok(host_name_regex.is_match(server_name))
+    }
     pub async fn is_allowed(
         &self,
         ip: String,
         headers_option: Option<HeaderMap<HeaderValue>>,
-    ) -> Result<bool, AppError> {
-        let mut is_allowed = ip_is_allowed(self.allow_deny_list.clone(), ip.clone())?;
+    ) -> Result<RouteAccess, AppError> {
+        let client_ip = match self.client_ip.as_ref() {
+            Some(client_ip_config) => {
+                client_ip_config.get_client_ip(ip.clone(), headers_option.as_ref())
+            }
+            None => ip.clone(),
+        };
+        let is_allowed = ip_is_allowed(
+            self.allow_deny_list.clone(),
+            self.allow_deny_default.unwrap_or_default(),
+            client_ip,
+        )?;
         if !is_allowed {
-            return Ok(is_allowed);
+            return Ok(RouteAccess::Denied);
         }
         if let (Some(header_map), Some(mut authentication_strategy)) =
             (headers_option.clone(), self.authentication.clone())
         {
-            is_allowed = authentication_strategy.check_authentication(header_map)?;
-            if !is_allowed {
-                return Ok(is_allowed);
+            if !authentication_strategy.check_authentication(header_map)? {
+                return Ok(RouteAccess::Denied);
             }
         }
         if let (Some(header_map), Some(mut ratelimit_strategy)) =
             (headers_option, self.ratelimit.clone())
         {
-            is_allowed = !ratelimit_strategy.should_limit(header_map, ip).await?;
+            if ratelimit_strategy.should_limit(header_map, ip).await? {
+                return Ok(RouteAccess::RateLimited);
+            }
         }
-        Ok(is_allowed)
+        Ok(RouteAccess::Allowed)
     }
 }
+/**
+ *The first matched rule wins, and the default policy is applied when no rule is matched.
+ */
 pub fn ip_is_allowed(
     allow_deny_list: Option<Vec<AllowDenyObject>>,
+    default_policy: DefaultPolicy,
     ip: String,
 ) -> Result<bool, AppError> {
-    if allow_deny_list.is_none() || allow_deny_list.clone().unwrap().is_empty() {
-        return Ok(true);
-    }
-    let allow_deny_list = allow_deny_list.unwrap();
-    // let iter = allow_deny_list.iter();
-
-    for item in allow_deny_list {
-        let is_allow = item.is_allow(ip.clone());
-        match is_allow {
-            Ok(AllowResult::Allow) => {
-                return Ok(true);
-            }
-            Ok(AllowResult::Deny) => {
-                return Ok(false);
-            }
-            Ok(AllowResult::Notmapping) => {
-                continue;
-            }
-            Err(err) => {
-                return Err(AppError(err.to_string()));
-            }
+    for item in allow_deny_list.unwrap_or_default() {
+        match item.is_allow(ip.clone())? {
+            AllowResult::Allow => return Ok(true),
+            AllowResult::Deny => return Ok(false),
+            AllowResult::Notmapping => continue,
         }
     }
-
-    Ok(true)
+    Ok(default_policy == DefaultPolicy::Allow)
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, strum_macros::Display)]
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default, strum_macros::Display,
+)]
 pub enum ServiceType {
     #[default]
     Http,
@@ -191,24 +860,142 @@ pub enum ServiceType {
     Tcp,
     Http2,
     Http2Tls,
+    Udp,
+    Redirect,
+}
+impl ServiceType {
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            ServiceType::Https | ServiceType::Http2Tls => "https",
+            _ => "http",
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum AccessLogFormat {
+    #[default]
+    Text,
+    Json,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RequestIdConfig {
+    #[serde(default = "default_trust_incoming")]
+    pub trust_incoming: bool,
+}
+fn default_trust_incoming() -> bool {
+    true
+}
+impl RequestIdConfig {
+    pub fn get_request_id(&self, headers: &HeaderMap) -> String {
+        headers
+            .get(X_REQUEST_ID)
+            .and_then(|item| item.to_str().ok())
+            .map(|item| item.trim())
+            .filter(|item| {
+                self.trust_incoming && !item.is_empty() && item.len() <= MAX_REQUEST_ID_LENGTH
+            })
+            .map(|item| item.to_string())
+            .unwrap_or_else(get_uuid)
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum TrailingSlashPolicy {
+    #[default]
+    Ignore,
+    Strip,
+    Add,
+}
+impl TrailingSlashPolicy {
+    pub fn redirect_location(&self, uri: &Uri) -> Option<String> {
+        let path = uri.path();
+        if path == "/" {
+            return None;
+        }
+        let new_path = match self {
+            TrailingSlashPolicy::Ignore => return None,
+            TrailingSlashPolicy::Strip => {
+                if !path.ends_with('/') {
+                    return None;
+                }
+                let trimmed = path.trim_end_matches('/');
+                if trimmed.is_empty() {
+                    String::from("/")
+                } else {
+                    trimmed.to_string()
+                }
+            }
+            TrailingSlashPolicy::Add => {
+                if path.ends_with('/') {
+                    return None;
+                }
+                format!("{}/", path)
+            }
+        };
+        match uri.query() {
+            Some(query) => Some(format!("{}?{}", new_path, query)),
+            None => Some(new_path),
+        }
+    }
 }
 #[derive(Debug, Clone, Default)]
 pub struct ServiceConfig {
     pub server_type: ServiceType,
     pub cert_str: Option<String>,
     pub key_str: Option<String>,
+    pub graceful_shutdown_timeout: Option<u64>,
+    pub upstream_policy: Option<UpstreamPolicy>,
+    pub trailing_slash: Option<TrailingSlashPolicy>,
+    pub socket_options: Option<SocketOptions>,
+    pub upstream_pool: Option<UpstreamPoolConfig>,
+    pub error_pages: Option<HashMap<u16, ErrorPage>>,
+    pub access_log_format: Option<AccessLogFormat>,
+    pub request_id: Option<RequestIdConfig>,
+    pub trace_context: Option<bool>,
+    pub tls_policy: Option<TlsPolicy>,
+    pub sni_certs: Option<Vec<SniCert>>,
+    pub tcp: Option<TcpConfig>,
+    pub udp: Option<UdpConfig>,
+    pub method_override: Option<bool>,
+    pub upstream_tls: Option<UpstreamTlsConfig>,
+    pub header_limit: Option<HeaderLimitConfig>,
+    pub bind_addresses: Option<Vec<IpAddr>>,
+    pub redirect: Option<RedirectConfig>,
     pub routes: Vec<Route>,
 }
 impl ServiceConfig {
     pub async fn from(service_config_vistor: ServiceConfigVistor) -> Result<Self, AppError> {
         let mut routes = vec![];
+        let upstream_policy = service_config_vistor
+            .upstream_policy
+            .clone()
+            .unwrap_or_default();
         for item in service_config_vistor.routes {
-            routes.push(Route::from(item).await?)
+            let route = Route::from(item).await?;
+            upstream_policy.check_route(&route).await?;
+            routes.push(route)
         }
         Ok(ServiceConfig {
             server_type: service_config_vistor.server_type,
             cert_str: service_config_vistor.cert_str,
             key_str: service_config_vistor.key_str,
+            graceful_shutdown_timeout: service_config_vistor.graceful_shutdown_timeout,
+            upstream_policy: service_config_vistor.upstream_policy,
+            trailing_slash: service_config_vistor.trailing_slash,
+            socket_options: service_config_vistor.socket_options,
+            upstream_pool: service_config_vistor.upstream_pool,
+            error_pages: service_config_vistor.error_pages,
+            access_log_format: service_config_vistor.access_log_format,
+            request_id: service_config_vistor.request_id,
+            trace_context: service_config_vistor.trace_context,
+            tls_policy: service_config_vistor.tls_policy,
+            sni_certs: service_config_vistor.sni_certs,
+            tcp: service_config_vistor.tcp,
+            udp: service_config_vistor.udp,
+            method_override: service_config_vistor.method_override,
+            upstream_tls: service_config_vistor.upstream_tls,
+            header_limit: service_config_vistor.header_limit,
+            bind_addresses: service_config_vistor.bind_addresses,
+            redirect: service_config_vistor.redirect,
             routes,
         })
     }
@@ -245,6 +1032,7 @@ pub struct AppConfig {
 mod tests {
     use super::*;
     use crate::utils::uuid::get_uuid;
+    use crate::vojo::allow_deny_ip::AllowType;
     use crate::vojo::anomaly_detection::BaseAnomalyDetectionParam;
     use crate::vojo::anomaly_detection::HttpAnomalyDetectionParam;
     use crate::vojo::app_config_vistor::BaseRouteVistor;
@@ -266,10 +1054,8 @@ mod tests {
     use crate::vojo::route::WeightBasedRoute;
     use crate::vojo::route::WeightRoute;
     use dashmap::DashMap;
-    use std::sync::atomic::AtomicIsize;
     use std::sync::Arc;
     use std::sync::Mutex;
-    use std::time::SystemTime;
     use tokio::sync::RwLock;
     fn create_new_route_with_host_name(host_name: Option<String>) -> Route {
         Route {
@@ -280,14 +1066,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
-                    index: Arc::new(AtomicIsize::new(0)),
                     weight: 100,
                 }])),
+                current_weights: Default::default(),
             }),
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
@@ -295,17 +1089,227 @@ mod tests {
             anomaly_detection: None,
             health_check: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("/"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         }
     }
     #[test]
+    fn test_ip_is_allowed_with_cidr() {
+        let allow_deny_list = Some(vec![
+            AllowDenyObject {
+                limit_type: AllowType::Deny,
+                value: Some(String::from("192.168.1.66")),
+            },
+            AllowDenyObject {
+                limit_type: AllowType::Allow,
+                value: Some(String::from("192.168.1.0/24")),
+            },
+            AllowDenyObject {
+                limit_type: AllowType::DenyAll,
+                value: None,
+            },
+        ]);
+        for (ip, expected) in [
+            ("192.168.1.1", true),
+            ("192.168.1.255", true),
+            ("192.168.1.66", false),
+            ("192.168.2.1", false),
+            ("2001:db8::1", false),
+        ] {
+            assert_eq!(
+                ip_is_allowed(
+                    allow_deny_list.clone(),
+                    DefaultPolicy::Allow,
+                    String::from(ip)
+                )
+                .unwrap(),
+                expected
+            );
+        }
+    }
+    #[test]
+    fn test_ip_is_allowed_first_match_and_default_policy() {
+        let deny_first = Some(vec![
+            AllowDenyObject {
+                limit_type: AllowType::Deny,
+                value: Some(String::from("10.0.0.0/8")),
+            },
+            AllowDenyObject {
+                limit_type: AllowType::Allow,
+                value: Some(String::from("10.1.0.1")),
+            },
+        ]);
+        let allow_first = Some(vec![
+            AllowDenyObject {
+                limit_type: AllowType::Allow,
+                value: Some(String::from("10.1.0.1")),
+            },
+            AllowDenyObject {
+                limit_type: AllowType::Deny,
+                value: Some(String::from("10.0.0.0/8")),
+            },
+        ]);
+        for default_policy in [DefaultPolicy::Allow, DefaultPolicy::Deny] {
+            let is_allowed = |allow_deny_list: &Option<Vec<AllowDenyObject>>, ip: &str| {
+                ip_is_allowed(allow_deny_list.clone(), default_policy, String::from(ip)).unwrap()
+            };
+            assert!(!is_allowed(&deny_first, "10.1.0.1"));
+            assert!(is_allowed(&allow_first, "10.1.0.1"));
+            assert!(!is_allowed(&allow_first, "10.1.0.2"));
+            let is_default_allowed = default_policy == DefaultPolicy::Allow;
+            assert_eq!(is_allowed(&deny_first, "192.168.0.1"), is_default_allowed);
+            assert_eq!(is_allowed(&allow_first, "192.168.0.1"), is_default_allowed);
+            assert_eq!(is_allowed(&None, "192.168.0.1"), is_default_allowed);
+        }
+    }
+    #[tokio::test]
+    async fn test_is_allowed_with_forwarded_for() {
+        let mut route = create_new_route_with_host_name(None);
+        route.allow_deny_list = Some(vec![
+            AllowDenyObject {
+                limit_type: AllowType::Allow,
+                value: Some(String::from("203.0.113.7")),
+            },
+            AllowDenyObject {
+                limit_type: AllowType::DenyAll,
+                value: None,
+            },
+        ]);
+        route.client_ip = Some(ClientIpConfig {
+            use_forwarded_proto: false,
+            trusted_proxies: None,
+            use_forwarded_for: true,
+            trusted_hops: 1,
+        });
+        let cdn_ip = String::from("10.0.0.1");
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7".parse().unwrap());
+        assert_eq!(
+            route
+                .is_allowed(cdn_ip.clone(), Some(headers.clone()))
+                .await
+                .unwrap(),
+            RouteAccess::Allowed
+        );
+        headers.insert(
+            "x-forwarded-for",
+            "203.0.113.7, 198.51.100.9".parse().unwrap(),
+        );
+        assert_eq!(
+            route
+                .is_allowed(cdn_ip.clone(), Some(headers.clone()))
+                .await
+                .unwrap(),
+            RouteAccess::Denied
+        );
+        route.client_ip = None;
+        headers.insert("x-forwarded-for", "203.0.113.7".parse().unwrap());
+        assert_eq!(
+            route.is_allowed(cdn_ip, Some(headers)).await.unwrap(),
+            RouteAccess::Denied
+        );
+    }
+    #[test]
+    fn test_liveness_config_panic_threshold() {
+        let mut liveness_config = LivenessConfig {
+            min_liveness_count: 1,
+            panic_threshold: None,
+            slow_start_second: None,
+        };
+        assert!(!liveness_config.is_panic_mode(0, 4));
+        assert_eq!(liveness_config.min_healthy_count(4), 0);
+        liveness_config.panic_threshold = Some(50);
+        assert!(!liveness_config.is_panic_mode(2, 4));
+        assert!(liveness_config.is_panic_mode(1, 4));
+        assert_eq!(liveness_config.min_healthy_count(4), 2);
+        assert_eq!(liveness_config.min_healthy_count(3), 2);
+        liveness_config.panic_threshold = Some(200);
+        assert!(liveness_config.is_panic_mode(3, 4));
+        assert_eq!(liveness_config.min_healthy_count(4), 4);
+    }
+    #[test]
+    fn test_timeout_config_human_duration() {
+        let timeout_config: TimeoutConfig =
+            serde_yaml::from_str("connect_timeout: 500\nread_timeout: 2s").unwrap();
+        assert_eq!(timeout_config.connect_timeout, Some(500));
+        assert_eq!(timeout_config.read_timeout, Some(2000));
+        assert_eq!(timeout_config.request_timeout, None);
+        let timeout_config: TimeoutConfig =
+            serde_json::from_str(r#"{"request_timeout":"1m","read_timeout":null}"#).unwrap();
+        assert_eq!(timeout_config.request_timeout, Some(60000));
+        assert_eq!(timeout_config.read_timeout, None);
+    }
+    #[test]
+    fn test_get_request_id() {
+        let mut headers = HeaderMap::new();
+        let trusted = RequestIdConfig {
+            trust_incoming: true,
+        };
+        let regenerated = RequestIdConfig {
+            trust_incoming: false,
+        };
+        assert_eq!(trusted.get_request_id(&headers).len(), 36);
+        headers.insert(X_REQUEST_ID, "incoming-id".parse().unwrap());
+        assert_eq!(trusted.get_request_id(&headers), "incoming-id");
+        let request_id = regenerated.get_request_id(&headers);
+        assert_ne!(request_id, "incoming-id");
+        assert_eq!(request_id.len(), 36);
+    }
+    #[test]
+    fn test_body_logging_redact_and_truncate() {
+        let body_logging = BodyLoggingConfig {
+            max_bytes: 56,
+            redact_headers: vec![String::from("Authorization")],
+            redact_fields: vec![String::from("password")],
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        headers.insert("x-client", "a".parse().unwrap());
+        assert_eq!(
+            body_logging.format_headers(&headers),
+            "authorization: ***, x-client: a"
+        );
+        let body = br#"{"user":{"name":"admin","password":"secret"},"items":[1,2,3]}"#;
+        let formatted = body_logging.format_body(body);
+        assert_eq!(
+            formatted,
+            r#"{"items":[1,2,3],"user":{"name":"admin","password":"***"...(2 bytes truncated)"#
+        );
+        assert!(!formatted.contains("secret"));
+        assert_eq!(body_logging.format_body(b"plain text"), "plain text");
+        assert_eq!(
+            body_logging.format_body("€".repeat(20).as_bytes()),
+            format!("{}...(6 bytes truncated)", "€".repeat(18))
+        );
+    }
+    #[test]
     fn test_host_name_is_none_ok1() {
         let route = create_new_route_with_host_name(None);
         let mut headermap = HeaderMap::new();
@@ -352,12 +1356,17 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
-                    index: 0,
                     weight: 100,
                 }],
             }),
@@ -367,6 +1376,7 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             liveness_status: LivenessStatus {
                 current_liveness_count: 0,
@@ -374,21 +1384,47 @@ mod tests {
             anomaly_detection: Some(AnomalyDetectionType::Http(HttpAnomalyDetectionParam {
                 consecutive_5xx: 23,
                 base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                    max_ejection_percent: None,
+                    window_second: None,
+                    window_size: None,
                     ejection_second: 23,
                 },
             })),
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             liveness_config: Some(LivenessConfig {
                 min_liveness_count: 32,
+                panic_threshold: None,
+                slow_start_second: None,
             }),
 
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -396,9 +1432,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -416,12 +1450,17 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
-                    index: 0,
                     weight: 100,
                 }],
             }),
@@ -431,14 +1470,35 @@ mod tests {
             anomaly_detection: None,
             health_check: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -446,9 +1506,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -466,12 +1524,17 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
-                    index: 0,
                     weight: 100,
                 }],
             }),
@@ -481,14 +1544,35 @@ mod tests {
             anomaly_detection: None,
             health_check: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -496,9 +1580,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -517,9 +1599,15 @@ mod tests {
                         base_route: BaseRouteVistor {
                             endpoint: String::from("/"),
                             try_file: None,
+                            index: None,
+                            autoindex: None,
+                            host_header: None,
+                            sni: None,
+                            preserve_header_case: true,
                             is_alive: None,
                             anomaly_detection_status: AnomalyDetectionStatus {
                                 consecutive_5xx: 100,
+                                recent_responses: Default::default(),
                             },
                         },
                     },
@@ -527,9 +1615,15 @@ mod tests {
                         base_route: BaseRouteVistor {
                             endpoint: String::from("/"),
                             try_file: None,
+                            index: None,
+                            autoindex: None,
+                            host_header: None,
+                            sni: None,
+                            preserve_header_case: true,
                             is_alive: None,
                             anomaly_detection_status: AnomalyDetectionStatus {
                                 consecutive_5xx: 100,
+                                recent_responses: Default::default(),
                             },
                         },
                     },
@@ -540,15 +1634,36 @@ mod tests {
                 current_liveness_count: 0,
             },
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             anomaly_detection: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             health_check: None,
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -556,10 +1671,8 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
 
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -576,9 +1689,15 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
                 }],
@@ -592,13 +1711,34 @@ mod tests {
             anomaly_detection: None,
             health_check: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -607,9 +1747,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -630,9 +1768,15 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
                 }],
@@ -642,17 +1786,38 @@ mod tests {
             anomaly_detection: None,
             health_check: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             liveness_status: LivenessStatus {
                 current_liveness_count: 0,
             },
             authentication: Some(basic_auth),
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -660,9 +1825,7 @@ mod tests {
             api_service_id: get_uuid(),
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -683,9 +1846,15 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
                 }],
@@ -695,17 +1864,38 @@ mod tests {
             anomaly_detection: None,
             health_check: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             liveness_status: LivenessStatus {
                 current_liveness_count: 0,
             },
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             authentication: Some(api_key_auth),
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -713,9 +1903,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -731,9 +1919,9 @@ mod tests {
             limit_location: LimitLocation::IP(IPBasedRatelimit {
                 value: String::from("192.168.0.0"),
             }),
-            current_count: Arc::new(RwLock::new(AtomicIsize::new(3))),
-            lock: Arc::new(Mutex::new(0)),
-            last_update_time: Arc::new(RwLock::new(SystemTime::now())),
+            response: None,
+            initial_tokens: Some(3),
+            bucket: Default::default(),
         };
         let ratelimit: Box<dyn RatelimitStrategy> = Box::new(token_bucket_ratelimit);
         let route = RouteVistor {
@@ -744,9 +1932,15 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
                 }],
@@ -759,14 +1953,35 @@ mod tests {
             anomaly_detection: None,
             health_check: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             liveness_config: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             authentication: None,
             ratelimit: Some(ratelimit),
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -774,9 +1989,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -791,6 +2004,7 @@ mod tests {
             limit_location: LimitLocation::IP(IPBasedRatelimit {
                 value: String::from("192.168.0.0"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -803,9 +2017,15 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
                 }],
@@ -816,17 +2036,38 @@ mod tests {
                 current_liveness_count: 0,
             },
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             anomaly_detection: None,
             health_check: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: None,
 
             ratelimit: Some(ratelimit),
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -834,9 +2075,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -858,9 +2097,15 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
                 }],
@@ -870,17 +2115,38 @@ mod tests {
             anomaly_detection: None,
             health_check: None,
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             allow_deny_list: Some(vec![allow_object]),
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: None,
             liveness_status: LivenessStatus {
                 current_liveness_count: 0,
             },
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiServiceVistor {
@@ -888,9 +2154,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let t = vec![api_service];
@@ -916,4 +2180,334 @@ mod tests {
         assert!(dst3.is_some());
         assert_eq!(dst3.unwrap(), "test/book");
     }
+    fn create_service_config_vistor_with_endpoint(endpoint: &str) -> ServiceConfigVistor {
+        let route = RouteVistor {
+            host_name: None,
+            route_id: get_uuid(),
+            route_cluster: LoadbalancerStrategyVistor::RandomRoute(RandomRouteVistor {
+                routes: vec![RandomBaseRouteVistor {
+                    base_route: BaseRouteVistor {
+                        endpoint: String::from(endpoint),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: None,
+                        anomaly_detection_status: AnomalyDetectionStatus {
+                            consecutive_5xx: 0,
+                            recent_responses: Default::default(),
+                        },
+                    },
+                }],
+            }),
+            liveness_status: LivenessStatus {
+                current_liveness_count: 0,
+            },
+            anomaly_detection: None,
+            health_check: None,
+            allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
+            authentication: None,
+            liveness_config: None,
+            rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
+            ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
+            matcher: Some(Matcher {
+                prefix: String::from("/"),
+                prefix_rewrite: String::from("/"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
+            }),
+        };
+        ServiceConfigVistor {
+            routes: vec![route],
+            ..Default::default()
+        }
+    }
+    #[tokio::test]
+    async fn test_service_config_reject_metadata_ip() {
+        let service_config_vistor =
+            create_service_config_vistor_with_endpoint("http://169.254.169.254/latest/meta-data");
+        let result = ServiceConfig::from(service_config_vistor).await;
+        assert!(result.is_err());
+
+        let service_config_vistor = create_service_config_vistor_with_endpoint("http://127.0.0.1");
+        let result = ServiceConfig::from(service_config_vistor).await;
+        assert!(result.is_ok());
+    }
+    #[tokio::test]
+    async fn test_service_config_upstream_policy_allow_hosts() {
+        let mut service_config_vistor =
+            create_service_config_vistor_with_endpoint("http://10.0.0.1:8080");
+        service_config_vistor.upstream_policy = Some(UpstreamPolicy {
+            allowed_hosts: Some(vec![String::from("192.168.0.0/16")]),
+            ..Default::default()
+        });
+        let result = ServiceConfig::from(service_config_vistor).await;
+        assert!(result.is_err());
+    }
+    #[test]
+    fn test_response_headers_apply() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Server", HeaderValue::from_static("nginx/1.0"));
+        headers.insert("X-Powered-By", HeaderValue::from_static("php"));
+        headers.insert("Content-Type", HeaderValue::from_static("text/plain"));
+        let response_headers = ResponseHeaders {
+            add: HashMap::from([(
+                String::from("Strict-Transport-Security"),
+                String::from("max-age=31536000"),
+            )]),
+            remove: vec![String::from("server"), String::from("X-Powered-By")],
+        };
+        response_headers.apply(&mut headers);
+        assert!(headers.get("Server").is_none());
+        assert!(headers.get("X-Powered-By").is_none());
+        assert_eq!(headers.get("Content-Type").unwrap(), "text/plain");
+        assert_eq!(
+            headers.get("Strict-Transport-Security").unwrap(),
+            "max-age=31536000"
+        );
+    }
+    #[test]
+    fn test_trailing_slash_policy() {
+        let uri: Uri = "/a/?name=1".parse().unwrap();
+        assert_eq!(
+            TrailingSlashPolicy::Strip.redirect_location(&uri),
+            Some(String::from("/a?name=1"))
+        );
+        assert_eq!(TrailingSlashPolicy::Add.redirect_location(&uri), None);
+        assert_eq!(TrailingSlashPolicy::Ignore.redirect_location(&uri), None);
+
+        let uri: Uri = "/a".parse().unwrap();
+        assert_eq!(TrailingSlashPolicy::Strip.redirect_location(&uri), None);
+        assert_eq!(
+            TrailingSlashPolicy::Add.redirect_location(&uri),
+            Some(String::from("/a/"))
+        );
+        assert_eq!(TrailingSlashPolicy::Ignore.redirect_location(&uri), None);
+
+        let uri: Uri = "/".parse().unwrap();
+        assert_eq!(TrailingSlashPolicy::Strip.redirect_location(&uri), None);
+        assert_eq!(TrailingSlashPolicy::Add.redirect_location(&uri), None);
+    }
+    #[test]
+    fn test_matcher_prefix_with_dot() {
+        let matcher = Matcher {
+            prefix: String::from("/api.v1/"),
+            prefix_rewrite: String::from("/"),
+            match_type: PathMatchType::Prefix,
+            paths: None,
+            methods: None,
+            schemes: None,
+        };
+        assert_eq!(
+            matcher.match_and_rewrite("/api.v1/users?id=1").unwrap(),
+            Some(String::from("/users?id=1"))
+        );
+        assert_eq!(matcher.match_and_rewrite("/apixv1/users").unwrap(), None);
+    }
+    #[test]
+    fn test_matcher_exact() {
+        let matcher = Matcher {
+            prefix: String::from("/health"),
+            prefix_rewrite: String::from("/status"),
+            match_type: PathMatchType::Exact,
+            paths: None,
+            methods: None,
+            schemes: None,
+        };
+        assert_eq!(
+            matcher.match_and_rewrite("/health?verbose=1").unwrap(),
+            Some(String::from("/status?verbose=1"))
+        );
+        assert_eq!(matcher.match_and_rewrite("/health/db").unwrap(), None);
+    }
+    #[test]
+    fn test_matcher_regex() {
+        let matcher = Matcher {
+            prefix: String::from("^/users/(?P<id>[0-9]+)/posts$"),
+            prefix_rewrite: String::from("/v2/posts/$id"),
+            match_type: PathMatchType::Regex,
+            paths: None,
+            methods: None,
+            schemes: None,
+        };
+        assert_eq!(
+            matcher.match_and_rewrite("/users/42/posts?page=2").unwrap(),
+            Some(String::from("/v2/posts/42?page=2"))
+        );
+        assert_eq!(matcher.match_and_rewrite("/users/abc/posts").unwrap(), None);
+    }
+    #[test]
+    fn test_matcher_regex_rewrite_with_query() {
+        let matcher = Matcher {
+            prefix: String::from(r"^/users/(\d+)/posts$"),
+            prefix_rewrite: String::from("/v2/posts?user=$1"),
+            match_type: PathMatchType::Regex,
+            paths: None,
+            methods: None,
+            schemes: None,
+        };
+        assert_eq!(
+            matcher.match_and_rewrite("/users/42/posts").unwrap(),
+            Some(String::from("/v2/posts?user=42"))
+        );
+        assert_eq!(
+            matcher.match_and_rewrite("/users/42/posts?page=2").unwrap(),
+            Some(String::from("/v2/posts?user=42&page=2"))
+        );
+
+        let named_matcher = Matcher {
+            prefix: String::from(r"^/shops/(?P<shop>\w+)/items/(?P<item>\d+)$"),
+            prefix_rewrite: String::from("/items/${item}?shop=${shop}"),
+            match_type: PathMatchType::Regex,
+            paths: None,
+            methods: None,
+            schemes: None,
+        };
+        assert_eq!(
+            named_matcher
+                .match_and_rewrite("/shops/abc/items/7")
+                .unwrap(),
+            Some(String::from("/items/7?shop=abc"))
+        );
+    }
+    #[tokio::test]
+    async fn test_route_from_keeps_regex_matcher() {
+        let mut route_vistor = create_service_config_vistor_with_endpoint("http://127.0.0.1:8080")
+            .routes
+            .remove(0);
+        route_vistor.matcher = Some(Matcher {
+            prefix: String::from("^/users/([0-9]+)$"),
+            prefix_rewrite: String::from("/v2/users/$1"),
+            match_type: PathMatchType::Regex,
+            paths: None,
+            methods: None,
+            schemes: None,
+        });
+        let route = Route::from(route_vistor).await.unwrap();
+        assert_eq!(
+            route.matcher.unwrap().prefix,
+            String::from("^/users/([0-9]+)$")
+        );
+        assert!(PATH_REGEX_CACHE.contains_key("^/users/([0-9]+)$"));
+    }
+    #[tokio::test]
+    async fn test_route_from_rejects_invalid_regex_matcher() {
+        let mut route_vistor = create_service_config_vistor_with_endpoint("http://127.0.0.1:8080")
+            .routes
+            .remove(0);
+        route_vistor.matcher = Some(Matcher {
+            prefix: String::from("^/users/$"),
+            prefix_rewrite: String::from("/"),
+            match_type: PathMatchType::Regex,
+            paths: Some(vec![String::from("^/orders/([0-9]+$")]),
+            methods: None,
+            schemes: None,
+        });
+        let err = Route::from(route_vistor).await.unwrap_err();
+        assert_eq!(err.kind, AppErrorKind::Config);
+        assert!(err.to_string().contains("^/orders/([0-9]+$"));
+    }
+    #[test]
+    fn test_matcher_paths() {
+        let matcher = Matcher {
+            prefix: String::from("/login"),
+            prefix_rewrite: String::from("/auth/login"),
+            match_type: PathMatchType::Exact,
+            paths: Some(vec![String::from("/signin"), String::from("/sign-in")]),
+            methods: None,
+            schemes: None,
+        };
+        for path in ["/login", "/signin", "/sign-in"] {
+            assert_eq!(
+                matcher.match_and_rewrite(path).unwrap(),
+                Some(String::from("/auth/login"))
+            );
+        }
+        assert_eq!(matcher.match_and_rewrite("/logout").unwrap(), None);
+    }
+    #[tokio::test]
+    async fn test_route_from_normalizes_matcher_paths() {
+        let mut route_vistor = create_service_config_vistor_with_endpoint("http://127.0.0.1:8080")
+            .routes
+            .remove(0);
+        route_vistor.matcher = Some(Matcher {
+            prefix: String::from("api"),
+            prefix_rewrite: String::from("/"),
+            match_type: PathMatchType::Prefix,
+            paths: Some(vec![String::from("v1"), String::from("/v2/")]),
+            methods: None,
+            schemes: None,
+        });
+        let matcher = Route::from(route_vistor).await.unwrap().matcher.unwrap();
+        assert_eq!(matcher.prefix, "/api/");
+        assert_eq!(
+            matcher.paths,
+            Some(vec![String::from("/v1/"), String::from("/v2/")])
+        );
+        assert_eq!(
+            matcher.match_and_rewrite("/v1/users").unwrap(),
+            Some(String::from("/users"))
+        );
+        assert_eq!(matcher.match_and_rewrite("/v3/users").unwrap(), None);
+    }
+    #[test]
+    fn test_error_page_render() {
+        let error_page: ErrorPage = serde_yaml::from_str("body: '<p>{status} {path}</p>'").unwrap();
+        assert_eq!(error_page.content_type, "text/html; charset=utf-8");
+        assert_eq!(
+            error_page.render(404, "/a<script>"),
+            "<p>404 /a&lt;script&gt;</p>"
+        );
+        let error_page = ErrorPage {
+            content_type: String::from("text/plain"),
+            body: String::from("{status}:{path}"),
+        };
+        assert_eq!(error_page.render(503, "/a<b>"), "503:/a<b>");
+    }
+    #[test]
+    fn test_query_rewrite() {
+        let query_rewrite = QueryRewrite {
+            add: HashMap::from([(String::from("api_version"), String::from("v 2"))]),
+            remove: vec![String::from("utm_source"), String::from("utm_medium")],
+            override_params: HashMap::from([(String::from("lang"), String::from("en"))]),
+        };
+        assert_eq!(
+            query_rewrite.rewrite(None),
+            Some(String::from("api_version=v+2&lang=en"))
+        );
+        assert_eq!(
+            query_rewrite.rewrite(Some("a=1&utm_source=x&b=%2F&utm_medium=y")),
+            Some(String::from("a=1&b=%2F&api_version=v+2&lang=en"))
+        );
+        assert_eq!(
+            query_rewrite.rewrite(Some("lang=fr&a=1&lang=de&api_version=v1")),
+            Some(String::from("lang=en&a=1&api_version=v1"))
+        );
+        let query_rewrite = QueryRewrite {
+            remove: vec![String::from("token")],
+            ..Default::default()
+        };
+        assert_eq!(query_rewrite.rewrite(Some("token=abc")), None);
+    }
 }
@@ -1,27 +1,62 @@
 use crate::vojo::allow_deny_ip::AllowDenyObject;
+use crate::vojo::allow_deny_ip::ClientIpConfig;
+use crate::vojo::allow_deny_ip::DefaultPolicy;
 use crate::vojo::anomaly_detection::AnomalyDetectionType;
+use crate::vojo::app_config::AccessLogFormat;
 use crate::vojo::app_config::ApiService;
 use crate::vojo::app_config::AppConfig;
+use crate::vojo::app_config::BodyLoggingConfig;
+use crate::vojo::app_config::ErrorPage;
+use crate::vojo::app_config::ForwardHeaders;
+use crate::vojo::app_config::HeaderLimitConfig;
+use crate::vojo::app_config::HedgeConfig;
 use crate::vojo::app_config::LivenessConfig;
 use crate::vojo::app_config::LivenessStatus;
+use crate::vojo::app_config::MaintenanceConfig;
 use crate::vojo::app_config::Matcher;
+use crate::vojo::app_config::MirrorConfig;
+use crate::vojo::app_config::QueryRewrite;
+use crate::vojo::app_config::RedirectConfig;
+use crate::vojo::app_config::RequestIdConfig;
+use crate::vojo::app_config::ResponseHeaders;
+use crate::vojo::app_config::RetryConfig;
 use crate::vojo::app_config::Route;
 use crate::vojo::app_config::ServiceConfig;
 use crate::vojo::app_config::ServiceType;
+use crate::vojo::app_config::SocketOptions;
 use crate::vojo::app_config::StaticConifg;
+use crate::vojo::app_config::TcpConfig;
+use crate::vojo::app_config::TimeoutConfig;
+use crate::vojo::app_config::TrailingSlashPolicy;
+use crate::vojo::app_config::UdpConfig;
+use crate::vojo::app_config::UpstreamPoolConfig;
+use crate::vojo::app_config::UpstreamTlsConfig;
+use crate::vojo::app_config::WebsocketConfig;
 use crate::vojo::authentication::AuthenticationStrategy;
+use crate::vojo::bulkhead::Bulkhead;
+use crate::vojo::circuit_breaker::CircuitBreakerConfig;
+use crate::vojo::combined_limit::CombinedLimit;
 use crate::vojo::health_check::HealthCheckType;
+use crate::vojo::jwt_claims::JwtDecodeConfig;
 use crate::vojo::rate_limit::RatelimitStrategy;
+use crate::vojo::response_cache::CacheConfig;
 use crate::vojo::route::AnomalyDetectionStatus;
 use crate::vojo::route::BaseRoute;
 use crate::vojo::route::HeaderValueMappingType;
 use crate::vojo::route::LoadbalancerStrategy;
 use crate::vojo::route::{
-    HeaderBasedRoute, PollBaseRoute, PollRoute, RandomBaseRoute, RandomRoute, WeightBasedRoute,
+    CanaryRoute, ClaimBasedRoute, ClaimRoute, HeaderBasedRoute, PollBaseRoute, PollRoute,
+    RandomBaseRoute, RandomRoute, RegionBasedRoute, RegionMapping, RegionRoute, WeightBasedRoute,
     WeightRoute,
 };
+use crate::vojo::single_flight::SingleFlightConfig;
+use crate::vojo::tls_policy::SniCert;
+use crate::vojo::tls_policy::TlsPolicy;
+use crate::vojo::upstream_policy::UpstreamPolicy;
 use std::collections::HashMap;
+use std::net::IpAddr;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::Ordering;
 
@@ -29,7 +64,7 @@ use uuid::Uuid;
 
 use super::app_error::AppError;
 use super::route::HeaderRoute;
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct ApiServiceVistor {
     pub listen_port: i32,
     #[serde(default = "new_uuid")]
@@ -40,28 +75,63 @@ pub fn new_uuid() -> String {
     let id = Uuid::new_v4();
     id.to_string()
 }
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct ServiceConfigVistor {
     pub server_type: ServiceType,
     pub cert_str: Option<String>,
     pub key_str: Option<String>,
+    pub graceful_shutdown_timeout: Option<u64>,
+    pub upstream_policy: Option<UpstreamPolicy>,
+    pub trailing_slash: Option<TrailingSlashPolicy>,
+    pub socket_options: Option<SocketOptions>,
+    pub upstream_pool: Option<UpstreamPoolConfig>,
+    pub error_pages: Option<HashMap<u16, ErrorPage>>,
+    pub access_log_format: Option<AccessLogFormat>,
+    pub request_id: Option<RequestIdConfig>,
+    pub trace_context: Option<bool>,
+    pub tls_policy: Option<TlsPolicy>,
+    pub sni_certs: Option<Vec<SniCert>>,
+    pub tcp: Option<TcpConfig>,
+    pub udp: Option<UdpConfig>,
+    pub method_override: Option<bool>,
+    pub upstream_tls: Option<UpstreamTlsConfig>,
+    pub header_limit: Option<HeaderLimitConfig>,
+    pub bind_addresses: Option<Vec<IpAddr>>,
+    pub redirect: Option<RedirectConfig>,
     pub routes: Vec<RouteVistor>,
 }
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
 pub struct RouteVistor {
     #[serde(default = "new_uuid")]
     pub route_id: String,
     pub host_name: Option<String>,
     pub matcher: Option<Matcher>,
     pub allow_deny_list: Option<Vec<AllowDenyObject>>,
+    pub allow_deny_default: Option<DefaultPolicy>,
+    pub client_ip: Option<ClientIpConfig>,
     pub authentication: Option<Box<dyn AuthenticationStrategy>>,
     pub anomaly_detection: Option<AnomalyDetectionType>,
     #[serde(skip_serializing, skip_deserializing)]
     pub liveness_status: LivenessStatus,
     pub rewrite_headers: Option<HashMap<String, String>>,
+    pub query_rewrite: Option<QueryRewrite>,
+    pub response_headers: Option<ResponseHeaders>,
+    pub forward_headers: Option<ForwardHeaders>,
+    pub mirror: Option<MirrorConfig>,
+    pub timeout: Option<TimeoutConfig>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    pub hedge: Option<HedgeConfig>,
+    pub retry: Option<RetryConfig>,
+    pub body_logging: Option<BodyLoggingConfig>,
+    pub maintenance: Option<MaintenanceConfig>,
+    pub cache: Option<CacheConfig>,
+    pub single_flight: Option<SingleFlightConfig>,
+    pub websocket: Option<WebsocketConfig>,
     pub liveness_config: Option<LivenessConfig>,
     pub health_check: Option<HealthCheckType>,
     pub ratelimit: Option<Box<dyn RatelimitStrategy>>,
+    pub combined_limit: Option<CombinedLimit>,
+    pub bulkhead: Option<Bulkhead>,
     pub route_cluster: LoadbalancerStrategyVistor,
 }
 
@@ -74,13 +144,30 @@ impl RouteVistor {
             host_name: route.host_name,
             matcher: route.matcher,
             rewrite_headers: route.rewrite_headers,
+            response_headers: route.response_headers,
+            forward_headers: route.forward_headers,
+            mirror: route.mirror,
+            timeout: route.timeout,
+            circuit_breaker: route.circuit_breaker,
+            hedge: route.hedge,
+            retry: route.retry,
+            body_logging: route.body_logging,
+            maintenance: route.maintenance,
+            cache: route.cache,
+            single_flight: route.single_flight,
+            websocket: route.websocket,
+            query_rewrite: route.query_rewrite,
             allow_deny_list: route.allow_deny_list,
+            allow_deny_default: route.allow_deny_default,
+            client_ip: route.client_ip,
             authentication: route.authentication,
             anomaly_detection: route.anomaly_detection,
             liveness_status: liveness_status.clone(),
             liveness_config: route.liveness_config,
             health_check: route.health_check,
             ratelimit: route.ratelimit,
+            combined_limit: route.combined_limit,
+            bulkhead: route.bulkhead,
             route_cluster: load,
         })
     }
@@ -95,6 +182,24 @@ impl ServiceConfigVistor {
             server_type: service_config.server_type,
             cert_str: service_config.cert_str,
             key_str: service_config.key_str,
+            graceful_shutdown_timeout: service_config.graceful_shutdown_timeout,
+            upstream_policy: service_config.upstream_policy,
+            trailing_slash: service_config.trailing_slash,
+            socket_options: service_config.socket_options,
+            upstream_pool: service_config.upstream_pool,
+            error_pages: service_config.error_pages,
+            access_log_format: service_config.access_log_format,
+            request_id: service_config.request_id,
+            trace_context: service_config.trace_context,
+            tls_policy: service_config.tls_policy,
+            sni_certs: service_config.sni_certs,
+            tcp: service_config.tcp,
+            udp: service_config.udp,
+            method_override: service_config.method_override,
+            upstream_tls: service_config.upstream_tls,
+            header_limit: service_config.header_limit,
+            bind_addresses: service_config.bind_addresses,
+            redirect: service_config.redirect,
             routes,
         })
     }
@@ -142,13 +247,16 @@ impl AppConfigVistor {
     }
 }
 #[allow(clippy::enum_variant_names)]
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum LoadbalancerStrategyVistor {
     PollRoute(PollRouteVistor),
     HeaderBasedRoute(HeaderBasedRouteVistor),
     RandomRoute(RandomRouteVistor),
     WeightBasedRoute(WeightBasedRouteVistor),
+    RegionBasedRoute(RegionBasedRouteVistor),
+    ClaimBasedRoute(ClaimBasedRouteVistor),
+    CanaryRoute(Box<CanaryRouteVistor>),
 }
 impl Default for LoadbalancerStrategyVistor {
     fn default() -> Self {
@@ -170,6 +278,13 @@ impl LoadbalancerStrategyVistor {
             LoadbalancerStrategyVistor::WeightBasedRoute(weight_based_route_vistor) => {
                 weight_based_route_vistor.routes.len()
             }
+            LoadbalancerStrategyVistor::RegionBasedRoute(region_based_route_vistor) => {
+                region_based_route_vistor.routes.len()
+            }
+            LoadbalancerStrategyVistor::ClaimBasedRoute(claim_based_route_vistor) => {
+                claim_based_route_vistor.routes.len()
+            }
+            LoadbalancerStrategyVistor::CanaryRoute(_) => 2,
         }
     }
 }
@@ -189,6 +304,15 @@ pub fn from_loadbalancer_strategy_vistor(
         LoadbalancerStrategyVistor::WeightBasedRoute(weight_based_route_vistor) => {
             LoadbalancerStrategy::WeightBased(WeightBasedRoute::from(weight_based_route_vistor))
         }
+        LoadbalancerStrategyVistor::RegionBasedRoute(region_based_route_vistor) => {
+            LoadbalancerStrategy::RegionBased(RegionBasedRoute::from(region_based_route_vistor))
+        }
+        LoadbalancerStrategyVistor::ClaimBasedRoute(claim_based_route_vistor) => {
+            LoadbalancerStrategy::ClaimBased(ClaimBasedRoute::from(claim_based_route_vistor))
+        }
+        LoadbalancerStrategyVistor::CanaryRoute(canary_route_vistor) => {
+            LoadbalancerStrategy::Canary(Box::new(CanaryRoute::from(*canary_route_vistor)))
+        }
     }
 }
 pub async fn from_loadbalancer_strategy(
@@ -211,9 +335,22 @@ pub async fn from_loadbalancer_strategy(
                 WeightBasedRouteVistor::from(weight_based_route).await,
             )
         }
+        LoadbalancerStrategy::RegionBased(region_based_route) => {
+            LoadbalancerStrategyVistor::RegionBasedRoute(
+                RegionBasedRouteVistor::from(region_based_route).await,
+            )
+        }
+        LoadbalancerStrategy::ClaimBased(claim_based_route) => {
+            LoadbalancerStrategyVistor::ClaimBasedRoute(
+                ClaimBasedRouteVistor::from(claim_based_route).await,
+            )
+        }
+        LoadbalancerStrategy::Canary(canary_route) => LoadbalancerStrategyVistor::CanaryRoute(
+            Box::new(CanaryRouteVistor::from(*canary_route).await),
+        ),
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 
 pub struct PollRouteVistor {
     #[serde(skip_serializing, skip_deserializing)]
@@ -233,7 +370,7 @@ impl PollRouteVistor {
         }
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct PollBaseRouteVistor {
     pub base_route: BaseRouteVistor,
 }
@@ -244,10 +381,16 @@ impl PollBaseRouteVistor {
         }
     }
 }
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
 pub struct BaseRouteVistor {
     pub endpoint: String,
     pub try_file: Option<String>,
+    pub index: Option<Vec<String>>,
+    pub autoindex: Option<bool>,
+    pub host_header: Option<String>,
+    pub sni: Option<String>,
+    #[serde(default = "default_preserve_header_case")]
+    pub preserve_header_case: bool,
     pub is_alive: Option<bool>,
     #[serde(skip_serializing, skip_deserializing)]
     pub anomaly_detection_status: AnomalyDetectionStatus,
@@ -259,12 +402,17 @@ impl BaseRouteVistor {
         BaseRouteVistor {
             endpoint: base_route.endpoint,
             try_file: base_route.try_file,
+            index: base_route.index,
+            autoindex: base_route.autoindex,
+            host_header: base_route.host_header,
+            sni: base_route.sni,
+            preserve_header_case: base_route.preserve_header_case,
             is_alive: *is_alive,
             anomaly_detection_status: anomaly_detection_status.clone(),
         }
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct HeaderRouteVistor {
     pub base_route: BaseRouteVistor,
     pub header_key: String,
@@ -285,10 +433,13 @@ impl HeaderRouteVistor {
         res
     }
 }
+pub fn default_preserve_header_case() -> bool {
+    true
+}
 fn default_weight() -> i32 {
     100
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct HeaderBasedRouteVistor {
     pub routes: Vec<HeaderRouteVistor>,
 }
@@ -299,7 +450,7 @@ impl HeaderBasedRouteVistor {
         }
     }
 }
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
 pub struct RandomBaseRouteVistor {
     pub base_route: BaseRouteVistor,
 }
@@ -314,13 +465,11 @@ impl RandomBaseRouteVistor {
         result
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WeightRouteVistor {
     pub base_route: BaseRouteVistor,
     #[serde(default = "default_weight")]
     pub weight: i32,
-    #[serde(skip_serializing, skip_deserializing)]
-    pub index: i32,
 }
 impl WeightRouteVistor {
     pub async fn new_list(weight_based_routes: Vec<WeightRoute>) -> Vec<WeightRouteVistor> {
@@ -330,13 +479,12 @@ impl WeightRouteVistor {
             res.push(WeightRouteVistor {
                 base_route: base_routes,
                 weight: item.weight,
-                index: item.index.load(Ordering::SeqCst) as i32,
             });
         }
         res
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct WeightBasedRouteVistor {
     pub routes: Vec<WeightRouteVistor>,
 }
@@ -348,7 +496,7 @@ impl WeightBasedRouteVistor {
         }
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct RandomRouteVistor {
     pub routes: Vec<RandomBaseRouteVistor>,
 }
@@ -359,6 +507,86 @@ impl RandomRouteVistor {
         }
     }
 }
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RegionRouteVistor {
+    pub base_route: BaseRouteVistor,
+    pub region: String,
+}
+impl RegionRouteVistor {
+    pub async fn new_list(region_routes: Vec<RegionRoute>) -> Vec<RegionRouteVistor> {
+        let mut res = vec![];
+        for item in region_routes {
+            res.push(RegionRouteVistor {
+                base_route: BaseRouteVistor::from(item.base_route).await,
+                region: item.region,
+            });
+        }
+        res
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RegionBasedRouteVistor {
+    pub regions: Vec<RegionMapping>,
+    pub routes: Vec<RegionRouteVistor>,
+}
+impl RegionBasedRouteVistor {
+    pub async fn from(region_based_route: RegionBasedRoute) -> Self {
+        RegionBasedRouteVistor {
+            regions: region_based_route.regions,
+            routes: RegionRouteVistor::new_list(region_based_route.routes).await,
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ClaimRouteVistor {
+    pub base_route: BaseRouteVistor,
+    pub claim_value: String,
+}
+impl ClaimRouteVistor {
+    pub async fn new_list(claim_routes: Vec<ClaimRoute>) -> Vec<ClaimRouteVistor> {
+        let mut res = vec![];
+        for item in claim_routes {
+            res.push(ClaimRouteVistor {
+                base_route: BaseRouteVistor::from(item.base_route).await,
+                claim_value: item.claim_value,
+            });
+        }
+        res
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ClaimBasedRouteVistor {
+    #[serde(default)]
+    pub jwt: JwtDecodeConfig,
+    pub claim: String,
+    pub routes: Vec<ClaimRouteVistor>,
+}
+impl ClaimBasedRouteVistor {
+    pub async fn from(claim_based_route: ClaimBasedRoute) -> Self {
+        ClaimBasedRouteVistor {
+            jwt: claim_based_route.jwt,
+            claim: claim_based_route.claim,
+            routes: ClaimRouteVistor::new_list(claim_based_route.routes).await,
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CanaryRouteVistor {
+    pub stable: BaseRouteVistor,
+    pub canary: BaseRouteVistor,
+    pub canary_percentage: f64,
+    pub canary_header: Option<String>,
+}
+impl CanaryRouteVistor {
+    pub async fn from(canary_route: CanaryRoute) -> Self {
+        CanaryRouteVistor {
+            stable: BaseRouteVistor::from(canary_route.stable).await,
+            canary: BaseRouteVistor::from(canary_route.canary).await,
+            canary_percentage: canary_route.canary_percentage,
+            canary_header: canary_route.canary_header,
+        }
+    }
+}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -366,12 +594,12 @@ mod tests {
     use crate::vojo::anomaly_detection::AnomalyDetectionType;
     use crate::vojo::anomaly_detection::BaseAnomalyDetectionParam;
     use crate::vojo::anomaly_detection::HttpAnomalyDetectionParam;
+    use crate::vojo::app_config::PathMatchType;
 
     use crate::vojo::health_check::BaseHealthCheckParam;
     use crate::vojo::health_check::HttpHealthCheckParam;
     use crate::vojo::route::HeaderValueMappingType;
     use crate::vojo::route::RegexMatch;
-    use std::sync::atomic::AtomicIsize;
     use std::sync::Arc;
     use tokio::sync::RwLock;
     #[tokio::test]
@@ -384,12 +612,17 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
-                    index: 0,
                     weight: 100,
                 }],
             }),
@@ -399,6 +632,7 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             liveness_status: LivenessStatus {
                 current_liveness_count: 0,
@@ -406,20 +640,46 @@ mod tests {
             anomaly_detection: Some(AnomalyDetectionType::Http(HttpAnomalyDetectionParam {
                 consecutive_5xx: 23,
                 base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                    max_ejection_percent: None,
+                    window_second: None,
+                    window_size: None,
                     ejection_second: 23,
                 },
             })),
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: Some(LivenessConfig {
                 min_liveness_count: 32,
+                panic_threshold: None,
+                slow_start_second: None,
             }),
 
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service_vistor = ApiServiceVistor {
@@ -427,9 +687,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let api_services = vec![api_service_vistor];
@@ -450,9 +708,15 @@ mod tests {
                     base_route: BaseRouteVistor {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: None,
                         anomaly_detection_status: AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         },
                     },
                 }],
@@ -463,6 +727,7 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             liveness_status: LivenessStatus {
                 current_liveness_count: 0,
@@ -470,21 +735,47 @@ mod tests {
             anomaly_detection: Some(AnomalyDetectionType::Http(HttpAnomalyDetectionParam {
                 consecutive_5xx: 23,
                 base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                    max_ejection_percent: None,
+                    window_second: None,
+                    window_size: None,
                     ejection_second: 23,
                 },
             })),
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: Some(LivenessConfig {
                 min_liveness_count: 32,
+                panic_threshold: None,
+                slow_start_second: None,
             }),
 
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service_vistor = ApiServiceVistor {
@@ -492,9 +783,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfigVistor {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let api_services = vec![api_service_vistor];
@@ -511,14 +800,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
-                    index: Arc::new(AtomicIsize::new(0)),
                     weight: 100,
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -526,6 +823,7 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
@@ -533,21 +831,47 @@ mod tests {
             anomaly_detection: Some(AnomalyDetectionType::Http(HttpAnomalyDetectionParam {
                 consecutive_5xx: 23,
                 base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                    max_ejection_percent: None,
+                    window_second: None,
+                    window_size: None,
                     ejection_second: 23,
                 },
             })),
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: Some(LivenessConfig {
                 min_liveness_count: 32,
+                panic_threshold: None,
+                slow_start_second: None,
             }),
 
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiService {
@@ -555,9 +879,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfig {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let api_services = vec![api_service];
@@ -574,14 +896,22 @@ mod tests {
                     base_route: BaseRoute {
                         endpoint: String::from("/"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     },
-                    index: Arc::new(AtomicIsize::new(0)),
                     weight: 100,
                 }])),
+                current_weights: Default::default(),
             }),
             health_check: Some(HealthCheckType::HttpGet(HttpHealthCheckParam {
                 base_health_check_param: BaseHealthCheckParam {
@@ -589,6 +919,7 @@ mod tests {
                     interval: 10,
                 },
                 path: String::from("value"),
+                header_criteria: None,
             })),
             liveness_status: Arc::new(RwLock::new(LivenessStatus {
                 current_liveness_count: 0,
@@ -596,21 +927,47 @@ mod tests {
             anomaly_detection: Some(AnomalyDetectionType::Http(HttpAnomalyDetectionParam {
                 consecutive_5xx: 23,
                 base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                    max_ejection_percent: None,
+                    window_second: None,
+                    window_size: None,
                     ejection_second: 23,
                 },
             })),
             rewrite_headers: None,
+            query_rewrite: None,
+            response_headers: None,
+            forward_headers: None,
+            mirror: None,
+            timeout: None,
+            circuit_breaker: None,
+            hedge: None,
+            retry: None,
+            body_logging: None,
+            maintenance: None,
+            cache: None,
+            single_flight: None,
+            websocket: None,
 
             allow_deny_list: None,
+            allow_deny_default: None,
+            client_ip: None,
             authentication: None,
             liveness_config: Some(LivenessConfig {
                 min_liveness_count: 32,
+                panic_threshold: None,
+                slow_start_second: None,
             }),
 
             ratelimit: None,
+            combined_limit: None,
+            bulkhead: None,
             matcher: Some(Matcher {
                 prefix: String::from("ss"),
                 prefix_rewrite: String::from("ssss"),
+                match_type: PathMatchType::Prefix,
+                paths: None,
+                methods: None,
+                schemes: None,
             }),
         };
         let api_service = ApiService {
@@ -618,9 +975,7 @@ mod tests {
             listen_port: 4486,
             service_config: ServiceConfig {
                 routes: vec![route],
-                server_type: Default::default(),
-                cert_str: Default::default(),
-                key_str: Default::default(),
+                ..Default::default()
             },
         };
         let api_services = vec![api_service];
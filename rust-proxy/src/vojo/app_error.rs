@@ -1,4 +1,77 @@
+use http::StatusCode;
 use thiserror::Error;
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AppErrorKind {
+    Config,
+    UpstreamUnavailable,
+    Timeout,
+    Tls,
+    Auth,
+    Forbidden,
+    NotFound,
+    #[default]
+    Internal,
+}
+impl AppErrorKind {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AppErrorKind::UpstreamUnavailable | AppErrorKind::Tls => StatusCode::BAD_GATEWAY,
+            AppErrorKind::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            AppErrorKind::Auth => StatusCode::UNAUTHORIZED,
+            AppErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            AppErrorKind::NotFound => StatusCode::NOT_FOUND,
+            AppErrorKind::Config | AppErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
 #[derive(Clone, Debug, Eq, Error, PartialEq)]
-#[error("Found no username in {0}")]
-pub struct AppError(pub String);
+#[error("{message}")]
+pub struct AppError {
+    pub kind: AppErrorKind,
+    pub message: String,
+}
+impl AppError {
+    pub fn new(kind: AppErrorKind, message: impl Into<String>) -> Self {
+        AppError {
+            kind,
+            message: message.into(),
+        }
+    }
+    pub fn status_code(&self) -> StatusCode {
+        self.kind.status_code()
+    }
+}
+/**
+ *The error without a kind is an internal error.
+ */
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        AppError::new(AppErrorKind::Internal, message)
+    }
+}
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        AppError::new(AppErrorKind::Internal, message)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_app_error_status_code() {
+        let err = AppError::from(String::from("internal"));
+        assert_eq!(err.kind, AppErrorKind::Internal);
+        assert_eq!(err.to_string(), "internal");
+        assert_eq!(err.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        for (kind, status) in [
+            (AppErrorKind::Config, StatusCode::INTERNAL_SERVER_ERROR),
+            (AppErrorKind::UpstreamUnavailable, StatusCode::BAD_GATEWAY),
+            (AppErrorKind::Timeout, StatusCode::GATEWAY_TIMEOUT),
+            (AppErrorKind::Tls, StatusCode::BAD_GATEWAY),
+            (AppErrorKind::Auth, StatusCode::UNAUTHORIZED),
+            (AppErrorKind::NotFound, StatusCode::NOT_FOUND),
+        ] {
+            assert_eq!(AppError::new(kind, "error").status_code(), status);
+        }
+    }
+}
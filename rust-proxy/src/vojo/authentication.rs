@@ -1,13 +1,17 @@
+use crate::configuration_service::config_schema::{one_of_schema, tagged_schema};
 use base64::{engine::general_purpose, Engine as _};
 use core::fmt::Debug;
 use dyn_clone::DynClone;
 use http::HeaderMap;
 use http::HeaderValue;
 
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 
-use super::app_error::AppError;
+use super::app_error::{AppError, AppErrorKind};
 
 #[typetag::serde(tag = "type")]
 pub trait AuthenticationStrategy: Sync + Send + DynClone {
@@ -26,7 +30,18 @@ impl Debug for dyn AuthenticationStrategy {
         write!(f, "{{{}}}", routes)
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+impl JsonSchema for dyn AuthenticationStrategy {
+    fn schema_name() -> String {
+        String::from("AuthenticationStrategy")
+    }
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        one_of_schema(vec![
+            tagged_schema::<BasicAuth>(gen, "BasicAuth"),
+            tagged_schema::<ApiKeyAuth>(gen, "ApiKeyAuth"),
+        ])
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct BasicAuth {
     pub credentials: String,
 }
@@ -40,7 +55,7 @@ impl AuthenticationStrategy for BasicAuth {
             .get("Authorization")
             .unwrap()
             .to_str()
-            .map_err(|err| AppError(err.to_string()))?;
+            .map_err(|err| AppError::new(AppErrorKind::Auth, err.to_string()))?;
         let split_list: Vec<_> = value.split(' ').collect();
         if split_list.len() != 2 || split_list[0] != "Basic" {
             return Ok(false);
@@ -56,7 +71,7 @@ impl AuthenticationStrategy for BasicAuth {
         self
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct ApiKeyAuth {
     pub key: String,
     pub value: String,
@@ -72,7 +87,7 @@ impl AuthenticationStrategy for ApiKeyAuth {
             .get(self.key.clone())
             .unwrap()
             .to_str()
-            .map_err(|err| AppError(err.to_string()))?;
+            .map_err(|err| AppError::new(AppErrorKind::Auth, err.to_string()))?;
         Ok(header_value == self.value)
     }
     fn as_any(&self) -> &dyn Any {
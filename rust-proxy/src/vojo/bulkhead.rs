@@ -0,0 +1,90 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::sync::OnceLock;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+
+#[derive(Debug)]
+pub struct BulkheadPermit {
+    _permit: OwnedSemaphorePermit,
+}
+struct QueueGuard {
+    queued: Arc<AtomicUsize>,
+}
+impl Drop for QueueGuard {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+pub struct Bulkhead {
+    pub max_concurrent: usize,
+    #[serde(default)]
+    pub max_queue: usize,
+    #[serde(skip)]
+    pub semaphore: Arc<OnceLock<Arc<Semaphore>>>,
+    #[serde(skip)]
+    pub queued: Arc<AtomicUsize>,
+}
+impl Bulkhead {
+    /**
+     *Wait for a permit when the queue is not full, otherwise the request is rejected with none.
+     */
+    pub async fn acquire(&self) -> Option<BulkheadPermit> {
+        let semaphore = self
+            .semaphore
+            .get_or_init(|| Arc::new(Semaphore::new(self.max_concurrent)))
+            .clone();
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return Some(BulkheadPermit { _permit: permit });
+        }
+        if self.queued.fetch_add(1, Ordering::SeqCst) >= self.max_queue {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        let _queue_guard = QueueGuard {
+            queued: self.queued.clone(),
+        };
+        let permit = semaphore.acquire_owned().await.ok()?;
+        Some(BulkheadPermit { _permit: permit })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn test_bulkhead_queue_and_reject() {
+        let bulkhead = Bulkhead {
+            max_concurrent: 1,
+            max_queue: 1,
+            ..Default::default()
+        };
+        let first = bulkhead.acquire().await.unwrap();
+        let queued_bulkhead = bulkhead.clone();
+        let queued = tokio::spawn(async move { queued_bulkhead.acquire().await.is_some() });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(bulkhead.queued.load(Ordering::SeqCst), 1);
+        assert!(bulkhead.acquire().await.is_none());
+        drop(first);
+        assert!(queued.await.unwrap());
+        assert_eq!(bulkhead.queued.load(Ordering::SeqCst), 0);
+    }
+    #[tokio::test]
+    async fn test_bulkhead_cancelled_waiter_leaves_queue() {
+        let bulkhead = Bulkhead {
+            max_concurrent: 1,
+            max_queue: 1,
+            ..Default::default()
+        };
+        let _first = bulkhead.acquire().await.unwrap();
+        assert!(timeout(Duration::from_millis(50), bulkhead.acquire())
+            .await
+            .is_err());
+        assert_eq!(bulkhead.queued.load(Ordering::SeqCst), 0);
+    }
+}
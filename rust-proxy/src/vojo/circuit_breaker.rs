@@ -0,0 +1,224 @@
+use crate::utils::human_duration;
+use crate::utils::human_duration::HumanDuration;
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+fn default_failure_threshold() -> i32 {
+    5
+}
+fn default_open_second() -> u64 {
+    30
+}
+fn default_half_open_max_requests() -> i32 {
+    1
+}
+fn default_half_open_success_threshold() -> i32 {
+    1
+}
+fn default_probe_jitter_millis() -> u64 {
+    100
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CircuitBreakerConfig {
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: i32,
+    #[serde(default = "default_open_second", with = "human_duration::seconds")]
+    #[schemars(with = "HumanDuration")]
+    pub open_second: u64,
+    #[serde(default = "default_half_open_max_requests")]
+    pub half_open_max_requests: i32,
+    #[serde(default = "default_half_open_success_threshold")]
+    pub half_open_success_threshold: i32,
+    #[serde(
+        default = "default_probe_jitter_millis",
+        with = "human_duration::millis"
+    )]
+    #[schemars(with = "HumanDuration")]
+    pub probe_jitter_millis: u64,
+}
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            failure_threshold: default_failure_threshold(),
+            open_second: default_open_second(),
+            half_open_max_requests: default_half_open_max_requests(),
+            half_open_success_threshold: default_half_open_success_threshold(),
+            probe_jitter_millis: default_probe_jitter_millis(),
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CircuitBreakerState {
+    #[default]
+    Closed = 0,
+    Open = 1,
+    HalfOpen = 2,
+}
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBreakerStatus {
+    pub state: CircuitBreakerState,
+    pub consecutive_failures: i32,
+    pub consecutive_successes: i32,
+    pub half_open_requests: i32,
+    pub half_open_max_requests: i32,
+    pub open_until: Option<Instant>,
+    pub next_probe_at: Option<Instant>,
+}
+impl CircuitBreakerStatus {
+    pub fn is_open(&self) -> bool {
+        let now = Instant::now();
+        match self.state {
+            CircuitBreakerState::Closed => false,
+            CircuitBreakerState::Open => self
+                .open_until
+                .map(|open_until| now < open_until)
+                .unwrap_or(false),
+            CircuitBreakerState::HalfOpen => {
+                self.half_open_requests >= self.half_open_max_requests
+                    || self
+                        .next_probe_at
+                        .map(|next_probe_at| now < next_probe_at)
+                        .unwrap_or(false)
+            }
+        }
+    }
+    pub fn try_acquire(&mut self, config: &CircuitBreakerConfig) -> bool {
+        if self.state == CircuitBreakerState::Closed {
+            return true;
+        }
+        if self.is_open() {
+            return false;
+        }
+        if self.state == CircuitBreakerState::Open {
+            self.state = CircuitBreakerState::HalfOpen;
+            self.consecutive_successes = 0;
+            self.half_open_requests = 0;
+        }
+        self.half_open_max_requests = config.half_open_max_requests;
+        self.half_open_requests += 1;
+        self.next_probe_at = Some(Instant::now() + probe_jitter(config.probe_jitter_millis));
+        true
+    }
+    pub fn record_outcome(&mut self, config: &CircuitBreakerConfig, is_failure: bool) {
+        match self.state {
+            CircuitBreakerState::Closed => {
+                if !is_failure {
+                    self.consecutive_failures = 0;
+                    return;
+                }
+                self.consecutive_failures += 1;
+                if self.consecutive_failures >= config.failure_threshold {
+                    self.open(config);
+                }
+            }
+            CircuitBreakerState::Open => {}
+            CircuitBreakerState::HalfOpen => {
+                self.half_open_requests = (self.half_open_requests - 1).max(0);
+                if is_failure {
+                    self.open(config);
+                    return;
+                }
+                self.consecutive_successes += 1;
+                if self.consecutive_successes >= config.half_open_success_threshold {
+                    self.reset_to_closed();
+                }
+            }
+        }
+    }
+    fn open(&mut self, config: &CircuitBreakerConfig) {
+        self.state = CircuitBreakerState::Open;
+        self.consecutive_failures = 0;
+        self.consecutive_successes = 0;
+        self.half_open_requests = 0;
+        self.open_until = Some(Instant::now() + Duration::from_secs(config.open_second));
+        self.next_probe_at = None;
+    }
+    fn reset_to_closed(&mut self) {
+        *self = CircuitBreakerStatus::default();
+    }
+}
+fn probe_jitter(probe_jitter_millis: u64) -> Duration {
+    if probe_jitter_millis == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(rand::thread_rng().gen_range(1..=probe_jitter_millis))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn create_config(half_open_success_threshold: i32) -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_threshold: 2,
+            open_second: 0,
+            half_open_max_requests: 1,
+            half_open_success_threshold,
+            probe_jitter_millis: 0,
+        }
+    }
+    #[test]
+    fn test_record_outcome_open() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 2,
+            ..Default::default()
+        };
+        let mut status = CircuitBreakerStatus::default();
+        status.record_outcome(&config, true);
+        assert!(!status.is_open());
+        status.record_outcome(&config, true);
+        assert!(status.is_open());
+        assert!(!status.try_acquire(&config));
+        status.record_outcome(&config, false);
+        assert!(status.is_open());
+    }
+    #[test]
+    fn test_half_open_single_success_is_insufficient() {
+        let config = create_config(3);
+        let mut status = CircuitBreakerStatus::default();
+        status.record_outcome(&config, true);
+        status.record_outcome(&config, true);
+        assert_eq!(status.state, CircuitBreakerState::Open);
+
+        assert!(status.try_acquire(&config));
+        assert_eq!(status.state, CircuitBreakerState::HalfOpen);
+        assert!(!status.try_acquire(&config));
+        status.record_outcome(&config, false);
+        assert_eq!(status.state, CircuitBreakerState::HalfOpen);
+
+        assert!(status.try_acquire(&config));
+        status.record_outcome(&config, false);
+        assert_eq!(status.state, CircuitBreakerState::HalfOpen);
+        assert!(status.try_acquire(&config));
+        status.record_outcome(&config, false);
+        assert_eq!(status.state, CircuitBreakerState::Closed);
+        assert!(!status.is_open());
+    }
+    #[test]
+    fn test_half_open_failure_reopen() {
+        let config = create_config(2);
+        let mut status = CircuitBreakerStatus::default();
+        status.record_outcome(&config, true);
+        status.record_outcome(&config, true);
+        assert!(status.try_acquire(&config));
+        status.record_outcome(&config, false);
+        assert!(status.try_acquire(&config));
+        status.record_outcome(&config, true);
+        assert_eq!(status.state, CircuitBreakerState::Open);
+    }
+    #[test]
+    fn test_half_open_probe_jitter() {
+        let config = CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_second: 0,
+            half_open_max_requests: 2,
+            half_open_success_threshold: 2,
+            probe_jitter_millis: 10000,
+        };
+        let mut status = CircuitBreakerStatus::default();
+        status.record_outcome(&config, true);
+        assert!(status.try_acquire(&config));
+        assert!(!status.try_acquire(&config));
+        assert!(status.is_open());
+    }
+}
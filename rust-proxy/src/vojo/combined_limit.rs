@@ -0,0 +1,181 @@
+use super::app_error::AppError;
+use super::rate_limit::RatelimitStrategy;
+use http::HeaderMap;
+use http::HeaderValue;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitReason {
+    Concurrency,
+    Rate,
+}
+impl LimitReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitReason::Concurrency => "concurrency",
+            LimitReason::Rate => "rate",
+        }
+    }
+}
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    in_flight: Option<Arc<AtomicUsize>>,
+}
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        if let Some(in_flight) = &self.in_flight {
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+#[derive(Debug)]
+pub enum LimitResult {
+    Allowed(ConcurrencyPermit),
+    Limited(LimitReason),
+}
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CombinedLimit {
+    pub max_concurrent_requests: Option<usize>,
+    pub ratelimit: Option<Box<dyn RatelimitStrategy>>,
+    pub response: Option<RatelimitResponse>,
+    #[serde(skip)]
+    pub in_flight: Arc<AtomicUsize>,
+}
+/**
+ *The response of the limited request,the default is 429 with the json body and the reason header.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct RatelimitResponse {
+    pub status_code: Option<u16>,
+    pub body: Option<String>,
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub omit_headers: bool,
+}
+impl RatelimitResponse {
+    pub fn status_code(&self) -> StatusCode {
+        self.status_code
+            .and_then(|item| StatusCode::from_u16(item).ok())
+            .unwrap_or(StatusCode::TOO_MANY_REQUESTS)
+    }
+}
+impl CombinedLimit {
+    fn acquire_concurrency(&self) -> Option<ConcurrencyPermit> {
+        let Some(max_concurrent_requests) = self.max_concurrent_requests else {
+            return Some(ConcurrencyPermit { in_flight: None });
+        };
+        if self.in_flight.fetch_add(1, Ordering::SeqCst) >= max_concurrent_requests {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(ConcurrencyPermit {
+            in_flight: Some(self.in_flight.clone()),
+        })
+    }
+    pub async fn check(
+        &self,
+        headers: HeaderMap<HeaderValue>,
+        remote_ip: String,
+    ) -> Result<LimitResult, AppError> {
+        let Some(permit) = self.acquire_concurrency() else {
+            return Ok(LimitResult::Limited(LimitReason::Concurrency));
+        };
+        if let Some(mut ratelimit) = self.ratelimit.clone() {
+            if ratelimit.should_limit(headers, remote_ip).await? {
+                return Ok(LimitResult::Limited(LimitReason::Rate));
+            }
+        }
+        Ok(LimitResult::Allowed(permit))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vojo::rate_limit::{
+        FixedWindowRateLimit, IPBasedRatelimit, LimitLocation, TimeUnit,
+    };
+    use dashmap::DashMap;
+    use std::sync::Mutex;
+
+    fn create_combined_limit(
+        max_concurrent_requests: Option<usize>,
+        rate_per_unit: Option<u128>,
+    ) -> CombinedLimit {
+        CombinedLimit {
+            max_concurrent_requests,
+            ratelimit: rate_per_unit.map(|rate_per_unit| {
+                Box::new(FixedWindowRateLimit {
+                    rate_per_unit,
+                    unit: TimeUnit::Minute,
+                    limit_location: LimitLocation::IP(IPBasedRatelimit {
+                        value: String::from("127.0.0.1"),
+                    }),
+                    response: None,
+                    count_map: Arc::new(DashMap::new()),
+                    lock: Arc::new(Mutex::new(0)),
+                }) as Box<dyn RatelimitStrategy>
+            }),
+            response: None,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    fn limited_reason(result: &LimitResult) -> Option<LimitReason> {
+        match result {
+            LimitResult::Allowed(_) => None,
+            LimitResult::Limited(reason) => Some(*reason),
+        }
+    }
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let combined_limit = create_combined_limit(Some(1), None);
+        let ip = String::from("127.0.0.1");
+        let first = combined_limit
+            .check(HeaderMap::new(), ip.clone())
+            .await
+            .unwrap();
+        assert_eq!(limited_reason(&first), None);
+        let second = combined_limit
+            .check(HeaderMap::new(), ip.clone())
+            .await
+            .unwrap();
+        assert_eq!(limited_reason(&second), Some(LimitReason::Concurrency));
+        drop(first);
+        let third = combined_limit.check(HeaderMap::new(), ip).await.unwrap();
+        assert_eq!(limited_reason(&third), None);
+    }
+    #[tokio::test]
+    async fn test_rate_limit_release_concurrency() {
+        let combined_limit = create_combined_limit(Some(1), Some(1));
+        let ip = String::from("127.0.0.1");
+        let first = combined_limit
+            .check(HeaderMap::new(), ip.clone())
+            .await
+            .unwrap();
+        assert_eq!(limited_reason(&first), None);
+        drop(first);
+        let second = combined_limit.check(HeaderMap::new(), ip).await.unwrap();
+        assert_eq!(limited_reason(&second), Some(LimitReason::Rate));
+        assert_eq!(combined_limit.in_flight.load(Ordering::SeqCst), 0);
+    }
+    #[test]
+    fn test_ratelimit_response_status_code() {
+        assert_eq!(
+            RatelimitResponse::default().status_code(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        let response: RatelimitResponse =
+            serde_yaml::from_str("status_code: 503\nbody: slow down\nomit_headers: true").unwrap();
+        assert_eq!(response.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.body, Some(String::from("slow down")));
+        assert!(response.omit_headers);
+        let invalid = RatelimitResponse {
+            status_code: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(invalid.status_code(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -1,15 +1,68 @@
+use crate::utils::human_duration;
+use crate::utils::human_duration::HumanDuration;
+use http::HeaderMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+/**
+ *The timeout and the interval are in seconds, or a duration like `30s` and `1m`.
+ */
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct BaseHealthCheckParam {
+    #[serde(with = "human_duration::seconds")]
+    #[schemars(with = "HumanDuration")]
     pub timeout: i32,
+    #[serde(with = "human_duration::seconds")]
+    #[schemars(with = "HumanDuration")]
     pub interval: i32,
 }
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct HttpHealthCheckParam {
     pub base_health_check_param: BaseHealthCheckParam,
     pub path: String,
+    pub header_criteria: Option<HeaderHealthCriteria>,
 }
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct HeaderHealthCriteria {
+    pub name: String,
+    #[serde(default = "default_healthy_values")]
+    pub healthy_values: Vec<String>,
+    #[serde(default = "default_degraded_values")]
+    pub degraded_values: Vec<String>,
+    #[serde(default = "default_unhealthy_values")]
+    pub unhealthy_values: Vec<String>,
+}
+fn default_healthy_values() -> Vec<String> {
+    vec![String::from("healthy")]
+}
+fn default_degraded_values() -> Vec<String> {
+    vec![String::from("degraded")]
+}
+fn default_unhealthy_values() -> Vec<String> {
+    vec![String::from("unhealthy")]
+}
+impl HeaderHealthCriteria {
+    pub fn get_status(&self, headers: &HeaderMap) -> Option<HealthStatus> {
+        let value = headers.get(self.name.as_str())?.to_str().ok()?.trim();
+        let contains =
+            |values: &Vec<String>| values.iter().any(|item| item.eq_ignore_ascii_case(value));
+        if contains(&self.unhealthy_values) {
+            Some(HealthStatus::Unhealthy)
+        } else if contains(&self.degraded_values) {
+            Some(HealthStatus::Degraded)
+        } else if contains(&self.healthy_values) {
+            Some(HealthStatus::Healthy)
+        } else {
+            None
+        }
+    }
+}
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum HealthCheckType {
     HttpGet(HttpHealthCheckParam),
@@ -25,3 +78,40 @@ impl HealthCheckType {
         }
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_header_health_criteria_get_status() {
+        let criteria: HeaderHealthCriteria = serde_yaml::from_str("name: X-Health").unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(criteria.get_status(&headers), None);
+        headers.insert("x-health", "Unhealthy".parse().unwrap());
+        assert_eq!(criteria.get_status(&headers), Some(HealthStatus::Unhealthy));
+        headers.insert("x-health", "degraded".parse().unwrap());
+        assert_eq!(criteria.get_status(&headers), Some(HealthStatus::Degraded));
+        headers.insert("x-health", "healthy".parse().unwrap());
+        assert_eq!(criteria.get_status(&headers), Some(HealthStatus::Healthy));
+        headers.insert("x-health", "unknown".parse().unwrap());
+        assert_eq!(criteria.get_status(&headers), None);
+    }
+    #[test]
+    fn test_base_health_check_param_human_duration() {
+        for content in ["timeout: 5\ninterval: 30", "timeout: 5s\ninterval: 30s"] {
+            let param: BaseHealthCheckParam = serde_yaml::from_str(content).unwrap();
+            assert_eq!(param.timeout, 5);
+            assert_eq!(param.interval, 30);
+        }
+        let param: BaseHealthCheckParam =
+            serde_yaml::from_str("timeout: 2000ms\ninterval: 1m").unwrap();
+        assert_eq!((param.timeout, param.interval), (2, 60));
+        assert_eq!(
+            serde_yaml::to_string(&param).unwrap(),
+            "timeout: 2\ninterval: 60\n"
+        );
+        assert!(
+            serde_yaml::from_str::<BaseHealthCheckParam>("timeout: 500ms\ninterval: 30").is_err()
+        );
+        assert!(serde_yaml::from_str::<BaseHealthCheckParam>("timeout: 5\ninterval: 30x").is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use super::app_error::{AppError, AppErrorKind};
+use base64::{engine::general_purpose, Engine as _};
+use http::HeaderMap;
+use http::HeaderValue;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+fn default_jwt_header() -> String {
+    String::from("Authorization")
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct JwtDecodeConfig {
+    #[serde(default = "default_jwt_header")]
+    pub header: String,
+    pub hs256_secret: Option<String>,
+}
+impl Default for JwtDecodeConfig {
+    fn default() -> Self {
+        JwtDecodeConfig {
+            header: default_jwt_header(),
+            hs256_secret: None,
+        }
+    }
+}
+fn decode_segment(segment: &str) -> Result<Vec<u8>, AppError> {
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(segment.trim_end_matches('='))
+        .map_err(|e| AppError::from(e.to_string()))
+}
+pub fn hs256_sign(secret: &str, message: &str) -> Result<Vec<u8>, AppError> {
+    let key = PKey::hmac(secret.as_bytes()).map_err(|e| AppError::from(e.to_string()))?;
+    let mut signer =
+        Signer::new(MessageDigest::sha256(), &key).map_err(|e| AppError::from(e.to_string()))?;
+    signer
+        .update(message.as_bytes())
+        .map_err(|e| AppError::from(e.to_string()))?;
+    signer
+        .sign_to_vec()
+        .map_err(|e| AppError::from(e.to_string()))
+}
+impl JwtDecodeConfig {
+    pub fn decode_claims(
+        &self,
+        headers: &HeaderMap<HeaderValue>,
+    ) -> Result<Map<String, Value>, AppError> {
+        let header_value = headers
+            .get(self.header.as_str())
+            .ok_or(AppError::new(
+                AppErrorKind::Auth,
+                format!("Can not find the jwt in {}!", self.header),
+            ))?
+            .to_str()
+            .map_err(|e| AppError::new(AppErrorKind::Auth, e.to_string()))?
+            .trim();
+        let token = header_value
+            .strip_prefix("Bearer ")
+            .or_else(|| header_value.strip_prefix("bearer "))
+            .unwrap_or(header_value)
+            .trim();
+        let segments: Vec<&str> = token.split('.').collect();
+        if segments.len() != 3 {
+            return Err(AppError::new(AppErrorKind::Auth, "The jwt is malformed!"));
+        }
+        if let Some(secret) = &self.hs256_secret {
+            let jwt_header: Value = serde_json::from_slice(&decode_segment(segments[0])?)
+                .map_err(|e| AppError::new(AppErrorKind::Auth, e.to_string()))?;
+            if jwt_header.get("alg").and_then(|item| item.as_str()) != Some("HS256") {
+                return Err(AppError::new(
+                    AppErrorKind::Auth,
+                    "The jwt algorithm is not HS256!",
+                ));
+            }
+            let expected = hs256_sign(secret, &format!("{}.{}", segments[0], segments[1]))?;
+            let signature = decode_segment(segments[2])?;
+            if signature.len() != expected.len() || !openssl::memcmp::eq(&signature, &expected) {
+                return Err(AppError::new(
+                    AppErrorKind::Auth,
+                    "The jwt signature is invalid!",
+                ));
+            }
+        }
+        let claims: Value = serde_json::from_slice(&decode_segment(segments[1])?)
+            .map_err(|e| AppError::new(AppErrorKind::Auth, e.to_string()))?;
+        match claims {
+            Value::Object(map) => Ok(map),
+            _ => Err(AppError::new(
+                AppErrorKind::Auth,
+                "The jwt claims is not an object!",
+            )),
+        }
+    }
+    pub fn get_claim(&self, headers: &HeaderMap<HeaderValue>, claim: &str) -> Option<String> {
+        let claims = self.decode_claims(headers).ok()?;
+        match claims.get(claim)? {
+            Value::String(value) => Some(value.clone()),
+            Value::Null => None,
+            other => Some(other.to_string()),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn create_token(claims: &str, secret: &str) -> String {
+        let header = general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload = general_purpose::URL_SAFE_NO_PAD.encode(claims);
+        let signature = hs256_sign(secret, &format!("{}.{}", header, payload)).unwrap();
+        format!(
+            "{}.{}.{}",
+            header,
+            payload,
+            general_purpose::URL_SAFE_NO_PAD.encode(signature)
+        )
+    }
+    #[test]
+    fn test_decode_claims_without_verification() {
+        let config = JwtDecodeConfig::default();
+        let mut headers = HeaderMap::new();
+        let token = create_token(r#"{"tenant":"acme","level":3}"#, "secret");
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        assert_eq!(
+            config.get_claim(&headers, "tenant"),
+            Some(String::from("acme"))
+        );
+        assert_eq!(config.get_claim(&headers, "level"), Some(String::from("3")));
+        assert_eq!(config.get_claim(&headers, "missing"), None);
+    }
+    #[test]
+    fn test_decode_claims_with_verification() {
+        let config = JwtDecodeConfig {
+            hs256_secret: Some(String::from("secret")),
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        let token = create_token(r#"{"tenant":"acme"}"#, "secret");
+        headers.insert("Authorization", token.parse().unwrap());
+        assert!(config.decode_claims(&headers).is_ok());
+
+        let forged_token = create_token(r#"{"tenant":"acme"}"#, "other");
+        headers.insert("Authorization", forged_token.parse().unwrap());
+        assert!(config.decode_claims(&headers).is_err());
+    }
+    #[test]
+    fn test_decode_claims_invalid_token() {
+        let config = JwtDecodeConfig::default();
+        let mut headers = HeaderMap::new();
+        assert!(config.decode_claims(&headers).is_err());
+        headers.insert("Authorization", "Bearer not-a-jwt".parse().unwrap());
+        assert!(config.decode_claims(&headers).is_err());
+        headers.insert("Authorization", "Bearer a.b!.c".parse().unwrap());
+        assert!(config.get_claim(&headers, "tenant").is_none());
+    }
+}
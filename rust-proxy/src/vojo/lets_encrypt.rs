@@ -1,4 +1,6 @@
 use super::app_error::AppError;
+use crate::constants::common_constants::ACME_CHALLENGE_PATH_PREFIX;
+use crate::constants::common_constants::DEFAULT_ACME_CHALLENGE_PORT;
 use crate::constants::common_constants::DEFAULT_TEMPORARY_DIR;
 use acme_lib::persist::FilePersist;
 use acme_lib::{create_p384_key, Certificate};
@@ -6,18 +8,35 @@ use acme_lib::{Directory, DirectoryUrl, Error};
 use axum::extract::State;
 use axum::{routing::get, Router};
 use dashmap::DashMap;
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::env;
-use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc::{self, Receiver};
+lazy_static! {
+    pub static ref GLOBAL_ACME_CHALLENGE_TOKENS: Arc<DashMap<String, String>> = Default::default();
+}
+/**
+ *The standalone mode serves the challenge on a temporary server, the integrated mode answers it from the gateway listeners.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Default)]
+pub enum AcmeChallengeMode {
+    #[default]
+    Standalone,
+    Integrated,
+}
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 
 pub struct LetsEntrypt {
     pub mail_name: String,
     pub domain_name: String,
+    pub challenge_mode: Option<AcmeChallengeMode>,
+    pub challenge_port: Option<u16>,
+    pub staging: Option<bool>,
+    pub persist_dir: Option<String>,
     #[serde(skip_serializing, skip_deserializing)]
     pub token_map: Arc<DashMap<String, String>>,
 }
@@ -45,69 +64,132 @@ pub async fn dyn_reply(
     }
     Ok((axum::http::StatusCode::OK, String::from("")))
 }
+pub fn get_challenge_proof(path: &str) -> Option<String> {
+    let token = path.strip_prefix(ACME_CHALLENGE_PATH_PREFIX)?;
+    GLOBAL_ACME_CHALLENGE_TOKENS
+        .get(token)
+        .map(|item| item.value().clone())
+}
 
 impl LetsEntrypt {
     pub fn _new(mail_name: String, domain_name: String) -> Self {
         LetsEntrypt {
             mail_name,
             domain_name,
+            challenge_mode: None,
+            challenge_port: None,
+            staging: None,
+            persist_dir: None,
             token_map: Arc::new(DashMap::new()),
         }
     }
     async fn create_temp_server(
         token_map: Arc<DashMap<String, String>>,
+        port: u16,
         mut rx: Receiver<()>,
     ) -> Result<(), AppError> {
         let app = Router::new()
-            .route("/.well-known/acme-challenge/:token", get(dyn_reply))
+            .route(
+                &format!("{}:token", ACME_CHALLENGE_PATH_PREFIX),
+                get(dyn_reply),
+            )
             .with_state(token_map);
         // Create a `TcpListener` using tokio.
-        let listener = TcpListener::bind("0.0.0.0:80").await.unwrap();
+        let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
+            .await
+            .map_err(|e| AppError::from(e.to_string()))?;
 
         // Run the server with graceful shutdown
         axum::serve(listener, app)
             .with_graceful_shutdown(async move {
                 rx.recv().await;
-                info!("Close the port 80 successfully!");
+                info!("Close the port {} successfully!", port);
             })
             .await
-            .unwrap();
-        info!("Stop listening on the port 80");
+            .map_err(|e| AppError::from(e.to_string()))?;
+        info!("Stop listening on the port {}", port);
         Ok(())
     }
+    fn directory_url(&self) -> DirectoryUrl<'static> {
+        if self.staging.unwrap_or(false) {
+            DirectoryUrl::LetsEncryptStaging
+        } else {
+            DirectoryUrl::LetsEncrypt
+        }
+    }
     pub async fn start_request(&self) -> Result<Certificate, AppError> {
+        if self.challenge_mode.unwrap_or_default() == AcmeChallengeMode::Integrated {
+            let lets_entrypt = LetsEntrypt {
+                token_map: GLOBAL_ACME_CHALLENGE_TOKENS.clone(),
+                ..self.clone()
+            };
+            return lets_entrypt
+                .request_cert(self.directory_url())
+                .map_err(|e| AppError::from(e.to_string()));
+        }
         let (tx, rx) = mpsc::channel(100);
         let cloned_map = self.token_map.clone();
+        let port = self.challenge_port.unwrap_or(DEFAULT_ACME_CHALLENGE_PORT);
         tokio::spawn(async move {
-            let _ = LetsEntrypt::create_temp_server(cloned_map, rx).await;
+            if let Err(err) = LetsEntrypt::create_temp_server(cloned_map, port, rx).await {
+                error!("Start the acme challenge server error,the error is {}", err);
+            }
         });
 
-        let request_result = self.request_cert(DirectoryUrl::LetsEncrypt);
+        let request_result = self.request_cert(self.directory_url());
         if request_result.is_ok() {
-            let send_result = tx.send(()).await.map_err(|e| AppError(format!("{}", e)));
+            let send_result = tx
+                .send(())
+                .await
+                .map_err(|e| AppError::from(format!("{}", e)));
             if send_result.is_err() {
                 error!(
                     "Close the 80 port error,the error is:{}",
                     send_result.unwrap_err()
                 );
             }
-            return request_result.map_err(|e| AppError(format!("{}", e.to_string())));
+            return request_result.map_err(|e| AppError::from(format!("{}", e.to_string())));
         } else {
             error!("{}", request_result.unwrap_err());
         }
 
-        Err(AppError(format!("Request the lets_encrypt fails")))
+        Err(AppError::from(format!("Request the lets_encrypt fails")))
     }
-    pub fn request_cert(&self, directory_url: DirectoryUrl) -> Result<Certificate, Error> {
-        let result: bool = Path::new(DEFAULT_TEMPORARY_DIR).is_dir();
-        if !result {
-            let path = env::current_dir()?;
-            let absolute_path = path.join(DEFAULT_TEMPORARY_DIR);
-            std::fs::create_dir_all(absolute_path)?;
+    /**
+     *The mail name could be configured with or without the `mailto:` scheme.
+     */
+    fn account_contact(&self) -> Result<(String, Vec<String>), AppError> {
+        let mail_name = self.mail_name.trim();
+        let mail_name = mail_name.strip_prefix("mailto:").unwrap_or(mail_name);
+        match mail_name.split_once('@') {
+            Some((user, host)) if !user.is_empty() && !host.is_empty() => {
+                Ok((mail_name.to_string(), vec![format!("mailto:{}", mail_name)]))
+            }
+            _ => Err(AppError::from(format!(
+                "The acme account email {} is invalid!",
+                self.mail_name
+            ))),
+        }
+    }
+    /**
+     *The account key is persisted by the realm, so the next request with the same email reuses the account instead of creating a new one.
+     */
+    fn create_persist_dir(&self) -> Result<PathBuf, std::io::Error> {
+        let persist_dir =
+            env::current_dir()?.join(self.persist_dir.as_deref().unwrap_or(DEFAULT_TEMPORARY_DIR));
+        std::fs::create_dir_all(&persist_dir)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&persist_dir, std::fs::Permissions::from_mode(0o700))?;
         }
-        let persist = FilePersist::new(DEFAULT_TEMPORARY_DIR);
+        Ok(persist_dir)
+    }
+    pub fn request_cert(&self, directory_url: DirectoryUrl) -> Result<Certificate, Error> {
+        let (mail_name, contact) = self.account_contact().map_err(|e| Error::from(e.message))?;
+        let persist = FilePersist::new(self.create_persist_dir()?);
         let dir = Directory::from_url(persist, directory_url)?;
-        let acc = dir.account(&self.mail_name)?;
+        let acc = dir.account_with_realm(&mail_name, Some(contact))?;
         let mut ord_new = acc.new_order(&self.domain_name, &[])?;
         let ord_csr = loop {
             if let Some(ord_csr) = ord_new.confirm_validations() {
@@ -135,6 +217,7 @@ impl LetsEntrypt {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::uuid::get_uuid;
 
     #[test]
     #[ignore]
@@ -156,4 +239,96 @@ mod tests {
         let request_result = lets_entrypt.start_request().await;
         assert!(request_result.is_err());
     }
+    #[test]
+    fn test_challenge_mode_and_proof() {
+        let lets_entrypt: LetsEntrypt = serde_json::from_str(
+            r#"{"mail_name":"lsk@gmail.com","domain_name":"www.silverwind.top","challenge_mode":"Integrated","challenge_port":8080}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            lets_entrypt.challenge_mode,
+            Some(AcmeChallengeMode::Integrated)
+        );
+        assert_eq!(lets_entrypt.challenge_port, Some(8080));
+        assert!(matches!(
+            lets_entrypt.directory_url(),
+            DirectoryUrl::LetsEncrypt
+        ));
+        GLOBAL_ACME_CHALLENGE_TOKENS.insert(String::from("test-token"), String::from("proof"));
+        assert_eq!(
+            get_challenge_proof("/.well-known/acme-challenge/test-token"),
+            Some(String::from("proof"))
+        );
+        assert_eq!(get_challenge_proof("/test-token"), None);
+        assert_eq!(
+            get_challenge_proof("/.well-known/acme-challenge/other-token"),
+            None
+        );
+        GLOBAL_ACME_CHALLENGE_TOKENS.remove("test-token");
+    }
+    #[test]
+    fn test_staging_directory_url() {
+        let mut lets_entrypt = LetsEntrypt::_new(
+            String::from("lsk@gmail.com"),
+            String::from("www.silverwind.top"),
+        );
+        assert!(matches!(
+            lets_entrypt.directory_url(),
+            DirectoryUrl::LetsEncrypt
+        ));
+        lets_entrypt.staging = Some(true);
+        assert!(matches!(
+            lets_entrypt.directory_url(),
+            DirectoryUrl::LetsEncryptStaging
+        ));
+    }
+    #[test]
+    fn test_create_persist_dir_keeps_account_key() {
+        let persist_dir = env::temp_dir().join(format!("silverwind-acme-{}", get_uuid()));
+        let mut lets_entrypt = LetsEntrypt::_new(
+            String::from("lsk@gmail.com"),
+            String::from("www.silverwind.top"),
+        );
+        lets_entrypt.persist_dir = Some(persist_dir.to_string_lossy().to_string());
+        assert_eq!(lets_entrypt.create_persist_dir().unwrap(), persist_dir);
+        let account_key = persist_dir.join("account.key");
+        std::fs::write(&account_key, "account key").unwrap();
+        assert_eq!(lets_entrypt.create_persist_dir().unwrap(), persist_dir);
+        assert_eq!(
+            std::fs::read_to_string(&account_key).unwrap(),
+            "account key"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&persist_dir)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        std::fs::remove_dir_all(persist_dir).unwrap();
+    }
+    #[test]
+    fn test_account_contact_from_mail_name() {
+        for mail_name in ["admin@silverwind.top", " mailto:admin@silverwind.top "] {
+            let lets_entrypt =
+                LetsEntrypt::_new(String::from(mail_name), String::from("www.silverwind.top"));
+            assert_eq!(
+                lets_entrypt.account_contact().unwrap(),
+                (
+                    String::from("admin@silverwind.top"),
+                    vec![String::from("mailto:admin@silverwind.top")]
+                )
+            );
+        }
+        for mail_name in ["", "EMAIL", "@silverwind.top", "admin@"] {
+            let lets_entrypt =
+                LetsEntrypt::_new(String::from(mail_name), String::from("www.silverwind.top"));
+            assert!(lets_entrypt.account_contact().is_err(), "{}", mail_name);
+            assert!(lets_entrypt
+                .request_cert(DirectoryUrl::LetsEncryptStaging)
+                .is_err());
+        }
+    }
 }
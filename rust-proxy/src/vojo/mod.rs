@@ -7,7 +7,17 @@ pub mod app_config_vistor;
 pub mod app_error;
 pub mod authentication;
 pub mod base_response;
+pub mod bulkhead;
+pub mod circuit_breaker;
+pub mod combined_limit;
 pub mod health_check;
+pub mod jwt_claims;
 pub mod lets_encrypt;
+pub mod proxy_protocol;
 pub mod rate_limit;
+pub mod response_cache;
 pub mod route;
+pub mod single_flight;
+pub mod tls_policy;
+pub mod trace_context;
+pub mod upstream_policy;
@@ -0,0 +1,108 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::net::SocketAddr;
+
+static PROXY_PROTOCOL_V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+static PROXY_PROTOCOL_V2_PROXY_COMMAND: u8 = 0x21;
+static PROXY_PROTOCOL_V2_TCP4: u8 = 0x11;
+static PROXY_PROTOCOL_V2_TCP6: u8 = 0x21;
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum ProxyProtocolVersion {
+    V1,
+    V2,
+}
+/**
+ *Both addresses must have the same family, so an IPv4 address is mapped to IPv6 when the other one is IPv6.
+ */
+fn same_family(source: SocketAddr, destination: SocketAddr) -> (IpAddr, IpAddr) {
+    match (source.ip(), destination.ip()) {
+        (IpAddr::V4(source_ip), IpAddr::V6(destination_ip)) => (
+            IpAddr::V6(source_ip.to_ipv6_mapped()),
+            IpAddr::V6(destination_ip),
+        ),
+        (IpAddr::V6(source_ip), IpAddr::V4(destination_ip)) => (
+            IpAddr::V6(source_ip),
+            IpAddr::V6(destination_ip.to_ipv6_mapped()),
+        ),
+        (source_ip, destination_ip) => (source_ip, destination_ip),
+    }
+}
+impl ProxyProtocolVersion {
+    pub fn build_header(&self, source: SocketAddr, destination: SocketAddr) -> Vec<u8> {
+        let (source_ip, destination_ip) = same_family(source, destination);
+        match self {
+            ProxyProtocolVersion::V1 => {
+                let protocol = if source_ip.is_ipv4() { "TCP4" } else { "TCP6" };
+                format!(
+                    "PROXY {} {} {} {} {}\r\n",
+                    protocol,
+                    source_ip,
+                    destination_ip,
+                    source.port(),
+                    destination.port()
+                )
+                .into_bytes()
+            }
+            ProxyProtocolVersion::V2 => {
+                let (family, mut addresses) = match (source_ip, destination_ip) {
+                    (IpAddr::V4(source_ip), IpAddr::V4(destination_ip)) => (
+                        PROXY_PROTOCOL_V2_TCP4,
+                        [source_ip.octets(), destination_ip.octets()].concat(),
+                    ),
+                    (IpAddr::V6(source_ip), IpAddr::V6(destination_ip)) => (
+                        PROXY_PROTOCOL_V2_TCP6,
+                        [source_ip.octets(), destination_ip.octets()].concat(),
+                    ),
+                    _ => unreachable!(),
+                };
+                addresses.extend_from_slice(&source.port().to_be_bytes());
+                addresses.extend_from_slice(&destination.port().to_be_bytes());
+                let mut header = PROXY_PROTOCOL_V2_SIGNATURE.to_vec();
+                header.push(PROXY_PROTOCOL_V2_PROXY_COMMAND);
+                header.push(family);
+                header.extend_from_slice(&(addresses.len() as u16).to_be_bytes());
+                header.extend_from_slice(&addresses);
+                header
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_build_proxy_protocol_v1_header() {
+        let header = ProxyProtocolVersion::V1.build_header(
+            "192.168.0.1:56324".parse().unwrap(),
+            "10.0.0.2:443".parse().unwrap(),
+        );
+        assert_eq!(header, b"PROXY TCP4 192.168.0.1 10.0.0.2 56324 443\r\n");
+        let header = ProxyProtocolVersion::V1.build_header(
+            "192.168.0.1:56324".parse().unwrap(),
+            "[::1]:443".parse().unwrap(),
+        );
+        assert_eq!(
+            header,
+            b"PROXY TCP6 ::ffff:192.168.0.1 ::1 56324 443\r\n".to_vec()
+        );
+    }
+    #[test]
+    fn test_build_proxy_protocol_v2_header() {
+        let header = ProxyProtocolVersion::V2.build_header(
+            "192.168.0.1:56324".parse().unwrap(),
+            "10.0.0.2:443".parse().unwrap(),
+        );
+        assert_eq!(&header[..12], &PROXY_PROTOCOL_V2_SIGNATURE);
+        assert_eq!(
+            &header[12..],
+            &[0x21, 0x11, 0x00, 0x0c, 192, 168, 0, 1, 10, 0, 0, 2, 0xdc, 0x04, 0x01, 0xbb]
+        );
+        let header = ProxyProtocolVersion::V2.build_header(
+            "[2001:db8::1]:1".parse().unwrap(),
+            "[2001:db8::2]:2".parse().unwrap(),
+        );
+        assert_eq!(header.len(), 16 + 36);
+        assert_eq!(&header[12..16], &[0x21, 0x21, 0x00, 0x24]);
+    }
+}
@@ -1,5 +1,6 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
+use crate::configuration_service::config_schema::{one_of_schema, tagged_schema};
 use crate::constants::common_constants::DEFAULT_FIXEDWINDOW_MAP_SIZE;
 use async_trait::async_trait;
 use core::fmt::Debug;
@@ -9,15 +10,19 @@ use http::HeaderMap;
 use http::HeaderValue;
 use ipnet::Ipv4Net;
 use iprange::IpRange;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::net::Ipv4Addr;
 use std::sync::atomic::{AtomicIsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
-use tokio::sync::RwLock;
+use std::sync::MutexGuard;
 
 use super::app_error::AppError;
+use super::combined_limit::RatelimitResponse;
 
 #[typetag::serde(tag = "type")]
 #[async_trait]
@@ -31,6 +36,7 @@ pub trait RatelimitStrategy: Sync + Send + DynClone {
     fn get_debug(&self) -> String {
         String::from("debug")
     }
+    fn get_response(&self) -> Option<RatelimitResponse>;
     fn as_any(&self) -> &dyn Any;
 }
 dyn_clone::clone_trait_object!(RatelimitStrategy);
@@ -41,11 +47,22 @@ impl Debug for dyn RatelimitStrategy {
         write!(f, "{{{}}}", routes)
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+impl JsonSchema for dyn RatelimitStrategy {
+    fn schema_name() -> String {
+        String::from("RatelimitStrategy")
+    }
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        one_of_schema(vec![
+            tagged_schema::<TokenBucketRateLimit>(gen, "TokenBucketRateLimit"),
+            tagged_schema::<FixedWindowRateLimit>(gen, "FixedWindowRateLimit"),
+        ])
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct IPBasedRatelimit {
     pub value: String,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct HeaderBasedRatelimit {
     pub key: String,
     pub value: String,
@@ -55,11 +72,11 @@ impl HeaderBasedRatelimit {
         format!("{}:{}", self.key, self.value)
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct IpRangeBasedRatelimit {
     pub value: String,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum LimitLocation {
     IP(IPBasedRatelimit),
@@ -75,7 +92,7 @@ impl LimitLocation {
         }
     }
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum TimeUnit {
     MillionSecond,
@@ -95,28 +112,67 @@ impl TimeUnit {
         }
     }
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/**
+ *The bucket starts with the initial tokens,which is the capacity by default,
+ *and it is refilled by rate_per_unit tokens per unit up to the capacity.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TokenBucketRateLimit {
     pub rate_per_unit: u128,
     pub unit: TimeUnit,
     pub capacity: i32,
+    pub initial_tokens: Option<i32>,
     pub limit_location: LimitLocation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<RatelimitResponse>,
     #[serde(skip_serializing, skip_deserializing)]
-    pub current_count: Arc<RwLock<AtomicIsize>>,
-    #[serde(skip_serializing, skip_deserializing)]
-    pub lock: Arc<Mutex<i32>>,
-    #[serde(skip_serializing, skip_deserializing, default = "default_time")]
-    pub last_update_time: Arc<RwLock<SystemTime>>,
+    pub bucket: Arc<Mutex<Option<TokenBucket>>>,
 }
-
-fn default_time() -> Arc<RwLock<SystemTime>> {
-    Arc::new(RwLock::new(SystemTime::now()))
+/**
+ *A token is counted as the nanoseconds of the unit,so the fraction of a slow refill is kept instead of being truncated to zero.
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBucket {
+    fractional_tokens: u128,
+    last_update_time: Instant,
+}
+impl TokenBucketRateLimit {
+    fn lock_bucket(&self) -> MutexGuard<'_, Option<TokenBucket>> {
+        self.bucket.lock().unwrap_or_else(|err| {
+            warn!("The lock of the token bucket ratelimit was poisoned,recover it!");
+            self.bucket.clear_poison();
+            err.into_inner()
+        })
+    }
+    fn try_acquire(&self, now: Instant) -> bool {
+        let token = self.unit.get_million_second() * 1_000_000;
+        let capacity = self.capacity.max(0) as u128 * token;
+        let mut bucket = self.lock_bucket();
+        let bucket = bucket.get_or_insert_with(|| TokenBucket {
+            fractional_tokens: self.initial_tokens.unwrap_or(self.capacity).max(0) as u128 * token,
+            last_update_time: now,
+        });
+        let added_tokens = now
+            .saturating_duration_since(bucket.last_update_time)
+            .as_nanos()
+            .saturating_mul(self.rate_per_unit);
+        bucket.fractional_tokens = bucket
+            .fractional_tokens
+            .saturating_add(added_tokens)
+            .min(capacity);
+        bucket.last_update_time = now;
+        if bucket.fractional_tokens < token {
+            return false;
+        }
+        bucket.fractional_tokens -= token;
+        true
+    }
 }
 fn get_time_key(time_unit: TimeUnit) -> Result<String, AppError> {
     let current_time = SystemTime::now();
     let since_the_epoch = current_time
         .duration_since(UNIX_EPOCH)
-        .map_err(|err| AppError(err.to_string()))?;
+        .map_err(|err| AppError::from(err.to_string()))?;
     let in_ms =
         since_the_epoch.as_secs() * 1000 + since_the_epoch.subsec_nanos() as u64 / 1_000_000;
     let key_u64 = match time_unit {
@@ -143,13 +199,13 @@ fn matched(
             let header_value = headers.get(header_based_ratelimit.key.clone()).unwrap();
             let header_value_str = header_value
                 .to_str()
-                .map_err(|err| AppError(err.to_string()))?;
+                .map_err(|err| AppError::from(err.to_string()))?;
 
             return Ok(header_value_str == header_based_ratelimit.value);
         }
         LimitLocation::Iprange(ip_range_based_ratelimit) => {
             if !ip_range_based_ratelimit.value.contains('/') {
-                return Err(AppError(format!("The Ip Range should contain '/'.")));
+                return Err(AppError::from(format!("The Ip Range should contain '/'.")));
             }
             let ip_range: IpRange<Ipv4Net> = [ip_range_based_ratelimit.value]
                 .iter()
@@ -157,7 +213,7 @@ fn matched(
                 .collect();
             let source_ip = remote_ip
                 .parse::<Ipv4Addr>()
-                .map_err(|err| AppError(err.to_string()))?;
+                .map_err(|err| AppError::from(err.to_string()))?;
             return Ok(ip_range.contains(&source_ip));
         }
     };
@@ -174,59 +230,37 @@ impl RatelimitStrategy for TokenBucketRateLimit {
         if !match_or_not {
             return Ok(false);
         }
-        let read_lock = self.current_count.read().await;
-        let current_value = read_lock.fetch_sub(1, Ordering::SeqCst);
-        if current_value <= 0 {
-            let elapsed = self
-                .last_update_time
-                .read()
-                .await
-                .elapsed()
-                .map_err(|err| AppError(err.to_string()))?;
-            let elapsed_millis = elapsed.as_millis();
-            let mut added_count =
-                elapsed_millis * self.rate_per_unit / self.unit.get_million_second();
-
-            if added_count == 0 {
-                return Ok(true);
-            }
-            drop(read_lock);
-            let mut write_lock = self.current_count.write().await;
-            if (write_lock.load(Ordering::SeqCst) as i32) < 0 {
-                if added_count > (self.capacity as u128) {
-                    added_count = self.capacity as u128;
-                }
-                *write_lock = AtomicIsize::new(added_count as isize);
-                let mut write_timestamp = self.last_update_time.write().await;
-                *write_timestamp = SystemTime::now();
-            }
-            drop(write_lock);
-            let current_value = self
-                .current_count
-                .read()
-                .await
-                .fetch_sub(1, Ordering::SeqCst);
-            if current_value <= 0 {
-                return Ok(true);
-            }
-        }
-        Ok(false)
+        Ok(!self.try_acquire(Instant::now()))
+    }
+    fn get_response(&self) -> Option<RatelimitResponse> {
+        self.response.clone()
     }
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 
 pub struct FixedWindowRateLimit {
     pub rate_per_unit: u128,
     pub unit: TimeUnit,
     pub limit_location: LimitLocation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<RatelimitResponse>,
     #[serde(skip_serializing, skip_deserializing)]
     pub count_map: Arc<DashMap<String, Arc<AtomicIsize>>>,
     #[serde(skip_serializing, skip_deserializing)]
     pub lock: Arc<Mutex<i32>>,
 }
+impl FixedWindowRateLimit {
+    fn lock_count_map(&self) -> MutexGuard<'_, i32> {
+        self.lock.lock().unwrap_or_else(|err| {
+            warn!("The lock of the fixed window ratelimit was poisoned,recover it!");
+            self.lock.clear_poison();
+            err.into_inner()
+        })
+    }
+}
 #[typetag::serde]
 #[async_trait]
 
@@ -244,7 +278,7 @@ impl RatelimitStrategy for FixedWindowRateLimit {
         let location_key = self.limit_location.get_key();
         let key = format!("{}:{}", location_key, time_unit_key);
         if !self.count_map.contains_key(key.as_str()) {
-            let _lock = self.lock.lock().map_err(|err| AppError(err.to_string()))?;
+            let _lock = self.lock_count_map();
             if !self.count_map.contains_key(key.as_str()) {
                 if self.count_map.len() > DEFAULT_FIXEDWINDOW_MAP_SIZE as usize {
                     let first = self.count_map.iter().next().unwrap();
@@ -259,7 +293,7 @@ impl RatelimitStrategy for FixedWindowRateLimit {
         let atomic_isize = self
             .count_map
             .get(key.as_str())
-            .ok_or(AppError(String::from(
+            .ok_or(AppError::from(String::from(
                 "Can not find the key in the map of FixedWindowRateLimit!",
             )))?;
         let res = atomic_isize.fetch_add(1, Ordering::SeqCst);
@@ -268,6 +302,9 @@ impl RatelimitStrategy for FixedWindowRateLimit {
         }
         Ok(false)
     }
+    fn get_response(&self) -> Option<RatelimitResponse> {
+        self.response.clone()
+    }
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -287,9 +324,9 @@ mod tests {
             limit_location: LimitLocation::IP(IPBasedRatelimit {
                 value: String::from("192.168.0.0"),
             }),
-            current_count: Arc::new(RwLock::new(AtomicIsize::new(3))),
-            lock: Arc::new(Mutex::new(0)),
-            last_update_time: Arc::new(RwLock::new(SystemTime::now())),
+            response: None,
+            initial_tokens: Some(3),
+            bucket: Default::default(),
         };
         let mut headermap1 = HeaderMap::new();
         headermap1.insert("api_key", "test2".parse().unwrap());
@@ -320,9 +357,9 @@ mod tests {
             limit_location: LimitLocation::Iprange(IpRangeBasedRatelimit {
                 value: String::from("245.168.0.0/8"),
             }),
-            current_count: Arc::new(RwLock::new(AtomicIsize::new(3))),
-            lock: Arc::new(Mutex::new(0)),
-            last_update_time: Arc::new(RwLock::new(SystemTime::now())),
+            response: None,
+            initial_tokens: Some(3),
+            bucket: Default::default(),
         };
         let mut headermap1 = HeaderMap::new();
         headermap1.insert("api_key", "test2".parse().unwrap());
@@ -355,9 +392,9 @@ mod tests {
                 key: String::from("lsk"),
                 value: String::from("test"),
             }),
-            current_count: Arc::new(RwLock::new(AtomicIsize::new(3))),
-            lock: Arc::new(Mutex::new(0)),
-            last_update_time: Arc::new(RwLock::new(SystemTime::now())),
+            response: None,
+            initial_tokens: Some(3),
+            bucket: Default::default(),
         };
         let mut headermap1 = HeaderMap::new();
         headermap1.insert("lsk", "test".parse().unwrap());
@@ -389,9 +426,9 @@ mod tests {
                 key: String::from("lsk"),
                 value: String::from("test"),
             }),
-            current_count: Arc::new(RwLock::new(AtomicIsize::new(3))),
-            lock: Arc::new(Mutex::new(0)),
-            last_update_time: Arc::new(RwLock::new(SystemTime::now())),
+            response: None,
+            initial_tokens: Some(3),
+            bucket: Default::default(),
         };
         let mut headermap1 = HeaderMap::new();
         headermap1.insert("lsk", "test1".parse().unwrap());
@@ -422,9 +459,9 @@ mod tests {
             limit_location: LimitLocation::Iprange(IpRangeBasedRatelimit {
                 value: String::from("245.168.0.0/8"),
             }),
-            current_count: Arc::new(RwLock::new(AtomicIsize::new(3))),
-            lock: Arc::new(Mutex::new(0)),
-            last_update_time: Arc::new(RwLock::new(SystemTime::now())),
+            response: None,
+            initial_tokens: Some(3),
+            bucket: Default::default(),
         };
         let mut headermap1 = HeaderMap::new();
         headermap1.insert("api_key", "test2".parse().unwrap());
@@ -454,9 +491,9 @@ mod tests {
             limit_location: LimitLocation::IP(IPBasedRatelimit {
                 value: String::from("192.168.0.0"),
             }),
-            current_count: Arc::new(RwLock::new(AtomicIsize::new(3))),
-            lock: Arc::new(Mutex::new(0)),
-            last_update_time: Arc::new(RwLock::new(SystemTime::now())),
+            response: None,
+            initial_tokens: Some(3),
+            bucket: Default::default(),
         };
         let mut headermap1 = HeaderMap::new();
         headermap1.insert("api_key", "test2".parse().unwrap());
@@ -487,9 +524,9 @@ mod tests {
             limit_location: LimitLocation::IP(IPBasedRatelimit {
                 value: String::from("192.168.0.0"),
             }),
-            current_count: Arc::new(RwLock::new(AtomicIsize::new(3))),
-            lock: Arc::new(Mutex::new(0)),
-            last_update_time: Arc::new(RwLock::new(SystemTime::now())),
+            response: None,
+            initial_tokens: Some(3),
+            bucket: Default::default(),
         };
         let mut headermap1 = HeaderMap::new();
         headermap1.insert("api_key", "test2".parse().unwrap());
@@ -513,6 +550,56 @@ mod tests {
             .await;
         assert!(!res4.unwrap(),);
     }
+    fn create_token_bucket(
+        rate_per_unit: u128,
+        unit: TimeUnit,
+        initial_tokens: Option<i32>,
+    ) -> TokenBucketRateLimit {
+        TokenBucketRateLimit {
+            rate_per_unit,
+            capacity: 2,
+            unit,
+            initial_tokens,
+            limit_location: LimitLocation::IP(IPBasedRatelimit {
+                value: String::from("192.168.0.0"),
+            }),
+            response: None,
+            bucket: Default::default(),
+        }
+    }
+    #[test]
+    fn test_token_bucket_initial_tokens() {
+        let now = Instant::now();
+        let full = create_token_bucket(1, TimeUnit::Minute, None);
+        assert!(full.try_acquire(now));
+        assert!(full.try_acquire(now));
+        assert!(!full.try_acquire(now));
+        let empty = create_token_bucket(1, TimeUnit::Minute, Some(0));
+        assert!(!empty.try_acquire(now));
+        let overflow = create_token_bucket(1, TimeUnit::Minute, Some(100));
+        assert!(overflow.try_acquire(now));
+        assert!(overflow.try_acquire(now));
+        assert!(!overflow.try_acquire(now));
+    }
+    #[test]
+    fn test_token_bucket_slow_refill_accumulates_fraction() {
+        let token_bucket = create_token_bucket(1, TimeUnit::Minute, Some(0));
+        let start = Instant::now();
+        assert!(!token_bucket.try_acquire(start));
+        for second in (10..60).step_by(10) {
+            assert!(
+                !token_bucket.try_acquire(start + time::Duration::from_secs(second)),
+                "{}",
+                second
+            );
+        }
+        assert!(token_bucket.try_acquire(start + time::Duration::from_secs(60)));
+        assert!(!token_bucket.try_acquire(start + time::Duration::from_secs(61)));
+        assert!(token_bucket.try_acquire(start + time::Duration::from_secs(120)));
+        assert!(token_bucket.try_acquire(start + time::Duration::from_secs(600)));
+        assert!(token_bucket.try_acquire(start + time::Duration::from_secs(600)));
+        assert!(!token_bucket.try_acquire(start + time::Duration::from_secs(600)));
+    }
     #[test]
     fn test_time_unit() {
         let million_second = TimeUnit::MillionSecond;
@@ -534,6 +621,7 @@ mod tests {
             limit_location: LimitLocation::IP(IPBasedRatelimit {
                 value: String::from("192.168.0.0"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -557,6 +645,36 @@ mod tests {
             .await;
         assert!(res4.unwrap());
     }
+    #[tokio::test]
+    async fn test_fixed_window_ratelimit_recover_from_poisoned_lock() {
+        let mut fixed_window_ratelimit = FixedWindowRateLimit {
+            rate_per_unit: 3,
+            unit: TimeUnit::Minute,
+            limit_location: LimitLocation::IP(IPBasedRatelimit {
+                value: String::from("192.168.0.0"),
+            }),
+            response: None,
+            count_map: Arc::new(DashMap::new()),
+            lock: Arc::new(Mutex::new(0)),
+        };
+        let lock = fixed_window_ratelimit.lock.clone();
+        let poison_result = thread::spawn(move || {
+            let _guard = lock.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+        assert!(poison_result.is_err());
+        assert!(fixed_window_ratelimit.lock.is_poisoned());
+
+        let headermap = HeaderMap::new();
+        for _ in 0..2 {
+            let res = fixed_window_ratelimit
+                .should_limit(headermap.clone(), String::from("192.168.0.0"))
+                .await;
+            assert!(!res.unwrap());
+        }
+        assert!(!fixed_window_ratelimit.lock.is_poisoned());
+    }
 
     #[tokio::test]
     async fn test_fixed_window_ratelimit_ok2() {
@@ -566,6 +684,7 @@ mod tests {
             limit_location: LimitLocation::IP(IPBasedRatelimit {
                 value: String::from("192.168.0.0"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -613,6 +732,7 @@ mod tests {
                 key: String::from("api_key"),
                 value: String::from("test2"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -644,6 +764,7 @@ mod tests {
             limit_location: LimitLocation::Iprange(IpRangeBasedRatelimit {
                 value: String::from("192.168.0.1/8"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -675,6 +796,7 @@ mod tests {
             limit_location: LimitLocation::Iprange(IpRangeBasedRatelimit {
                 value: String::from("192.168.0.1/8"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -706,6 +828,7 @@ mod tests {
             limit_location: LimitLocation::Iprange(IpRangeBasedRatelimit {
                 value: String::from("192.168.0.1/8"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -737,6 +860,7 @@ mod tests {
             limit_location: LimitLocation::Iprange(IpRangeBasedRatelimit {
                 value: String::from("192.168.0.1/8"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -768,6 +892,7 @@ mod tests {
             limit_location: LimitLocation::Iprange(IpRangeBasedRatelimit {
                 value: String::from("192.168.0.1/8"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -799,6 +924,7 @@ mod tests {
             limit_location: LimitLocation::IP(IPBasedRatelimit {
                 value: String::from("192.168.0.0"),
             }),
+            response: None,
             count_map: Arc::new(DashMap::new()),
             lock: Arc::new(Mutex::new(0)),
         };
@@ -0,0 +1,283 @@
+use crate::constants::common_constants::DEFAULT_CACHE_MAX_BODY_SIZE;
+use bytes::Bytes;
+use dashmap::DashMap;
+use http::header::{CACHE_CONTROL, SET_COOKIE, VARY};
+use http::HeaderMap;
+use http::Method;
+use http::StatusCode;
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+lazy_static! {
+    static ref GLOBAL_RESPONSE_CACHE: Arc<DashMap<String, DashMap<String, CachedResponse>>> =
+        Default::default();
+}
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+    expires_at: Instant,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CacheConfig {
+    #[serde(default = "default_cache_ttl_seconds")]
+    pub ttl_seconds: u64,
+    #[serde(default = "default_cache_max_entries")]
+    pub max_entries: usize,
+    #[serde(default)]
+    pub key_headers: Vec<String>,
+    #[serde(default = "default_cache_max_body_bytes")]
+    pub max_body_bytes: u64,
+}
+fn default_cache_ttl_seconds() -> u64 {
+    60
+}
+fn default_cache_max_entries() -> usize {
+    1024
+}
+fn default_cache_max_body_bytes() -> u64 {
+    DEFAULT_CACHE_MAX_BODY_SIZE
+}
+pub fn cache_directives(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|item| item.to_str().ok())
+        .flat_map(|item| item.split(','))
+        .map(|item| item.trim().to_ascii_lowercase())
+        .collect()
+}
+impl CacheConfig {
+    /**
+     *Only the GET request is cached, and the request with no-cache or no-store always goes to the upstream.
+     */
+    pub fn cache_key(&self, method: &Method, path: &str, headers: &HeaderMap) -> Option<String> {
+        if method != Method::GET {
+            return None;
+        }
+        if cache_directives(headers)
+            .iter()
+            .any(|item| item == "no-cache" || item == "no-store")
+        {
+            return None;
+        }
+        let mut key = format!("{} {}", method, path);
+        for header_name in self.key_headers.iter() {
+            let value = headers
+                .get(header_name.as_str())
+                .and_then(|item| item.to_str().ok())
+                .unwrap_or_default();
+            key.push_str(&format!("\n{}:{}", header_name.to_ascii_lowercase(), value));
+        }
+        Some(key)
+    }
+    /**
+     *The max-age of the response overrides the configured ttl, the private or uncacheable response is not stored.
+     *The response of the authorized request is stored only when it is public or has s-maxage.
+     */
+    pub fn get_ttl(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+        authorized: bool,
+    ) -> Option<Duration> {
+        if !status.is_success() || headers.contains_key(SET_COOKIE) || !self.is_vary_keyed(headers)
+        {
+            return None;
+        }
+        let directives = cache_directives(headers);
+        if directives
+            .iter()
+            .any(|item| item == "no-store" || item == "no-cache" || item == "private")
+        {
+            return None;
+        }
+        if authorized
+            && !directives
+                .iter()
+                .any(|item| item == "public" || item.starts_with("s-maxage="))
+        {
+            return None;
+        }
+        let max_age = ["s-maxage=", "max-age="].iter().find_map(|prefix| {
+            directives
+                .iter()
+                .find_map(|item| item.strip_prefix(prefix))
+                .and_then(|item| item.parse::<u64>().ok())
+        });
+        match max_age.unwrap_or(self.ttl_seconds) {
+            0 => None,
+            ttl => Some(Duration::from_secs(ttl)),
+        }
+    }
+    /**
+     *The response varying on a header out of the key headers can not be told apart by the cache key.
+     */
+    fn is_vary_keyed(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get_all(VARY)
+            .iter()
+            .filter_map(|item| item.to_str().ok())
+            .flat_map(|item| item.split(','))
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .all(|item| {
+                self.key_headers
+                    .iter()
+                    .any(|header_name| header_name.eq_ignore_ascii_case(item))
+            })
+    }
+    pub fn get(&self, route_id: &str, key: &str) -> Option<CachedResponse> {
+        let entries = GLOBAL_RESPONSE_CACHE.get(route_id)?;
+        let cached_response = entries.get(key)?.clone();
+        if cached_response.expires_at <= Instant::now() {
+            entries.remove(key);
+            return None;
+        }
+        Some(cached_response)
+    }
+    pub fn insert(
+        &self,
+        route_id: &str,
+        key: String,
+        status: StatusCode,
+        headers: HeaderMap,
+        body: Bytes,
+        ttl: Duration,
+    ) {
+        let entries = GLOBAL_RESPONSE_CACHE
+            .entry(route_id.to_string())
+            .or_default()
+            .downgrade();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            let now = Instant::now();
+            entries.retain(|_, item| item.expires_at > now);
+            if entries.len() >= self.max_entries {
+                let oldest_key = entries
+                    .iter()
+                    .min_by_key(|item| item.expires_at)
+                    .map(|item| item.key().clone());
+                if let Some(oldest_key) = oldest_key {
+                    entries.remove(&oldest_key);
+                }
+            }
+        }
+        if self.max_entries == 0 {
+            return;
+        }
+        entries.insert(
+            key,
+            CachedResponse {
+                status,
+                headers,
+                body,
+                expires_at: Instant::now() + ttl,
+            },
+        );
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+    fn create_cache_config(max_entries: usize) -> CacheConfig {
+        CacheConfig {
+            ttl_seconds: 30,
+            max_entries,
+            key_headers: vec![String::from("Accept-Language")],
+            max_body_bytes: DEFAULT_CACHE_MAX_BODY_SIZE,
+        }
+    }
+    #[test]
+    fn test_cache_key_and_ttl() {
+        let cache_config = create_cache_config(10);
+        let mut headers = HeaderMap::new();
+        headers.insert("accept-language", HeaderValue::from_static("en"));
+        assert_eq!(
+            cache_config.cache_key(&Method::GET, "/a?b=1", &headers),
+            Some(String::from("GET /a?b=1\naccept-language:en"))
+        );
+        assert_eq!(cache_config.cache_key(&Method::POST, "/a", &headers), None);
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        assert_eq!(cache_config.cache_key(&Method::GET, "/a", &headers), None);
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            cache_config.get_ttl(StatusCode::OK, &headers, false),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            cache_config.get_ttl(StatusCode::BAD_GATEWAY, &headers, false),
+            None
+        );
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("public, max-age=5"));
+        assert_eq!(
+            cache_config.get_ttl(StatusCode::OK, &headers, false),
+            Some(Duration::from_secs(5))
+        );
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("private"));
+        assert_eq!(cache_config.get_ttl(StatusCode::OK, &headers, false), None);
+    }
+    #[test]
+    fn test_cache_ttl_of_authorized_request() {
+        let cache_config = create_cache_config(10);
+        let mut headers = HeaderMap::new();
+        assert_eq!(cache_config.get_ttl(StatusCode::OK, &headers, true), None);
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("max-age=5"));
+        assert_eq!(cache_config.get_ttl(StatusCode::OK, &headers, true), None);
+        for value in ["public", "s-maxage=5"] {
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static(value));
+            assert!(
+                cache_config
+                    .get_ttl(StatusCode::OK, &headers, true)
+                    .is_some(),
+                "{}",
+                value
+            );
+        }
+    }
+    #[test]
+    fn test_cache_ttl_with_vary() {
+        let cache_config = create_cache_config(10);
+        let mut headers = HeaderMap::new();
+        headers.insert(VARY, HeaderValue::from_static("accept-language"));
+        assert_eq!(
+            cache_config.get_ttl(StatusCode::OK, &headers, false),
+            Some(Duration::from_secs(30))
+        );
+        for value in ["Accept-Language, Accept-Encoding", "*"] {
+            headers.insert(VARY, HeaderValue::from_str(value).unwrap());
+            assert_eq!(
+                cache_config.get_ttl(StatusCode::OK, &headers, false),
+                None,
+                "{}",
+                value
+            );
+        }
+    }
+    #[test]
+    fn test_cache_evict_oldest_entry() {
+        let cache_config = create_cache_config(2);
+        let route_id = "test_cache_evict_oldest_entry";
+        for (index, ttl) in [30, 10, 20].iter().enumerate() {
+            cache_config.insert(
+                route_id,
+                format!("key-{}", index),
+                StatusCode::OK,
+                HeaderMap::new(),
+                Bytes::from(format!("body-{}", index)),
+                Duration::from_secs(*ttl),
+            );
+        }
+        assert!(cache_config.get(route_id, "key-0").is_some());
+        assert!(cache_config.get(route_id, "key-1").is_none());
+        assert_eq!(
+            cache_config.get(route_id, "key-2").unwrap().body,
+            Bytes::from("body-2")
+        );
+    }
+}
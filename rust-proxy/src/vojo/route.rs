@@ -1,47 +1,120 @@
 use super::app_config::LivenessConfig;
 use super::app_config::LivenessStatus;
+use super::app_config_vistor::default_preserve_header_case;
 use super::app_config_vistor::BaseRouteVistor;
-use super::app_error::AppError;
-use crate::vojo::anomaly_detection::HttpAnomalyDetectionParam;
+use super::app_error::{AppError, AppErrorKind};
+use crate::constants::common_constants::X_CANARY;
+use crate::monitor::prometheus_exporter::{
+    get_circuit_breaker_rejected_counter, get_circuit_breaker_state_gauge,
+};
+use crate::vojo::anomaly_detection::{BaseAnomalyDetectionParam, HttpAnomalyDetectionParam};
 use crate::vojo::app_config_vistor::{
-    HeaderBasedRouteVistor, HeaderRouteVistor, PollBaseRouteVistor, PollRouteVistor,
-    RandomBaseRouteVistor, RandomRouteVistor, WeightBasedRouteVistor, WeightRouteVistor,
+    CanaryRouteVistor, ClaimBasedRouteVistor, ClaimRouteVistor, HeaderBasedRouteVistor,
+    HeaderRouteVistor, PollBaseRouteVistor, PollRouteVistor, RandomBaseRouteVistor,
+    RandomRouteVistor, RegionBasedRouteVistor, RegionRouteVistor, WeightBasedRouteVistor,
+    WeightRouteVistor,
+};
+use crate::vojo::circuit_breaker::{
+    CircuitBreakerConfig, CircuitBreakerState, CircuitBreakerStatus,
 };
+use crate::vojo::jwt_claims::JwtDecodeConfig;
 use core::fmt::Debug;
 use http::HeaderMap;
 use http::HeaderValue;
+use ipnet::IpNet;
 use log::Level;
 use rand::prelude::*;
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::sync::MutexGuard;
 use tokio::sync::RwLock;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, Duration, Instant};
 #[derive(Debug, Clone)]
 pub enum LoadbalancerStrategy {
     PollRoute(PollRoute),
     HeaderBased(HeaderBasedRoute),
     Random(RandomRoute),
     WeightBased(WeightBasedRoute),
+    RegionBased(RegionBasedRoute),
+    ClaimBased(ClaimBasedRoute),
+    Canary(Box<CanaryRoute>),
 }
 
 impl LoadbalancerStrategy {
+    /**
+     *Route to all the backends regardless of the liveness once the healthy ones fall below the panic threshold.
+     */
     pub async fn get_route(
         &mut self,
         headers: HeaderMap<HeaderValue>,
+        remote_addr: SocketAddr,
+        liveness_config: Option<&LivenessConfig>,
     ) -> Result<BaseRoute, AppError> {
+        let fail_open = match liveness_config {
+            Some(liveness_config) if liveness_config.panic_threshold.is_some() => {
+                self.is_panic_mode(liveness_config).await?
+            }
+            _ => false,
+        };
+        let selection = RouteSelection {
+            fail_open,
+            slow_start: liveness_config
+                .and_then(|item| item.slow_start_second)
+                .map(Duration::from_secs),
+        };
         match self {
-            LoadbalancerStrategy::PollRoute(poll_route) => poll_route.get_route(headers).await,
+            LoadbalancerStrategy::PollRoute(poll_route) => {
+                poll_route.get_route(headers, selection).await
+            }
 
-            LoadbalancerStrategy::HeaderBased(poll_route) => poll_route.get_route(headers).await,
+            LoadbalancerStrategy::HeaderBased(poll_route) => {
+                poll_route.get_route(headers, selection).await
+            }
 
-            LoadbalancerStrategy::Random(poll_route) => poll_route.get_route(headers).await,
+            LoadbalancerStrategy::Random(poll_route) => {
+                poll_route.get_route(headers, selection).await
+            }
+
+            LoadbalancerStrategy::WeightBased(poll_route) => {
+                poll_route.get_route(headers, selection).await
+            }
 
-            LoadbalancerStrategy::WeightBased(poll_route) => poll_route.get_route(headers).await,
+            LoadbalancerStrategy::RegionBased(poll_route) => {
+                poll_route.get_route(remote_addr, selection).await
+            }
+            LoadbalancerStrategy::ClaimBased(poll_route) => {
+                poll_route.get_route(headers, selection).await
+            }
+            LoadbalancerStrategy::Canary(poll_route) => {
+                poll_route.get_route(headers, selection).await
+            }
         }
     }
+    async fn is_panic_mode(&mut self, liveness_config: &LivenessConfig) -> Result<bool, AppError> {
+        let route_list = self.get_all_route().await?;
+        let mut healthy_count = 0;
+        for item in route_list.iter() {
+            if item.is_alive.read().await.unwrap_or(true) {
+                healthy_count += 1;
+            }
+        }
+        let is_panic_mode = liveness_config.is_panic_mode(healthy_count, route_list.len());
+        if is_panic_mode && log_enabled!(Level::Debug) {
+            debug!(
+                "The healthy count {} of {} is below the panic threshold,route to all the backends.",
+                healthy_count,
+                route_list.len()
+            );
+        }
+        Ok(is_panic_mode)
+    }
     pub async fn get_all_route(&mut self) -> Result<Vec<BaseRoute>, AppError> {
         match self {
             LoadbalancerStrategy::PollRoute(poll_route) => poll_route.get_all_route().await,
@@ -50,36 +123,125 @@ impl LoadbalancerStrategy {
             LoadbalancerStrategy::Random(poll_route) => poll_route.get_all_route().await,
 
             LoadbalancerStrategy::WeightBased(poll_route) => poll_route.get_all_route().await,
+
+            LoadbalancerStrategy::RegionBased(poll_route) => poll_route.get_all_route().await,
+
+            LoadbalancerStrategy::ClaimBased(poll_route) => poll_route.get_all_route().await,
+
+            LoadbalancerStrategy::Canary(poll_route) => poll_route.get_all_route().await,
         }
     }
 }
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub struct AnomalyDetectionStatus {
     pub consecutive_5xx: i32,
+    #[serde(skip)]
+    pub recent_responses: VecDeque<(Instant, bool)>,
+}
+impl AnomalyDetectionStatus {
+    pub fn record_response(&mut self, is_5xx: bool, param: &BaseAnomalyDetectionParam) -> i32 {
+        let now = Instant::now();
+        self.recent_responses.push_back((now, is_5xx));
+        if let Some(window_second) = param.window_second {
+            let window = Duration::from_secs(window_second);
+            while let Some((time, _)) = self.recent_responses.front() {
+                if now.duration_since(*time) <= window {
+                    break;
+                }
+                self.recent_responses.pop_front();
+            }
+        }
+        if let Some(window_size) = param.window_size {
+            while self.recent_responses.len() > window_size {
+                self.recent_responses.pop_front();
+            }
+        }
+        self.recent_responses
+            .iter()
+            .filter(|(_, is_5xx)| *is_5xx)
+            .count() as i32
+    }
 }
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct BaseRoute {
     pub endpoint: String,
     pub try_file: Option<String>,
+    pub index: Option<Vec<String>>,
+    pub autoindex: Option<bool>,
+    pub host_header: Option<String>,
+    pub sni: Option<String>,
+    #[serde(default = "default_preserve_header_case")]
+    pub preserve_header_case: bool,
     #[serde(skip_deserializing)]
     pub is_alive: Arc<RwLock<Option<bool>>>,
     #[serde(skip_serializing, skip_deserializing)]
     pub anomaly_detection_status: Arc<RwLock<AnomalyDetectionStatus>>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub circuit_breaker_status: Arc<RwLock<CircuitBreakerStatus>>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub recovered_at: Arc<RwLock<Option<Instant>>>,
 }
 impl BaseRoute {
     pub fn from(base_route_vistor: BaseRouteVistor) -> Self {
         BaseRoute {
             endpoint: base_route_vistor.endpoint,
             try_file: base_route_vistor.try_file,
+            index: base_route_vistor.index,
+            autoindex: base_route_vistor.autoindex,
+            host_header: base_route_vistor.host_header,
+            sni: base_route_vistor.sni,
+            preserve_header_case: base_route_vistor.preserve_header_case,
             is_alive: Arc::new(RwLock::new(base_route_vistor.is_alive)),
             anomaly_detection_status: Arc::new(RwLock::new(
                 base_route_vistor.anomaly_detection_status,
             )),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         }
     }
 }
 
 impl BaseRoute {
+    pub async fn is_circuit_open(&self) -> bool {
+        self.circuit_breaker_status.read().await.is_open()
+    }
+    pub async fn try_acquire_circuit(&self, route_id: &str, config: &CircuitBreakerConfig) -> bool {
+        let mut circuit_breaker_status = self.circuit_breaker_status.write().await;
+        let old_state = circuit_breaker_status.state;
+        let acquired = circuit_breaker_status.try_acquire(config);
+        self.on_circuit_state_changed(route_id, old_state, circuit_breaker_status.state);
+        if !acquired {
+            get_circuit_breaker_rejected_counter(route_id.to_string(), self.endpoint.clone()).inc();
+        }
+        acquired
+    }
+    pub async fn record_outcome(
+        &self,
+        route_id: &str,
+        config: &CircuitBreakerConfig,
+        is_failure: bool,
+    ) {
+        let mut circuit_breaker_status = self.circuit_breaker_status.write().await;
+        let old_state = circuit_breaker_status.state;
+        circuit_breaker_status.record_outcome(config, is_failure);
+        self.on_circuit_state_changed(route_id, old_state, circuit_breaker_status.state);
+    }
+    fn on_circuit_state_changed(
+        &self,
+        route_id: &str,
+        old_state: CircuitBreakerState,
+        new_state: CircuitBreakerState,
+    ) {
+        if old_state == new_state {
+            return;
+        }
+        info!(
+            "The circuit breaker of route-{} has changed from {:?} to {:?}!",
+            self.endpoint, old_state, new_state
+        );
+        get_circuit_breaker_state_gauge(route_id.to_string(), self.endpoint.clone())
+            .set(new_state as i64);
+    }
     async fn update_ok(&self, liveness_status_lock: Arc<RwLock<LivenessStatus>>) -> bool {
         let mut is_alive_lock = self.is_alive.write().await;
         if is_alive_lock.is_none() {
@@ -91,6 +253,7 @@ impl BaseRoute {
             return true;
         } else if !is_alive_lock.unwrap() {
             *is_alive_lock = Some(true);
+            *self.recovered_at.write().await = Some(Instant::now());
             let mut liveness_status = liveness_status_lock.write().await;
             liveness_status.current_liveness_count += 1;
             info!(
@@ -172,39 +335,45 @@ impl BaseRoute {
         liveness_config: LivenessConfig,
     ) -> Result<(), AppError> {
         let consecutive_5xx_config = http_anomaly_detection_param.consecutive_5xx;
+        let base_anomaly_detection_param =
+            &http_anomaly_detection_param.base_anomaly_detection_param;
         let mut anomaly_detection_status = self
             .anomaly_detection_status
             .try_write()
-            .map_err(|e| AppError(e.to_string()))?;
-        if !is_5xx && anomaly_detection_status.consecutive_5xx > 0 {
+            .map_err(|e| AppError::from(e.to_string()))?;
+        if base_anomaly_detection_param.is_windowed() {
+            let failure_count =
+                anomaly_detection_status.record_response(is_5xx, base_anomaly_detection_param);
+            if !is_5xx || failure_count < consecutive_5xx_config {
+                return Ok(());
+            }
+        } else if !is_5xx && anomaly_detection_status.consecutive_5xx > 0 {
             anomaly_detection_status.consecutive_5xx = 0;
             return Ok(());
-        }
-
-        if anomaly_detection_status.consecutive_5xx < consecutive_5xx_config - 1 {
+        } else if anomaly_detection_status.consecutive_5xx < consecutive_5xx_config - 1 {
             anomaly_detection_status.consecutive_5xx += 1;
-        } else {
-            drop(anomaly_detection_status);
-            let update_success = self
-                .update_health_check_status_with_fail(liveness_status_lock.clone(), liveness_config)
+            return Ok(());
+        }
+        drop(anomaly_detection_status);
+        let update_success = self
+            .update_health_check_status_with_fail(liveness_status_lock.clone(), liveness_config)
+            .await;
+        if update_success {
+            let alive_lock = self.is_alive.clone();
+            let ejection_second = base_anomaly_detection_param.ejection_second;
+            let anomaly_detection_status_lock = self.anomaly_detection_status.clone();
+            let recovered_at_lock = self.recovered_at.clone();
+            tokio::spawn(async move {
+                BaseRoute::wait_for_alive(
+                    alive_lock,
+                    ejection_second,
+                    liveness_status_lock,
+                    anomaly_detection_status_lock,
+                    recovered_at_lock,
+                )
                 .await;
-            if update_success {
-                let alive_lock = self.is_alive.clone();
-                let ejection_second = http_anomaly_detection_param
-                    .base_anomaly_detection_param
-                    .ejection_second;
-                let anomaly_detection_status_lock = self.anomaly_detection_status.clone();
-                tokio::spawn(async move {
-                    BaseRoute::wait_for_alive(
-                        alive_lock,
-                        ejection_second,
-                        liveness_status_lock,
-                        anomaly_detection_status_lock,
-                    )
-                    .await;
-                    info!("Wait for alive successfully!");
-                });
-            }
+                info!("Wait for alive successfully!");
+            });
         }
         Ok(())
     }
@@ -214,6 +383,7 @@ impl BaseRoute {
         wait_second: u64,
         liveness_status_lock: Arc<RwLock<LivenessStatus>>,
         anomaly_detection_status_lock: Arc<RwLock<AnomalyDetectionStatus>>,
+        recovered_at_lock: Arc<RwLock<Option<Instant>>>,
     ) {
         sleep(Duration::from_secs(wait_second)).await;
         let mut is_alive_option = is_alive_lock.write().await;
@@ -222,14 +392,70 @@ impl BaseRoute {
         *is_alive_option = Some(true);
         liveness_status.current_liveness_count += 1;
         anomaly_detection_status.consecutive_5xx = 0;
+        anomaly_detection_status.recent_responses.clear();
+        *recovered_at_lock.write().await = Some(Instant::now());
     }
+    /**
+     *The recovered backend is selected with a chance growing linearly with the time since the recovery.
+     */
+    pub async fn is_warmed_up(&self, slow_start: Duration) -> bool {
+        let Some(recovered_at) = *self.recovered_at.read().await else {
+            return true;
+        };
+        let elapsed = recovered_at.elapsed();
+        if elapsed >= slow_start {
+            return true;
+        }
+        thread_rng().gen::<f64>() < elapsed.as_secs_f64() / slow_start.as_secs_f64()
+    }
+}
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RouteSelection {
+    pub fail_open: bool,
+    pub slow_start: Option<Duration>,
+}
+async fn skip_warming_up<T: Clone>(
+    cluster: Vec<T>,
+    get_base_route: fn(&T) -> &BaseRoute,
+    slow_start: Option<Duration>,
+) -> Vec<T> {
+    let Some(slow_start) = slow_start else {
+        return cluster;
+    };
+    let mut warmed_cluster = vec![];
+    for item in cluster.iter() {
+        if get_base_route(item).is_warmed_up(slow_start).await {
+            warmed_cluster.push(item.clone());
+        }
+    }
+    if warmed_cluster.is_empty() {
+        return cluster;
+    }
+    warmed_cluster
 }
 
+async fn skip_open_circuits<T: Clone>(
+    cluster: Vec<T>,
+    get_base_route: fn(&T) -> &BaseRoute,
+) -> Result<Vec<T>, AppError> {
+    let mut closed_cluster = vec![];
+    for item in cluster.iter() {
+        if !get_base_route(item).is_circuit_open().await {
+            closed_cluster.push(item.clone());
+        }
+    }
+    if closed_cluster.is_empty() && !cluster.is_empty() {
+        return Err(AppError::new(
+            AppErrorKind::UpstreamUnavailable,
+            "The circuit breakers of all the hosts in the clusters are open!",
+        ));
+    }
+    Ok(closed_cluster)
+}
 #[derive(Debug, Clone, Default)]
 pub struct WeightRoute {
     pub base_route: BaseRoute,
     pub weight: i32,
-    pub index: Arc<AtomicIsize>,
 }
 impl WeightRoute {
     pub fn new_list(weight_route_vistors: Vec<WeightRouteVistor>) -> Vec<WeightRoute> {
@@ -238,31 +464,30 @@ impl WeightRoute {
             .map(|item| WeightRoute {
                 base_route: BaseRoute::from(item.base_route.clone()),
                 weight: item.weight,
-                index: Arc::new(AtomicIsize::new(item.index as isize)),
             })
             .collect::<Vec<WeightRoute>>()
     }
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SplitSegment {
     pub split_by: String,
     pub split_list: Vec<String>,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct SplitItem {
     pub header_key: String,
     pub header_value: String,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 
 pub struct RegexMatch {
     pub value: String,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct TextMatch {
     pub value: String,
 }
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum HeaderValueMappingType {
     Regex(RegexMatch),
@@ -310,16 +535,21 @@ impl HeaderBasedRoute {
             .collect::<Vec<BaseRoute>>())
     }
 
-    async fn get_route(&mut self, headers: HeaderMap<HeaderValue>) -> Result<BaseRoute, AppError> {
+    async fn get_route(
+        &mut self,
+        headers: HeaderMap<HeaderValue>,
+        selection: RouteSelection,
+    ) -> Result<BaseRoute, AppError> {
         let mut alive_cluster: Vec<HeaderRoute> = vec![];
         for item in self.routes.clone() {
             let is_alve_result = item.base_route.is_alive.read().await;
             // let is_alive_option = is_alve_result.unwrap();
-            let is_alive = is_alve_result.unwrap_or(true);
+            let is_alive = selection.fail_open || is_alve_result.unwrap_or(true);
             if is_alive {
                 alive_cluster.push(item.clone());
             }
         }
+        let alive_cluster = skip_open_circuits(alive_cluster, |item| &item.base_route).await?;
         for item in alive_cluster.iter() {
             let headers_contais_key = headers.contains_key(item.header_key.clone());
             if !headers_contais_key {
@@ -404,16 +634,22 @@ impl RandomRoute {
             .collect::<Vec<BaseRoute>>())
     }
 
-    async fn get_route(&mut self, _headers: HeaderMap<HeaderValue>) -> Result<BaseRoute, AppError> {
+    async fn get_route(
+        &mut self,
+        _headers: HeaderMap<HeaderValue>,
+        selection: RouteSelection,
+    ) -> Result<BaseRoute, AppError> {
         let mut alive_cluster: Vec<BaseRoute> = vec![];
         for item in self.routes.clone() {
             let is_alve_result = item.base_route.is_alive.read().await;
-            let is_alive = is_alve_result.unwrap_or(true);
+            let is_alive = selection.fail_open || is_alve_result.unwrap_or(true);
             if is_alive {
                 alive_cluster.push(item.base_route.clone());
             }
             drop(is_alve_result);
         }
+        let alive_cluster = skip_open_circuits(alive_cluster, |item| item).await?;
+        let alive_cluster = skip_warming_up(alive_cluster, |item| item, selection.slow_start).await;
         let mut rng = thread_rng();
         let index = rng.gen_range(0..alive_cluster.len());
         let dst = alive_cluster[index].clone();
@@ -457,20 +693,28 @@ impl PollRoute {
             .collect::<Vec<BaseRoute>>())
     }
 
-    async fn get_route(&mut self, _headers: HeaderMap<HeaderValue>) -> Result<BaseRoute, AppError> {
+    async fn get_route(
+        &mut self,
+        _headers: HeaderMap<HeaderValue>,
+        selection: RouteSelection,
+    ) -> Result<BaseRoute, AppError> {
         let mut alive_cluster: Vec<PollBaseRoute> = vec![];
         for item in self.routes.clone() {
             let is_alve_result = item.base_route.is_alive.read().await;
-            let is_alive = is_alve_result.unwrap_or(true);
+            let is_alive = selection.fail_open || is_alve_result.unwrap_or(true);
             if is_alive {
                 alive_cluster.push(item.clone());
             }
         }
         if alive_cluster.is_empty() {
-            return Err(AppError(String::from(
+            return Err(AppError::new(
+                AppErrorKind::UpstreamUnavailable,
                 "Can not find alive host in the clusters",
-            )));
+            ));
         }
+        let alive_cluster = skip_open_circuits(alive_cluster, |item| &item.base_route).await?;
+        let alive_cluster =
+            skip_warming_up(alive_cluster, |item| &item.base_route, selection.slow_start).await;
         let older = self.current_index.fetch_add(1, Ordering::SeqCst);
         let len = alive_cluster.len();
         let current_index = older % len;
@@ -481,9 +725,19 @@ impl PollRoute {
         Ok(dst.base_route)
     }
 }
+/**
+ *The current weights of the smooth weighted round-robin,indexed as the endpoints.
+ *They are reset when the endpoints of the routes are changed.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct SmoothWeights {
+    endpoints: Vec<String>,
+    current_weights: Vec<isize>,
+}
 #[derive(Debug, Clone, Default)]
 pub struct WeightBasedRoute {
     pub routes: Arc<RwLock<Vec<WeightRoute>>>,
+    pub current_weights: Arc<std::sync::Mutex<SmoothWeights>>,
 }
 impl WeightBasedRoute {
     pub fn from(weight_based_route_vistor: WeightBasedRouteVistor) -> Self {
@@ -491,11 +745,19 @@ impl WeightBasedRoute {
             routes: Arc::new(RwLock::new(WeightRoute::new_list(
                 weight_based_route_vistor.routes,
             ))),
+            current_weights: Default::default(),
         }
     }
 }
 
 impl WeightBasedRoute {
+    fn lock_current_weights(&self) -> MutexGuard<'_, SmoothWeights> {
+        self.current_weights.lock().unwrap_or_else(|err| {
+            warn!("The lock of the current weights was poisoned,recover it!");
+            self.current_weights.clear_poison();
+            err.into_inner()
+        })
+    }
     async fn get_all_route(&mut self) -> Result<Vec<BaseRoute>, AppError> {
         let read_lock = self.routes.read().await;
         let array = read_lock
@@ -505,61 +767,338 @@ impl WeightBasedRoute {
         Ok(array)
     }
 
-    async fn get_route(&mut self, _headers: HeaderMap<HeaderValue>) -> Result<BaseRoute, AppError> {
-        let cluster_read_lock = self.routes.read().await;
-        let mut all_cluster_dead = true;
-        for (pos, e) in cluster_read_lock.iter().enumerate() {
-            let is_alive_option_lock = e.base_route.is_alive.read().await;
-            let is_alive = is_alive_option_lock.unwrap_or(true);
-            if is_alive {
-                all_cluster_dead = false;
-                let old_value = e.index.fetch_sub(1, Ordering::SeqCst);
-                if old_value > 0 {
-                    if log_enabled!(Level::Debug) {
-                        debug!("WeightRoute current index:{}", pos as i32);
-                    }
-                    return Ok(e.base_route.clone());
+    async fn get_route(
+        &mut self,
+        _headers: HeaderMap<HeaderValue>,
+        selection: RouteSelection,
+    ) -> Result<BaseRoute, AppError> {
+        let cluster_lock = self.routes.read().await;
+        let mut alive_cluster: Vec<bool> = vec![];
+        for e in cluster_lock.iter() {
+            let is_alive =
+                selection.fail_open || e.base_route.is_alive.read().await.unwrap_or(true);
+            alive_cluster.push(is_alive);
+        }
+        if alive_cluster.iter().all(|is_alive| !is_alive) {
+            return Err(AppError::new(
+                AppErrorKind::UpstreamUnavailable,
+                "There are no alive host!",
+            ));
+        }
+        let mut available_cluster: Vec<bool> = vec![];
+        for (pos, e) in cluster_lock.iter().enumerate() {
+            available_cluster.push(alive_cluster[pos] && !e.base_route.is_circuit_open().await);
+        }
+        if available_cluster.iter().all(|is_available| !is_available) {
+            return Err(AppError::new(
+                AppErrorKind::UpstreamUnavailable,
+                "The circuit breakers of all the hosts in the clusters are open!",
+            ));
+        }
+        if let Some(slow_start) = selection.slow_start {
+            let mut warmed_cluster = available_cluster.clone();
+            for (pos, e) in cluster_lock.iter().enumerate() {
+                if warmed_cluster[pos] && !e.base_route.is_warmed_up(slow_start).await {
+                    warmed_cluster[pos] = false;
+                }
+            }
+            if warmed_cluster.iter().any(|is_warmed| *is_warmed) {
+                available_cluster = warmed_cluster;
+            }
+        }
+        // The smooth weighted round-robin of nginx,there is no await while the current weights are locked.
+        let selected = {
+            let mut smooth_weights = self.lock_current_weights();
+            if !smooth_weights
+                .endpoints
+                .iter()
+                .eq(cluster_lock.iter().map(|item| &item.base_route.endpoint))
+            {
+                smooth_weights.endpoints = cluster_lock
+                    .iter()
+                    .map(|item| item.base_route.endpoint.clone())
+                    .collect();
+                smooth_weights.current_weights = vec![0; cluster_lock.len()];
+            }
+            let current_weights = &mut smooth_weights.current_weights;
+            let mut total_weight = 0;
+            let mut selected: Option<usize> = None;
+            for (pos, e) in cluster_lock.iter().enumerate() {
+                if !available_cluster[pos] || e.weight <= 0 {
+                    continue;
+                }
+                total_weight += e.weight as isize;
+                current_weights[pos] += e.weight as isize;
+                if selected.is_none_or(|max_pos| current_weights[pos] > current_weights[max_pos]) {
+                    selected = Some(pos);
                 }
             }
+            if let Some(pos) = selected {
+                current_weights[pos] -= total_weight;
+            }
+            selected
+        };
+        let pos = selected.ok_or(AppError::new(
+            AppErrorKind::UpstreamUnavailable,
+            "WeightRoute get route error",
+        ))?;
+        if log_enabled!(Level::Debug) {
+            debug!("WeightRoute current index:{}", pos as i32);
         }
+        Ok(cluster_lock[pos].base_route.clone())
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct RegionMapping {
+    pub region: String,
+    pub ip_ranges: Vec<String>,
+}
+impl RegionMapping {
+    fn contains(&self, ip: IpAddr) -> bool {
+        self.ip_ranges.iter().any(|item| {
+            if let Ok(ip_net) = item.parse::<IpNet>() {
+                ip_net.contains(&ip)
+            } else if let Ok(single_ip) = item.parse::<IpAddr>() {
+                single_ip == ip
+            } else {
+                false
+            }
+        })
+    }
+}
+#[derive(Debug, Clone, Default)]
+pub struct RegionRoute {
+    pub base_route: BaseRoute,
+    pub region: String,
+}
+impl RegionRoute {
+    pub fn new_list(region_route_vistors: Vec<RegionRouteVistor>) -> Vec<RegionRoute> {
+        region_route_vistors
+            .iter()
+            .map(|item| RegionRoute {
+                base_route: BaseRoute::from(item.base_route.clone()),
+                region: item.region.clone(),
+            })
+            .collect::<Vec<RegionRoute>>()
+    }
+}
+#[derive(Debug, Clone, Default)]
+pub struct RegionBasedRoute {
+    pub regions: Vec<RegionMapping>,
+    pub routes: Vec<RegionRoute>,
+}
+impl RegionBasedRoute {
+    pub fn from(region_based_route_vistor: RegionBasedRouteVistor) -> Self {
+        RegionBasedRoute {
+            regions: region_based_route_vistor.regions,
+            routes: RegionRoute::new_list(region_based_route_vistor.routes),
+        }
+    }
+}
 
-        drop(cluster_read_lock);
-        if all_cluster_dead {
-            return Err(AppError(String::from("There are no alive host!")));
-        }
-        let mut new_lock = self.routes.write().await;
-        let index_is_alive = new_lock.iter().any(|f| {
-            let tt = f.index.load(Ordering::SeqCst);
-            tt.is_positive()
-        });
-        if !index_is_alive {
-            (*new_lock).iter_mut().for_each(|weight_route| {
-                weight_route.index = Arc::new(AtomicIsize::from(weight_route.weight as isize))
-            });
+impl RegionBasedRoute {
+    async fn get_all_route(&mut self) -> Result<Vec<BaseRoute>, AppError> {
+        Ok(self
+            .routes
+            .iter()
+            .map(|item| item.base_route.clone())
+            .collect::<Vec<BaseRoute>>())
+    }
+    fn get_region(&self, ip: IpAddr) -> Option<String> {
+        self.regions
+            .iter()
+            .find(|item| item.contains(ip))
+            .map(|item| item.region.clone())
+    }
+
+    async fn get_route(
+        &mut self,
+        remote_addr: SocketAddr,
+        selection: RouteSelection,
+    ) -> Result<BaseRoute, AppError> {
+        let mut alive_cluster: Vec<RegionRoute> = vec![];
+        for item in self.routes.clone() {
+            let is_alve_result = item.base_route.is_alive.read().await;
+            let is_alive = selection.fail_open || is_alve_result.unwrap_or(true);
+            if is_alive {
+                alive_cluster.push(item.clone());
+            }
+        }
+        if alive_cluster.is_empty() {
+            return Err(AppError::new(
+                AppErrorKind::UpstreamUnavailable,
+                "Can not find alive host in the clusters",
+            ));
+        }
+        let alive_cluster = skip_open_circuits(alive_cluster, |item| &item.base_route).await?;
+        let region_option = self.get_region(remote_addr.ip());
+        let region_cluster = match region_option.clone() {
+            Some(region) => alive_cluster
+                .iter()
+                .filter(|item| item.region == region)
+                .cloned()
+                .collect::<Vec<RegionRoute>>(),
+            None => vec![],
+        };
+        if log_enabled!(Level::Debug) {
+            debug!(
+                "RegionBasedRoute,the region of {} is {:?}",
+                remote_addr, region_option
+            );
+        }
+        let candidates = if region_cluster.is_empty() {
+            alive_cluster
+        } else {
+            region_cluster
+        };
+        let mut rng = thread_rng();
+        let index = rng.gen_range(0..candidates.len());
+        Ok(candidates[index].base_route.clone())
+    }
+}
+#[derive(Debug, Clone, Default)]
+pub struct ClaimRoute {
+    pub base_route: BaseRoute,
+    pub claim_value: String,
+}
+impl ClaimRoute {
+    pub fn new_list(claim_route_vistors: Vec<ClaimRouteVistor>) -> Vec<ClaimRoute> {
+        claim_route_vistors
+            .iter()
+            .map(|item| ClaimRoute {
+                base_route: BaseRoute::from(item.base_route.clone()),
+                claim_value: item.claim_value.clone(),
+            })
+            .collect::<Vec<ClaimRoute>>()
+    }
+}
+#[derive(Debug, Clone, Default)]
+pub struct ClaimBasedRoute {
+    pub jwt: JwtDecodeConfig,
+    pub claim: String,
+    pub routes: Vec<ClaimRoute>,
+}
+impl ClaimBasedRoute {
+    pub fn from(claim_based_route_vistor: ClaimBasedRouteVistor) -> Self {
+        ClaimBasedRoute {
+            jwt: claim_based_route_vistor.jwt,
+            claim: claim_based_route_vistor.claim,
+            routes: ClaimRoute::new_list(claim_based_route_vistor.routes),
         }
-        drop(new_lock);
-        let cluster_read_lock2 = self.routes.read().await;
+    }
+}
 
-        for (pos, e) in cluster_read_lock2.iter().enumerate() {
-            let is_alive_option_lock = e.base_route.is_alive.read().await;
-            let is_alive = is_alive_option_lock.unwrap_or(true);
+impl ClaimBasedRoute {
+    async fn get_all_route(&mut self) -> Result<Vec<BaseRoute>, AppError> {
+        Ok(self
+            .routes
+            .iter()
+            .map(|item| item.base_route.clone())
+            .collect::<Vec<BaseRoute>>())
+    }
+
+    async fn get_route(
+        &mut self,
+        headers: HeaderMap<HeaderValue>,
+        selection: RouteSelection,
+    ) -> Result<BaseRoute, AppError> {
+        let mut alive_cluster: Vec<ClaimRoute> = vec![];
+        for item in self.routes.clone() {
+            let is_alve_result = item.base_route.is_alive.read().await;
+            let is_alive = selection.fail_open || is_alve_result.unwrap_or(true);
             if is_alive {
-                let old_value = e.index.fetch_sub(1, Ordering::SeqCst);
-                if old_value > 0 {
-                    if log_enabled!(Level::Debug) {
-                        debug!("WeightRoute current index:{}", pos as i32);
-                    }
-                    return Ok(e.base_route.clone());
-                }
+                alive_cluster.push(item.clone());
             }
         }
-        Err(AppError(String::from("WeightRoute get route error")))
+        let alive_cluster = skip_open_circuits(alive_cluster, |item| &item.base_route).await?;
+        let first = alive_cluster.first().ok_or(AppError::new(
+            AppErrorKind::UpstreamUnavailable,
+            "Can not find alive host in the clusters",
+        ))?;
+        let claim_option = self.jwt.get_claim(&headers, self.claim.as_str());
+        if let Some(claim_value) = claim_option.clone() {
+            if let Some(item) = alive_cluster
+                .iter()
+                .find(|item| item.claim_value == claim_value)
+            {
+                return Ok(item.base_route.clone());
+            }
+        }
+        if log_enabled!(Level::Debug) {
+            debug!(
+                "ClaimBasedRoute,can not route by the claim {} with value {:?},and the first route has been selected!",
+                self.claim, claim_option
+            );
+        }
+        Ok(first.base_route.clone())
+    }
+}
+#[derive(Debug, Clone, Default)]
+pub struct CanaryRoute {
+    pub stable: BaseRoute,
+    pub canary: BaseRoute,
+    pub canary_percentage: f64,
+    pub canary_header: Option<String>,
+}
+impl CanaryRoute {
+    pub fn from(canary_route_vistor: CanaryRouteVistor) -> Self {
+        CanaryRoute {
+            stable: BaseRoute::from(canary_route_vistor.stable),
+            canary: BaseRoute::from(canary_route_vistor.canary),
+            canary_percentage: canary_route_vistor.canary_percentage,
+            canary_header: canary_route_vistor.canary_header,
+        }
+    }
+}
+
+impl CanaryRoute {
+    async fn get_all_route(&mut self) -> Result<Vec<BaseRoute>, AppError> {
+        Ok(vec![self.stable.clone(), self.canary.clone()])
+    }
+    /**
+     *The header forces the target with "true" or "false",otherwise the canary is selected by the percentage.
+     */
+    fn is_canary_selected(&self, headers: &HeaderMap<HeaderValue>) -> bool {
+        let header_name = self.canary_header.as_deref().unwrap_or(X_CANARY);
+        match headers
+            .get(header_name)
+            .and_then(|item| item.to_str().ok())
+            .map(|item| item.trim().to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("true") => true,
+            Some("false") => false,
+            _ => thread_rng().gen::<f64>() * 100.0 < self.canary_percentage,
+        }
+    }
+
+    async fn get_route(
+        &mut self,
+        headers: HeaderMap<HeaderValue>,
+        selection: RouteSelection,
+    ) -> Result<BaseRoute, AppError> {
+        let (selected, fallback) = if self.is_canary_selected(&headers) {
+            (&self.canary, &self.stable)
+        } else {
+            (&self.stable, &self.canary)
+        };
+        for item in [selected, fallback] {
+            let is_alive = selection.fail_open || item.is_alive.read().await.unwrap_or(true);
+            if is_alive && !item.is_circuit_open().await {
+                return Ok(item.clone());
+            }
+        }
+        Err(AppError::new(
+            AppErrorKind::UpstreamUnavailable,
+            "Can not find alive host in the clusters",
+        ))
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::vojo::anomaly_detection::BaseAnomalyDetectionParam;
+    use std::net::Ipv4Addr;
     use std::vec;
     #[derive(PartialEq, Eq, Debug)]
     pub struct BaseRouteWithoutLock {
@@ -588,10 +1127,18 @@ mod tests {
                     BaseRoute {
                         endpoint: String::from("http://localhost:4444"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     }
                 },
             },
@@ -600,10 +1147,18 @@ mod tests {
                     BaseRoute {
                         endpoint: String::from("http://localhost:5555"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     }
                 },
             },
@@ -612,10 +1167,18 @@ mod tests {
                     BaseRoute {
                         endpoint: String::from("http://localhost:5555"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     }
                 },
             },
@@ -628,10 +1191,18 @@ mod tests {
                     BaseRoute {
                         endpoint: String::from("http://localhost:4444"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     }
                 },
             },
@@ -640,10 +1211,18 @@ mod tests {
                     BaseRoute {
                         endpoint: String::from("http://localhost:5555"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     }
                 },
             },
@@ -652,10 +1231,18 @@ mod tests {
                     BaseRoute {
                         endpoint: String::from("http://localhost:5555"),
                         try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
                         is_alive: Arc::new(RwLock::new(None)),
                         anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                             consecutive_5xx: 100,
+                            recent_responses: Default::default(),
                         })),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
                     }
                 },
             },
@@ -667,37 +1254,58 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("http://localhost:4444"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
                 weight: 100,
-                index: Arc::new(AtomicIsize::new(0)),
             },
             WeightRoute {
                 base_route: BaseRoute {
                     endpoint: String::from("http://localhost:5555"),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                 },
                 weight: 100,
-                index: Arc::new(AtomicIsize::new(0)),
             },
             WeightRoute {
                 base_route: BaseRoute {
                     endpoint: String::from("http://localhost:6666"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
                 weight: 100,
-                index: Arc::new(AtomicIsize::new(0)),
             },
         ]
     }
@@ -707,10 +1315,18 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("http://localhost:4444"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
                 header_key: String::from("x-client"),
                 header_value_mapping_type: HeaderValueMappingType::Regex(RegexMatch {
@@ -721,10 +1337,18 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("http://localhost:5555"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
                 header_key: String::from("x-client"),
                 header_value_mapping_type: HeaderValueMappingType::Split(SplitSegment {
@@ -740,10 +1364,18 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("http://localhost:7777"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
                 header_key: String::from("x-client"),
                 header_value_mapping_type: HeaderValueMappingType::Split(SplitSegment {
@@ -759,10 +1391,18 @@ mod tests {
                 base_route: BaseRoute {
                     endpoint: String::from("http://localhost:8888"),
                     try_file: None,
+                    index: None,
+                    autoindex: None,
+                    host_header: None,
+                    sni: None,
+                    preserve_header_case: true,
                     is_alive: Arc::new(RwLock::new(None)),
                     anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                         consecutive_5xx: 100,
+                        recent_responses: Default::default(),
                     })),
+                    circuit_breaker_status: Default::default(),
+                    recovered_at: Default::default(),
                 },
                 header_key: String::from("x-client"),
                 header_value_mapping_type: HeaderValueMappingType::Text(TextMatch {
@@ -786,7 +1426,10 @@ mod tests {
             routes: routes.clone(),
         };
         for i in 0..100 {
-            let current_route = poll_rate.get_route(HeaderMap::new()).await.unwrap();
+            let current_route = poll_rate
+                .get_route(HeaderMap::new(), RouteSelection::default())
+                .await
+                .unwrap();
             let current_route_vistor = BaseRouteVistor::from(current_route).await;
             let another_route_vistor =
                 BaseRouteVistor::from(routes[i % routes.len()].base_route.clone()).await;
@@ -794,11 +1437,213 @@ mod tests {
         }
     }
     #[tokio::test]
+    async fn test_poll_route_fail_open_below_panic_threshold() {
+        let routes = get_poll_routes();
+        for item in routes[1..].iter() {
+            *item.base_route.is_alive.write().await = Some(false);
+        }
+        let mut poll_route = LoadbalancerStrategy::PollRoute(PollRoute {
+            current_index: Default::default(),
+            routes: routes.clone(),
+        });
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let mut liveness_config = LivenessConfig {
+            min_liveness_count: 1,
+            panic_threshold: None,
+            slow_start_second: None,
+        };
+        for _ in 0..routes.len() {
+            let route = poll_route
+                .get_route(HeaderMap::new(), socket, Some(&liveness_config))
+                .await
+                .unwrap();
+            assert_eq!(route.endpoint, routes[0].base_route.endpoint);
+        }
+
+        liveness_config.panic_threshold = Some(50);
+        let mut endpoints = HashSet::new();
+        for _ in 0..routes.len() {
+            let route = poll_route
+                .get_route(HeaderMap::new(), socket, Some(&liveness_config))
+                .await
+                .unwrap();
+            endpoints.insert(route.endpoint);
+        }
+        let all_endpoints = routes
+            .iter()
+            .map(|item| item.base_route.endpoint.clone())
+            .collect::<HashSet<String>>();
+        assert_eq!(endpoints, all_endpoints);
+
+        *routes[0].base_route.is_alive.write().await = Some(false);
+        assert!(poll_route
+            .get_route(HeaderMap::new(), socket, None)
+            .await
+            .is_err());
+        assert!(poll_route
+            .get_route(HeaderMap::new(), socket, Some(&liveness_config))
+            .await
+            .is_ok());
+    }
+    #[tokio::test]
+    async fn test_poll_route_slow_start_after_recovery() {
+        let recovered = BaseRoute {
+            endpoint: String::from("http://localhost:5555"),
+            is_alive: Arc::new(RwLock::new(Some(false))),
+            ..Default::default()
+        };
+        let mut poll_route = LoadbalancerStrategy::PollRoute(PollRoute {
+            current_index: Default::default(),
+            routes: vec![
+                PollBaseRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://localhost:4444"),
+                        ..Default::default()
+                    },
+                },
+                PollBaseRoute {
+                    base_route: recovered.clone(),
+                },
+            ],
+        });
+        let liveness_status = Arc::new(RwLock::new(LivenessStatus {
+            current_liveness_count: 1,
+        }));
+        assert!(
+            recovered
+                .update_health_check_status_with_ok(liveness_status)
+                .await
+        );
+        assert!(recovered.recovered_at.read().await.is_some());
+        let liveness_config = LivenessConfig {
+            min_liveness_count: 1,
+            panic_threshold: None,
+            slow_start_second: Some(100),
+        };
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let mut recovered_count = vec![];
+        for elapsed_second in [10, 80, 100] {
+            *recovered.recovered_at.write().await =
+                Instant::now().checked_sub(Duration::from_secs(elapsed_second));
+            let mut count = 0;
+            for _ in 0..2000 {
+                let route = poll_route
+                    .get_route(HeaderMap::new(), socket, Some(&liveness_config))
+                    .await
+                    .unwrap();
+                if route.endpoint == recovered.endpoint {
+                    count += 1;
+                }
+            }
+            recovered_count.push(count);
+        }
+        assert!(recovered_count[0] < 300);
+        assert!(recovered_count[1] > 500 && recovered_count[1] < 1000);
+        assert_eq!(recovered_count[2], 1000);
+    }
+    #[tokio::test]
+    async fn test_poll_route_skip_open_circuit() {
+        let routes = get_poll_routes()[0..2].to_vec();
+        let mut poll_route = PollRoute {
+            current_index: Default::default(),
+            routes: routes.clone(),
+        };
+        let circuit_breaker = CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_second: 30,
+            ..Default::default()
+        };
+        routes[0]
+            .base_route
+            .record_outcome("route", &circuit_breaker, true)
+            .await;
+        for _ in 0..10 {
+            let current_route = poll_route
+                .get_route(HeaderMap::new(), RouteSelection::default())
+                .await
+                .unwrap();
+            assert_eq!(current_route.endpoint, "http://localhost:5555");
+        }
+        routes[1]
+            .base_route
+            .record_outcome("route", &circuit_breaker, true)
+            .await;
+        assert!(poll_route
+            .get_route(HeaderMap::new(), RouteSelection::default())
+            .await
+            .is_err());
+    }
+    #[tokio::test]
+    async fn test_circuit_breaker_state_metrics() {
+        let base_route = BaseRoute {
+            endpoint: String::from("http://localhost:7777"),
+            ..Default::default()
+        };
+        let route_id = "circuit-breaker-metrics-route";
+        let state_gauge =
+            get_circuit_breaker_state_gauge(route_id.to_string(), base_route.endpoint.clone());
+        let rejected_counter =
+            get_circuit_breaker_rejected_counter(route_id.to_string(), base_route.endpoint.clone());
+        let circuit_breaker = CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_second: 0,
+            probe_jitter_millis: 0,
+            ..Default::default()
+        };
+        base_route
+            .record_outcome(route_id, &circuit_breaker, true)
+            .await;
+        assert_eq!(state_gauge.get(), CircuitBreakerState::Open as i64);
+        assert!(
+            base_route
+                .try_acquire_circuit(route_id, &circuit_breaker)
+                .await
+        );
+        assert_eq!(state_gauge.get(), CircuitBreakerState::HalfOpen as i64);
+        assert!(
+            !base_route
+                .try_acquire_circuit(route_id, &circuit_breaker)
+                .await
+        );
+        assert_eq!(rejected_counter.get(), 1);
+        base_route
+            .record_outcome(route_id, &circuit_breaker, false)
+            .await;
+        assert_eq!(state_gauge.get(), CircuitBreakerState::Closed as i64);
+    }
+    #[tokio::test]
+    async fn test_weight_route_skip_open_circuit() {
+        let routes = get_weight_routes();
+        let mut weight_route = WeightBasedRoute {
+            routes: Arc::new(RwLock::new(routes.clone())),
+            current_weights: Default::default(),
+        };
+        let circuit_breaker = CircuitBreakerConfig {
+            failure_threshold: 1,
+            open_second: 30,
+            ..Default::default()
+        };
+        routes[0]
+            .base_route
+            .record_outcome("route", &circuit_breaker, true)
+            .await;
+        for _ in 0..300 {
+            let current_route = weight_route
+                .get_route(HeaderMap::new(), RouteSelection::default())
+                .await
+                .unwrap();
+            assert_ne!(current_route.endpoint, "http://localhost:4444");
+        }
+    }
+    #[tokio::test]
     async fn test_random_route_successfully() {
         let routes = get_random_routes();
         let mut random_rate = RandomRoute { routes };
         for _ in 0..100 {
-            random_rate.get_route(HeaderMap::new()).await.unwrap();
+            random_rate
+                .get_route(HeaderMap::new(), RouteSelection::default())
+                .await
+                .unwrap();
         }
     }
     #[tokio::test]
@@ -806,78 +1651,404 @@ mod tests {
         let routes = get_weight_routes();
         let mut weight_route = WeightBasedRoute {
             routes: Arc::new(RwLock::new(routes.clone())),
+            current_weights: Default::default(),
         };
-
-        for _ in 0..100 {
-            let current_route = weight_route.get_route(HeaderMap::new()).await.unwrap();
+        for i in 0..300 {
+            let current_route = weight_route
+                .get_route(HeaderMap::new(), RouteSelection::default())
+                .await
+                .unwrap();
             assert_eq!(
                 BaseRouteWithoutLock::new(current_route).await,
-                BaseRouteWithoutLock::new(routes[0].base_route.clone()).await
+                BaseRouteWithoutLock::new(routes[i % 3].base_route.clone()).await
             );
         }
-        for _ in 0..100 {
-            let current_route = weight_route.get_route(HeaderMap::new()).await.unwrap();
-            assert_eq!(
-                BaseRouteWithoutLock::new(current_route.clone()).await,
-                BaseRouteWithoutLock::new(routes[1].base_route.clone()).await
-            );
+    }
+    #[tokio::test]
+    async fn test_weight_route_smooth_sequence() {
+        let mut routes = get_weight_routes();
+        for (route, weight) in routes.iter_mut().zip([5, 1, 1]) {
+            route.weight = weight;
         }
-        for _ in 0..100 {
-            let current_route = weight_route.get_route(HeaderMap::new()).await.unwrap();
-
-            assert_eq!(
-                BaseRouteWithoutLock::new(current_route.clone()).await,
-                BaseRouteWithoutLock::new(routes[2].base_route.clone()).await
-            );
+        let mut weight_route = WeightBasedRoute {
+            routes: Arc::new(RwLock::new(routes)),
+            current_weights: Default::default(),
+        };
+        let mut sequence = vec![];
+        for _ in 0..14 {
+            let current_route = weight_route
+                .get_route(HeaderMap::new(), RouteSelection::default())
+                .await
+                .unwrap();
+            sequence.push(current_route.endpoint);
         }
-        for _ in 0..100 {
-            let current_route = weight_route.get_route(HeaderMap::new()).await.unwrap();
-
-            assert_eq!(
-                BaseRouteWithoutLock::new(current_route).await,
-                BaseRouteWithoutLock::new(routes[0].base_route.clone()).await
-            );
+        let (a, b, c) = (
+            "http://localhost:4444",
+            "http://localhost:5555",
+            "http://localhost:6666",
+        );
+        assert_eq!(sequence, [a, a, b, a, c, a, a, a, a, b, a, c, a, a]);
+        assert_eq!(
+            weight_route.lock_current_weights().current_weights,
+            [0, 0, 0]
+        );
+    }
+    #[tokio::test]
+    async fn test_weight_route_reset_when_routes_changed() {
+        let mut routes = get_weight_routes();
+        for (route, weight) in routes.iter_mut().zip([5, 1, 1]) {
+            route.weight = weight;
         }
+        let mut weight_route = WeightBasedRoute {
+            routes: Arc::new(RwLock::new(routes)),
+            current_weights: Default::default(),
+        };
+        weight_route
+            .get_route(HeaderMap::new(), RouteSelection::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            weight_route.lock_current_weights().current_weights,
+            [-2, 1, 1]
+        );
+        weight_route.routes.write().await.reverse();
+        let current_route = weight_route
+            .get_route(HeaderMap::new(), RouteSelection::default())
+            .await
+            .unwrap();
+        assert_eq!(current_route.endpoint, "http://localhost:4444");
+        assert_eq!(
+            weight_route.lock_current_weights().current_weights,
+            [1, 1, -2]
+        );
+    }
+    #[test]
+    fn test_weight_route_recover_poisoned_lock() {
+        let weight_route = WeightBasedRoute::default();
+        let current_weights = weight_route.current_weights.clone();
+        let _ = std::thread::spawn(move || {
+            let _lock = current_weights.lock().unwrap();
+            panic!("poison the lock");
+        })
+        .join();
+        assert!(weight_route.current_weights.is_poisoned());
+        assert!(weight_route
+            .lock_current_weights()
+            .current_weights
+            .is_empty());
+        assert!(!weight_route.current_weights.is_poisoned());
+    }
+    #[tokio::test]
+    async fn test_weight_route_with_routes_read_locked() {
+        let mut weight_route = WeightBasedRoute {
+            routes: Arc::new(RwLock::new(get_weight_routes())),
+            current_weights: Default::default(),
+        };
+        let routes = weight_route.routes.clone();
+        let _read_lock = routes.read().await;
+        let current_route = tokio::time::timeout(
+            Duration::from_secs(1),
+            weight_route.get_route(HeaderMap::new(), RouteSelection::default()),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(current_route.endpoint, "http://localhost:4444");
     }
-
     #[tokio::test]
     async fn test_header_based_route_successfully() {
         let routes = get_header_based_routes();
         let header_route = HeaderBasedRoute { routes };
         let mut header_route = LoadbalancerStrategy::HeaderBased(header_route);
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
         let mut headermap1 = HeaderMap::new();
         headermap1.insert("x-client", "100zh-CN,zh;q=0.9,en;q=0.8".parse().unwrap());
-        let result1 = header_route.get_route(headermap1.clone()).await;
+        let result1 = header_route
+            .get_route(headermap1.clone(), socket, None)
+            .await;
         assert!(result1.is_ok());
         assert_eq!(result1.unwrap().endpoint, "http://localhost:4444");
 
         let mut headermap2 = HeaderMap::new();
         headermap2.insert("x-client", "a=1;b=2;c:3;d=4;f5=6667".parse().unwrap());
-        let result2 = header_route.get_route(headermap2.clone()).await;
+        let result2 = header_route
+            .get_route(headermap2.clone(), socket, None)
+            .await;
         assert!(result2.is_ok());
         assert_eq!(result2.unwrap().endpoint, "http://localhost:5555");
 
         let mut headermap3 = HeaderMap::new();
         headermap3.insert("x-client", "a:12,b:9,c=7,d=4;f5=6667".parse().unwrap());
-        let result3 = header_route.get_route(headermap3.clone()).await;
+        let result3 = header_route
+            .get_route(headermap3.clone(), socket, None)
+            .await;
         assert!(result3.is_ok());
         assert_eq!(result3.unwrap().endpoint, "http://localhost:7777");
 
         let mut headermap4 = HeaderMap::new();
         headermap4.insert("x-client", "google chrome".parse().unwrap());
-        let result4 = header_route.get_route(headermap4.clone()).await;
+        let result4 = header_route
+            .get_route(headermap4.clone(), socket, None)
+            .await;
         assert!(result4.is_ok());
         assert_eq!(result4.unwrap().endpoint, "http://localhost:8888");
     }
+    fn get_region_based_route() -> RegionBasedRoute {
+        RegionBasedRoute {
+            regions: vec![
+                RegionMapping {
+                    region: String::from("eu"),
+                    ip_ranges: vec![String::from("10.1.0.0/16"), String::from("2001:db8::/32")],
+                },
+                RegionMapping {
+                    region: String::from("us"),
+                    ip_ranges: vec![String::from("10.2.0.0/16")],
+                },
+            ],
+            routes: vec![
+                RegionRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://localhost:4444"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(Default::default())),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                    region: String::from("eu"),
+                },
+                RegionRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://localhost:5555"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(Default::default())),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                    region: String::from("us"),
+                },
+            ],
+        }
+    }
+    #[tokio::test]
+    async fn test_region_based_route_successfully() {
+        let mut region_route = LoadbalancerStrategy::RegionBased(get_region_based_route());
+        let eu_socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)), 8080);
+        let us_socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 2, 2, 3)), 8080);
+        let eu_v6_socket = SocketAddr::new("2001:db8::1".parse().unwrap(), 8080);
+        for _ in 0..10 {
+            let result1 = region_route
+                .get_route(HeaderMap::new(), eu_socket, None)
+                .await;
+            assert_eq!(result1.unwrap().endpoint, "http://localhost:4444");
+            let result2 = region_route
+                .get_route(HeaderMap::new(), us_socket, None)
+                .await;
+            assert_eq!(result2.unwrap().endpoint, "http://localhost:5555");
+            let result3 = region_route
+                .get_route(HeaderMap::new(), eu_v6_socket, None)
+                .await;
+            assert_eq!(result3.unwrap().endpoint, "http://localhost:4444");
+        }
+    }
+    #[tokio::test]
+    async fn test_region_based_route_fallback() {
+        let region_based_route = get_region_based_route();
+        let mut eu_alive = region_based_route.routes[0]
+            .base_route
+            .is_alive
+            .write()
+            .await;
+        *eu_alive = Some(false);
+        drop(eu_alive);
+        let mut region_route = LoadbalancerStrategy::RegionBased(region_based_route);
+        let eu_socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)), 8080);
+        for _ in 0..10 {
+            let result = region_route
+                .get_route(HeaderMap::new(), eu_socket, None)
+                .await;
+            assert_eq!(result.unwrap().endpoint, "http://localhost:5555");
+        }
+        let unknown_socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)), 8080);
+        let result = region_route
+            .get_route(HeaderMap::new(), unknown_socket, None)
+            .await;
+        assert_eq!(result.unwrap().endpoint, "http://localhost:5555");
+    }
+    fn get_claim_based_route() -> ClaimBasedRoute {
+        ClaimBasedRoute {
+            jwt: JwtDecodeConfig::default(),
+            claim: String::from("tenant"),
+            routes: vec![
+                ClaimRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://localhost:4444"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(Default::default())),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                    claim_value: String::from("acme"),
+                },
+                ClaimRoute {
+                    base_route: BaseRoute {
+                        endpoint: String::from("http://localhost:5555"),
+                        try_file: None,
+                        index: None,
+                        autoindex: None,
+                        host_header: None,
+                        sni: None,
+                        preserve_header_case: true,
+                        is_alive: Arc::new(RwLock::new(None)),
+                        anomaly_detection_status: Arc::new(RwLock::new(Default::default())),
+                        circuit_breaker_status: Default::default(),
+                        recovered_at: Default::default(),
+                    },
+                    claim_value: String::from("globex"),
+                },
+            ],
+        }
+    }
+    fn create_jwt_headers(claims: &str) -> HeaderMap<HeaderValue> {
+        use base64::{engine::general_purpose, Engine as _};
+        let header = general_purpose::URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
+        let payload = general_purpose::URL_SAFE_NO_PAD.encode(claims);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            format!("Bearer {}.{}.", header, payload).parse().unwrap(),
+        );
+        headers
+    }
+    #[tokio::test]
+    async fn test_claim_based_route_successfully() {
+        let mut claim_route = LoadbalancerStrategy::ClaimBased(get_claim_based_route());
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)), 8080);
+        let result1 = claim_route
+            .get_route(create_jwt_headers(r#"{"tenant":"acme"}"#), socket, None)
+            .await;
+        assert_eq!(result1.unwrap().endpoint, "http://localhost:4444");
+        let result2 = claim_route
+            .get_route(create_jwt_headers(r#"{"tenant":"globex"}"#), socket, None)
+            .await;
+        assert_eq!(result2.unwrap().endpoint, "http://localhost:5555");
+    }
+    #[tokio::test]
+    async fn test_claim_based_route_missing_or_invalid_token() {
+        let mut claim_route = LoadbalancerStrategy::ClaimBased(get_claim_based_route());
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 1, 2, 3)), 8080);
+        let result1 = claim_route.get_route(HeaderMap::new(), socket, None).await;
+        assert_eq!(result1.unwrap().endpoint, "http://localhost:4444");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", "Bearer invalid".parse().unwrap());
+        let result2 = claim_route.get_route(headers, socket, None).await;
+        assert_eq!(result2.unwrap().endpoint, "http://localhost:4444");
+
+        let result3 = claim_route
+            .get_route(create_jwt_headers(r#"{"tenant":"unknown"}"#), socket, None)
+            .await;
+        assert_eq!(result3.unwrap().endpoint, "http://localhost:4444");
+    }
+    #[tokio::test]
+    async fn test_canary_route_percentage() {
+        let mut canary_route = LoadbalancerStrategy::Canary(Box::new(CanaryRoute {
+            stable: BaseRoute {
+                endpoint: String::from("http://localhost:8080"),
+                ..Default::default()
+            },
+            canary: BaseRoute {
+                endpoint: String::from("http://localhost:8081"),
+                ..Default::default()
+            },
+            canary_percentage: 5.0,
+            canary_header: None,
+        }));
+        let mut canary_count = 0;
+        for _ in 0..20000 {
+            let route = canary_route
+                .get_route(HeaderMap::new(), "127.0.0.1:3000".parse().unwrap(), None)
+                .await
+                .unwrap();
+            if route.endpoint == "http://localhost:8081" {
+                canary_count += 1;
+            }
+        }
+        let ratio = canary_count as f64 / 20000.0;
+        assert!((0.04..0.06).contains(&ratio), "the ratio is {}", ratio);
+    }
+    #[tokio::test]
+    async fn test_canary_route_header_override() {
+        let mut canary_route = CanaryRoute {
+            stable: BaseRoute {
+                endpoint: String::from("http://localhost:8080"),
+                ..Default::default()
+            },
+            canary: BaseRoute {
+                endpoint: String::from("http://localhost:8081"),
+                ..Default::default()
+            },
+            canary_percentage: 0.0,
+            canary_header: None,
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Canary", HeaderValue::from_static("true"));
+        for _ in 0..100 {
+            let route = canary_route
+                .get_route(headers.clone(), RouteSelection::default())
+                .await
+                .unwrap();
+            assert_eq!(route.endpoint, "http://localhost:8081");
+        }
+        canary_route.canary_percentage = 100.0;
+        headers.insert("X-Canary", HeaderValue::from_static("false"));
+        let route = canary_route
+            .get_route(headers, RouteSelection::default())
+            .await
+            .unwrap();
+        assert_eq!(route.endpoint, "http://localhost:8080");
+
+        *canary_route.canary.is_alive.write().await = Some(false);
+        let route = canary_route
+            .get_route(HeaderMap::new(), RouteSelection::default())
+            .await
+            .unwrap();
+        assert_eq!(route.endpoint, "http://localhost:8080");
+    }
     #[tokio::test]
     async fn test_update_health_check_status_with_ok_success1() {
         let base_route = BaseRoute {
             endpoint: String::from("/"),
             try_file: None,
+            index: None,
+            autoindex: None,
+            host_header: None,
+            sni: None,
+            preserve_header_case: true,
             is_alive: Arc::new(RwLock::new(None)),
             anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                 consecutive_5xx: 0,
+                recent_responses: Default::default(),
             })),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         };
         let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
             current_liveness_count: 3,
@@ -897,10 +2068,18 @@ mod tests {
         let base_route = BaseRoute {
             endpoint: String::from("/"),
             try_file: None,
+            index: None,
+            autoindex: None,
+            host_header: None,
+            sni: None,
+            preserve_header_case: true,
             is_alive: Arc::new(RwLock::new(Some(true))),
             anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                 consecutive_5xx: 0,
+                recent_responses: Default::default(),
             })),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         };
         let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
             current_liveness_count: 3,
@@ -920,10 +2099,18 @@ mod tests {
         let base_route = BaseRoute {
             endpoint: String::from("/"),
             try_file: None,
+            index: None,
+            autoindex: None,
+            host_header: None,
+            sni: None,
+            preserve_header_case: true,
             is_alive: Arc::new(RwLock::new(Some(false))),
             anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                 consecutive_5xx: 0,
+                recent_responses: Default::default(),
             })),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         };
         let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
             current_liveness_count: 3,
@@ -944,10 +2131,18 @@ mod tests {
         let base_route = BaseRoute {
             endpoint: String::from("/"),
             try_file: None,
+            index: None,
+            autoindex: None,
+            host_header: None,
+            sni: None,
+            preserve_header_case: true,
             is_alive: Arc::new(RwLock::new(None)),
             anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                 consecutive_5xx: 0,
+                recent_responses: Default::default(),
             })),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         };
         let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
             current_liveness_count: 3,
@@ -958,6 +2153,8 @@ mod tests {
                 liveness_status_lock,
                 LivenessConfig {
                     min_liveness_count: 3,
+                    panic_threshold: None,
+                    slow_start_second: None,
                 },
             )
             .await;
@@ -970,10 +2167,18 @@ mod tests {
         let base_route = BaseRoute {
             endpoint: String::from("/"),
             try_file: None,
+            index: None,
+            autoindex: None,
+            host_header: None,
+            sni: None,
+            preserve_header_case: true,
             is_alive: Arc::new(RwLock::new(Some(true))),
             anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                 consecutive_5xx: 0,
+                recent_responses: Default::default(),
             })),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         };
         let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
             current_liveness_count: 3,
@@ -984,6 +2189,8 @@ mod tests {
                 liveness_status_lock.clone(),
                 LivenessConfig {
                     min_liveness_count: 3,
+                    panic_threshold: None,
+                    slow_start_second: None,
                 },
             )
             .await;
@@ -1000,10 +2207,18 @@ mod tests {
         let base_route = BaseRoute {
             endpoint: String::from("/"),
             try_file: None,
+            index: None,
+            autoindex: None,
+            host_header: None,
+            sni: None,
+            preserve_header_case: true,
             is_alive: Arc::new(RwLock::new(Some(false))),
             anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                 consecutive_5xx: 0,
+                recent_responses: Default::default(),
             })),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         };
         let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
             current_liveness_count: 3,
@@ -1014,6 +2229,8 @@ mod tests {
                 liveness_status_lock.clone(),
                 LivenessConfig {
                     min_liveness_count: 3,
+                    panic_threshold: None,
+                    slow_start_second: None,
                 },
             )
             .await;
@@ -1030,17 +2247,30 @@ mod tests {
         let base_route = BaseRoute {
             endpoint: String::from("/"),
             try_file: None,
+            index: None,
+            autoindex: None,
+            host_header: None,
+            sni: None,
+            preserve_header_case: true,
             is_alive: Arc::new(RwLock::new(Some(false))),
             anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                 consecutive_5xx: 0,
+                recent_responses: Default::default(),
             })),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         };
         let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
             current_liveness_count: 3,
         }));
         let http_anomaly_detection_param = HttpAnomalyDetectionParam {
             consecutive_5xx: 2,
-            base_anomaly_detection_param: BaseAnomalyDetectionParam { ejection_second: 3 },
+            base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                ejection_second: 3,
+                window_size: None,
+                window_second: None,
+                max_ejection_percent: None,
+            },
         };
         let result = base_route
             .trigger_http_anomaly_detection(
@@ -1049,6 +2279,8 @@ mod tests {
                 true,
                 LivenessConfig {
                     min_liveness_count: 3,
+                    panic_threshold: None,
+                    slow_start_second: None,
                 },
             )
             .await;
@@ -1061,17 +2293,30 @@ mod tests {
         let base_route = BaseRoute {
             endpoint: String::from("/"),
             try_file: None,
+            index: None,
+            autoindex: None,
+            host_header: None,
+            sni: None,
+            preserve_header_case: true,
             is_alive: Arc::new(RwLock::new(Some(true))),
             anomaly_detection_status: Arc::new(RwLock::new(AnomalyDetectionStatus {
                 consecutive_5xx: 1,
+                recent_responses: Default::default(),
             })),
+            circuit_breaker_status: Default::default(),
+            recovered_at: Default::default(),
         };
         let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
             current_liveness_count: 4,
         }));
         let http_anomaly_detection_param = HttpAnomalyDetectionParam {
             consecutive_5xx: 3,
-            base_anomaly_detection_param: BaseAnomalyDetectionParam { ejection_second: 3 },
+            base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                ejection_second: 3,
+                window_size: None,
+                window_second: None,
+                max_ejection_percent: None,
+            },
         };
         let result = base_route
             .trigger_http_anomaly_detection(
@@ -1080,6 +2325,8 @@ mod tests {
                 true,
                 LivenessConfig {
                     min_liveness_count: 3,
+                    panic_threshold: None,
+                    slow_start_second: None,
                 },
             )
             .await;
@@ -1103,6 +2350,8 @@ mod tests {
                     true,
                     LivenessConfig {
                         min_liveness_count: 3,
+                        panic_threshold: None,
+                        slow_start_second: None,
                     },
                 )
                 .await;
@@ -1129,4 +2378,133 @@ mod tests {
             assert_eq!(liveness_status3.current_liveness_count, 4);
         }
     }
+    fn create_windowed_anomaly_detection_param(
+        window_size: Option<usize>,
+        window_second: Option<u64>,
+    ) -> HttpAnomalyDetectionParam {
+        HttpAnomalyDetectionParam {
+            consecutive_5xx: 2,
+            base_anomaly_detection_param: BaseAnomalyDetectionParam {
+                ejection_second: 1,
+                window_size,
+                window_second,
+                max_ejection_percent: Some(50),
+            },
+        }
+    }
+    #[tokio::test]
+    async fn test_windowed_anomaly_detection_eject_and_restore() {
+        let base_route = BaseRoute {
+            endpoint: String::from("http://127.0.0.1:8080"),
+            is_alive: Arc::new(RwLock::new(Some(true))),
+            ..Default::default()
+        };
+        let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
+            current_liveness_count: 4,
+        }));
+        let param = create_windowed_anomaly_detection_param(Some(3), None);
+        for is_5xx in [true, false, true] {
+            base_route
+                .trigger_http_anomaly_detection(
+                    param.clone(),
+                    liveness_status_lock.clone(),
+                    is_5xx,
+                    LivenessConfig {
+                        min_liveness_count: 1,
+                        panic_threshold: None,
+                        slow_start_second: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        assert_eq!(*base_route.is_alive.read().await, Some(false));
+        assert_eq!(liveness_status_lock.read().await.current_liveness_count, 3);
+
+        sleep(Duration::from_millis(1500)).await;
+        assert_eq!(*base_route.is_alive.read().await, Some(true));
+        assert_eq!(liveness_status_lock.read().await.current_liveness_count, 4);
+        let anomaly_detection_status = base_route.anomaly_detection_status.read().await;
+        assert!(anomaly_detection_status.recent_responses.is_empty());
+    }
+    #[tokio::test]
+    async fn test_windowed_anomaly_detection_drop_old_responses() {
+        let base_route = BaseRoute {
+            endpoint: String::from("http://127.0.0.1:8080"),
+            is_alive: Arc::new(RwLock::new(Some(true))),
+            ..Default::default()
+        };
+        let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
+            current_liveness_count: 4,
+        }));
+        let param = create_windowed_anomaly_detection_param(Some(2), Some(1));
+        for is_5xx in [true, false, true] {
+            base_route
+                .trigger_http_anomaly_detection(
+                    param.clone(),
+                    liveness_status_lock.clone(),
+                    is_5xx,
+                    LivenessConfig {
+                        min_liveness_count: 1,
+                        panic_threshold: None,
+                        slow_start_second: None,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        assert_eq!(*base_route.is_alive.read().await, Some(true));
+        sleep(Duration::from_millis(1100)).await;
+        base_route
+            .trigger_http_anomaly_detection(
+                param,
+                liveness_status_lock.clone(),
+                true,
+                LivenessConfig {
+                    min_liveness_count: 1,
+                    panic_threshold: None,
+                    slow_start_second: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(*base_route.is_alive.read().await, Some(true));
+        assert_eq!(liveness_status_lock.read().await.current_liveness_count, 4);
+    }
+    #[tokio::test]
+    async fn test_anomaly_detection_respect_max_ejection_percent() {
+        let liveness_status_lock = Arc::new(RwLock::new(LivenessStatus {
+            current_liveness_count: 4,
+        }));
+        let param = create_windowed_anomaly_detection_param(Some(2), None);
+        let liveness_config = LivenessConfig {
+            min_liveness_count: param.base_anomaly_detection_param.min_liveness_count(4, 0),
+            panic_threshold: None,
+            slow_start_second: None,
+        };
+        let mut base_routes = vec![];
+        for port in 0..3 {
+            let base_route = BaseRoute {
+                endpoint: format!("http://127.0.0.1:{}", 8080 + port),
+                is_alive: Arc::new(RwLock::new(Some(true))),
+                ..Default::default()
+            };
+            for _ in 0..2 {
+                base_route
+                    .trigger_http_anomaly_detection(
+                        param.clone(),
+                        liveness_status_lock.clone(),
+                        true,
+                        liveness_config.clone(),
+                    )
+                    .await
+                    .unwrap();
+            }
+            base_routes.push(base_route);
+        }
+        assert_eq!(*base_routes[0].is_alive.read().await, Some(false));
+        assert_eq!(*base_routes[1].is_alive.read().await, Some(false));
+        assert_eq!(*base_routes[2].is_alive.read().await, Some(true));
+        assert_eq!(liveness_status_lock.read().await.current_liveness_count, 2);
+    }
 }
@@ -0,0 +1,213 @@
+use crate::constants::common_constants::DEFAULT_CACHE_MAX_BODY_SIZE;
+use crate::vojo::response_cache::cache_directives;
+use bytes::Bytes;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use futures::future::{BoxFuture, Shared};
+use futures::FutureExt;
+use http::header::{AUTHORIZATION, COOKIE, SET_COOKIE};
+use http::HeaderMap;
+use http::Method;
+use http::StatusCode;
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+type InFlightResponse = Shared<BoxFuture<'static, Option<SharedResponse>>>;
+lazy_static! {
+    static ref GLOBAL_IN_FLIGHT_REQUESTS: DashMap<String, InFlightResponse> = Default::default();
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SingleFlightConfig {
+    #[serde(default)]
+    pub key_headers: Vec<String>,
+    #[serde(default = "default_single_flight_max_body_bytes")]
+    pub max_body_bytes: u64,
+}
+fn default_single_flight_max_body_bytes() -> u64 {
+    DEFAULT_CACHE_MAX_BODY_SIZE
+}
+/**
+ *The first request of a key is the leader which sends the request to the upstream, the others wait for its response.
+ */
+pub enum SingleFlight {
+    Leader(SingleFlightLeader),
+    Follower(InFlightResponse),
+}
+pub struct SingleFlightLeader {
+    key: String,
+    sender: Option<oneshot::Sender<Option<SharedResponse>>>,
+}
+impl SingleFlightLeader {
+    /**
+     *Publish none when the response could not be shared, then the followers send their own requests.
+     */
+    pub fn publish(mut self, response: Option<SharedResponse>) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(response);
+        }
+    }
+}
+impl Drop for SingleFlightLeader {
+    fn drop(&mut self) {
+        GLOBAL_IN_FLIGHT_REQUESTS.remove(&self.key);
+    }
+}
+impl SingleFlightConfig {
+    /**
+     *Only the GET request is coalesced, the request is identified by the path and the key headers.
+     *The request with the credentials is not coalesced unless the credential headers are in the key headers.
+     */
+    pub fn flight_key(
+        &self,
+        route_id: &str,
+        method: &Method,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Option<String> {
+        if method != Method::GET {
+            return None;
+        }
+        let has_unkeyed_credentials = [AUTHORIZATION, COOKIE].iter().any(|name| {
+            headers.contains_key(name)
+                && !self
+                    .key_headers
+                    .iter()
+                    .any(|item| item.eq_ignore_ascii_case(name.as_str()))
+        });
+        if has_unkeyed_credentials {
+            return None;
+        }
+        let mut key = format!("{}\n{} {}", route_id, method, path);
+        for header_name in self.key_headers.iter() {
+            let value = headers
+                .get_all(header_name.as_str())
+                .iter()
+                .filter_map(|item| item.to_str().ok())
+                .collect::<Vec<_>>()
+                .join(",");
+            key.push_str(&format!("\n{}:{}", header_name.to_ascii_lowercase(), value));
+        }
+        Some(key)
+    }
+    /**
+     *The response which sets the cookie or is private to the user is never shared with the followers.
+     */
+    pub fn is_shareable(&self, headers: &HeaderMap) -> bool {
+        !headers.contains_key(SET_COOKIE)
+            && !cache_directives(headers)
+                .iter()
+                .any(|item| item == "private" || item == "no-store")
+    }
+    pub fn join(&self, key: String) -> SingleFlight {
+        match GLOBAL_IN_FLIGHT_REQUESTS.entry(key.clone()) {
+            Entry::Occupied(entry) => SingleFlight::Follower(entry.get().clone()),
+            Entry::Vacant(entry) => {
+                let (sender, receiver) = oneshot::channel();
+                entry.insert(receiver.map(|item| item.ok().flatten()).boxed().shared());
+                SingleFlight::Leader(SingleFlightLeader {
+                    key,
+                    sender: Some(sender),
+                })
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+    fn create_single_flight_config() -> SingleFlightConfig {
+        SingleFlightConfig {
+            key_headers: vec![String::from("Accept-Language")],
+            max_body_bytes: DEFAULT_CACHE_MAX_BODY_SIZE,
+        }
+    }
+    #[test]
+    fn test_flight_key() {
+        let config = create_single_flight_config();
+        let mut headers = HeaderMap::new();
+        headers.insert("accept-language", HeaderValue::from_static("en"));
+        assert_eq!(
+            config.flight_key("route", &Method::GET, "/a?b=1", &headers),
+            Some(String::from("route\nGET /a?b=1\naccept-language:en"))
+        );
+        assert_eq!(
+            config.flight_key("route", &Method::POST, "/a", &headers),
+            None
+        );
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer a"));
+        assert_eq!(
+            config.flight_key("route", &Method::GET, "/a", &headers),
+            None
+        );
+        let keyed_config = SingleFlightConfig {
+            key_headers: vec![String::from("Authorization")],
+            ..create_single_flight_config()
+        };
+        assert_eq!(
+            keyed_config.flight_key("route", &Method::GET, "/a", &headers),
+            Some(String::from("route\nGET /a\nauthorization:Bearer a"))
+        );
+    }
+    #[test]
+    fn test_is_shareable() {
+        let config = create_single_flight_config();
+        let mut headers = HeaderMap::new();
+        assert!(config.is_shareable(&headers));
+        headers.insert(
+            "cache-control",
+            HeaderValue::from_static("public, max-age=5"),
+        );
+        assert!(config.is_shareable(&headers));
+        for value in ["private", "no-store"] {
+            headers.insert("cache-control", HeaderValue::from_static(value));
+            assert!(!config.is_shareable(&headers), "{}", value);
+        }
+        let mut headers = HeaderMap::new();
+        headers.insert(SET_COOKIE, HeaderValue::from_static("session=1"));
+        assert!(!config.is_shareable(&headers));
+    }
+    #[tokio::test]
+    async fn test_followers_share_the_leader_response() {
+        let config = create_single_flight_config();
+        let key = String::from("test_followers_share_the_leader_response");
+        let SingleFlight::Leader(leader) = config.join(key.clone()) else {
+            panic!("The first request should be the leader!");
+        };
+        let followers = (0..3)
+            .map(|_| match config.join(key.clone()) {
+                SingleFlight::Follower(response) => response,
+                SingleFlight::Leader(_) => panic!("The other requests should be followers!"),
+            })
+            .collect::<Vec<_>>();
+        let response = SharedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from("hello"),
+        };
+        leader.publish(Some(response.clone()));
+        for follower in followers {
+            assert_eq!(follower.await, Some(response.clone()));
+        }
+        assert!(matches!(config.join(key.clone()), SingleFlight::Leader(_)));
+    }
+    #[tokio::test]
+    async fn test_followers_get_none_when_leader_dropped() {
+        let config = create_single_flight_config();
+        let key = String::from("test_followers_get_none_when_leader_dropped");
+        let leader = config.join(key.clone());
+        let SingleFlight::Follower(follower) = config.join(key.clone()) else {
+            panic!("The second request should be a follower!");
+        };
+        drop(leader);
+        assert_eq!(follower.await, None);
+    }
+}
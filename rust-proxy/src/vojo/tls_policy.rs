@@ -0,0 +1,500 @@
+use crate::vojo::app_error::{AppError, AppErrorKind};
+use arc_swap::ArcSwap;
+use rustls::crypto::ring::default_provider;
+use rustls::crypto::ring::sign::any_supported_type;
+use rustls::crypto::ring::ALL_CIPHER_SUITES;
+use rustls::crypto::CryptoProvider;
+use rustls::server::ClientHello;
+use rustls::server::ResolvesServerCert;
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use rustls::SupportedCipherSuite;
+use rustls::SupportedProtocolVersion;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::BufReader;
+use std::sync::Arc;
+
+pub const DEFAULT_CERT_NAME: &str = "default";
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum TlsVersion {
+    Tls12,
+    Tls13,
+}
+impl TlsVersion {
+    fn to_protocol_version(self) -> &'static SupportedProtocolVersion {
+        match self {
+            TlsVersion::Tls12 => &rustls::version::TLS12,
+            TlsVersion::Tls13 => &rustls::version::TLS13,
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct TlsPolicy {
+    pub versions: Option<Vec<TlsVersion>>,
+    pub cipher_suites: Option<Vec<String>>,
+    pub alpn_protocols: Option<Vec<String>>,
+    #[serde(default)]
+    pub prefer_server_cipher_order: bool,
+}
+impl TlsPolicy {
+    /**
+     *The cipher suites use the rustls names, e.g. `TLS13_AES_256_GCM_SHA384`, and keep the configured order.
+     */
+    fn get_cipher_suites(&self) -> Result<Vec<SupportedCipherSuite>, AppError> {
+        let Some(cipher_suite_names) = self.cipher_suites.as_ref() else {
+            return Ok(ALL_CIPHER_SUITES.to_vec());
+        };
+        cipher_suite_names
+            .iter()
+            .map(|name| {
+                ALL_CIPHER_SUITES
+                    .iter()
+                    .find(|item| format!("{:?}", item.suite()).eq_ignore_ascii_case(name))
+                    .copied()
+                    .ok_or(AppError::new(
+                        AppErrorKind::Tls,
+                        format!("The cipher suite {} is not supported!", name),
+                    ))
+            })
+            .collect()
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SniCert {
+    pub server_name: String,
+    pub cert_str: String,
+    pub key_str: String,
+}
+fn load_certified_key(cert_str: &str, key_str: &str) -> Result<Arc<CertifiedKey>, AppError> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(cert_str.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::new(AppErrorKind::Tls, e.to_string()))?;
+    if certs.is_empty() {
+        return Err(AppError::new(
+            AppErrorKind::Tls,
+            "Can not find the certificate!",
+        ));
+    }
+    let key_der = rustls_pemfile::private_key(&mut BufReader::new(key_str.as_bytes()))
+        .map_err(|e| AppError::new(AppErrorKind::Tls, e.to_string()))?
+        .ok_or(AppError::new(
+            AppErrorKind::Tls,
+            "Can not find the private key!",
+        ))?;
+    let signing_key = any_supported_type(&key_der)
+        .map_err(|e| AppError::new(AppErrorKind::Tls, e.to_string()))?;
+    Ok(Arc::new(CertifiedKey::new(certs, signing_key)))
+}
+#[derive(Debug, Clone)]
+struct LoadedCert {
+    cert_str: String,
+    key_str: String,
+    certified_key: Arc<CertifiedKey>,
+}
+impl LoadedCert {
+    fn load(cert_str: &str, key_str: &str) -> Result<Self, AppError> {
+        Ok(LoadedCert {
+            cert_str: cert_str.to_string(),
+            key_str: key_str.to_string(),
+            certified_key: load_certified_key(cert_str, key_str)?,
+        })
+    }
+    fn is_same(&self, cert_str: &str, key_str: &str) -> bool {
+        self.cert_str == cert_str && self.key_str == key_str
+    }
+}
+#[derive(Debug, Clone)]
+struct LoadedCerts {
+    default_cert: LoadedCert,
+    sni_certs: HashMap<String, LoadedCert>,
+}
+/**
+ *Select the certificate by the server name in the ClientHello, the server name could be an exact domain or a wildcard like `*.example.com`.
+ *The default certificate is used when the client sends no server name or the server name is not configured.
+ *The certificates are swapped as a whole on reload, so the handshakes read them without locking and the established connections are kept.
+ */
+#[derive(Debug)]
+pub struct SniCertResolver {
+    certs: ArcSwap<LoadedCerts>,
+}
+impl SniCertResolver {
+    pub fn new(cert_str: &str, key_str: &str, sni_certs: &[SniCert]) -> Result<Self, AppError> {
+        let cert_resolver = SniCertResolver {
+            certs: ArcSwap::from_pointee(LoadedCerts {
+                default_cert: LoadedCert::load(cert_str, key_str)?,
+                sni_certs: HashMap::new(),
+            }),
+        };
+        cert_resolver.reload(cert_str, key_str, sni_certs)?;
+        Ok(cert_resolver)
+    }
+    /**
+     *Only the changed certificates are loaded again, and an invalid certificate keeps all the current ones.
+     *Return the server names whose certificates are added, changed or removed.
+     */
+    pub fn reload(
+        &self,
+        cert_str: &str,
+        key_str: &str,
+        sni_certs: &[SniCert],
+    ) -> Result<Vec<String>, AppError> {
+        let mut updated = vec![];
+        let current = self.certs.load();
+        let default_cert = if current.default_cert.is_same(cert_str, key_str) {
+            current.default_cert.clone()
+        } else {
+            updated.push(String::from(DEFAULT_CERT_NAME));
+            LoadedCert::load(cert_str, key_str)?
+        };
+        let mut new_certs = HashMap::new();
+        for item in sni_certs.iter() {
+            let server_name = item.server_name.to_ascii_lowercase();
+            let loaded_cert = match current.sni_certs.get(&server_name) {
+                Some(current) if current.is_same(&item.cert_str, &item.key_str) => current.clone(),
+                _ => {
+                    updated.push(server_name.clone());
+                    LoadedCert::load(&item.cert_str, &item.key_str).map_err(|e| {
+                        AppError::new(
+                            AppErrorKind::Tls,
+                            format!(
+                                "Can not load the certificate of {},the error is {}",
+                                item.server_name, e
+                            ),
+                        )
+                    })?
+                }
+            };
+            new_certs.insert(server_name, loaded_cert);
+        }
+        updated.extend(
+            current
+                .sni_certs
+                .keys()
+                .filter(|item| !new_certs.contains_key(*item))
+                .cloned(),
+        );
+        if !updated.is_empty() {
+            self.certs.store(Arc::new(LoadedCerts {
+                default_cert,
+                sni_certs: new_certs,
+            }));
+        }
+        updated.sort();
+        Ok(updated)
+    }
+    pub fn get_cert(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        let certs = self.certs.load();
+        let sni_cert = server_name
+            .map(|item| item.to_ascii_lowercase())
+            .and_then(|server_name| {
+                certs.sni_certs.get(&server_name).or_else(|| {
+                    server_name
+                        .split_once('.')
+                        .and_then(|(_, parent)| certs.sni_certs.get(&format!("*.{}", parent)))
+                })
+            });
+        Some(
+            sni_cert
+                .unwrap_or(&certs.default_cert)
+                .certified_key
+                .clone(),
+        )
+    }
+}
+impl ResolvesServerCert for SniCertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        self.get_cert(client_hello.server_name())
+    }
+}
+pub fn build_server_config(
+    tls_policy: Option<&TlsPolicy>,
+    cert_resolver: Arc<SniCertResolver>,
+    default_alpn_protocols: Vec<Vec<u8>>,
+) -> Result<ServerConfig, AppError> {
+    let tls_policy = tls_policy.cloned().unwrap_or_default();
+    let provider = CryptoProvider {
+        cipher_suites: tls_policy.get_cipher_suites()?,
+        ..default_provider()
+    };
+    let versions = tls_policy
+        .versions
+        .clone()
+        .unwrap_or(vec![TlsVersion::Tls12, TlsVersion::Tls13])
+        .into_iter()
+        .map(|item| item.to_protocol_version())
+        .collect::<Vec<&'static SupportedProtocolVersion>>();
+    let mut cfg = ServerConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(&versions)
+        .map_err(|e| AppError::new(AppErrorKind::Tls, e.to_string()))?
+        .with_no_client_auth()
+        .with_cert_resolver(cert_resolver);
+    cfg.alpn_protocols = match tls_policy.alpn_protocols {
+        Some(alpn_protocols) => alpn_protocols
+            .into_iter()
+            .map(|item| item.into_bytes())
+            .collect(),
+        None => default_alpn_protocols,
+    };
+    cfg.ignore_client_order = tls_policy.prefer_server_cipher_order;
+    Ok(cfg)
+}
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use rustls::client::danger::HandshakeSignatureValid;
+    use rustls::client::danger::ServerCertVerified;
+    use rustls::client::danger::ServerCertVerifier;
+    use rustls::ClientConfig;
+    use rustls::DigitallySignedStruct;
+    use rustls::SignatureScheme;
+    use rustls_pki_types::CertificateDer;
+    use rustls_pki_types::ServerName;
+    use rustls_pki_types::UnixTime;
+    use std::env;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::TlsAcceptor;
+    use tokio_rustls::TlsConnector;
+    #[derive(Debug)]
+    pub struct AcceptAnyCert;
+    impl ServerCertVerifier for AcceptAnyCert {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            default_provider()
+                .signature_verification_algorithms
+                .supported_schemes()
+        }
+    }
+    pub fn read_test_pem(file_name: &str) -> String {
+        std::fs::read_to_string(env::current_dir().unwrap().join("config").join(file_name)).unwrap()
+    }
+    fn load_test_cert() -> Arc<SniCertResolver> {
+        load_test_sni_cert(&[])
+    }
+    fn load_test_sni_cert(sni_certs: &[SniCert]) -> Arc<SniCertResolver> {
+        let cert_str = read_test_pem("test_cert.pem");
+        let key_str = read_test_pem("test_key.pem");
+        Arc::new(SniCertResolver::new(&cert_str, &key_str, sni_certs).unwrap())
+    }
+    pub fn first_cert_der(cert_str: &str) -> CertificateDer<'static> {
+        rustls_pemfile::certs(&mut BufReader::new(cert_str.as_bytes()))
+            .next()
+            .unwrap()
+            .unwrap()
+    }
+    async fn connect(
+        server_config: ServerConfig,
+        client_version: &'static SupportedProtocolVersion,
+        server_name: &'static str,
+    ) -> Result<CertificateDer<'static>, String> {
+        let client_config = ClientConfig::builder_with_protocol_versions(&[client_version])
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+            .with_no_client_auth();
+        let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+        let acceptor = TlsAcceptor::from(Arc::new(server_config));
+        let server_task = tokio::spawn(async move { acceptor.accept(server_stream).await });
+        let connector = TlsConnector::from(Arc::new(client_config));
+        let client_result = connector
+            .connect(ServerName::try_from(server_name).unwrap(), client_stream)
+            .await;
+        let server_result = server_task.await.unwrap();
+        let client_stream = client_result.map_err(|e| e.to_string())?;
+        server_result.map_err(|e| e.to_string())?;
+        let peer_certificates = client_stream.get_ref().1.peer_certificates().unwrap();
+        Ok(peer_certificates[0].clone().into_owned())
+    }
+    async fn handshake(
+        tls_policy: &TlsPolicy,
+        client_version: &'static SupportedProtocolVersion,
+    ) -> Result<(), String> {
+        let server_config =
+            build_server_config(Some(tls_policy), load_test_cert(), vec![]).unwrap();
+        connect(server_config, client_version, "localhost")
+            .await
+            .map(|_| ())
+    }
+    #[tokio::test]
+    async fn test_tls13_only_policy_rejects_tls12() {
+        let tls_policy = TlsPolicy {
+            versions: Some(vec![TlsVersion::Tls13]),
+            cipher_suites: None,
+            alpn_protocols: None,
+            prefer_server_cipher_order: false,
+        };
+        assert!(handshake(&tls_policy, &rustls::version::TLS13)
+            .await
+            .is_ok());
+        let err = handshake(&tls_policy, &rustls::version::TLS12)
+            .await
+            .unwrap_err();
+        assert!(err.to_lowercase().contains("protocol"), "{}", err);
+        assert!(handshake(&TlsPolicy::default(), &rustls::version::TLS12)
+            .await
+            .is_ok());
+    }
+    #[tokio::test]
+    async fn test_tls_policy_cipher_suites() {
+        let tls_policy = TlsPolicy {
+            versions: Some(vec![TlsVersion::Tls13]),
+            cipher_suites: Some(vec![String::from("TLS13_AES_256_GCM_SHA384")]),
+            alpn_protocols: Some(vec![String::from("h2")]),
+            prefer_server_cipher_order: true,
+        };
+        assert_eq!(tls_policy.get_cipher_suites().unwrap().len(), 1);
+        assert!(handshake(&tls_policy, &rustls::version::TLS13)
+            .await
+            .is_ok());
+        let server_config =
+            build_server_config(Some(&tls_policy), load_test_cert(), vec![]).unwrap();
+        assert_eq!(server_config.alpn_protocols, vec![b"h2".to_vec()]);
+        assert!(server_config.ignore_client_order);
+
+        let unknown_policy = TlsPolicy {
+            cipher_suites: Some(vec![String::from("TLS_UNKNOWN")]),
+            ..Default::default()
+        };
+        assert!(unknown_policy.get_cipher_suites().is_err());
+        let mismatched_policy = TlsPolicy {
+            versions: Some(vec![TlsVersion::Tls12]),
+            cipher_suites: Some(vec![String::from("TLS13_AES_256_GCM_SHA384")]),
+            ..Default::default()
+        };
+        assert!(build_server_config(Some(&mismatched_policy), load_test_cert(), vec![]).is_err());
+    }
+    #[tokio::test]
+    async fn test_sni_cert_resolver_selects_cert_by_server_name() {
+        let sni_certs = ["a", "b"]
+            .iter()
+            .map(|item| SniCert {
+                server_name: format!("{}.example.com", item),
+                cert_str: read_test_pem(&format!("test_sni_{}_cert.pem", item)),
+                key_str: read_test_pem(&format!("test_sni_{}_key.pem", item)),
+            })
+            .chain(std::iter::once(SniCert {
+                server_name: String::from("*.wildcard.com"),
+                cert_str: read_test_pem("test_sni_b_cert.pem"),
+                key_str: read_test_pem("test_sni_b_key.pem"),
+            }))
+            .collect::<Vec<SniCert>>();
+        let cert_resolver = load_test_sni_cert(&sni_certs);
+        let default_cert = first_cert_der(&read_test_pem("test_cert.pem"));
+        let cert_a = first_cert_der(&sni_certs[0].cert_str);
+        let cert_b = first_cert_der(&sni_certs[1].cert_str);
+        assert_ne!(cert_a, cert_b);
+        for (server_name, expected) in [
+            ("a.example.com", &cert_a),
+            ("A.Example.com", &cert_a),
+            ("b.example.com", &cert_b),
+            ("api.wildcard.com", &cert_b),
+            ("c.example.com", &default_cert),
+            ("localhost", &default_cert),
+        ] {
+            for client_version in [&rustls::version::TLS12, &rustls::version::TLS13] {
+                let server_config =
+                    build_server_config(None, cert_resolver.clone(), vec![]).unwrap();
+                let peer_cert = connect(server_config, client_version, server_name)
+                    .await
+                    .unwrap();
+                assert_eq!(&peer_cert, expected, "{}", server_name);
+            }
+        }
+        let invalid_cert = SniCert {
+            server_name: String::from("a.example.com"),
+            cert_str: String::from("invalid"),
+            key_str: sni_certs[0].key_str.clone(),
+        };
+        let cert_str = read_test_pem("test_cert.pem");
+        let key_str = read_test_pem("test_key.pem");
+        assert!(SniCertResolver::new(&cert_str, &key_str, &[invalid_cert]).is_err());
+    }
+    #[tokio::test]
+    async fn test_sni_cert_resolver_reload_keeps_established_connections() {
+        let cert_resolver = load_test_cert();
+        let first_key = cert_resolver.get_cert(None).unwrap();
+        assert!(Arc::ptr_eq(
+            &first_key,
+            &cert_resolver.get_cert(None).unwrap()
+        ));
+
+        let server_config =
+            Arc::new(build_server_config(None, cert_resolver.clone(), vec![]).unwrap());
+        let client_config = Arc::new(
+            ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(AcceptAnyCert))
+                .with_no_client_auth(),
+        );
+        let open_connection = || {
+            let acceptor = TlsAcceptor::from(server_config.clone());
+            let connector = TlsConnector::from(client_config.clone());
+            async move {
+                let (client_stream, server_stream) = tokio::io::duplex(64 * 1024);
+                let server_task = tokio::spawn(async move { acceptor.accept(server_stream).await });
+                let client_stream = connector
+                    .connect(ServerName::try_from("localhost").unwrap(), client_stream)
+                    .await
+                    .unwrap();
+                (client_stream, server_task.await.unwrap().unwrap())
+            }
+        };
+        let (mut old_client, mut old_server) = open_connection().await;
+        let old_cert = old_client.get_ref().1.peer_certificates().unwrap()[0].clone();
+        assert_eq!(old_cert, first_cert_der(&read_test_pem("test_cert.pem")));
+
+        let new_cert_str = read_test_pem("test_sni_a_cert.pem");
+        let new_key_str = read_test_pem("test_sni_a_key.pem");
+        assert_eq!(
+            cert_resolver
+                .reload(&new_cert_str, &new_key_str, &[])
+                .unwrap(),
+            vec![String::from(DEFAULT_CERT_NAME)]
+        );
+        let reloaded_key = cert_resolver.get_cert(None).unwrap();
+        assert!(!Arc::ptr_eq(&first_key, &reloaded_key));
+        assert!(cert_resolver
+            .reload(&new_cert_str, &new_key_str, &[])
+            .unwrap()
+            .is_empty());
+        assert!(Arc::ptr_eq(
+            &reloaded_key,
+            &cert_resolver.get_cert(None).unwrap()
+        ));
+
+        let (new_client, _new_server) = open_connection().await;
+        let new_cert = new_client.get_ref().1.peer_certificates().unwrap()[0].clone();
+        assert_eq!(new_cert, first_cert_der(&new_cert_str));
+
+        old_client.write_all(b"ping").await.unwrap();
+        old_client.flush().await.unwrap();
+        let mut buf = [0; 4];
+        old_server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+}
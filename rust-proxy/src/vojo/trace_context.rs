@@ -0,0 +1,139 @@
+use crate::constants::common_constants::TRACEPARENT;
+use http::HeaderMap;
+use rand::Rng;
+
+static TRACE_FLAG_SAMPLED: u8 = 0x01;
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub parent_span_id: Option<String>,
+    pub span_id: String,
+    pub trace_flags: u8,
+}
+fn is_hex_id(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .chars()
+            .all(|item| item.is_ascii_digit() || ('a'..='f').contains(&item))
+        && value.chars().any(|item| item != '0')
+}
+fn random_hex_id(bytes_len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    loop {
+        let id = (0..bytes_len)
+            .map(|_| format!("{:02x}", rng.gen::<u8>()))
+            .collect::<String>();
+        if is_hex_id(&id, bytes_len * 2) {
+            return id;
+        }
+    }
+}
+impl TraceContext {
+    /**
+     *Parse the `version-trace_id-parent_id-trace_flags` format, the unknown versions may append more fields.
+     */
+    pub fn parse(traceparent: &str) -> Option<(String, String, u8)> {
+        let fields = traceparent.trim().split('-').collect::<Vec<&str>>();
+        let (version, trace_id, parent_id, trace_flags) = match fields.as_slice() {
+            [version, trace_id, parent_id, trace_flags, ..] => {
+                (*version, *trace_id, *parent_id, *trace_flags)
+            }
+            _ => return None,
+        };
+        if version.len() != 2
+            || version == "ff"
+            || (version == "00" && fields.len() != 4)
+            || u8::from_str_radix(version, 16).is_err()
+            || !is_hex_id(trace_id, 32)
+            || !is_hex_id(parent_id, 16)
+            || trace_flags.len() != 2
+        {
+            return None;
+        }
+        let trace_flags = u8::from_str_radix(trace_flags, 16).ok()?;
+        Some((trace_id.to_string(), parent_id.to_string(), trace_flags))
+    }
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let incoming = headers
+            .get(TRACEPARENT)
+            .and_then(|item| item.to_str().ok())
+            .and_then(TraceContext::parse);
+        match incoming {
+            Some((trace_id, parent_span_id, trace_flags)) => TraceContext {
+                trace_id,
+                parent_span_id: Some(parent_span_id),
+                span_id: random_hex_id(8),
+                trace_flags,
+            },
+            None => TraceContext {
+                trace_id: random_hex_id(16),
+                parent_span_id: None,
+                span_id: random_hex_id(8),
+                trace_flags: TRACE_FLAG_SAMPLED,
+            },
+        }
+    }
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id, self.span_id, self.trace_flags
+        )
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_ID: &str = "00f067aa0ba902b7";
+    #[test]
+    fn test_parse_traceparent() {
+        assert_eq!(
+            TraceContext::parse(&format!("00-{}-{}-01", TRACE_ID, PARENT_ID)),
+            Some((TRACE_ID.to_string(), PARENT_ID.to_string(), 1))
+        );
+        assert_eq!(
+            TraceContext::parse(&format!("01-{}-{}-00-extra", TRACE_ID, PARENT_ID)),
+            Some((TRACE_ID.to_string(), PARENT_ID.to_string(), 0))
+        );
+        for invalid in [
+            format!("00-{}-{}-01-extra", TRACE_ID, PARENT_ID),
+            format!("ff-{}-{}-01", TRACE_ID, PARENT_ID),
+            format!("00-{}-{}-01", "0".repeat(32), PARENT_ID),
+            format!("00-{}-{}-01", TRACE_ID, "0".repeat(16)),
+            format!("00-{}-{}-01", TRACE_ID.to_uppercase(), PARENT_ID),
+            format!("00-{}-{}-1", TRACE_ID, PARENT_ID),
+            String::from("not-a-traceparent"),
+        ] {
+            assert_eq!(TraceContext::parse(&invalid), None, "{}", invalid);
+        }
+    }
+    #[test]
+    fn test_child_span_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            TRACEPARENT,
+            format!("00-{}-{}-01", TRACE_ID, PARENT_ID).parse().unwrap(),
+        );
+        let trace_context = TraceContext::from_headers(&headers);
+        assert_eq!(trace_context.trace_id, TRACE_ID);
+        assert_eq!(trace_context.parent_span_id, Some(PARENT_ID.to_string()));
+        assert_ne!(trace_context.span_id, PARENT_ID);
+        let traceparent = trace_context.to_traceparent();
+        assert_eq!(
+            traceparent,
+            format!("00-{}-{}-01", TRACE_ID, trace_context.span_id)
+        );
+        assert!(TraceContext::parse(&traceparent).is_some());
+    }
+    #[test]
+    fn test_new_trace_without_traceparent() {
+        let trace_context = TraceContext::from_headers(&HeaderMap::new());
+        assert_eq!(trace_context.parent_span_id, None);
+        assert_eq!(trace_context.trace_flags, TRACE_FLAG_SAMPLED);
+        let (trace_id, span_id, trace_flags) =
+            TraceContext::parse(&trace_context.to_traceparent()).unwrap();
+        assert_eq!(trace_id, trace_context.trace_id);
+        assert_eq!(span_id, trace_context.span_id);
+        assert_eq!(trace_flags, TRACE_FLAG_SAMPLED);
+    }
+}
@@ -0,0 +1,207 @@
+use super::app_config::Route;
+use super::app_error::{AppError, AppErrorKind};
+use ipnet::IpNet;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use url::Host;
+use url::Url;
+
+pub const DEFAULT_DENIED_UPSTREAM_RANGES: [&str; 3] =
+    ["169.254.0.0/16", "fe80::/10", "fd00:ec2::254/128"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub struct UpstreamPolicy {
+    pub allowed_schemes: Option<Vec<String>>,
+    pub allowed_ports: Option<Vec<u16>>,
+    pub allowed_hosts: Option<Vec<String>>,
+    pub denied_hosts: Option<Vec<String>>,
+}
+fn host_matched(patterns: &[String], host: &Host<&str>) -> bool {
+    patterns.iter().any(|pattern| match host {
+        Host::Domain(domain) => pattern.eq_ignore_ascii_case(domain),
+        Host::Ipv4(ip) => ip_matched(pattern, IpAddr::V4(*ip)),
+        Host::Ipv6(ip) => ip_matched(pattern, IpAddr::V6(*ip)),
+    })
+}
+fn ip_matched(pattern: &str, ip: IpAddr) -> bool {
+    let ip = match ip {
+        IpAddr::V6(ipv6) => ipv6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        IpAddr::V4(_) => ip,
+    };
+    if let Ok(ip_net) = pattern.parse::<IpNet>() {
+        ip_net.contains(&ip)
+    } else if let Ok(pattern_ip) = pattern.parse::<IpAddr>() {
+        pattern_ip == ip
+    } else {
+        false
+    }
+}
+impl UpstreamPolicy {
+    pub fn check(&self, endpoint: &str) -> Result<(), AppError> {
+        let url = Url::parse(endpoint).map_err(|e| AppError::from(e.to_string()))?;
+        if let Some(allowed_schemes) = &self.allowed_schemes {
+            if !allowed_schemes
+                .iter()
+                .any(|item| item.eq_ignore_ascii_case(url.scheme()))
+            {
+                return Err(AppError::new(
+                    AppErrorKind::Config,
+                    format!("The scheme of the upstream {} is not allowed!", endpoint),
+                ));
+            }
+        }
+        if let Some(allowed_ports) = &self.allowed_ports {
+            let port = url.port_or_known_default().unwrap_or_default();
+            if !allowed_ports.contains(&port) {
+                return Err(AppError::new(
+                    AppErrorKind::Config,
+                    format!("The port of the upstream {} is not allowed!", endpoint),
+                ));
+            }
+        }
+        let host = url.host().ok_or(AppError::new(
+            AppErrorKind::Config,
+            format!("Can not find the host of the upstream {}!", endpoint),
+        ))?;
+        if let Some(denied_hosts) = &self.denied_hosts {
+            if host_matched(denied_hosts, &host) {
+                return Err(AppError::new(
+                    AppErrorKind::Config,
+                    format!("The host of the upstream {} is denied!", endpoint),
+                ));
+            }
+        }
+        if let Some(allowed_hosts) = &self.allowed_hosts {
+            if host_matched(allowed_hosts, &host) {
+                return Ok(());
+            }
+            return Err(AppError::new(
+                AppErrorKind::Config,
+                format!("The host of the upstream {} is not allowed!", endpoint),
+            ));
+        }
+        let default_denied_hosts = DEFAULT_DENIED_UPSTREAM_RANGES
+            .iter()
+            .map(|item| item.to_string())
+            .collect::<Vec<String>>();
+        if host_matched(&default_denied_hosts, &host) {
+            return Err(AppError::new(
+                AppErrorKind::Config,
+                format!(
+                    "The host of the upstream {} is a link-local address and is denied by default!",
+                    endpoint
+                ),
+            ));
+        }
+        Ok(())
+    }
+    /**
+     *The resolved addresses of the upstream are checked too,so a domain could not point the upstream to a denied address.
+     */
+    pub fn check_address(&self, host: &str, ip: IpAddr) -> Result<(), AppError> {
+        let denied = DEFAULT_DENIED_UPSTREAM_RANGES
+            .iter()
+            .any(|pattern| ip_matched(pattern, ip))
+            || self
+                .denied_hosts
+                .iter()
+                .flatten()
+                .any(|pattern| ip_matched(pattern, ip));
+        if denied {
+            return Err(AppError::new(
+                AppErrorKind::Forbidden,
+                format!(
+                    "The upstream {} is resolved to the denied address {}!",
+                    host, ip
+                ),
+            ));
+        }
+        Ok(())
+    }
+    pub async fn check_route(&self, route: &Route) -> Result<(), AppError> {
+        let base_routes = route.route_cluster.clone().get_all_route().await?;
+        for base_route in base_routes {
+            let endpoint = base_route.endpoint;
+            if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+                self.check(&endpoint)?;
+            }
+        }
+        if let Some(mirror) = &route.mirror {
+            self.check(&mirror.endpoint)?;
+        }
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_check_metadata_ip_denied_by_default() {
+        let policy = UpstreamPolicy::default();
+        assert!(policy.check("http://169.254.169.254/latest").is_err());
+        assert!(policy.check("http://[fe80::1]:8080").is_err());
+        assert!(policy.check("http://127.0.0.1:8080").is_ok());
+        assert!(policy.check("https://httpbin.org").is_ok());
+    }
+    #[test]
+    fn test_check_schemes_and_ports() {
+        let policy = UpstreamPolicy {
+            allowed_schemes: Some(vec![String::from("https")]),
+            allowed_ports: Some(vec![443, 8443]),
+            ..Default::default()
+        };
+        assert!(policy.check("https://httpbin.org").is_ok());
+        assert!(policy.check("https://httpbin.org:8443").is_ok());
+        assert!(policy.check("http://httpbin.org").is_err());
+        assert!(policy.check("https://httpbin.org:9000").is_err());
+    }
+    #[test]
+    fn test_check_allowed_and_denied_hosts() {
+        let policy = UpstreamPolicy {
+            allowed_hosts: Some(vec![
+                String::from("10.0.0.0/8"),
+                String::from("backend.local"),
+                String::from("169.254.10.1"),
+            ]),
+            denied_hosts: Some(vec![String::from("10.0.0.1")]),
+            ..Default::default()
+        };
+        assert!(policy.check("http://10.1.2.3:8080").is_ok());
+        assert!(policy.check("http://Backend.local:8080").is_ok());
+        assert!(policy.check("http://169.254.10.1").is_ok());
+        assert!(policy.check("http://10.0.0.1:8080").is_err());
+        assert!(policy.check("http://192.168.0.1").is_err());
+    }
+    #[test]
+    fn test_check_ipv4_mapped_address() {
+        let policy = UpstreamPolicy {
+            denied_hosts: Some(vec![String::from("10.0.0.0/8")]),
+            ..Default::default()
+        };
+        assert!(policy
+            .check("http://[::ffff:169.254.169.254]/latest")
+            .is_err());
+        assert!(policy.check("http://[::ffff:10.0.0.1]:8080").is_err());
+        assert!(policy.check("http://[::ffff:192.168.0.1]:8080").is_ok());
+    }
+    #[test]
+    fn test_check_address() {
+        let policy = UpstreamPolicy {
+            denied_hosts: Some(vec![String::from("127.0.0.0/8")]),
+            ..Default::default()
+        };
+        let loopback: IpAddr = "127.0.0.1".parse().unwrap();
+        assert!(policy.check("http://localhost:8080").is_ok());
+        assert!(policy.check_address("localhost", loopback).is_err());
+        assert!(policy
+            .check_address("localhost", "::ffff:127.0.0.1".parse().unwrap())
+            .is_err());
+        assert!(policy
+            .check_address("metadata", "169.254.169.254".parse().unwrap())
+            .is_err());
+        assert!(UpstreamPolicy::default()
+            .check_address("localhost", loopback)
+            .is_ok());
+    }
+}